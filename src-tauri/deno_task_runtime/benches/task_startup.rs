@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use deno_core::error::AnyError;
+use deno_core::{ModuleLoader as _, ModuleSpecifier, RequestedModuleType};
+use deno_task_runtime::test_support::{
+    create_startup_snapshot, set_startup_snapshot, TestHarness, TypescriptModuleLoader,
+};
+use deno_task_runtime::{ModuleLoader, TaskEvent};
+
+// Typed enough for the transpiler to have something to strip
+const MODULE: &str = r#"
+interface Item {
+  name: string;
+  price: number;
+}
+
+export function total(items: Item[]): number {
+  return items.reduce((sum, item) => sum + item.price, 0);
+}
+
+export default total([{ name: "apples", price: 3 }]);
+"#;
+
+// Per iteration of the events bench
+const EVENTS: usize = 1000;
+
+fn bootstrap(c: &mut Criterion) {
+    let harness = TestHarness::new("bench_bootstrap");
+    let snapshot = create_startup_snapshot();
+
+    let mut group = c.benchmark_group("bootstrap");
+    // a task per iteration, each one a thread and an isolate
+    group.sample_size(20);
+
+    set_startup_snapshot(None);
+    group.bench_function("cold", |b| b.iter(|| harness.run("")));
+
+    set_startup_snapshot(Some(snapshot));
+    group.bench_function("snapshot", |b| b.iter(|| harness.run("")));
+    set_startup_snapshot(None);
+
+    group.finish();
+}
+
+// Serves `MODULE`, followed by a comment changing on every load when
+// `unique` is set, so no load hits the transpile cache
+struct Source {
+    unique: bool,
+    loads: AtomicUsize,
+}
+
+impl ModuleLoader for Source {
+    fn load_source(&self, _specifier: &ModuleSpecifier) -> Result<Option<String>, AnyError> {
+        let load = self.loads.fetch_add(1, Ordering::SeqCst);
+
+        Ok(Some(if self.unique {
+            format!("{}// load {}\n", MODULE, load)
+        } else {
+            MODULE.to_string()
+        }))
+    }
+}
+
+fn transpile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transpile");
+
+    for (name, unique) in [("hit", false), ("miss", true)] {
+        let loader = TypescriptModuleLoader {
+            source_maps: Default::default(),
+            sources: Default::default(),
+            diagnostics: Default::default(),
+            custom_loader: Some(Arc::new(Source {
+                unique,
+                loads: AtomicUsize::new(0),
+            })),
+            lock: None,
+        };
+        let specifier = ModuleSpecifier::parse(&format!("file:///bench/{}.ts", name)).unwrap();
+
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(loader.load(&specifier, None, false, RequestedModuleType::None)))
+        });
+    }
+
+    group.finish();
+}
+
+// From the task side of the bridge to the JSON payload Tauri emits
+fn events(c: &mut Criterion) {
+    let harness = TestHarness::new("bench_events");
+    let task = harness.run("console.log(\"Ready\");");
+    harness.take_events();
+
+    let mut group = c.benchmark_group("events");
+    group.throughput(Throughput::Elements(EVENTS as u64));
+
+    group.bench_function("state_changed", |b| {
+        b.iter(|| {
+            for _ in 0..EVENTS {
+                harness.emit(TaskEvent::StateChanged(Box::new(task.clone())));
+            }
+
+            for event in harness.take_events() {
+                black_box(serde_json::to_string(&event).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bootstrap, transpile, events);
+criterion_main!(benches);
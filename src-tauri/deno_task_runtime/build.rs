@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+
+// Crates whose resolved version is reported by `get_runtime_capabilities`
+const REPORTED_CRATES: &[&str] = &[
+    "deno_runtime",
+    "deno_kv",
+    "deno_webgpu",
+    "deno_canvas",
+    "deno_node",
+];
+
+fn main() {
+    emit_crate_versions();
+}
+
+// Exposes the versions resolved in Cargo.lock as `<CRATE>_VERSION` env vars
+fn emit_crate_versions() {
+    let lock = find_lockfile();
+    if let Some(lock) = &lock {
+        println!("cargo:rerun-if-changed={}", lock.display());
+    }
+
+    let lock = lock
+        .and_then(|lock| std::fs::read_to_string(lock).ok())
+        .unwrap_or_default();
+    let mut name = None;
+    let mut emitted = Vec::new();
+
+    for line in lock.lines() {
+        if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"'));
+        } else if let Some(value) = line.strip_prefix("version = ") {
+            let name = name
+                .take()
+                .filter(|name| REPORTED_CRATES.contains(name) && !emitted.contains(name));
+            if let Some(name) = name {
+                emitted.push(name);
+                println!(
+                    "cargo:rustc-env={}_VERSION={}",
+                    name.to_uppercase(),
+                    value.trim_matches('"')
+                );
+            }
+        }
+    }
+
+    // keep `env!` compiling when a crate is missing from the lockfile
+    for name in REPORTED_CRATES {
+        if !emitted.contains(name) {
+            println!("cargo:rustc-env={}_VERSION=unknown", name.to_uppercase());
+        }
+    }
+}
+
+// The lockfile belongs to the workspace, usually the app embedding the crate
+fn find_lockfile() -> Option<PathBuf> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").ok()?;
+
+    Path::new(&manifest_dir)
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock| lock.is_file())
+}
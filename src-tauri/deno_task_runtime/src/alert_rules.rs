@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::config::log;
+use super::task_ids::{resolve_task_id, validate_task_id};
+use super::{scripts, RunOptions, TaskEvent, TaskRuntime, TaskState};
+
+const RULES_FILE: &str = "alert_rules.json";
+
+/// What to do when a saved script keeps failing, e.g. notify the user after 3
+/// failures in a row or run a script that repairs what it depends on.
+/// Configured with `TaskRuntime::set_alert_rule`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct AlertRule {
+    id: String,
+    /// Saved script whose runs are watched
+    script: String,
+    /// Fires once the script fails this many times in a row, and again only
+    /// after it has completed once
+    consecutive_failures: u32,
+    action: AlertAction,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertAction {
+    /// Emits a `task-alert` event for the frontend to show
+    Notify { message: Option<String> },
+    /// Runs a saved script, as an interactive task
+    RunScript {
+        script: String,
+        #[serde(default)]
+        #[ts(type = "unknown")]
+        args: Option<serde_json::Value>,
+    },
+}
+
+/// A rule that fired, emitted as `task-alert`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct Alert {
+    rule_id: String,
+    script: String,
+    /// The run that made the rule fire
+    pub(crate) task_id: String,
+    message: Option<String>,
+    /// Error of that run
+    error: String,
+}
+
+impl AlertRule {
+    fn validate(&self) -> Result<(), String> {
+        validate_task_id(&self.id).map_err(|e| format!("Invalid rule id {:?}: {}", self.id, e))?;
+        scripts::validate_name(&self.script)?;
+        if self.consecutive_failures == 0 {
+            return Err("consecutive_failures must be at least 1".to_string());
+        }
+        if let AlertAction::RunScript { script, .. } = &self.action {
+            scripts::validate_name(script)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rules by id, along with how many times in a row each script failed so far.
+#[derive(Debug, Default)]
+pub struct AlertRules {
+    rules: Option<Vec<AlertRule>>,
+    failures: HashMap<String, u32>,
+}
+
+fn rules_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(RULES_FILE)
+}
+
+impl AlertRules {
+    // Read from disk on first use
+    fn rules(&mut self, data_dir: &Path) -> &mut Vec<AlertRule> {
+        self.rules.get_or_insert_with(|| {
+            std::fs::read(rules_path(data_dir))
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn save(&mut self, data_dir: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self.rules(data_dir)).map_err(|e| e.to_string())?;
+
+        std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+        std::fs::write(rules_path(data_dir), json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&mut self, data_dir: &Path) -> Vec<AlertRule> {
+        self.rules(data_dir).clone()
+    }
+
+    /// Adds `rule`, replacing the one with the same id.
+    pub fn set(&mut self, data_dir: &Path, rule: AlertRule) -> Result<(), String> {
+        rule.validate()?;
+
+        let rules = self.rules(data_dir);
+        match rules.iter_mut().find(|existing| existing.id == rule.id) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+        self.save(data_dir)
+    }
+
+    pub fn remove(&mut self, data_dir: &Path, id: &str) -> Result<(), String> {
+        let rules = self.rules(data_dir);
+        let len = rules.len();
+        rules.retain(|rule| rule.id != id);
+        if rules.len() == len {
+            return Err(format!("Alert rule not found: {}", id));
+        }
+
+        self.save(data_dir)
+    }
+
+    // Counts the outcome of a run of `script`, returns the rules it fires
+    fn record(&mut self, data_dir: &Path, script: &str, failed: bool) -> Vec<AlertRule> {
+        if !failed {
+            self.failures.remove(script);
+            return Vec::new();
+        }
+
+        let failures = self.failures.entry(script.to_string()).or_default();
+        *failures += 1;
+        let failures = *failures;
+
+        self.rules(data_dir)
+            .iter()
+            .filter(|rule| rule.script == script && rule.consecutive_failures == failures)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Applies the rules watching the saved script a finished task ran.
+pub fn on_task_finished(runtime: &TaskRuntime, task_id: &str, script: &str) {
+    let Some(task) = runtime.get_task_state(task_id) else {
+        return;
+    };
+    // a stopped run says nothing about the script
+    let failed = match task.state() {
+        TaskState::Completed => false,
+        TaskState::Error { .. } => true,
+        _ => return,
+    };
+
+    let fired =
+        runtime
+            .inner
+            .alert_rules
+            .lock()
+            .unwrap()
+            .record(&runtime.inner.data_dir, script, failed);
+
+    for rule in fired {
+        log!(Info, "Alert rule {} fired on task {}", rule.id, task_id);
+
+        match rule.action {
+            AlertAction::Notify { message } => {
+                runtime.emit_task_event(TaskEvent::Alert(Box::new(Alert {
+                    rule_id: rule.id,
+                    script: rule.script,
+                    task_id: task_id.to_string(),
+                    message,
+                    error: task.error().to_string(),
+                })))
+            }
+            AlertAction::RunScript { script, args } => {
+                let result = resolve_task_id(None, None).and_then(|run_id| {
+                    runtime.run_saved_script(&run_id, &script, args, RunOptions::default())
+                });
+                if let Err(e) = result {
+                    log!(
+                        Error,
+                        "Alert rule {} failed to run {}: {}",
+                        rule.id,
+                        script,
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
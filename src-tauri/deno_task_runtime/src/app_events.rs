@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tauri::{AppHandle, EventId, Listener};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// An app event a task listens to, its payload as JSON.
+#[derive(Debug, serde::Serialize)]
+pub struct AppEvent {
+    event: String,
+    payload: String,
+}
+
+/// The app events a task listens to with `RuntimeExtension.listen`, in its
+/// worker's `OpState`. The listeners are removed from the app along with it.
+pub struct AppEventListeners {
+    sender: UnboundedSender<AppEvent>,
+    receiver: Rc<tokio::sync::Mutex<UnboundedReceiver<AppEvent>>>,
+    listening: Option<(AppHandle, HashMap<String, EventId>)>,
+}
+
+impl Default for AppEventListeners {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        Self {
+            sender,
+            receiver: Rc::new(tokio::sync::Mutex::new(receiver)),
+            listening: None,
+        }
+    }
+}
+
+impl AppEventListeners {
+    /// Starts delivering `event` to the task, once however many of its
+    /// listeners there are.
+    pub fn listen(&mut self, app_handle: &AppHandle, event: &str) {
+        let (app_handle, ids) = self
+            .listening
+            .get_or_insert_with(|| (app_handle.clone(), HashMap::new()));
+        if ids.contains_key(event) {
+            return;
+        }
+
+        let sender = self.sender.clone();
+        let name = event.to_string();
+        let id = app_handle.listen_any(event, move |app_event| {
+            // the task is gone
+            let _ = sender.send(AppEvent {
+                event: name.clone(),
+                payload: app_event.payload().to_string(),
+            });
+        });
+        ids.insert(event.to_string(), id);
+    }
+
+    /// Once the task has no listeners of `event` left.
+    pub fn unlisten(&mut self, event: &str) {
+        if let Some((app_handle, ids)) = &mut self.listening {
+            if let Some(id) = ids.remove(event) {
+                app_handle.unlisten(id);
+            }
+        }
+    }
+
+    pub fn receiver(&self) -> Rc<tokio::sync::Mutex<UnboundedReceiver<AppEvent>>> {
+        self.receiver.clone()
+    }
+}
+
+impl Drop for AppEventListeners {
+    fn drop(&mut self) {
+        if let Some((app_handle, ids)) = self.listening.take() {
+            for id in ids.into_values() {
+                app_handle.unlisten(id);
+            }
+        }
+    }
+}
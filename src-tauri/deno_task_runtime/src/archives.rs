@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::artifacts;
+use super::config::log;
+use super::task_history::{TaskHistoryFilter, TaskHistoryPage, TaskRecord};
+use super::task_ids::validate_task_id;
+use super::task_logs::TaskLog;
+use super::{now_ms, TaskRuntime};
+
+const ARCHIVES_DIR: &str = "archives";
+const INDEX_FILE: &str = "index.jsonl";
+
+/// A run moved out of the task history by `TaskRuntime::archive_tasks`. The
+/// last run of a task also carries its logs and artifacts.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ArchivedTask {
+    record: TaskRecord,
+    logs: Vec<TaskLog>,
+    artifacts: Vec<ArchivedArtifact>,
+}
+
+impl ArchivedTask {
+    pub fn record(&self) -> &TaskRecord {
+        &self.record
+    }
+
+    pub fn logs(&self) -> &[TaskLog] {
+        &self.logs
+    }
+
+    pub fn artifacts(&self) -> &[ArchivedArtifact] {
+        &self.artifacts
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ArchivedArtifact {
+    name: String,
+    /// Contents of the file, base64 encoded
+    data: String,
+}
+
+impl ArchivedArtifact {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn data(&self) -> Result<Vec<u8>, String> {
+        STANDARD.decode(&self.data).map_err(|e| e.to_string())
+    }
+}
+
+/// Line of the archive index, enough to find an archived run without
+/// decompressing every archive.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ArchiveEntry {
+    /// Archive to pass to `get_archived_task`
+    archive: String,
+    task_id: String,
+    script: Option<String>,
+    // missing from the entries of earlier versions
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Name of the state it ended in
+    state: String,
+    #[ts(type = "number")]
+    finished_at_ms: u64,
+}
+
+impl ArchiveEntry {
+    pub fn archive(&self) -> &str {
+        &self.archive
+    }
+
+    pub fn task_id(&self) -> &str {
+        &self.task_id
+    }
+}
+
+/// A page of the archive index, most recently finished runs first.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ArchivedTasks {
+    entries: Vec<ArchiveEntry>,
+    /// Entries matching the filter, across all pages
+    total: usize,
+}
+
+impl ArchivedTasks {
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ArchiveSummary {
+    /// `None` when no run finished early enough
+    archive: Option<String>,
+    /// Runs moved to the archive
+    tasks: usize,
+    artifacts: usize,
+}
+
+impl ArchiveSummary {
+    pub fn archive(&self) -> Option<&str> {
+        self.archive.as_deref()
+    }
+
+    pub fn tasks(&self) -> usize {
+        self.tasks
+    }
+
+    pub fn artifacts(&self) -> usize {
+        self.artifacts
+    }
+}
+
+fn archive_path(data_dir: &Path, archive: &str) -> PathBuf {
+    data_dir
+        .join(ARCHIVES_DIR)
+        .join(format!("{}.jsonl.gz", archive))
+}
+
+fn index_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(ARCHIVES_DIR).join(INDEX_FILE)
+}
+
+/// Moves the runs finished before `before_ms` out of the task history into a
+/// new compressed archive, along with the logs and artifacts of the tasks
+/// that have no run left.
+pub fn archive(runtime: &TaskRuntime, before_ms: u64) -> Result<ArchiveSummary, String> {
+    let data_dir = runtime.data_dir();
+
+    // held throughout, runs finishing meanwhile must not be dropped unarchived
+    let mut history = runtime.inner.history.lock().unwrap();
+    let records = history.finished_before(data_dir, before_ms);
+    if records.is_empty() {
+        return Ok(ArchiveSummary {
+            archive: None,
+            tasks: 0,
+            artifacts: 0,
+        });
+    }
+
+    // tasks whose every run is archived, unless they're running again
+    let last_runs: HashSet<String> = records
+        .iter()
+        .map(|record| record.task_id().to_string())
+        .filter(|task_id| !history.has_run_since(data_dir, task_id, before_ms))
+        .filter(|task_id| {
+            runtime
+                .get_task_state(task_id)
+                .is_none_or(|task| task.is_finished())
+        })
+        .collect();
+
+    let archive = format!("tasks-{}", now_ms());
+    let mut tasks = Vec::new();
+    let mut artifact_count = 0;
+    for (i, record) in records.iter().enumerate() {
+        let task_id = record.task_id();
+        let is_last = last_runs.contains(task_id)
+            && !records[i + 1..]
+                .iter()
+                .any(|later| later.task_id() == task_id);
+
+        let mut task = ArchivedTask {
+            record: record.clone(),
+            logs: Vec::new(),
+            artifacts: Vec::new(),
+        };
+        if is_last {
+            task.logs = runtime.inner.task_logs.lock().unwrap().get(task_id);
+            task.artifacts = artifacts::read_all(data_dir, task_id)
+                .map_err(|e| format!("Failed to read the artifacts of {}: {}", task_id, e))?
+                .into_iter()
+                .map(|(name, data)| ArchivedArtifact {
+                    name,
+                    data: STANDARD.encode(data),
+                })
+                .collect();
+            artifact_count += task.artifacts.len();
+        }
+        tasks.push(task);
+    }
+
+    write_archive(data_dir, &archive, &tasks)?;
+    append_to_index(data_dir, &archive, &records)?;
+    history.remove_finished_before(data_dir, before_ms)?;
+    drop(history);
+
+    // only once they're safely in the archive
+    let archived: Vec<String> = last_runs.into_iter().collect();
+    for task_id in &archived {
+        artifacts::remove_all(data_dir, task_id);
+    }
+    runtime.forget_finished_tasks(&archived);
+    log!(
+        Info,
+        "Archived {} runs to {}",
+        records.len(),
+        archive_path(data_dir, &archive).display()
+    );
+
+    Ok(ArchiveSummary {
+        archive: Some(archive),
+        tasks: records.len(),
+        artifacts: artifact_count,
+    })
+}
+
+fn write_archive(data_dir: &Path, archive: &str, tasks: &[ArchivedTask]) -> Result<(), String> {
+    let path = archive_path(data_dir, archive);
+    std::fs::create_dir_all(data_dir.join(ARCHIVES_DIR)).map_err(|e| e.to_string())?;
+
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    for task in tasks {
+        serde_json::to_writer(&mut encoder, task).map_err(|e| e.to_string())?;
+        encoder.write_all(b"\n").map_err(|e| e.to_string())?;
+    }
+
+    encoder
+        .finish()
+        .and_then(|file| file.sync_all())
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn append_to_index(data_dir: &Path, archive: &str, records: &[TaskRecord]) -> Result<(), String> {
+    let mut lines = Vec::new();
+    for record in records {
+        let entry = ArchiveEntry {
+            archive: archive.to_string(),
+            task_id: record.task_id().to_string(),
+            script: record.script().map(str::to_string),
+            tags: record.tags().to_vec(),
+            state: record.state().name().to_string(),
+            finished_at_ms: record.finished_at_ms(),
+        };
+        serde_json::to_writer(&mut lines, &entry).map_err(|e| e.to_string())?;
+        lines.push(b'\n');
+    }
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(index_path(data_dir))
+        .and_then(|mut file| file.write_all(&lines))
+        .map_err(|e| e.to_string())
+}
+
+/// Searches the archive index, most recently finished runs first.
+pub fn search(
+    data_dir: &Path,
+    filter: &TaskHistoryFilter,
+    page: &TaskHistoryPage,
+) -> ArchivedTasks {
+    let (offset, limit) = page.bounds();
+    let index = std::fs::read_to_string(index_path(data_dir)).unwrap_or_default();
+    let mut matching: Vec<ArchiveEntry> = index
+        .lines()
+        .filter_map(|line| serde_json::from_str::<ArchiveEntry>(line).ok())
+        .filter(|entry| {
+            filter.matches_run(
+                &entry.task_id,
+                entry.script.as_deref(),
+                &entry.tags,
+                &entry.state,
+                entry.finished_at_ms,
+            )
+        })
+        .collect();
+    // archives are appended oldest runs first
+    matching.sort_by_key(|entry| std::cmp::Reverse(entry.finished_at_ms));
+
+    ArchivedTasks {
+        total: matching.len(),
+        entries: matching.into_iter().skip(offset).take(limit).collect(),
+    }
+}
+
+/// The runs of `task_id` in `archive`, oldest first.
+pub fn read(data_dir: &Path, archive: &str, task_id: &str) -> Result<Vec<ArchivedTask>, String> {
+    // ends up in a path
+    validate_task_id(archive).map_err(|_| format!("Invalid archive {:?}", archive))?;
+
+    let path = archive_path(data_dir, archive);
+    let file = std::fs::File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut tasks = Vec::new();
+    for line in BufReader::new(GzDecoder::new(file)).lines() {
+        let line = line.map_err(|e| format!("{}: {}", path.display(), e))?;
+        let task: ArchivedTask = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+        if task.record.task_id() == task_id {
+            tasks.push(task);
+        }
+    }
+
+    if tasks.is_empty() {
+        return Err(format!("Task {} isn't in archive {}", task_id, archive));
+    }
+
+    Ok(tasks)
+}
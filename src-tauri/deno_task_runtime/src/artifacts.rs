@@ -0,0 +1,185 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use tauri::http::{header, Request, Response, StatusCode};
+
+use super::config::log;
+use super::result_protocol::{error, parse_range, profile_param, respond};
+use super::task_ids::validate_task_id;
+use super::Profiles;
+
+/// Scheme to register `task_artifact_response` under.
+pub const TASK_ARTIFACT_SCHEME: &str = "task-artifact";
+
+const ARTIFACTS_DIR: &str = "artifacts";
+
+// Types sniffing can't tell apart from plain text
+const TEXT_TYPES: &[(&str, &str)] = &[
+    ("svg", "image/svg+xml"),
+    ("json", "application/json"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("txt", "text/plain; charset=utf-8"),
+];
+
+fn artifact_path(data_dir: &Path, task_id: &str, name: &str) -> PathBuf {
+    data_dir.join(ARTIFACTS_DIR).join(task_id).join(name)
+}
+
+/// Names end up in paths, they follow the rules of task ids.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    validate_task_id(name).map_err(|_| format!("Invalid artifact name {:?}", name))
+}
+
+pub fn save(data_dir: &Path, task_id: &str, name: &str, data: &[u8]) -> std::io::Result<()> {
+    let path = artifact_path(data_dir, task_id, name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, data)
+}
+
+/// The artifacts of a task by name, none if it saved none.
+pub fn read_all(data_dir: &Path, task_id: &str) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+    let entries = match std::fs::read_dir(data_dir.join(ARTIFACTS_DIR).join(task_id)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut artifacts = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        artifacts.push((name, std::fs::read(entry.path())?));
+    }
+    artifacts.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(artifacts)
+}
+
+/// Ids of the tasks that saved artifacts.
+pub fn task_ids(data_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(data_dir.join(ARTIFACTS_DIR)) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Removes the artifacts of a task, e.g. once it's cleared.
+pub fn remove_all(data_dir: &Path, task_id: &str) {
+    let dir = data_dir.join(ARTIFACTS_DIR).join(task_id);
+
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log!(Error, "Failed to remove {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Serves the files tasks saved with `RuntimeExtension.saveArtifact(name,
+/// data)`, so `<img>` and `<video>` tags can show them. The frontend gets the
+/// URL with `convertFileSrc("<task_id>/<name>", "task-artifact")`, adding
+/// `?profile=<name>` for tasks of another profile than the default one. Byte
+/// ranges are supported, video elements rely on them.
+///
+/// Only `window`, the one making the request, can load the artifacts of the
+/// tasks it started. Tasks owned by the app share theirs with every window.
+pub fn task_artifact_response(
+    profiles: &Profiles,
+    window: &str,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let path = request.uri().path().trim_matches('/');
+    let Some((task_id, name)) = path.split_once('/') else {
+        return error(StatusCode::NOT_FOUND, format!("No such artifact: {}", path));
+    };
+
+    if let Err(e) = validate_task_id(task_id).and_then(|_| validate_name(name)) {
+        return error(StatusCode::BAD_REQUEST, e);
+    }
+
+    let runtime = match profiles.get(profile_param(request)) {
+        Ok(runtime) => runtime,
+        Err(e) => return error(StatusCode::NOT_FOUND, e),
+    };
+
+    // not found either way, so windows can't probe for other windows' tasks
+    let not_found = || error(StatusCode::NOT_FOUND, format!("No such artifact: {}", path));
+
+    let Some(task) = runtime.get_task_state(task_id) else {
+        return not_found();
+    };
+
+    let owned_by_window = task
+        .owner_window
+        .as_deref()
+        .is_none_or(|owner| owner == window);
+    if !owned_by_window || !task.artifacts.iter().any(|artifact| artifact == name) {
+        return not_found();
+    }
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok());
+
+    match read(&artifact_path(runtime.data_dir(), task_id, name), range) {
+        Ok(response) => response,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => not_found(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+// Reads only the requested range, artifacts can be whole videos
+fn read(path: &Path, range: Option<&str>) -> std::io::Result<Response<Vec<u8>>> {
+    let content_type = content_type(path)?;
+
+    let mut file = File::open(path)?;
+    let len = usize::try_from(file.metadata()?.len()).map_err(std::io::Error::other)?;
+
+    let (status, range) = match range.map(|range| parse_range(range, len)) {
+        None => (StatusCode::OK, 0..len),
+        Some(Some(range)) => (StatusCode::PARTIAL_CONTENT, range),
+        Some(None) => {
+            return respond(StatusCode::RANGE_NOT_SATISFIABLE, &content_type)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .body(Vec::new())
+                .map_err(std::io::Error::other);
+        }
+    };
+
+    let mut body = vec![0; range.len()];
+    file.seek(SeekFrom::Start(range.start as u64))?;
+    file.read_exact(&mut body)?;
+
+    let mut response = respond(status, &content_type).header(header::CONTENT_LENGTH, body.len());
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", range.start, range.end - 1, len),
+        );
+    }
+
+    response.body(body).map_err(std::io::Error::other)
+}
+
+fn content_type(path: &Path) -> std::io::Result<String> {
+    if let Some(kind) = infer::get_from_path(path)? {
+        return Ok(kind.mime_type().to_string());
+    }
+
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let text_type = TEXT_TYPES
+        .iter()
+        .find(|(known, _)| Some(*known) == extension)
+        .map(|(_, content_type)| content_type.to_string());
+
+    Ok(text_type.unwrap_or_else(|| "application/octet-stream".to_string()))
+}
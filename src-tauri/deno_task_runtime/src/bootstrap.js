@@ -0,0 +1,397 @@
+import { core } from "ext:core/mod.js";
+import { stdin } from "ext:deno_io/12_io.js";
+import {
+  next_host_event,
+  return_value,
+  document_dir,
+  run_options,
+  capture_log,
+  task_args,
+  task_input,
+  cassette_record,
+  cassette_replay,
+  save_checkpoint,
+  save_artifact,
+  bridge_store_get,
+  bridge_store_set,
+  waiting_for_input,
+  report_progress,
+  next_task_message,
+  post_message,
+  emit_event,
+  listen_app_event,
+  unlisten_app_event,
+  next_app_event,
+} from "ext:core/ops";
+
+function returnValue(value) {
+  // `undefined` and functions have no JSON, they're returned as `null`
+  return_value(JSON.stringify(value) ?? "null");
+}
+
+function documentDir() {
+  return document_dir();
+}
+
+// Virtual clock and seeded Math.random for reproducible runs. Timers still
+// fire in real time, only the values scripts read are deterministic.
+function installDeterminism(epochMs, seed) {
+  let ticks = 0;
+  const now = () => epochMs + ticks++;
+
+  const RealDate = Date;
+  class DeterministicDate extends RealDate {
+    constructor(...args) {
+      if (args.length === 0) {
+        super(now());
+      } else {
+        super(...args);
+      }
+    }
+
+    static now() {
+      return now();
+    }
+  }
+  globalThis.Date = DeterministicDate;
+
+  const performanceStart = now();
+  performance.now = () => now() - performanceStart;
+
+  // mulberry32
+  let state = Number(BigInt(seed) % 4294967296n) >>> 0;
+  Math.random = () => {
+    state = (state + 0x6d2b79f5) >>> 0;
+    let t = state;
+    t = Math.imul(t ^ (t >>> 15), t | 1);
+    t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+  };
+}
+
+// Wraps fetch to record responses to the task's cassette, or to serve them
+// from it. Only fetch is covered, module imports are not recorded.
+function installCassette(mode) {
+  const realFetch = globalThis.fetch;
+
+  globalThis.fetch = async function fetch(input, init) {
+    const request = new Request(input, init);
+
+    if (mode === "replay") {
+      const recorded = cassette_replay(request.method, request.url);
+      if (recorded === null) {
+        throw new TypeError(
+          `No recorded response for ${request.method} ${request.url}`,
+        );
+      }
+
+      // these statuses can't be constructed with a body
+      const nullBody = [101, 204, 205, 304].includes(recorded.status);
+      return new Response(nullBody ? null : recorded.body, {
+        status: recorded.status,
+        statusText: recorded.status_text,
+        headers: recorded.headers,
+      });
+    }
+
+    const response = await realFetch(request);
+    const body = await response.clone().arrayBuffer();
+    cassette_record(
+      {
+        method: request.method,
+        url: request.url,
+        status: response.status,
+        status_text: response.statusText,
+        headers: [...response.headers],
+      },
+      new Uint8Array(body),
+    );
+
+    return response;
+  };
+}
+
+// A console argument as JSON, see `TaskLog`
+function serializeLogArg(value) {
+  if (value instanceof Error) {
+    return JSON.stringify({
+      name: value.name,
+      message: value.message,
+      stack: value.stack ?? null,
+    });
+  }
+
+  try {
+    const json = JSON.stringify(value, (_key, value) =>
+      typeof value === "bigint" ? value.toString() : value
+    );
+    if (json !== undefined) {
+      return json;
+    }
+  } catch {
+    // cycles
+  }
+
+  return JSON.stringify(Deno.inspect(value));
+}
+
+// Reports console calls to the host as structured logs, they're still
+// printed as usual
+for (const level of ["debug", "log", "info", "warn", "error"]) {
+  const print = console[level];
+  console[level] = function (...args) {
+    capture_log(level, `[${args.map(serializeLogArg).join(",")}]`);
+    return print.apply(this, args);
+  };
+}
+
+const options = run_options();
+
+if (options.deterministic_epoch_ms !== null) {
+  installDeterminism(options.deterministic_epoch_ms, options.rng_seed);
+}
+
+if (options.cassette_mode !== null) {
+  installCassette(options.cassette_mode);
+}
+
+// Saves state a resumed run will find in RuntimeExtension.checkpointState
+function saveCheckpoint(state) {
+  save_checkpoint(JSON.stringify(state));
+}
+
+// Saves a file the app's windows can show, e.g. in an <img> tag, from a
+// string or bytes
+function saveArtifact(name, data) {
+  const bytes =
+    typeof data === "string" ? new TextEncoder().encode(data) : data;
+  save_artifact(name, bytes);
+}
+
+// Latest progress of the task for the app's progress bars, from 0 to 100
+function reportProgress(percent, message = "") {
+  report_progress(percent, String(message));
+}
+
+// Key-value store of the app, the first use in a task asks the user
+const store = {
+  get(key) {
+    return bridge_store_get(key) ?? undefined;
+  },
+  set(key, value) {
+    bridge_store_set(key, value);
+  },
+};
+
+// Listeners by host event name
+const hostEventListeners = new Map();
+let listeningToHost = false;
+
+async function listenToHost() {
+  while (true) {
+    const next = next_host_event();
+    // waiting for the host doesn't keep the task running
+    core.unrefOpPromise(next);
+
+    const event = await next;
+    if (event === null) {
+      return;
+    }
+
+    for (const listener of hostEventListeners.get(event) ?? []) {
+      queueMicrotask(() => listener(event));
+    }
+  }
+}
+
+// Calls `listener` on events from the app, e.g. "memory-pressure" when it's
+// short on memory. Returns a function removing the listener.
+function onHostEvent(event, listener) {
+  if (!hostEventListeners.has(event)) {
+    hostEventListeners.set(event, new Set());
+  }
+  hostEventListeners.get(event).add(listener);
+
+  if (!listeningToHost) {
+    listeningToHost = true;
+    listenToHost();
+  }
+
+  return () => hostEventListeners.get(event).delete(listener);
+}
+
+const messageListeners = new Set();
+let listeningToMessages = false;
+
+async function listenToMessages() {
+  while (true) {
+    const next = next_task_message();
+    // like host events, waiting for messages doesn't keep the task running
+    core.unrefOpPromise(next);
+
+    const message = await next;
+    if (message === null) {
+      return;
+    }
+
+    const data = JSON.parse(message);
+    for (const listener of messageListeners) {
+      queueMicrotask(() => listener(data));
+    }
+  }
+}
+
+// Calls `listener` with the messages the app posts to the task. Returns a
+// function removing the listener.
+function onMessage(listener) {
+  messageListeners.add(listener);
+
+  if (!listeningToMessages) {
+    listeningToMessages = true;
+    listenToMessages();
+  }
+
+  return () => messageListeners.delete(listener);
+}
+
+// Sends a message to the app's windows as a `task-message` event
+function postMessage(message) {
+  post_message(JSON.stringify(message) ?? "null");
+}
+
+// Emits `event` to the app's windows with `{ task_id, payload }`, the first
+// use in a task asks the user
+function emit(event, payload) {
+  emit_event(event, JSON.stringify(payload) ?? "null");
+}
+
+// Listeners by app event name
+const appEventListeners = new Map();
+let listeningToApp = false;
+
+async function listenToApp() {
+  while (true) {
+    const next = next_app_event();
+    // like host events, listening doesn't keep the task running
+    core.unrefOpPromise(next);
+
+    const appEvent = await next;
+    if (appEvent === null) {
+      return;
+    }
+
+    const payload = appEvent.payload ? JSON.parse(appEvent.payload) : null;
+    for (const listener of appEventListeners.get(appEvent.event) ?? []) {
+      queueMicrotask(() => listener(payload));
+    }
+  }
+}
+
+// Calls `listener` with the payload of `event` each time the app emits it,
+// the first use in a task asks the user. Returns a function removing the
+// listener.
+function listen(event, listener) {
+  if (!appEventListeners.has(event)) {
+    listen_app_event(event);
+    appEventListeners.set(event, new Set());
+  }
+  const listeners = appEventListeners.get(event);
+  listeners.add(listener);
+
+  if (!listeningToApp) {
+    listeningToApp = true;
+    listenToApp();
+  }
+
+  return () => {
+    listeners.delete(listener);
+    if (listeners.size === 0 && appEventListeners.get(event) === listeners) {
+      appEventListeners.delete(event);
+      unlisten_app_event(event);
+    }
+  };
+}
+
+// A line of stdin without its line break, `null` once stdin is closed and
+// nothing is left
+function readLine() {
+  const byte = new Uint8Array(1);
+  const line = [];
+
+  while (true) {
+    const n = stdin.readSync(byte);
+    if (n === null || n === 0) {
+      if (line.length === 0) {
+        return null;
+      }
+      break;
+    }
+    if (byte[0] === 0x0a) {
+      break;
+    }
+    line.push(byte[0]);
+  }
+
+  if (line[line.length - 1] === 0x0d) {
+    line.pop();
+  }
+  return core.decode(new Uint8Array(line));
+}
+
+function ask(message) {
+  core.print(message, false);
+  // shown on the task as what it waits on until answered
+  waiting_for_input(message.trim());
+  try {
+    return readLine();
+  } finally {
+    waiting_for_input("");
+  }
+}
+
+// Deno's `alert`, `confirm` and `prompt` only read stdin when it's a
+// terminal, a task's is a pipe the app writes with `write_task_stdin`. They
+// are defined on bootstrap, after this module runs, so the host installs
+// these ones then.
+globalThis[Symbol.for("runtimeExtension.installPrompts")] = () => {
+  globalThis.alert = (message = "Alert") => {
+    ask(`${message} [Enter] `);
+  };
+
+  globalThis.confirm = (message = "Confirm") => {
+    const answer = ask(`${message} [y/N] `);
+    return answer === "y" || answer === "Y";
+  };
+
+  globalThis.prompt = (message = "Prompt", defaultValue) => {
+    const answer = ask(`${message} `);
+    if (answer === null) {
+      return null;
+    }
+    return answer === "" ? (defaultValue ?? "") : answer;
+  };
+};
+
+globalThis.RuntimeExtension = {
+  returnValue,
+  onHostEvent,
+  onMessage,
+  postMessage,
+  emit,
+  listen,
+  documentDir,
+  checkpoint: saveCheckpoint,
+  saveArtifact,
+  reportProgress,
+  store,
+  checkpointState:
+    options.checkpoint_state === null
+      ? undefined
+      : JSON.parse(options.checkpoint_state),
+  get args() {
+    return task_args() ?? undefined;
+  },
+  get input() {
+    return task_input() ?? undefined;
+  },
+};
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use deno_runtime::deno_core::error::{custom_error, AnyError};
+use deno_runtime::deno_core::OpState;
+use deno_runtime::deno_permissions::PromptResponse;
+
+use super::prompts::prompt_current_task;
+use super::PermissionPrompt;
+
+// Shown as the permission name in prompts, next to deno's `read`, `net`, ...
+const BRIDGE_PERMISSION: &str = "bridge";
+// Apart from the bridge, its "Allow All" doesn't let a script send events
+const EMIT_PERMISSION: &str = "emit";
+// Nor hear the ones of the app
+const LISTEN_PERMISSION: &str = "listen";
+
+/// A host capability scripts reach through the bridge ops. The first use of
+/// each one in a task is confirmed by the user with the usual permission
+/// prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeCapability {
+    InvokeHost,
+    Store,
+    Clipboard,
+    Dialog,
+    /// Sending app events with `RuntimeExtension.emit`
+    Emit,
+    /// Receiving app events with `RuntimeExtension.listen`
+    Listen,
+}
+
+impl BridgeCapability {
+    /// Descriptor of the capability in prompts and the permission history.
+    pub fn descriptor(&self) -> &'static str {
+        match self {
+            BridgeCapability::InvokeHost => "invoke_host",
+            BridgeCapability::Store => "store",
+            BridgeCapability::Clipboard => "clipboard",
+            BridgeCapability::Dialog => "dialog",
+            BridgeCapability::Emit => "emit",
+            BridgeCapability::Listen => "listen",
+        }
+    }
+
+    /// Name of the permission it's prompted for.
+    pub fn permission(&self) -> &'static str {
+        match self {
+            BridgeCapability::Emit => EMIT_PERMISSION,
+            BridgeCapability::Listen => LISTEN_PERMISSION,
+            _ => BRIDGE_PERMISSION,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            BridgeCapability::Emit => "sending events to the app".to_string(),
+            BridgeCapability::Listen => "receiving events from the app".to_string(),
+            _ => format!("{} access through the app bridge", self.descriptor()),
+        }
+    }
+}
+
+/// The user's answers for the task running on an `OpState`.
+#[derive(Debug, Default)]
+pub struct BridgePermissions {
+    granted: HashSet<BridgeCapability>,
+    denied: HashSet<BridgeCapability>,
+    /// Permissions answered with "Allow All"
+    all_granted: HashSet<&'static str>,
+}
+
+/// Prompts on the first use of `capability` in the task, later uses get the
+/// same answer. "Allow All" grants every capability of the same permission.
+///
+/// Blocks the task thread while the prompt is pending, like deno's own
+/// permission checks.
+pub fn check(
+    state: &mut OpState,
+    capability: BridgeCapability,
+    api_name: &str,
+) -> Result<(), AnyError> {
+    let permissions = state.borrow_mut::<BridgePermissions>();
+
+    if permissions.all_granted.contains(capability.permission())
+        || permissions.granted.contains(&capability)
+    {
+        return Ok(());
+    }
+
+    if !permissions.denied.contains(&capability) {
+        let prompt = PermissionPrompt::new(
+            capability.description(),
+            capability.permission(),
+            Some(api_name),
+            false,
+        );
+
+        match prompt_current_task(prompt) {
+            PromptResponse::Allow => {
+                permissions.granted.insert(capability);
+                return Ok(());
+            }
+            PromptResponse::AllowAll => {
+                permissions.all_granted.insert(capability.permission());
+                return Ok(());
+            }
+            PromptResponse::Deny => {
+                permissions.denied.insert(capability);
+            }
+        }
+    }
+
+    Err(custom_error(
+        "PermissionDenied",
+        format!(
+            "Requires {}, denied for {}",
+            capability.description(),
+            api_name
+        ),
+    ))
+}
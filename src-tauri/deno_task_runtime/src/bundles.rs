@@ -0,0 +1,298 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use deno_ast::swc::ast::ModuleDecl;
+use deno_ast::{MediaType, ModuleItemRef, ParseParams};
+use deno_runtime::deno_core::{resolve_import, ModuleSpecifier};
+use serde_json::Value;
+
+use super::catalog::sha256_hex;
+use super::config;
+use super::module_loader::{load_remote_source, ModuleLoader, ModuleLock};
+use super::op_grants::OpGrant;
+use super::signatures::{self, BundleSignature};
+use super::{script_inputs, scripts};
+
+const BUNDLE_FORMAT: &str = "denotask";
+
+// Bumped on changes older runtimes can't read
+const BUNDLE_VERSION: u32 = 1;
+
+/// A saved script in a single `.denotask` file, with what it needs to run
+/// the same way on another machine.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct ScriptBundle {
+    format: String,
+    version: u32,
+    metadata: BundleMetadata,
+    source: String,
+    /// Hex SHA-256 of the remote modules the script imports, directly or
+    /// through each other
+    #[serde(default)]
+    lockfile: ModuleLock,
+    /// JSON schema of its args, see `script_inputs`
+    #[serde(default)]
+    input_schema: Option<Value>,
+    /// Grants its runs get unless they set their own, all of them if unset
+    #[serde(default)]
+    grants: Option<Vec<OpGrant>>,
+    /// ed25519 signature of the above, see `signatures::signed_message`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<BundleSignature>,
+}
+
+impl ScriptBundle {
+    fn signed_message(&self) -> Vec<u8> {
+        signatures::signed_message(
+            &self.metadata.name,
+            &self.source,
+            &self.lockfile,
+            self.grants.as_deref(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct BundleMetadata {
+    /// Name it's saved under once imported
+    name: String,
+    /// Version of the runtime that exported it
+    runtime_version: String,
+    exported_at_ms: u64,
+}
+
+// Static imports and re-exports, type-only ones are gone once transpiled
+fn imports(specifier: &ModuleSpecifier, code: &str) -> Result<Vec<String>, String> {
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier: specifier.clone(),
+        text: code.into(),
+        media_type: MediaType::TypeScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .map_err(|e| format!("Failed to parse {}: {}", specifier, e))?;
+
+    let mut imports = Vec::new();
+    for item in parsed.program_ref().body() {
+        let ModuleItemRef::ModuleDecl(decl) = item else {
+            continue;
+        };
+
+        let src = match decl {
+            ModuleDecl::Import(import) if !import.type_only => Some(&import.src),
+            ModuleDecl::ExportAll(export) if !export.type_only => Some(&export.src),
+            ModuleDecl::ExportNamed(export) if !export.type_only => export.src.as_ref(),
+            _ => None,
+        };
+        if let Some(src) = src {
+            imports.push(src.value.to_string());
+        }
+    }
+
+    Ok(imports)
+}
+
+// Hashes of the remote modules `code` loads, checked against `pinned` for
+// those already locked
+fn lock_imports(
+    name: &str,
+    code: &str,
+    pinned: &ModuleLock,
+    custom_loader: Option<&dyn ModuleLoader>,
+) -> Result<ModuleLock, String> {
+    let main_module =
+        ModuleSpecifier::parse(&format!("file:///{}.ts", name)).map_err(|e| e.to_string())?;
+    let mut pending: Vec<(String, String)> = imports(&main_module, code)?
+        .into_iter()
+        .map(|import| (import, main_module.to_string()))
+        .collect();
+
+    let mut lock = ModuleLock::new();
+    while let Some((import, referrer)) = pending.pop() {
+        let specifier: ModuleSpecifier = resolve_import(&import, &referrer)
+            .map_err(|e| format!("Invalid import {:?}: {}", import, e))?;
+        if specifier.scheme() == "file" {
+            return Err(format!(
+                "{} imports {:?}, local modules can't be bundled",
+                name, import
+            ));
+        }
+        if lock.contains_key(specifier.as_str()) {
+            continue;
+        }
+
+        let source = load_remote_source(custom_loader, &specifier)
+            .map_err(|e| format!("Failed to load {}: {}", specifier, e))?;
+        let sha256 = sha256_hex(&source);
+        if pinned
+            .get(specifier.as_str())
+            .is_some_and(|locked| !locked.eq_ignore_ascii_case(&sha256))
+        {
+            return Err(format!(
+                "{} doesn't match the hash in the lockfile",
+                specifier
+            ));
+        }
+
+        pending.extend(
+            imports(&specifier, &source)?
+                .into_iter()
+                .map(|import| (import, specifier.to_string())),
+        );
+        lock.insert(specifier.to_string(), sha256);
+    }
+
+    Ok(lock)
+}
+
+/// Writes the saved script `name` to a bundle at `path`, locking the remote
+/// modules it imports to their current source. Blocks while they're fetched.
+pub fn export_script_bundle(
+    data_dir: &Path,
+    name: &str,
+    path: &Path,
+    custom_loader: Option<&dyn ModuleLoader>,
+) -> Result<(), String> {
+    scripts::validate_name(name)?;
+    let source = scripts::load(data_dir, name)
+        .map_err(|e| format!("Failed to load script {}: {}", name, e))?;
+
+    let lockfile = lock_imports(
+        name,
+        &source,
+        &scripts::load_lock(data_dir, name),
+        custom_loader,
+    )?;
+
+    let mut bundle = ScriptBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        metadata: BundleMetadata {
+            name: name.to_string(),
+            runtime_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        },
+        input_schema: script_inputs::script_inputs(name, &source)?,
+        grants: scripts::load_grants(data_dir, name),
+        lockfile,
+        source,
+        signature: None,
+    };
+
+    // still signed if it's exported the way it was imported
+    bundle.signature = scripts::load_signature(data_dir, name).filter(|signature| {
+        signatures::verify(data_dir, signature, &bundle.signed_message()).is_ok()
+    });
+
+    write_bundle(path, &bundle)
+}
+
+fn write_bundle(path: &Path, bundle: &ScriptBundle) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(bundle).map_err(|e| e.to_string())?;
+    std::fs::write(path, json)
+        .map_err(|e| format!("Failed to write bundle {}: {}", path.display(), e))
+}
+
+fn read_bundle(path: &Path) -> Result<ScriptBundle, String> {
+    let json = std::fs::read(path)
+        .map_err(|e| format!("Failed to read bundle {}: {}", path.display(), e))?;
+    let bundle: ScriptBundle = serde_json::from_slice(&json)
+        .map_err(|e| format!("Invalid bundle {}: {}", path.display(), e))?;
+
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(format!("{} isn't a script bundle", path.display()));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle version {} needs a newer runtime, this one reads up to {}",
+            bundle.version, BUNDLE_VERSION
+        ));
+    }
+
+    Ok(bundle)
+}
+
+/// Signs the bundle at `path` with `private_key`, in place. Those importing
+/// it check the signature against their trusted keys.
+pub fn sign_script_bundle(path: &Path, private_key: &str) -> Result<(), String> {
+    let mut bundle = read_bundle(path)?;
+    bundle.signature = Some(signatures::sign(private_key, &bundle.signed_message())?);
+
+    write_bundle(path, &bundle)
+}
+
+/// Checks the signature of a saved script imported from a signed bundle
+/// before it runs, it fails once the script was changed on disk or its key is
+/// no longer trusted.
+pub fn check_signature(data_dir: &Path, name: &str, source: &str) -> Result<(), String> {
+    let Some(signature) = scripts::load_signature(data_dir, name) else {
+        return Ok(());
+    };
+
+    let message = signatures::signed_message(
+        name,
+        source,
+        &scripts::load_lock(data_dir, name),
+        scripts::load_grants(data_dir, name).as_deref(),
+    );
+    signatures::verify(data_dir, &signature, &message)
+        .map(|_| ())
+        .map_err(|e| format!("Script {} can't run: {}", name, e))
+}
+
+/// Saves the script of the bundle at `path` along with its grants and
+/// lockfile, returns the name it's saved under. A saved script with the same
+/// name isn't replaced, and a signed bundle needs a trusted key.
+pub fn import_script_bundle(data_dir: &Path, path: &Path) -> Result<String, String> {
+    let bundle = read_bundle(path)?;
+
+    match &bundle.signature {
+        Some(signature) => {
+            signatures::verify(data_dir, signature, &bundle.signed_message())
+                .map_err(|e| format!("Refused bundle {}: {}", path.display(), e))?;
+        }
+        None if config::get().require_signed_bundles() => {
+            return Err(format!(
+                "Refused bundle {}: it isn't signed, and only signed bundles can be imported",
+                path.display()
+            ));
+        }
+        None => {}
+    }
+
+    let name = bundle.metadata.name;
+    scripts::validate_name(&name)?;
+    if scripts::load(data_dir, &name).is_ok() {
+        return Err(format!("A script named {} is already saved", name));
+    }
+
+    for (url, sha256) in &bundle.lockfile {
+        ModuleSpecifier::parse(url).map_err(|e| format!("Invalid lockfile URL {}: {}", url, e))?;
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid lockfile hash of {}: {}", url, sha256));
+        }
+    }
+    if script_inputs::script_inputs(&name, &bundle.source)? != bundle.input_schema {
+        return Err(format!(
+            "The input schema of {} doesn't match its source",
+            name
+        ));
+    }
+
+    let save = || {
+        scripts::save(data_dir, &name, &bundle.source)?;
+        scripts::save_grants(data_dir, &name, bundle.grants.as_deref())?;
+        scripts::save_lock(data_dir, &name, &bundle.lockfile)?;
+        match &bundle.signature {
+            Some(signature) => scripts::save_signature(data_dir, &name, signature),
+            None => scripts::remove_signature(data_dir, &name),
+        }
+    };
+    save().map_err(|e| format!("Failed to save script {}: {}", name, e))?;
+
+    Ok(name)
+}
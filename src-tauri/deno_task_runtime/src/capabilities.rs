@@ -0,0 +1,130 @@
+use deno_runtime::deno_core::v8;
+use deno_runtime::deno_kv;
+use deno_runtime::deno_webgpu;
+
+use super::features::{self, is_unstable_feature_enabled};
+
+/// An optional runtime feature and whether tasks can use it in this build.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Capability {
+    name: String,
+    available: bool,
+    /// Version of the crate implementing it, when it's compiled in
+    version: Option<String>,
+    /// Limitations worth showing next to the feature, e.g. why it's missing
+    caveats: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RuntimeCapabilities {
+    deno_runtime: String,
+    v8: String,
+    os: String,
+    arch: String,
+    capabilities: Vec<Capability>,
+}
+
+/// Reports which optional features tasks can use, so the frontend can hide
+/// what this build doesn't support.
+pub fn get_runtime_capabilities() -> RuntimeCapabilities {
+    RuntimeCapabilities {
+        deno_runtime: env!("DENO_RUNTIME_VERSION").to_string(),
+        v8: v8::V8::get_version().to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        capabilities: vec![
+            npm(),
+            kv(),
+            webgpu(),
+            node_compat(),
+            image_ops(),
+            inspector(),
+        ],
+    }
+}
+
+// The module loader only resolves file and remote URLs
+fn npm() -> Capability {
+    Capability {
+        name: "npm".to_string(),
+        available: false,
+        version: None,
+        caveats: vec!["npm: specifiers are not resolved by the module loader".to_string()],
+    }
+}
+
+// The extension is part of every worker, the API is only exposed with the
+// `kv` cargo feature
+fn kv() -> Capability {
+    let available = is_unstable_feature_enabled(deno_kv::UNSTABLE_FEATURE_NAME);
+
+    let mut caveats = Vec::new();
+    if !available {
+        caveats.push("built without the kv feature".to_string());
+    }
+
+    Capability {
+        name: "kv".to_string(),
+        available,
+        version: Some(env!("DENO_KV_VERSION").to_string()),
+        caveats,
+    }
+}
+
+fn webgpu() -> Capability {
+    let available = is_unstable_feature_enabled(deno_webgpu::UNSTABLE_FEATURE_NAME);
+
+    let mut caveats = Vec::new();
+    if !available {
+        caveats.push("built without the webgpu feature".to_string());
+    }
+
+    if cfg!(any(target_os = "android", target_os = "ios")) {
+        caveats.push("WebGPU is not supported on mobile".to_string());
+    } else if cfg!(target_os = "linux") {
+        caveats.push("WebGPU needs a Vulkan driver on Linux".to_string());
+    }
+
+    Capability {
+        name: "webgpu".to_string(),
+        available,
+        version: Some(env!("DENO_WEBGPU_VERSION").to_string()),
+        caveats,
+    }
+}
+
+// deno_node is compiled in but the worker runs without node services
+fn node_compat() -> Capability {
+    Capability {
+        name: "node_compat".to_string(),
+        available: false,
+        version: Some(env!("DENO_NODE_VERSION").to_string()),
+        caveats: vec!["node: specifiers and the Node.js globals are not set up".to_string()],
+    }
+}
+
+fn image_ops() -> Capability {
+    Capability {
+        name: "image_ops".to_string(),
+        available: true,
+        version: Some(env!("DENO_CANVAS_VERSION").to_string()),
+        caveats: vec!["createImageBitmap only decodes PNG images".to_string()],
+    }
+}
+
+fn inspector() -> Capability {
+    let server = features::inspector_server();
+
+    let caveats = match &server {
+        Some(server) => vec![format!("listening on {}", server.host)],
+        None if cfg!(feature = "inspector") => vec!["failed to start".to_string()],
+        None => vec!["built without the inspector feature".to_string()],
+    };
+
+    Capability {
+        name: "inspector".to_string(),
+        available: server.is_some(),
+        version: None,
+        caveats,
+    }
+}
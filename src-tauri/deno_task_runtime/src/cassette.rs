@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use deno_runtime::deno_core::error::AnyError;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CassetteMode {
+    /// Requests hit the network and every response is saved to the cassette
+    Record,
+    /// Responses are served from the cassette, nothing hits the network
+    Replay,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct CassetteOptions {
+    name: String,
+    mode: CassetteMode,
+}
+
+impl CassetteOptions {
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+}
+
+/// Response metadata sent by the `fetch` wrapper in `bootstrap.js`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordedResponse {
+    method: String,
+    url: String,
+    status: u16,
+    status_text: String,
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct Interaction {
+    #[serde(flatten)]
+    response: RecordedResponse,
+    // base64 so binary bodies survive the JSON file
+    body: String,
+}
+
+/// A task's recorded HTTP interactions, saved as `cassettes/<name>.jsonl` in
+/// the data directory of the task's profile, one interaction per line.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    // opened on the first recorded interaction
+    file: Option<File>,
+    interactions: Vec<Interaction>,
+    replayed: Vec<bool>,
+}
+
+impl Cassette {
+    pub fn open(data_dir: &Path, options: &CassetteOptions) -> Result<Self, AnyError> {
+        let is_valid_name = !options.name.is_empty()
+            && options
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !is_valid_name {
+            bail!("Invalid cassette name {:?}", options.name);
+        }
+
+        let path = data_dir
+            .join("cassettes")
+            .join(format!("{}.jsonl", options.name));
+
+        // recording always starts from an empty cassette
+        let interactions: Vec<Interaction> = match options.mode {
+            CassetteMode::Record => Vec::new(),
+            CassetteMode::Replay => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Failed to read cassette {}: {}", path.display(), e))?;
+                read_interactions(&contents)?
+            }
+        };
+
+        Ok(Self {
+            path,
+            file: None,
+            replayed: vec![false; interactions.len()],
+            interactions,
+        })
+    }
+
+    /// Appends an interaction to the cassette file right away, so a stopped
+    /// or crashed task still leaves a usable cassette behind.
+    pub fn record(&mut self, response: RecordedResponse, body: &[u8]) -> Result<(), AnyError> {
+        let interaction = Interaction {
+            response,
+            body: STANDARD.encode(body),
+        };
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                if let Some(parent) = self.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                // replaces what an earlier recording left
+                self.file.insert(File::create(&self.path)?)
+            }
+        };
+        // in a single write, so the file only ever ends with a partial line
+        let mut line = serde_json::to_vec(&interaction)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+
+        self.interactions.push(interaction);
+        self.replayed.push(true);
+
+        Ok(())
+    }
+
+    /// Returns the first recorded response for the request that hasn't been
+    /// replayed yet, so repeated requests replay in the recorded order.
+    pub fn replay(
+        &mut self,
+        method: &str,
+        url: &str,
+    ) -> Result<Option<(RecordedResponse, Vec<u8>)>, AnyError> {
+        let position = self
+            .interactions
+            .iter()
+            .enumerate()
+            .position(|(i, interaction)| {
+                !self.replayed[i]
+                    && interaction.response.method.eq_ignore_ascii_case(method)
+                    && interaction.response.url == url
+            });
+
+        let Some(position) = position else {
+            return Ok(None);
+        };
+
+        self.replayed[position] = true;
+
+        let interaction = &self.interactions[position];
+        let body = STANDARD.decode(&interaction.body)?;
+
+        Ok(Some((interaction.response.clone(), body)))
+    }
+}
+
+fn read_interactions(contents: &str) -> Result<Vec<Interaction>, AnyError> {
+    let lines: Vec<&str> = contents.lines().filter(|line| !line.is_empty()).collect();
+    let mut interactions = Vec::with_capacity(lines.len());
+
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(interaction) => interactions.push(interaction),
+            // cut short by a crash while recording
+            Err(_) if i == lines.len() - 1 && !contents.ends_with('\n') => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(interactions)
+}
@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use deno_runtime::deno_core::ModuleSpecifier;
+use sha2::{Digest, Sha256};
+
+use super::scripts::{self, CatalogPin};
+
+/// A community script listed in a catalog's index.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CatalogEntry {
+    /// Name it's saved under once installed
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    version: Option<String>,
+    /// URL of the source, relative ones are resolved against the index
+    source_url: String,
+    /// Hex SHA-256 of the source, a source not matching it is refused
+    sha256: String,
+    /// Hash of the copy installed from the catalog, not part of the index
+    #[serde(default, skip_deserializing)]
+    installed_sha256: Option<String>,
+}
+
+impl CatalogEntry {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+
+    pub fn installed_sha256(&self) -> Option<&str> {
+        self.installed_sha256.as_deref()
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CatalogIndex {
+    scripts: Vec<CatalogEntry>,
+}
+
+/// A catalog script with its source, for the user to read before installing
+/// it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CatalogScriptReview {
+    entry: CatalogEntry,
+    source: String,
+}
+
+impl CatalogScriptReview {
+    pub fn entry(&self) -> &CatalogEntry {
+        &self.entry
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+// Plain HTTP only for a catalog served on this machine, e.g. while it's
+// being written
+fn parse_url(url: &str) -> Result<ModuleSpecifier, String> {
+    let parsed =
+        ModuleSpecifier::parse(url).map_err(|e| format!("Invalid catalog URL {}: {}", url, e))?;
+
+    let loopback = matches!(parsed.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match parsed.scheme() {
+        "https" => Ok(parsed),
+        "http" if loopback => Ok(parsed),
+        _ => Err(format!("Catalogs are only fetched over HTTPS: {}", url)),
+    }
+}
+
+fn get(url: &ModuleSpecifier) -> Result<String, String> {
+    ureq::get(url.as_str())
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn sha256_hex(source: &str) -> String {
+    Sha256::digest(source.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Scripts of the catalog at `url`, with the hash of those already installed
+/// from it.
+pub fn list_catalog(data_dir: &Path, url: &str) -> Result<Vec<CatalogEntry>, String> {
+    let index_url = parse_url(url)?;
+    let index = get(&index_url).map_err(|e| format!("Failed to fetch catalog {}: {}", url, e))?;
+    let index: CatalogIndex =
+        serde_json::from_str(&index).map_err(|e| format!("Invalid catalog {}: {}", url, e))?;
+
+    let mut entries = index.scripts;
+    for entry in &mut entries {
+        entry.installed_sha256 = scripts::load_catalog_pin(data_dir, &entry.name)
+            .filter(|pin| pin.catalog_url == url)
+            .map(|pin| pin.sha256);
+    }
+
+    Ok(entries)
+}
+
+/// Fetches the source of the catalog script `name`, checked against the hash
+/// the index pins. Nothing is saved or run.
+pub fn review_catalog_script(
+    data_dir: &Path,
+    url: &str,
+    name: &str,
+) -> Result<CatalogScriptReview, String> {
+    let entry = list_catalog(data_dir, url)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| format!("Script {} isn't in catalog {}", name, url))?;
+    scripts::validate_name(&entry.name)?;
+
+    let source_url = parse_url(url)?
+        .join(&entry.source_url)
+        .map_err(|e| format!("Invalid source URL {}: {}", entry.source_url, e))?;
+    let source_url = parse_url(source_url.as_str())?;
+    let source =
+        get(&source_url).map_err(|e| format!("Failed to fetch the source of {}: {}", name, e))?;
+
+    let sha256 = sha256_hex(&source);
+    if !sha256.eq_ignore_ascii_case(&entry.sha256) {
+        return Err(format!(
+            "The source of {} doesn't match the catalog's hash: expected {}, got {}",
+            name, entry.sha256, sha256
+        ));
+    }
+
+    Ok(CatalogScriptReview { entry, source })
+}
+
+/// Saves the catalog script `name` to the saved scripts if its source is
+/// still the one reviewed, the one hashing to `reviewed_sha256`. Scripts
+/// saved by the user aren't replaced, only earlier installs from the same
+/// catalog.
+pub fn install_catalog_script(
+    data_dir: &Path,
+    url: &str,
+    name: &str,
+    reviewed_sha256: &str,
+) -> Result<(), String> {
+    let review = review_catalog_script(data_dir, url, name)?;
+    if !review.entry.sha256.eq_ignore_ascii_case(reviewed_sha256) {
+        return Err(format!(
+            "Script {} changed since it was reviewed, review it again",
+            name
+        ));
+    }
+
+    let saved = scripts::load(data_dir, name).is_ok();
+    let pin = scripts::load_catalog_pin(data_dir, name);
+    if saved && pin.is_none_or(|pin| pin.catalog_url != url) {
+        return Err(format!("A script named {} is already saved", name));
+    }
+
+    scripts::save(data_dir, name, &review.source)
+        .map_err(|e| format!("Failed to save script {}: {}", name, e))?;
+    scripts::save_catalog_pin(
+        data_dir,
+        name,
+        &CatalogPin {
+            catalog_url: url.to_string(),
+            sha256: sha256_hex(&review.source),
+        },
+    )
+    .map_err(|e| format!("Failed to save script {}: {}", name, e))
+}
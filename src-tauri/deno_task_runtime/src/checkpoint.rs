@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+
+use super::config::log;
+use super::RunOptions;
+
+/// Last state a task declared with `RuntimeExtension.checkpoint(state)`, along
+/// with what is needed to start the script again.
+///
+/// Experimental: only the declared state survives, the script is restarted
+/// from the top and has to pick up from `RuntimeExtension.checkpointState`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Checkpoint {
+    pub code: String,
+    pub options: RunOptions,
+    /// JSON produced by the script
+    pub state: String,
+}
+
+fn checkpoint_path(data_dir: &Path, task_id: &str) -> PathBuf {
+    data_dir
+        .join("checkpoints")
+        .join(format!("{}.json", task_id))
+}
+
+pub fn save(data_dir: &Path, task_id: &str, checkpoint: &Checkpoint) -> std::io::Result<()> {
+    let path = checkpoint_path(data_dir, task_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // write then rename, a crash mid-write must not lose the previous checkpoint
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(checkpoint)?)?;
+    std::fs::rename(&tmp_path, &path)
+}
+
+pub fn load(data_dir: &Path, task_id: &str) -> std::io::Result<Checkpoint> {
+    let contents = std::fs::read(checkpoint_path(data_dir, task_id))?;
+
+    Ok(serde_json::from_slice(&contents)?)
+}
+
+pub fn remove(data_dir: &Path, task_id: &str) {
+    let path = checkpoint_path(data_dir, task_id);
+
+    if let Err(e) = std::fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log!(Error, "Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use deno_runtime::deno_core::error::AnyError;
+use deno_runtime::deno_fetch;
+use deno_runtime::deno_tls::{load_certs, load_private_keys, TlsKey, TlsKeys};
+use deno_runtime::worker::MainWorker;
+
+/// Certificate a task's `fetch` calls present to servers asking for one, for
+/// internal APIs behind mutual TLS.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClientCertificate {
+    /// PEM files of the certificate chain and of its private key
+    Files { cert_path: String, key_path: String },
+    /// The PEM contents themselves
+    Pem { cert: String, key: String },
+    /// Looked up by name in the app's `CertificateStore`, e.g. the OS keychain
+    Keychain { name: String },
+}
+
+/// Hands out the client certificates kept outside of files, e.g. in the OS
+/// keychain, for `ClientCertificate::Keychain`.
+pub trait CertificateStore: Send + Sync {
+    /// PEM certificate chain and private key of `name`, `None` when there's no
+    /// such certificate.
+    fn load(&self, name: &str) -> Result<Option<(String, String)>, AnyError>;
+}
+
+impl fmt::Debug for dyn CertificateStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CertificateStore")
+    }
+}
+
+impl ClientCertificate {
+    // Read on the task thread when it starts, files may change between runs
+    fn load(&self, store: Option<&dyn CertificateStore>) -> Result<TlsKey, String> {
+        let (cert, key) = match self {
+            ClientCertificate::Files {
+                cert_path,
+                key_path,
+            } => {
+                let read = |path: &str| {
+                    std::fs::read_to_string(path)
+                        .map_err(|e| format!("Failed to read {}: {}", path, e))
+                };
+                (read(cert_path)?, read(key_path)?)
+            }
+            ClientCertificate::Pem { cert, key } => (cert.clone(), key.clone()),
+            ClientCertificate::Keychain { name } => {
+                let store = store.ok_or("No certificate store is set")?;
+                store
+                    .load(name)
+                    .map_err(|e| format!("Failed to load certificate {}: {}", name, e))?
+                    .ok_or_else(|| format!("Certificate not found: {}", name))?
+            }
+        };
+
+        let certs = load_certs(&mut cert.as_bytes()).map_err(|e| e.to_string())?;
+        let key = load_private_keys(key.as_bytes())
+            .map_err(|e| e.to_string())?
+            .remove(0);
+
+        Ok(TlsKey(certs, key))
+    }
+}
+
+/// Makes the `fetch` client of `worker` present `certificate`, before the task
+/// runs like the proxy.
+pub fn apply_to_worker(
+    worker: &mut MainWorker,
+    certificate: &ClientCertificate,
+    store: Option<&dyn CertificateStore>,
+) -> Result<(), String> {
+    let key = certificate
+        .load(store)
+        .map_err(|e| format!("Invalid client certificate: {}", e))?;
+
+    let op_state = worker.js_runtime.op_state();
+    let mut state = op_state.borrow_mut();
+
+    let mut options = state.borrow::<deno_fetch::Options>().clone();
+    options.client_cert_chain_and_key = TlsKeys::Static(key);
+    state.put(options);
+    state.try_take::<deno_fetch::Client>();
+
+    Ok(())
+}
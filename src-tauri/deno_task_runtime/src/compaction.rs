@@ -0,0 +1,162 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::artifacts;
+use super::config::log;
+use super::idempotency;
+use super::profiles::is_data_dir;
+use super::TaskRuntime;
+
+// First bytes of every SQLite database file
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// What `TaskRuntime::compact_storage` cleaned up.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct CompactionReport {
+    /// Disk freed in the data directory, by all the steps together
+    #[ts(type = "number")]
+    reclaimed_bytes: u64,
+    /// Disk used by the data directory afterwards
+    #[ts(type = "number")]
+    total_bytes: u64,
+    vacuumed_databases: usize,
+    /// Idempotency keys whose task no longer stands for them
+    expired_cache_entries: usize,
+    /// Artifacts of tasks that are neither in the runtime nor in the history
+    orphaned_artifacts: usize,
+}
+
+impl CompactionReport {
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.reclaimed_bytes
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    pub fn vacuumed_databases(&self) -> usize {
+        self.vacuumed_databases
+    }
+
+    pub fn expired_cache_entries(&self) -> usize {
+        self.expired_cache_entries
+    }
+
+    pub fn orphaned_artifacts(&self) -> usize {
+        self.orphaned_artifacts
+    }
+}
+
+pub fn compact(runtime: &TaskRuntime) -> CompactionReport {
+    let data_dir = runtime.data_dir();
+    let bytes_before = disk_usage(data_dir);
+
+    let expired_cache_entries = idempotency::prune(runtime);
+    let orphaned_artifacts = remove_orphaned_artifacts(runtime);
+    let vacuumed_databases = sqlite_files(data_dir)
+        .iter()
+        .filter(|path| vacuum(path))
+        .count();
+
+    let total_bytes = disk_usage(data_dir);
+    let report = CompactionReport {
+        reclaimed_bytes: bytes_before.saturating_sub(total_bytes),
+        total_bytes,
+        vacuumed_databases,
+        expired_cache_entries,
+        orphaned_artifacts,
+    };
+    log!(
+        Info,
+        "Compacted {}, reclaimed {} bytes",
+        data_dir.display(),
+        report.reclaimed_bytes
+    );
+
+    report
+}
+
+fn remove_orphaned_artifacts(runtime: &TaskRuntime) -> usize {
+    let data_dir = runtime.data_dir();
+
+    // held so a run finishing meanwhile is seen
+    let mut history = runtime.inner.history.lock().unwrap();
+    let orphaned: Vec<String> = artifacts::task_ids(data_dir)
+        .into_iter()
+        .filter(|task_id| runtime.get_task_state(task_id).is_none())
+        .filter(|task_id| !history.has_run_since(data_dir, task_id, 0))
+        .collect();
+
+    for task_id in &orphaned {
+        artifacts::remove_all(data_dir, task_id);
+    }
+
+    orphaned.len()
+}
+
+// Whether the database was rebuilt, it can't be while a task writes to it
+fn vacuum(path: &Path) -> bool {
+    let result = rusqlite::Connection::open(path).and_then(|db| db.execute_batch("VACUUM"));
+
+    match result {
+        Ok(_) => true,
+        Err(e) => {
+            log!(Error, "Failed to vacuum {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+fn is_sqlite_file(path: &Path) -> bool {
+    let mut header = [0; 16];
+
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == SQLITE_HEADER)
+}
+
+fn sqlite_files(data_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_files(data_dir, data_dir, &mut files);
+
+    files.retain(|path| is_sqlite_file(path));
+    files
+}
+
+fn disk_usage(data_dir: &Path) -> u64 {
+    let mut files = Vec::new();
+    collect_files(data_dir, data_dir, &mut files);
+
+    files
+        .iter()
+        .filter_map(|path| std::fs::symlink_metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// The files of the data directory, without those of the profiles nested in
+// the default one
+fn collect_files(data_dir: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // symlinks are not followed, whatever they point to isn't ours
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if path != data_dir && is_data_dir(&path) {
+                continue;
+            }
+            collect_files(data_dir, &path, files);
+        } else if metadata.is_file() {
+            files.push(path);
+        }
+    }
+}
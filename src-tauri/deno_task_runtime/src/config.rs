@@ -0,0 +1,313 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use super::output::DEFAULT_MAX_TASK_OUTPUT_BYTES;
+use super::storage::{self, DEFAULT_STORAGE_QUOTA_BYTES};
+
+const CONFIG_FILE: &str = "config.json";
+
+/// What to do when a task needs a permission it doesn't have.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptPolicy {
+    /// Ask the frontend and wait for the user
+    #[default]
+    Ask,
+    /// Deny right away, for unattended runs
+    Deny,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Off,
+    Error,
+    #[default]
+    Info,
+    Debug,
+}
+
+/// Runtime settings, stored as `config.json` in the app directory.
+///
+/// Changes apply to the next task, or right away where noted. `data_dir` is
+/// only read at startup.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// Tasks of a profile that can run at the same time, unlimited if unset
+    max_concurrent_tasks: Option<usize>,
+    /// Console output kept per task, applies right away
+    max_task_output_bytes: usize,
+    /// Cap on the disk used by the app directory, enforced right away
+    #[ts(type = "number | null")]
+    storage_quota_bytes: Option<u64>,
+    /// Applies right away, also to tasks that are running
+    prompt_policy: PromptPolicy,
+    /// Verbosity of the runtime logs on the host stdout, applies right away
+    log_level: LogLevel,
+    /// Where profiles keep their data (cassettes, checkpoints, ...), defaults
+    /// to the app directory. Needs a restart.
+    data_dir: Option<PathBuf>,
+    /// Appends every command and emitted event to this file, one JSON object
+    /// per line, for `replay_trace`. Applies right away.
+    trace_file: Option<PathBuf>,
+    /// Threads V8 compiles and collects garbage on, shared by every task. One
+    /// per core if unset. Needs a restart.
+    executor_threads: Option<usize>,
+    /// Pins the UI thread to the first core and keeps task threads off it, on
+    /// Linux and Windows. Needs a restart.
+    reserve_ui_core: bool,
+    /// Keeps a worker of the default profile bootstrapped ahead of the next
+    /// task once the runtimes are started, so it starts right away. Needs a
+    /// restart.
+    prewarm_worker: bool,
+    /// Resident memory of the app past which queued tasks are held back,
+    /// caches are dropped and running tasks get a `memory-pressure` host
+    /// event. Unlimited if unset, applies right away.
+    #[ts(type = "number | null")]
+    memory_pressure_threshold_mb: Option<u64>,
+    /// Refuses to import script bundles that aren't signed with a trusted
+    /// key, applies right away
+    require_signed_bundles: bool,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_tasks: None,
+            max_task_output_bytes: DEFAULT_MAX_TASK_OUTPUT_BYTES,
+            storage_quota_bytes: Some(DEFAULT_STORAGE_QUOTA_BYTES),
+            prompt_policy: PromptPolicy::default(),
+            log_level: LogLevel::default(),
+            data_dir: None,
+            trace_file: None,
+            executor_threads: None,
+            reserve_ui_core: false,
+            prewarm_worker: false,
+            memory_pressure_threshold_mb: None,
+            require_signed_bundles: false,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn max_concurrent_tasks(&self) -> Option<usize> {
+        self.max_concurrent_tasks
+    }
+
+    pub fn max_task_output_bytes(&self) -> usize {
+        self.max_task_output_bytes
+    }
+
+    pub fn storage_quota_bytes(&self) -> Option<u64> {
+        self.storage_quota_bytes
+    }
+
+    pub fn set_storage_quota_bytes(&mut self, quota_bytes: Option<u64>) {
+        self.storage_quota_bytes = quota_bytes;
+    }
+
+    pub fn prompt_policy(&self) -> PromptPolicy {
+        self.prompt_policy
+    }
+
+    pub fn trace_file(&self) -> Option<&Path> {
+        self.trace_file.as_deref()
+    }
+
+    pub fn memory_pressure_threshold_mb(&self) -> Option<u64> {
+        self.memory_pressure_threshold_mb
+    }
+
+    pub fn require_signed_bundles(&self) -> bool {
+        self.require_signed_bundles
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent_tasks == Some(0) {
+            return Err("max_concurrent_tasks must be at least 1".to_string());
+        }
+
+        if self.executor_threads == Some(0) {
+            return Err("executor_threads must be at least 1".to_string());
+        }
+
+        if self.memory_pressure_threshold_mb == Some(0) {
+            return Err("memory_pressure_threshold_mb must be at least 1".to_string());
+        }
+
+        if self.max_task_output_bytes == 0 {
+            return Err("max_task_output_bytes must be at least 1".to_string());
+        }
+
+        if let Some(data_dir) = &self.data_dir {
+            if !data_dir.is_absolute() {
+                return Err(format!("data_dir must be absolute: {}", data_dir.display()));
+            }
+        }
+
+        if let Some(trace_file) = &self.trace_file {
+            if !trace_file.is_absolute() {
+                return Err(format!(
+                    "trace_file must be absolute: {}",
+                    trace_file.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct AppliedConfig {
+    config: RuntimeConfig,
+    /// Some of the changes only apply after restarting the app
+    restart_required: bool,
+}
+
+static CONFIG: Lazy<RwLock<RuntimeConfig>> = Lazy::new(|| RwLock::new(load()));
+
+// The config the app started with, for the settings read only at startup
+static STARTUP_CONFIG: Lazy<RuntimeConfig> = Lazy::new(get);
+
+// Fixed location, `data_dir` can't move the config itself
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap()
+        .join(".tauri_deno_example")
+        .join(CONFIG_FILE)
+}
+
+fn load() -> RuntimeConfig {
+    let path = config_path();
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return RuntimeConfig::default(),
+        Err(e) => {
+            println!("Failed to read {}: {}, using defaults", path.display(), e);
+            return RuntimeConfig::default();
+        }
+    };
+
+    match serde_json::from_str::<RuntimeConfig>(&contents) {
+        Ok(config) if config.validate().is_ok() => config,
+        Ok(_) | Err(_) => {
+            println!("Invalid config in {}, using defaults", path.display());
+            RuntimeConfig::default()
+        }
+    }
+}
+
+pub fn get() -> RuntimeConfig {
+    CONFIG.read().unwrap().clone()
+}
+
+pub fn get_runtime_config() -> AppliedConfig {
+    applied(get())
+}
+
+/// Validates, saves and applies a new config.
+pub fn set_runtime_config(config: RuntimeConfig) -> Result<AppliedConfig, String> {
+    config.validate()?;
+
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    apply(config.clone());
+
+    Ok(applied(config))
+}
+
+/// Re-reads `config.json`, e.g. after it was edited by hand.
+pub fn reload_runtime_config() -> AppliedConfig {
+    let config = load();
+    apply(config.clone());
+
+    applied(config)
+}
+
+pub fn update(f: impl FnOnce(&mut RuntimeConfig)) -> Result<AppliedConfig, String> {
+    let mut config = get();
+    f(&mut config);
+
+    set_runtime_config(config)
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    STARTUP_CONFIG.data_dir.clone()
+}
+
+pub fn executor_threads() -> Option<usize> {
+    STARTUP_CONFIG.executor_threads
+}
+
+pub fn reserve_ui_core() -> bool {
+    STARTUP_CONFIG.reserve_ui_core
+}
+
+pub fn prewarm_worker() -> bool {
+    STARTUP_CONFIG.prewarm_worker
+}
+
+pub fn log_enabled(level: LogLevel) -> bool {
+    level <= CONFIG.read().unwrap().log_level
+}
+
+fn apply(config: RuntimeConfig) {
+    // keep what the app started with before it's replaced
+    Lazy::force(&STARTUP_CONFIG);
+
+    *CONFIG.write().unwrap() = config;
+
+    storage::enforce_storage_quota();
+}
+
+fn applied(config: RuntimeConfig) -> AppliedConfig {
+    AppliedConfig {
+        restart_required: config.data_dir != STARTUP_CONFIG.data_dir
+            || config.executor_threads != STARTUP_CONFIG.executor_threads
+            || config.reserve_ui_core != STARTUP_CONFIG.reserve_ui_core
+            || config.prewarm_worker != STARTUP_CONFIG.prewarm_worker,
+        config,
+    }
+}
+
+/// `println!` gated by the configured log level.
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::config::log_enabled($crate::config::LogLevel::$level) {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use log;
@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::RunOptions;
+
+/// A background run that failed, kept until it's retried or dismissed so
+/// failures of scheduled jobs don't go unnoticed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct DeadLetter {
+    pub(crate) id: String,
+    pub(crate) task_id: String,
+    /// Saved script the task ran, if any
+    pub(crate) script: Option<String>,
+    error: String,
+    #[ts(type = "unknown")]
+    args: Option<serde_json::Value>,
+    /// Milliseconds since the Unix epoch
+    #[ts(type = "number")]
+    failed_at_ms: u64,
+}
+
+// What's needed to run the task again, on disk
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub letter: DeadLetter,
+    pub code: String,
+    pub options: RunOptions,
+}
+
+fn dead_letters_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("dead_letters")
+}
+
+fn entry_path(data_dir: &Path, id: &str) -> PathBuf {
+    dead_letters_dir(data_dir).join(format!("{}.json", id))
+}
+
+pub fn record(
+    data_dir: &Path,
+    task_id: &str,
+    code: &str,
+    options: &RunOptions,
+    error: &str,
+) -> std::io::Result<DeadLetter> {
+    let failed_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default();
+
+    let letter = DeadLetter {
+        // a task failing again gets a letter of its own
+        id: format!("{}-{:x}", task_id, failed_at_ms),
+        task_id: task_id.to_string(),
+        script: options.saved_script.clone(),
+        error: error.to_string(),
+        args: options.args.clone(),
+        failed_at_ms,
+    };
+    let entry = Entry {
+        letter: letter.clone(),
+        code: code.to_string(),
+        options: options.clone(),
+    };
+
+    std::fs::create_dir_all(dead_letters_dir(data_dir))?;
+    std::fs::write(
+        entry_path(data_dir, &letter.id),
+        serde_json::to_vec(&entry)?,
+    )?;
+
+    Ok(letter)
+}
+
+/// Oldest first
+pub fn list(data_dir: &Path) -> Vec<DeadLetter> {
+    let Ok(entries) = std::fs::read_dir(dead_letters_dir(data_dir)) else {
+        return Vec::new();
+    };
+
+    let mut letters: Vec<DeadLetter> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let contents = std::fs::read(entry.path()).ok()?;
+            serde_json::from_slice::<Entry>(&contents).ok()
+        })
+        .map(|entry| entry.letter)
+        .collect();
+    letters.sort_by_key(|letter| letter.failed_at_ms);
+
+    letters
+}
+
+pub fn load(data_dir: &Path, id: &str) -> std::io::Result<Entry> {
+    let contents = std::fs::read(entry_path(data_dir, id))?;
+
+    Ok(serde_json::from_slice(&contents)?)
+}
+
+pub fn remove(data_dir: &Path, id: &str) -> std::io::Result<()> {
+    std::fs::remove_file(entry_path(data_dir, id))
+}
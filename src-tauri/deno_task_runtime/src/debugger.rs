@@ -0,0 +1,593 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex, Weak};
+use std::time::Duration;
+
+use deno_runtime::deno_core::futures::channel::mpsc::UnboundedSender;
+use deno_runtime::deno_core::{
+    v8, InspectorMsg, InspectorMsgKind, InspectorSessionKind, InspectorSessionOptions,
+    JsRuntimeInspector, ModuleSpecifier,
+};
+use deno_runtime::worker::MainWorker;
+use serde_json::{json, Value};
+
+use super::config::log;
+use super::remote_objects::{self, RemoteObject};
+use super::{TaskEvent, TaskRuntime};
+
+// A paused isolate answers right away, a running one once it's interrupted
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Of the objects previewed by `inspect`, released right after
+const INSPECT_OBJECT_GROUP: &str = "evaluate_in_task";
+
+/// Where a task run with `TaskRuntime::debug_run_task` is paused.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct DebugPause {
+    /// As in the `Debugger.paused` event of the DevTools protocol, `other`
+    /// for a breakpoint and `step` after a step
+    reason: String,
+    /// Innermost first
+    call_frames: Vec<DebugCallFrame>,
+}
+
+impl DebugPause {
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn call_frames(&self) -> &[DebugCallFrame] {
+        &self.call_frames
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct DebugCallFrame {
+    /// Empty at the top level of a module
+    function_name: String,
+    /// URL of the imported module it's in, `None` in the task's own code
+    module: Option<String>,
+    /// From 1, in the code as written for the task's own code
+    line: u32,
+    column: u32,
+}
+
+impl DebugCallFrame {
+    pub fn function_name(&self) -> &str {
+        &self.function_name
+    }
+
+    pub fn module(&self) -> Option<&str> {
+        self.module.as_deref()
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugStep {
+    /// To the next statement, over function calls
+    Over,
+    /// Into the function called by the statement, if any
+    Into,
+    /// Out of the current function
+    Out,
+}
+
+impl DebugStep {
+    fn method(self) -> &'static str {
+        match self {
+            DebugStep::Over => "Debugger.stepOver",
+            DebugStep::Into => "Debugger.stepInto",
+            DebugStep::Out => "Debugger.stepOut",
+        }
+    }
+}
+
+/// Inspector session of a task's run, driven from other threads, including
+/// while the task's own is parked at a pause.
+pub struct DebugSession {
+    task_id: String,
+    // attached by `debug_run_task`, not on demand by `evaluate_in_task`
+    debug_run: bool,
+    to_inspector: UnboundedSender<String>,
+    main_module: String,
+    // lines staged ahead of the code as written
+    prefix_lines: u32,
+    state: Mutex<SessionState>,
+}
+
+#[derive(Default)]
+struct SessionState {
+    next_id: i32,
+    /// `None` once the run is over
+    responses: Option<HashMap<i32, mpsc::Sender<Result<Value, String>>>>,
+    /// URLs by script id, the paused call frames only have the id
+    scripts: HashMap<String, String>,
+    /// With the id of its innermost call frame, to evaluate in
+    pause: Option<(DebugPause, String)>,
+}
+
+impl std::fmt::Debug for DebugSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DebugSession")
+            .field("task_id", &self.task_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DebugSession {
+    /// Connects to the worker's inspector for a debug run, set to pause at the
+    /// first statement of the code and at `breakpoints`, lines of the code as
+    /// written from 1. Must be called before the main module is evaluated.
+    pub fn attach(
+        runtime: &TaskRuntime,
+        worker: &mut MainWorker,
+        task_id: &str,
+        main_module: &ModuleSpecifier,
+        prefix_lines: u32,
+        breakpoints: &[u32],
+    ) -> Arc<Self> {
+        let inspector = worker.js_runtime.inspector();
+        let session = Self::connect(
+            runtime,
+            &inspector.borrow(),
+            task_id,
+            main_module.as_str(),
+            prefix_lines,
+            true,
+        );
+
+        session.send("Debugger.enable", json!({}));
+        // the first line resolves to the first statement after it
+        for line in std::iter::once(1).chain(breakpoints.iter().copied()) {
+            session.send(
+                "Debugger.setBreakpointByUrl",
+                json!({
+                    "url": session.main_module,
+                    "lineNumber": line - 1 + prefix_lines,
+                }),
+            );
+        }
+        // the task's thread dispatches them, it isn't running anything yet
+        let _ = inspector.borrow().poll_sessions(None);
+
+        session
+    }
+
+    // On the task's thread
+    fn connect(
+        runtime: &TaskRuntime,
+        inspector: &JsRuntimeInspector,
+        task_id: &str,
+        main_module: &str,
+        prefix_lines: u32,
+        debug_run: bool,
+    ) -> Arc<Self> {
+        Arc::new_cyclic(|session: &Weak<DebugSession>| {
+            let session = session.clone();
+            let runtime = runtime.clone();
+            let to_inspector = inspector.create_raw_session(
+                InspectorSessionOptions {
+                    kind: InspectorSessionKind::NonBlocking {
+                        wait_for_disconnect: false,
+                    },
+                },
+                Box::new(move |message| {
+                    if let Some(session) = session.upgrade() {
+                        session.receive(&runtime, message);
+                    }
+                }),
+            );
+
+            DebugSession {
+                task_id: task_id.to_string(),
+                debug_run,
+                to_inspector,
+                main_module: main_module.to_string(),
+                prefix_lines,
+                state: Mutex::new(SessionState {
+                    responses: Some(HashMap::new()),
+                    ..Default::default()
+                }),
+            }
+        })
+    }
+
+    pub fn is_debug_run(&self) -> bool {
+        self.debug_run
+    }
+
+    /// Where the task is paused, `None` while it runs.
+    pub fn pause(&self) -> Option<DebugPause> {
+        let state = self.state.lock().unwrap();
+        state.pause.as_ref().map(|(pause, _)| pause.clone())
+    }
+
+    pub fn step(&self, step: DebugStep) -> Result<(), String> {
+        self.paused_frame()?;
+        self.call(step.method(), json!({})).map(|_| ())
+    }
+
+    pub fn resume(&self) -> Result<(), String> {
+        self.paused_frame()?;
+        self.call("Debugger.resume", json!({})).map(|_| ())
+    }
+
+    /// Evaluates `expression` in the innermost call frame when paused, at the
+    /// top level of the task otherwise. What can't be returned as JSON comes
+    /// back as V8 describes it, `undefined` as `null`.
+    pub fn evaluate(&self, expression: &str) -> Result<Value, String> {
+        let value = self.evaluate_with(expression, json!({ "returnByValue": true }))?;
+
+        Ok(match value.get("value") {
+            Some(value) => value.clone(),
+            None => value.get("description").cloned().unwrap_or(Value::Null),
+        })
+    }
+
+    /// Evaluates `expression` where `evaluate` does, for a preview of the
+    /// result. Expressions that would change the task's state fail unless
+    /// `allow_side_effects`.
+    pub fn inspect(
+        &self,
+        expression: &str,
+        allow_side_effects: bool,
+    ) -> Result<RemoteObject, String> {
+        let result = self.evaluate_with(
+            expression,
+            json!({
+                "generatePreview": true,
+                "throwOnSideEffect": !allow_side_effects,
+                "objectGroup": INSPECT_OBJECT_GROUP,
+            }),
+        );
+        self.send(
+            "Runtime.releaseObjectGroup",
+            json!({ "objectGroup": INSPECT_OBJECT_GROUP }),
+        );
+
+        result.map(|object| remote_objects::from_protocol(&object))
+    }
+
+    // The `RemoteObject` of the result, in the paused call frame if any
+    fn evaluate_with(&self, expression: &str, options: Value) -> Result<Value, String> {
+        let (method, mut params) = match self.paused_frame() {
+            Ok(call_frame_id) => (
+                "Debugger.evaluateOnCallFrame",
+                json!({ "callFrameId": call_frame_id, "expression": expression }),
+            ),
+            Err(_) => ("Runtime.evaluate", json!({ "expression": expression })),
+        };
+        if let (Some(params), Value::Object(options)) = (params.as_object_mut(), options) {
+            params.extend(options);
+        }
+
+        let mut result = self.call(method, params)?;
+        if let Some(exception) = result.get("exceptionDetails") {
+            let description = exception["exception"]["description"]
+                .as_str()
+                .or(exception["text"].as_str())
+                .unwrap_or("Uncaught exception");
+            return Err(description.to_string());
+        }
+
+        Ok(result["result"].take())
+    }
+
+    /// Drops the breakpoints of a debug run and lets it go if it's paused,
+    /// without waiting for it, e.g. for it to see that it's stopped.
+    pub fn detach(&self) {
+        self.send("Debugger.disable", json!({}));
+    }
+
+    /// Fails the calls still waiting on a response, once the run is over.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.responses = None;
+        state.pause = None;
+    }
+
+    fn paused_frame(&self) -> Result<String, String> {
+        let state = self.state.lock().unwrap();
+        state
+            .pause
+            .as_ref()
+            .map(|(_, call_frame_id)| call_frame_id.clone())
+            .ok_or_else(|| format!("Task {} isn't paused", self.task_id))
+    }
+
+    fn send(&self, method: &str, params: Value) -> i32 {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            state.next_id += 1;
+            state.next_id
+        };
+        let message = json!({ "id": id, "method": method, "params": params });
+        // gone along with the worker
+        let _ = self.to_inspector.unbounded_send(message.to_string());
+
+        id
+    }
+
+    // Blocks until the task's thread answers, never call it from there
+    fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        let (sender, receiver) = mpsc::channel();
+        let not_running = || format!("Task {} isn't running", self.task_id);
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            state.next_id += 1;
+            let id = state.next_id;
+            state
+                .responses
+                .as_mut()
+                .ok_or_else(not_running)?
+                .insert(id, sender);
+            id
+        };
+
+        let message = json!({ "id": id, "method": method, "params": params });
+        self.to_inspector
+            .unbounded_send(message.to_string())
+            .map_err(|_| not_running())?;
+
+        match receiver.recv_timeout(RESPONSE_TIMEOUT) {
+            Ok(response) => response,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if let Some(responses) = &mut self.state.lock().unwrap().responses {
+                    responses.remove(&id);
+                }
+                Err(format!(
+                    "Task {} didn't respond to {}",
+                    self.task_id, method
+                ))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(not_running()),
+        }
+    }
+
+    // Called on the task's thread, with whatever the inspector sends
+    fn receive(&self, runtime: &TaskRuntime, message: InspectorMsg) {
+        let Ok(content) = serde_json::from_str::<Value>(&message.content) else {
+            log!(Error, "Invalid inspector message: {}", message.content);
+            return;
+        };
+
+        let event = {
+            let mut state = self.state.lock().unwrap();
+            match message.kind {
+                InspectorMsgKind::Message(id) => {
+                    let response = match content.get("error") {
+                        Some(error) => Err(error["message"]
+                            .as_str()
+                            .unwrap_or("Inspector error")
+                            .to_string()),
+                        None => Ok(content["result"].clone()),
+                    };
+                    let sender = state
+                        .responses
+                        .as_mut()
+                        .and_then(|responses| responses.remove(&id));
+                    if let Some(sender) = sender {
+                        let _ = sender.send(response);
+                    }
+                    None
+                }
+                InspectorMsgKind::Notification => {
+                    self.notification(&mut state, &content["method"], &content["params"])
+                }
+            }
+        };
+
+        if let Some(event) = event {
+            runtime.emit_task_event(event);
+        }
+    }
+
+    fn notification(
+        &self,
+        state: &mut SessionState,
+        method: &Value,
+        params: &Value,
+    ) -> Option<TaskEvent> {
+        match method.as_str()? {
+            "Debugger.scriptParsed" => {
+                let script_id = params["scriptId"].as_str()?;
+                let url = params["url"].as_str()?;
+                state.scripts.insert(script_id.to_string(), url.to_string());
+                None
+            }
+            "Debugger.paused" => {
+                let frames = params["callFrames"].as_array()?;
+                let call_frame_id = frames.first()?["callFrameId"].as_str()?.to_string();
+                let pause = DebugPause {
+                    reason: params["reason"].as_str().unwrap_or("other").to_string(),
+                    call_frames: frames
+                        .iter()
+                        .map(|frame| self.call_frame(&state.scripts, frame))
+                        .collect(),
+                };
+                state.pause = Some((pause.clone(), call_frame_id));
+
+                Some(TaskEvent::DebugPaused {
+                    task_id: self.task_id.clone(),
+                    pause,
+                })
+            }
+            "Debugger.resumed" => {
+                state.pause = None;
+                Some(TaskEvent::DebugResumed {
+                    task_id: self.task_id.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn call_frame(&self, scripts: &HashMap<String, String>, frame: &Value) -> DebugCallFrame {
+        let location = &frame["location"];
+        let url = location["scriptId"]
+            .as_str()
+            .and_then(|script_id| scripts.get(script_id));
+        // from 0 in the protocol
+        let line = location["lineNumber"].as_u64().unwrap_or(0) as u32 + 1;
+        let column = location["columnNumber"].as_u64().unwrap_or(0) as u32 + 1;
+
+        let is_task_code = url.is_some_and(|url| *url == self.main_module);
+        DebugCallFrame {
+            function_name: frame["functionName"].as_str().unwrap_or("").to_string(),
+            module: url.filter(|_| !is_task_code).cloned(),
+            line: match is_task_code {
+                true => line.saturating_sub(self.prefix_lines),
+                false => line,
+            },
+            column,
+        }
+    }
+}
+
+/// Asks for a session of a task run that has none, for `evaluate_in_task`.
+/// Answered on the task's thread, at its next interrupt while it runs JS and
+/// right away while it waits in its event loop.
+#[derive(Debug, Default)]
+pub struct AttachRequests {
+    pending: Mutex<Vec<mpsc::Sender<Arc<DebugSession>>>>,
+    notify: tokio::sync::Notify,
+}
+
+impl AttachRequests {
+    /// Blocks until the task's thread attaches a session, or finds one
+    /// attached by an earlier request.
+    pub fn attach(&self, isolate: Option<&v8::IsolateHandle>) -> Result<Arc<DebugSession>, ()> {
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock().unwrap().push(sender);
+
+        self.notify.notify_one();
+        if let Some(isolate) = isolate {
+            isolate.request_interrupt(attach_on_interrupt, std::ptr::null_mut());
+        }
+
+        receiver.recv_timeout(RESPONSE_TIMEOUT).map_err(drop)
+    }
+}
+
+// What a session attached on demand needs, of the run on this thread
+struct AttachTarget {
+    runtime: TaskRuntime,
+    task_id: String,
+    main_module: String,
+    prefix_lines: u32,
+    inspector: Rc<RefCell<JsRuntimeInspector>>,
+    requests: Arc<AttachRequests>,
+}
+
+thread_local! {
+    static ATTACH_TARGET: RefCell<Option<AttachTarget>> = const { RefCell::new(None) };
+}
+
+/// Answers `requests` for the run of `worker` on the current thread until
+/// the guard is dropped, which must be before the worker.
+pub fn answer_attach_requests(
+    runtime: &TaskRuntime,
+    worker: &mut MainWorker,
+    task_id: &str,
+    main_module: &ModuleSpecifier,
+    prefix_lines: u32,
+    requests: Arc<AttachRequests>,
+) -> AttachRequestsGuard {
+    let notified = requests.clone();
+    let listener = tokio::task::spawn_local(async move {
+        loop {
+            notified.notify.notified().await;
+            attach_pending();
+        }
+    });
+
+    ATTACH_TARGET.set(Some(AttachTarget {
+        runtime: runtime.clone(),
+        task_id: task_id.to_string(),
+        main_module: main_module.to_string(),
+        prefix_lines,
+        inspector: worker.js_runtime.inspector(),
+        requests,
+    }));
+
+    AttachRequestsGuard(listener)
+}
+
+pub struct AttachRequestsGuard(tokio::task::JoinHandle<()>);
+
+impl Drop for AttachRequestsGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+        // the runtime checks nothing holds on to its inspector once dropped
+        ATTACH_TARGET.take();
+    }
+}
+
+fn attach_pending() {
+    ATTACH_TARGET.with_borrow(|target| {
+        let Some(target) = target else {
+            return;
+        };
+        let pending = std::mem::take(&mut *target.requests.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+
+        let existing = target
+            .runtime
+            .inner
+            .debuggers
+            .lock()
+            .unwrap()
+            .get(&target.task_id)
+            .cloned();
+        let session = match existing {
+            Some(session) => session,
+            None => {
+                // in use by the event loop, which gets to the requests next
+                let Ok(inspector) = target.inspector.try_borrow() else {
+                    target.requests.pending.lock().unwrap().extend(pending);
+                    target.requests.notify.notify_one();
+                    return;
+                };
+                let session = DebugSession::connect(
+                    &target.runtime,
+                    &inspector,
+                    &target.task_id,
+                    &target.main_module,
+                    target.prefix_lines,
+                    false,
+                );
+                target
+                    .runtime
+                    .inner
+                    .debuggers
+                    .lock()
+                    .unwrap()
+                    .insert(target.task_id.clone(), session.clone());
+                session
+            }
+        };
+
+        for sender in pending {
+            let _ = sender.send(session.clone());
+        }
+    });
+}
+
+extern "C" fn attach_on_interrupt(_isolate: &mut v8::Isolate, _data: *mut c_void) {
+    attach_pending();
+}
@@ -0,0 +1,171 @@
+use serde_json::Value;
+
+use super::Task;
+
+// Past this many line pairs the changed middle of the output is reported as a
+// whole instead of being aligned line by line
+const MAX_LINE_DIFF_CELLS: usize = 4_000_000;
+
+/// A value that differs between two runs, `path` is a JSON pointer into the
+/// compared value and a missing side means the value only exists in one run.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ValueChange {
+    path: String,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LineChange {
+    Removed { line: usize, text: String },
+    Added { line: usize, text: String },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TaskRunDiff {
+    run_a: String,
+    run_b: String,
+    state: Option<ValueChange>,
+    return_value: Vec<ValueChange>,
+    output: Vec<LineChange>,
+    permissions: Vec<ValueChange>,
+}
+
+pub fn diff_task_runs(run_a: &Task, run_b: &Task) -> TaskRunDiff {
+    // errors compare with their message
+    let (state_a, state_b) = (
+        serde_json::to_value(&run_a.state).unwrap_or_default(),
+        serde_json::to_value(&run_b.state).unwrap_or_default(),
+    );
+    let state = (state_a != state_b).then(|| ValueChange {
+        path: String::new(),
+        before: Some(state_a),
+        after: Some(state_b),
+    });
+
+    let mut return_value = Vec::new();
+    diff_values(
+        "",
+        Some(run_a.return_value.as_ref().unwrap_or(&Value::Null)),
+        Some(run_b.return_value.as_ref().unwrap_or(&Value::Null)),
+        &mut return_value,
+    );
+
+    // the permission history is the task's access log
+    let mut permissions = Vec::new();
+    diff_values(
+        "",
+        Some(&serde_json::to_value(&run_a.permission_history).unwrap_or_default()),
+        Some(&serde_json::to_value(&run_b.permission_history).unwrap_or_default()),
+        &mut permissions,
+    );
+
+    TaskRunDiff {
+        run_a: run_a.id.clone(),
+        run_b: run_b.id.clone(),
+        state,
+        return_value,
+        output: diff_lines(&run_a.output.to_string(), &run_b.output.to_string()),
+        permissions,
+    }
+}
+
+fn diff_values(path: &str, a: Option<&Value>, b: Option<&Value>, changes: &mut Vec<ValueChange>) {
+    match (a, b) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            for (key, value) in a {
+                diff_values(&child_path(path, key), Some(value), b.get(key), changes);
+            }
+            for (key, value) in b {
+                if !a.contains_key(key) {
+                    diff_values(&child_path(path, key), None, Some(value), changes);
+                }
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_values(
+                    &child_path(path, &i.to_string()),
+                    a.get(i),
+                    b.get(i),
+                    changes,
+                );
+            }
+        }
+        (a, b) if a != b => changes.push(ValueChange {
+            path: path.to_string(),
+            before: a.cloned(),
+            after: b.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Line diff of two outputs, line numbers are 1-based and refer to the run
+/// the line comes from.
+fn diff_lines(a: &str, b: &str) -> Vec<LineChange> {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let removed = |i: usize| LineChange::Removed {
+        line: prefix + i + 1,
+        text: a_mid[i].to_string(),
+    };
+    let added = |j: usize| LineChange::Added {
+        line: prefix + j + 1,
+        text: b_mid[j].to_string(),
+    };
+
+    if a_mid.len().saturating_mul(b_mid.len()) > MAX_LINE_DIFF_CELLS {
+        return (0..a_mid.len())
+            .map(removed)
+            .chain((0..b_mid.len()).map(added))
+            .collect();
+    }
+
+    // longest common subsequence of the changed middle, lcs[i][j] is the
+    // length for a_mid[i..] and b_mid[j..]
+    let mut lcs = vec![vec![0usize; b_mid.len() + 1]; a_mid.len() + 1];
+    for i in (0..a_mid.len()).rev() {
+        for j in (0..b_mid.len()).rev() {
+            lcs[i][j] = if a_mid[i] == b_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a_mid.len() || j < b_mid.len() {
+        if i < a_mid.len() && j < b_mid.len() && a_mid[i] == b_mid[j] {
+            i += 1;
+            j += 1;
+        } else if j == b_mid.len() || (i < a_mid.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(removed(i));
+            i += 1;
+        } else {
+            changes.push(added(j));
+            j += 1;
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,128 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::TaskEvent;
+
+// Per task, on top of the latest state snapshot
+const MAX_BUFFERED_EVENTS_PER_TASK: usize = 256;
+
+/// A task event with its position in the runtime's event stream.
+#[derive(Debug, Clone, serde::Serialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct SequencedEvent {
+    /// Increases by one for every event of the runtime
+    #[ts(type = "number")]
+    seq: u64,
+    /// Increases by one for every event of the task, a jump means events were
+    /// missed (or compacted, see `EventLog`)
+    #[ts(type = "number")]
+    task_seq: u64,
+    #[serde(flatten)]
+    event: TaskEvent,
+}
+
+impl SequencedEvent {
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn event(&self) -> &TaskEvent {
+        &self.event
+    }
+}
+
+/// Recent events of every task, kept so a window that missed some (closed,
+/// reloading, emit failed) can catch up with `sync_task_events`.
+///
+/// A state change carries the whole task, so only the latest one is kept per
+/// task: replaying it is enough to reconcile the task state.
+///
+/// Delivery is at least once, an event can be both emitted and returned when
+/// syncing, so consumers should skip the `task_seq`s they have already seen.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    last_seq: u64,
+    // never forgotten so a task id that is run again (e.g. resumed) keeps
+    // counting up
+    last_task_seqs: HashMap<String, u64>,
+    tasks: HashMap<String, VecDeque<SequencedEvent>>,
+}
+
+impl EventLog {
+    pub fn record(&mut self, event: TaskEvent) -> SequencedEvent {
+        self.last_seq += 1;
+
+        let last_task_seq = self
+            .last_task_seqs
+            .entry(event.task_id().to_string())
+            .or_default();
+        *last_task_seq += 1;
+
+        let event = SequencedEvent {
+            seq: self.last_seq,
+            task_seq: *last_task_seq,
+            event,
+        };
+
+        let buffer = self
+            .tasks
+            .entry(event.event.task_id().to_string())
+            .or_default();
+
+        if matches!(event.event, TaskEvent::StateChanged(_)) {
+            buffer.retain(|buffered| !matches!(buffered.event, TaskEvent::StateChanged(_)));
+        }
+        // only the latest progress matters too
+        if matches!(event.event, TaskEvent::Progress { .. }) {
+            buffer.retain(|buffered| !matches!(buffered.event, TaskEvent::Progress { .. }));
+        }
+
+        buffer.push_back(event.clone());
+
+        if buffer.len() > MAX_BUFFERED_EVENTS_PER_TASK + 1 {
+            let oldest = buffer
+                .iter()
+                .position(|buffered| !matches!(buffered.event, TaskEvent::StateChanged(_)));
+            if let Some(oldest) = oldest {
+                buffer.remove(oldest);
+            }
+        }
+
+        event
+    }
+
+    /// Seq of the latest event, 0 before the first one.
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// Buffered events after `seq`, oldest first.
+    pub fn since(&self, seq: u64) -> Vec<SequencedEvent> {
+        let mut events: Vec<SequencedEvent> = self
+            .tasks
+            .values()
+            .flatten()
+            .filter(|event| event.seq > seq)
+            .cloned()
+            .collect();
+
+        events.sort_by_key(|event| event.seq);
+
+        events
+    }
+
+    /// Buffered events of a task after its `task_seq`, oldest first.
+    pub fn task_events_since(&self, task_id: &str, task_seq: u64) -> Vec<SequencedEvent> {
+        self.tasks
+            .get(task_id)
+            .into_iter()
+            .flatten()
+            .filter(|event| event.task_seq > task_seq)
+            .cloned()
+            .collect()
+    }
+
+    pub fn forget(&mut self, task_ids: &[String]) {
+        for task_id in task_ids {
+            self.tasks.remove(task_id);
+        }
+    }
+}
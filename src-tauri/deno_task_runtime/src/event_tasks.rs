@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+use tauri::{EventId, Listener};
+
+use super::config::log;
+use super::task_ids::resolve_task_id;
+use super::{RunOptions, RuntimeState, TaskRuntime};
+
+const EVENT_TASKS_FILE: &str = "event_tasks.json";
+
+// Where the runtime's own events start, a task run on them would start
+// itself again
+const RUNTIME_EVENT_PREFIXES: &[&str] = &["task-", "pipeline-"];
+
+/// A task run whenever a Tauri event is emitted, registered with
+/// `TaskRuntime::register_event_task`. Each run gets the payload of the event
+/// as its input, and is a task of its own with the registration id as its
+/// namespace.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct EventTask {
+    id: String,
+    event_name: String,
+    code: String,
+    options: RunOptions,
+    /// Events closer than this are batched into a single run, whose input
+    /// is the list of their distinct payloads. Each event runs the task when
+    /// unset
+    #[ts(type = "number | null")]
+    debounce_ms: Option<u64>,
+    /// Task of the last run, `None` until the event was emitted
+    last_task_id: Option<String>,
+}
+
+impl EventTask {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn event_name(&self) -> &str {
+        &self.event_name
+    }
+
+    pub fn last_task_id(&self) -> Option<&str> {
+        self.last_task_id.as_deref()
+    }
+
+    pub fn debounce_ms(&self) -> Option<u64> {
+        self.debounce_ms
+    }
+}
+
+// Tauri panics on listening to names with other characters
+fn validate_event_name(event_name: &str) -> Result<(), String> {
+    let valid = !event_name.is_empty()
+        && event_name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '/' | ':' | '_'));
+    if !valid {
+        return Err(format!("Invalid event name {:?}", event_name));
+    }
+
+    if RUNTIME_EVENT_PREFIXES
+        .iter()
+        .any(|prefix| event_name.starts_with(prefix))
+    {
+        return Err(format!(
+            "{} is an event of the runtime, tasks can't be run on it",
+            event_name
+        ));
+    }
+
+    Ok(())
+}
+
+/// The event tasks of a runtime, and the listeners running them once it has
+/// an app handle.
+#[derive(Debug, Default)]
+pub struct EventTasks {
+    event_tasks: Option<Vec<EventTask>>,
+    listeners: HashMap<String, EventId>,
+    // payloads of the debounced ones, dropped to end the thread batching them
+    batches: HashMap<String, mpsc::Sender<serde_json::Value>>,
+}
+
+fn event_tasks_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(EVENT_TASKS_FILE)
+}
+
+impl EventTasks {
+    // Read from disk on first use
+    fn event_tasks(&mut self, data_dir: &Path) -> &mut Vec<EventTask> {
+        self.event_tasks.get_or_insert_with(|| {
+            std::fs::read(event_tasks_path(data_dir))
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn save(&mut self, data_dir: &Path) -> Result<(), String> {
+        let json =
+            serde_json::to_vec_pretty(self.event_tasks(data_dir)).map_err(|e| e.to_string())?;
+
+        std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+        std::fs::write(event_tasks_path(data_dir), json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&mut self, data_dir: &Path) -> Vec<EventTask> {
+        self.event_tasks(data_dir).clone()
+    }
+
+    fn get(&mut self, data_dir: &Path, id: &str) -> Option<EventTask> {
+        self.event_tasks(data_dir)
+            .iter()
+            .find(|event_task| event_task.id == id)
+            .cloned()
+    }
+
+    fn record_run(&mut self, data_dir: &Path, id: &str, task_id: &str) {
+        let event_task = self
+            .event_tasks(data_dir)
+            .iter_mut()
+            .find(|event_task| event_task.id == id);
+        // unregistered meanwhile
+        let Some(event_task) = event_task else {
+            return;
+        };
+
+        event_task.last_task_id = Some(task_id.to_string());
+        if let Err(e) = self.save(data_dir) {
+            log!(Error, "Failed to save event task {}: {}", id, e);
+        }
+    }
+}
+
+/// Registers `code` to run on `event_name`, listening right away when the
+/// runtime has an app handle. Returns the registration id.
+pub fn register(
+    runtime: &TaskRuntime,
+    event_name: &str,
+    code: &str,
+    options: RunOptions,
+    debounce_ms: Option<u64>,
+) -> Result<String, String> {
+    validate_event_name(event_name)?;
+    if debounce_ms == Some(0) {
+        return Err("debounce_ms must be at least 1".to_string());
+    }
+    let id = resolve_task_id(None, None)?;
+    let event_task = EventTask {
+        id: id.clone(),
+        event_name: event_name.to_string(),
+        code: code.to_string(),
+        options,
+        debounce_ms,
+        last_task_id: None,
+    };
+
+    {
+        let mut event_tasks = runtime.inner.event_tasks.lock().unwrap();
+        event_tasks
+            .event_tasks(runtime.data_dir())
+            .push(event_task.clone());
+        event_tasks.save(runtime.data_dir())?;
+    }
+    listen_to(runtime, &event_task);
+
+    Ok(id)
+}
+
+pub fn unregister(runtime: &TaskRuntime, id: &str) -> Result<(), String> {
+    let data_dir = runtime.data_dir();
+    let mut event_tasks = runtime.inner.event_tasks.lock().unwrap();
+
+    let registered = event_tasks.event_tasks(data_dir);
+    let len = registered.len();
+    registered.retain(|event_task| event_task.id != id);
+    if registered.len() == len {
+        return Err(format!("Event task not found: {}", id));
+    }
+
+    let listener = event_tasks.listeners.remove(id);
+    // a batch being collected is dropped along with it
+    event_tasks.batches.remove(id);
+    event_tasks.save(data_dir)?;
+    drop(event_tasks);
+
+    // not while locked, the listener may be running
+    if let (Some(listener), Some(app_handle)) = (listener, runtime.inner.app_handle.get()) {
+        app_handle.unlisten(listener);
+    }
+
+    Ok(())
+}
+
+fn listen_to(runtime: &TaskRuntime, event_task: &EventTask) {
+    let Some(app_handle) = runtime.inner.app_handle.get() else {
+        return;
+    };
+
+    let inner: Weak<RuntimeState> = Arc::downgrade(&runtime.inner);
+    let id = event_task.id.clone();
+    let listener = app_handle.listen_any(event_task.event_name.clone(), move |event| {
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        let payload = serde_json::from_str(event.payload()).unwrap_or_default();
+        trigger(&TaskRuntime { inner }, &id, payload);
+    });
+
+    runtime
+        .inner
+        .event_tasks
+        .lock()
+        .unwrap()
+        .listeners
+        .insert(event_task.id.clone(), listener);
+}
+
+/// Listens to the events of the tasks registered in earlier sessions, once
+/// the runtime has an app handle.
+pub fn listen(runtime: &TaskRuntime) {
+    let event_tasks = {
+        let mut event_tasks = runtime.inner.event_tasks.lock().unwrap();
+        let data_dir = runtime.data_dir();
+        event_tasks
+            .list(data_dir)
+            .into_iter()
+            .filter(|event_task| !event_tasks.listeners.contains_key(&event_task.id))
+            .collect::<Vec<_>>()
+    };
+
+    for event_task in &event_tasks {
+        listen_to(runtime, event_task);
+    }
+}
+
+// Runs the event task `id` for an event, or adds the payload to the batch of
+// a debounced one. Returns the id of the task started right away, if any
+fn trigger(runtime: &TaskRuntime, id: &str, payload: serde_json::Value) -> Option<String> {
+    let data_dir = runtime.data_dir();
+    let mut event_tasks = runtime.inner.event_tasks.lock().unwrap();
+    let debounce_ms = event_tasks.get(data_dir, id)?.debounce_ms;
+
+    let Some(debounce_ms) = debounce_ms else {
+        drop(event_tasks);
+        return run(runtime, id, payload);
+    };
+
+    let batch = event_tasks
+        .batches
+        .entry(id.to_string())
+        .or_insert_with(|| batch(runtime, id, Duration::from_millis(debounce_ms)));
+    // the batching thread ends with the runtime
+    let _ = batch.send(payload);
+
+    None
+}
+
+// A thread running the event task with the payloads sent to it, once none
+// came for `debounce`
+fn batch(runtime: &TaskRuntime, id: &str, debounce: Duration) -> mpsc::Sender<serde_json::Value> {
+    let (payloads_tx, payloads) = mpsc::channel::<serde_json::Value>();
+    let inner: Weak<RuntimeState> = Arc::downgrade(&runtime.inner);
+    let id = id.to_string();
+
+    thread::spawn(move || {
+        while let Ok(payload) = payloads.recv() {
+            let mut batch = vec![payload];
+            while let Ok(payload) = payloads.recv_timeout(debounce) {
+                if !batch.contains(&payload) {
+                    batch.push(payload);
+                }
+            }
+
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            run(&TaskRuntime { inner }, &id, serde_json::Value::Array(batch));
+        }
+    });
+
+    payloads_tx
+}
+
+/// Starts a run of the event task `id` with `payload` as its input, through
+/// `run_task` like any task. Returns the id of the task started.
+pub fn run(runtime: &TaskRuntime, id: &str, payload: serde_json::Value) -> Option<String> {
+    let data_dir = runtime.data_dir();
+    let event_task = runtime
+        .inner
+        .event_tasks
+        .lock()
+        .unwrap()
+        .get(data_dir, id)?;
+
+    let mut options = event_task.options;
+    options.set_input(payload);
+
+    let result = resolve_task_id(None, Some(&event_task.id)).and_then(|task_id| {
+        runtime
+            .run_task(&task_id, &event_task.code, options)
+            .map(|_| task_id)
+    });
+
+    match result {
+        Ok(task_id) => {
+            log!(
+                Info,
+                "Event {} started task {}",
+                event_task.event_name,
+                task_id
+            );
+            runtime
+                .inner
+                .event_tasks
+                .lock()
+                .unwrap()
+                .record_run(data_dir, id, &task_id);
+            Some(task_id)
+        }
+        Err(e) => {
+            log!(Error, "Event task {} failed to start: {}", event_task.id, e);
+            None
+        }
+    }
+}
+
+/// Runs the tasks registered on `event_name` as if it was emitted with
+/// `payload`. Returns the ids of the tasks started.
+#[cfg(any(test, feature = "test-support"))]
+pub fn run_on(runtime: &TaskRuntime, event_name: &str, payload: serde_json::Value) -> Vec<String> {
+    let ids: Vec<String> = runtime
+        .inner
+        .event_tasks
+        .lock()
+        .unwrap()
+        .list(runtime.data_dir())
+        .into_iter()
+        .filter(|event_task| event_task.event_name == event_name)
+        .map(|event_task| event_task.id)
+        .collect();
+
+    ids.iter()
+        .filter_map(|id| trigger(runtime, id, payload.clone()))
+        .collect()
+}
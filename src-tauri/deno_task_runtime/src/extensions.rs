@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use deno_runtime::deno_broadcast_channel;
+use deno_runtime::deno_cache;
+use deno_runtime::deno_canvas;
+use deno_runtime::deno_console;
+use deno_runtime::deno_core::{Extension, JsRuntime, ModuleSpecifier, OpDecl, RuntimeOptions};
+use deno_runtime::deno_cron;
+use deno_runtime::deno_crypto;
+use deno_runtime::deno_fetch;
+use deno_runtime::deno_ffi;
+use deno_runtime::deno_fs::{self, RealFs};
+use deno_runtime::deno_http;
+use deno_runtime::deno_io;
+use deno_runtime::deno_kv;
+use deno_runtime::deno_napi;
+use deno_runtime::deno_net;
+use deno_runtime::deno_node;
+use deno_runtime::deno_permissions::{Permissions, PermissionsContainer};
+use deno_runtime::deno_tls;
+use deno_runtime::deno_url;
+use deno_runtime::deno_web;
+use deno_runtime::deno_webgpu;
+use deno_runtime::deno_webidl;
+use deno_runtime::deno_websocket;
+use deno_runtime::deno_webstorage;
+use deno_runtime::ops;
+
+use super::{create_worker, runtime_extension, RunOptions, TaskRuntime};
+
+const CORE_EXTENSION: &str = "deno_core";
+const UNKNOWN_EXTENSION: &str = "unknown";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OpDescription {
+    name: String,
+    is_async: bool,
+    arg_count: u8,
+    /// Declared as not observable from outside the isolate (pure getters)
+    no_side_effects: bool,
+}
+
+impl From<&OpDecl> for OpDescription {
+    fn from(decl: &OpDecl) -> Self {
+        Self {
+            name: decl.name.to_string(),
+            is_async: decl.is_async,
+            arg_count: decl.arg_count,
+            no_side_effects: decl.no_side_effects,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExtensionDescription {
+    name: String,
+    ops: Vec<OpDescription>,
+    /// `ext:` specifiers of the JS modules the extension loads into the isolate
+    esm_modules: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExtensionsReport {
+    extensions: Vec<ExtensionDescription>,
+    op_count: usize,
+}
+
+/// Lists every op and JS module a task's worker is set up with, grouped by the
+/// extension that provides them, so users can audit what scripts can reach.
+///
+/// The ops come from a throwaway worker, so anything deno_runtime registers
+/// that isn't attributed below still shows up, under `unknown`.
+///
+/// Blocks while the isolates are created, don't call it from the main thread.
+pub fn describe_extensions(runtime: &TaskRuntime) -> Result<ExtensionsReport, String> {
+    let runtime = runtime.clone();
+
+    // isolates must not be created on the main thread
+    let handle = std::thread::spawn(move || {
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        tokio_runtime.block_on(async { Ok::<_, String>(describe(&runtime)) })
+    });
+
+    handle
+        .join()
+        .map_err(|_| "Describing the extensions panicked".to_string())?
+}
+
+fn describe(runtime: &TaskRuntime) -> ExtensionsReport {
+    let core_ops: HashSet<&'static str> = core_op_decls().iter().map(|decl| decl.name).collect();
+
+    let mut extensions: BTreeMap<&'static str, ExtensionDescription> = BTreeMap::new();
+    let mut attributed_ops = HashMap::new();
+
+    for extension in known_extensions() {
+        for decl in extension.ops.iter() {
+            attributed_ops.insert(decl.name, extension.name);
+        }
+
+        extensions.insert(
+            extension.name,
+            ExtensionDescription {
+                name: extension.name.to_string(),
+                ops: Vec::new(),
+                esm_modules: extension
+                    .get_esm_sources()
+                    .iter()
+                    .map(|source| source.specifier.to_string())
+                    .collect(),
+            },
+        );
+    }
+
+    // our extension isn't part of the throwaway worker, it needs a task
+    let task_extension = task_extension(runtime);
+    let mut decls = worker_op_decls();
+    decls.extend(task_extension.ops.iter().copied());
+    for decl in task_extension.ops.iter() {
+        attributed_ops.insert(decl.name, task_extension.name);
+    }
+
+    let mut op_count = 0;
+    let mut seen = HashSet::new();
+
+    for decl in &decls {
+        if !seen.insert(decl.name) {
+            continue;
+        }
+
+        let extension = if core_ops.contains(decl.name) {
+            CORE_EXTENSION
+        } else {
+            attributed_ops
+                .get(decl.name)
+                .copied()
+                .unwrap_or(UNKNOWN_EXTENSION)
+        };
+
+        extensions
+            .entry(extension)
+            .or_insert_with(|| ExtensionDescription {
+                name: extension.to_string(),
+                ops: Vec::new(),
+                esm_modules: Vec::new(),
+            })
+            .ops
+            .push(OpDescription::from(decl));
+        op_count += 1;
+    }
+
+    ExtensionsReport {
+        extensions: extensions.into_values().collect(),
+        op_count,
+    }
+}
+
+// Sees every op of the isolate it's registered into, including the built-in
+// ones of deno_core
+fn op_recorder(decls: Rc<RefCell<Vec<OpDecl>>>) -> Extension {
+    Extension {
+        name: "op_recorder",
+        middleware_fn: Some(Box::new(move |decl| {
+            decls.borrow_mut().push(decl);
+            decl
+        })),
+        ..Default::default()
+    }
+}
+
+fn core_op_decls() -> Vec<OpDecl> {
+    let decls = Rc::new(RefCell::new(Vec::new()));
+
+    let _runtime = JsRuntime::new(RuntimeOptions {
+        extensions: vec![op_recorder(decls.clone())],
+        ..Default::default()
+    });
+
+    decls.take()
+}
+
+fn worker_op_decls() -> Vec<OpDecl> {
+    let decls = Rc::new(RefCell::new(Vec::new()));
+
+    let main_module = ModuleSpecifier::parse("file:///describe_extensions.js").unwrap();
+    let _worker = create_worker(
+        &main_module,
+        Arc::new(RealFs),
+        Permissions::none_without_prompt(),
+        vec![op_recorder(decls.clone())],
+        &RunOptions::default(),
+        Default::default(),
+        None,
+    );
+
+    decls.take()
+}
+
+fn task_extension(runtime: &TaskRuntime) -> Extension {
+    runtime_extension::init_ops_and_esm(
+        runtime.clone(),
+        String::new(),
+        String::new(),
+        RunOptions::default(),
+        None,
+    )
+}
+
+// Descriptors only, their state is never set up. Mirrors the extensions
+// deno_runtime's MainWorker registers (deno_telemetry isn't re-exported, its
+// ops show up as unknown), keep in sync when bumping it.
+fn known_extensions() -> Vec<Extension> {
+    let fs: deno_fs::FileSystemRc = Arc::new(RealFs);
+
+    vec![
+        deno_webidl::deno_webidl::init_ops_and_esm(),
+        deno_console::deno_console::init_ops_and_esm(),
+        deno_url::deno_url::init_ops_and_esm(),
+        deno_web::deno_web::init_ops_and_esm::<PermissionsContainer>(
+            Default::default(),
+            Default::default(),
+        ),
+        deno_webgpu::deno_webgpu::init_ops_and_esm(),
+        deno_canvas::deno_canvas::init_ops_and_esm(),
+        deno_fetch::deno_fetch::init_ops_and_esm::<PermissionsContainer>(Default::default()),
+        deno_cache::deno_cache::init_ops_and_esm::<deno_cache::SqliteBackedCache>(None),
+        deno_websocket::deno_websocket::init_ops_and_esm::<PermissionsContainer>(
+            String::new(),
+            None,
+            None,
+        ),
+        deno_webstorage::deno_webstorage::init_ops_and_esm(None),
+        deno_crypto::deno_crypto::init_ops_and_esm(None),
+        deno_broadcast_channel::deno_broadcast_channel::init_ops_and_esm(
+            deno_broadcast_channel::InMemoryBroadcastChannel::default(),
+        ),
+        deno_ffi::deno_ffi::init_ops_and_esm::<PermissionsContainer>(),
+        deno_net::deno_net::init_ops_and_esm::<PermissionsContainer>(None, None),
+        deno_tls::deno_tls::init_ops_and_esm(),
+        deno_kv::deno_kv::init_ops_and_esm(
+            deno_kv::sqlite::SqliteDbHandler::<PermissionsContainer>::new(None, None),
+            deno_kv::KvConfig::builder().build(),
+        ),
+        deno_cron::deno_cron::init_ops_and_esm(deno_cron::local::LocalCronHandler::new()),
+        deno_napi::deno_napi::init_ops_and_esm::<PermissionsContainer>(),
+        deno_http::deno_http::init_ops_and_esm::<deno_http::DefaultHttpPropertyExtractor>(
+            deno_http::Options::default(),
+        ),
+        deno_io::deno_io::init_ops_and_esm(Default::default()),
+        deno_fs::deno_fs::init_ops_and_esm::<PermissionsContainer>(fs.clone()),
+        deno_node::deno_node::init_ops_and_esm::<PermissionsContainer>(None, fs),
+        deno_runtime::runtime::init_ops_and_esm(),
+        ops::runtime::deno_runtime::init_ops("deno:runtime".parse().unwrap()),
+        ops::worker_host::deno_worker_host::init_ops(
+            Arc::new(|_| unreachable!("never set up")),
+            None,
+        ),
+        ops::fs_events::deno_fs_events::init_ops(),
+        ops::os::deno_os::init_ops(Default::default()),
+        ops::permissions::deno_permissions::init_ops(),
+        ops::process::deno_process::init_ops(None),
+        ops::signal::deno_signal::init_ops(),
+        ops::tty::deno_tty::init_ops(),
+        ops::http::deno_http_runtime::init_ops(),
+        ops::bootstrap::deno_bootstrap::init_ops(None),
+        ops::web_worker::deno_web_worker::init_ops(),
+    ]
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use deno_runtime::deno_core::FeatureChecker;
+use deno_runtime::deno_kv;
+use deno_runtime::deno_webgpu;
+use deno_runtime::inspector_server::InspectorServer;
+use deno_runtime::UNSTABLE_GRANULAR_FLAGS;
+
+// Unstable Deno APIs exposed to tasks, each behind the cargo feature of the
+// same name
+const UNSTABLE_FEATURES: &[(&str, bool)] = &[
+    (deno_kv::UNSTABLE_FEATURE_NAME, cfg!(feature = "kv")),
+    (deno_webgpu::UNSTABLE_FEATURE_NAME, cfg!(feature = "webgpu")),
+];
+
+#[cfg(feature = "inspector")]
+const INSPECTOR_HOST: &str = "127.0.0.1:9229";
+
+pub fn is_unstable_feature_enabled(name: &str) -> bool {
+    UNSTABLE_FEATURES
+        .iter()
+        .any(|(feature, enabled)| *feature == name && *enabled)
+}
+
+/// Checked by the ops of unstable APIs.
+pub fn feature_checker() -> Arc<FeatureChecker> {
+    let mut checker = FeatureChecker::default();
+
+    for (feature, enabled) in UNSTABLE_FEATURES {
+        if *enabled {
+            checker.enable_feature(feature);
+        }
+    }
+
+    Arc::new(checker)
+}
+
+/// Ids of the enabled unstable features, used by the JS side of the runtime
+/// to expose their APIs.
+pub fn unstable_feature_ids() -> Vec<i32> {
+    UNSTABLE_GRANULAR_FLAGS
+        .iter()
+        .filter(|flag| is_unstable_feature_enabled(flag.name))
+        .map(|flag| flag.id)
+        .collect()
+}
+
+/// Shared by every task, each one shows up as a target in chrome://inspect.
+#[cfg(feature = "inspector")]
+pub fn inspector_server() -> Option<Arc<InspectorServer>> {
+    use once_cell::sync::Lazy;
+
+    use super::config::log;
+
+    static SERVER: Lazy<Option<Arc<InspectorServer>>> = Lazy::new(|| {
+        let host = INSPECTOR_HOST.parse().unwrap();
+
+        match InspectorServer::new(host, "tauri-deno-example") {
+            Ok(server) => Some(Arc::new(server)),
+            Err(e) => {
+                log!(Error, "Failed to start the inspector on {}: {}", host, e);
+                None
+            }
+        }
+    });
+
+    SERVER.clone()
+}
+
+#[cfg(not(feature = "inspector"))]
+pub fn inspector_server() -> Option<Arc<InspectorServer>> {
+    None
+}
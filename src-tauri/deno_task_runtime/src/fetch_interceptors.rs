@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use deno_runtime::deno_core::error::AnyError;
+use deno_runtime::deno_fetch::{self, ReqBody};
+use deno_runtime::worker::MainWorker;
+use http::{HeaderName, HeaderValue};
+use once_cell::sync::Lazy;
+
+/// Host-side middleware of the `fetch` calls of a saved script, e.g. adding
+/// the credentials of an API so they're never in the script, logging the
+/// requests or blocking URLs.
+///
+/// Registered by the app with `register_fetch_interceptor` and enabled per
+/// script with `TaskRuntime::set_script_fetch_interceptors`. Runs on the
+/// task's thread before each request is sent, an error blocks the request.
+pub trait FetchInterceptor: Send + Sync {
+    fn intercept(&self, script: &str, request: &mut FetchRequest) -> Result<(), String>;
+}
+
+impl fmt::Debug for dyn FetchInterceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FetchInterceptor")
+    }
+}
+
+/// A request of a task about to be sent.
+pub struct FetchRequest<'a> {
+    request: &'a mut http::Request<ReqBody>,
+}
+
+impl FetchRequest<'_> {
+    pub fn method(&self) -> &str {
+        self.request.method().as_str()
+    }
+
+    pub fn url(&self) -> String {
+        self.request.uri().to_string()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.request.headers().get(name)?.to_str().ok()
+    }
+
+    /// Replaces the header if the script set it.
+    pub fn set_header(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let name = HeaderName::try_from(name).map_err(|e| e.to_string())?;
+        let mut value = HeaderValue::try_from(value).map_err(|e| e.to_string())?;
+        // kept out of HTTP/2 header compression
+        value.set_sensitive(true);
+
+        self.request.headers_mut().insert(name, value);
+        Ok(())
+    }
+
+    pub fn remove_header(&mut self, name: &str) {
+        self.request.headers_mut().remove(name);
+    }
+}
+
+// Shared by the runtimes of every profile
+static INTERCEPTORS: Lazy<Mutex<BTreeMap<String, Arc<dyn FetchInterceptor>>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Registers `interceptor` under `name`, replacing the one registered under
+/// the same name.
+pub fn register_fetch_interceptor(name: &str, interceptor: impl FetchInterceptor + 'static) {
+    INTERCEPTORS
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), Arc::new(interceptor));
+}
+
+/// Names of the registered interceptors, sorted.
+pub fn list_fetch_interceptors() -> Vec<String> {
+    INTERCEPTORS.lock().unwrap().keys().cloned().collect()
+}
+
+pub fn is_registered(name: &str) -> bool {
+    INTERCEPTORS.lock().unwrap().contains_key(name)
+}
+
+struct TaskInterceptors {
+    script: String,
+    names: Vec<String>,
+}
+
+thread_local! {
+    // Interceptors of the task whose worker runs on this thread, deno_fetch
+    // only takes a plain function as its hook
+    static TASK_INTERCEPTORS: RefCell<Option<TaskInterceptors>> = const { RefCell::new(None) };
+}
+
+/// Runs the interceptors named in `names` on the requests of the task
+/// running `script` on the current thread, until the guard is dropped.
+pub(crate) fn install(
+    worker: &mut MainWorker,
+    script: &str,
+    names: Vec<String>,
+) -> TaskInterceptorsGuard {
+    TASK_INTERCEPTORS.set(Some(TaskInterceptors {
+        script: script.to_string(),
+        names,
+    }));
+
+    let op_state = worker.js_runtime.op_state();
+    let mut state = op_state.borrow_mut();
+    let mut options = state.borrow::<deno_fetch::Options>().clone();
+    options.request_builder_hook = Some(intercept);
+    state.put(options);
+
+    TaskInterceptorsGuard
+}
+
+pub(crate) struct TaskInterceptorsGuard;
+
+impl Drop for TaskInterceptorsGuard {
+    fn drop(&mut self) {
+        TASK_INTERCEPTORS.take();
+    }
+}
+
+fn intercept(request: &mut http::Request<ReqBody>) -> Result<(), AnyError> {
+    TASK_INTERCEPTORS.with_borrow(|task| {
+        let Some(task) = task else {
+            return Ok(());
+        };
+
+        for name in &task.names {
+            // not held while the interceptor runs
+            let interceptor = INTERCEPTORS.lock().unwrap().get(name).cloned();
+            // a missing one may have been meant to block the request
+            let Some(interceptor) = interceptor else {
+                return Err(AnyError::msg(format!(
+                    "Fetch interceptor {} isn't registered",
+                    name
+                )));
+            };
+
+            interceptor
+                .intercept(&task.script, &mut FetchRequest { request })
+                .map_err(|e| {
+                    AnyError::msg(format!("Blocked by fetch interceptor {}: {}", name, e))
+                })?;
+        }
+
+        Ok(())
+    })
+}
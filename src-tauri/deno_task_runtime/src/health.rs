@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use deno_ast::MediaType;
+use deno_runtime::deno_core::v8;
+use deno_runtime::deno_core::ModuleSpecifier;
+use deno_runtime::deno_fs::RealFs;
+use deno_runtime::deno_permissions::Permissions;
+
+use super::module_loader::transpile;
+use super::staging::code_dir;
+use super::{create_worker, RunOptions, TaskRuntime};
+
+// Exercises the isolate and the ICU data (formatting fails or falls back to
+// plain digits when the ICU data is missing)
+const CANARY_SCRIPT: &str = r#"JSON.stringify({
+  sum: [1, 2, 3].reduce((a, b) => a + b, 0),
+  formatted: new Intl.NumberFormat("de-DE").format(1234.5),
+})"#;
+
+const CANARY_TYPESCRIPT: &str = "const answer: number = 42;\nexport default answer;\n";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HealthCheck {
+    name: String,
+    ok: bool,
+    detail: String,
+}
+
+impl HealthCheck {
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+
+        Self {
+            name: name.to_string(),
+            ok,
+            detail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HealthReport {
+    ok: bool,
+    checks: Vec<HealthCheck>,
+}
+
+/// Verifies the pieces every task depends on, so a broken runtime is detected
+/// before the first user task fails.
+///
+/// Blocks while the canary isolate runs, don't call it from the main thread.
+pub fn runtime_health_check(runtime: &TaskRuntime) -> HealthReport {
+    let checks = vec![
+        HealthCheck::new("isolate", check_isolate()),
+        HealthCheck::new("transpiler", check_transpiler()),
+        HealthCheck::new("cache_dirs", check_cache_dirs()),
+        HealthCheck::new("event_bridge", check_event_bridge(runtime)),
+    ];
+
+    HealthReport {
+        ok: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+fn check_isolate() -> Result<String, String> {
+    let output = evaluate_in_isolate(CANARY_SCRIPT.to_string())?;
+
+    let output: serde_json::Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+
+    if output["sum"] != 6 {
+        return Err(format!("Unexpected canary result: {}", output));
+    }
+
+    if output["formatted"] != "1.234,5" {
+        return Err(format!(
+            "ICU data seems to be missing, Intl formatted 1234.5 as {}",
+            output["formatted"]
+        ));
+    }
+
+    Ok(format!("Canary script returned {}", output))
+}
+
+/// Evaluates a script in a throwaway isolate without permissions and returns
+/// its completion value as a string.
+pub(super) fn evaluate_in_isolate(script: String) -> Result<String, String> {
+    // isolates must not be created on the main thread, run it like a task does
+    let handle = std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        runtime.block_on(async {
+            let main_module = ModuleSpecifier::parse("file:///health_check.js").unwrap();
+
+            let mut worker = create_worker(
+                &main_module,
+                Arc::new(RealFs),
+                Permissions::none_without_prompt(),
+                vec![],
+                &RunOptions::default(),
+                Default::default(),
+                None,
+            );
+
+            let value = worker
+                .execute_script("[health_check]", script.into())
+                .map_err(|e| e.to_string())?;
+
+            let scope = &mut worker.js_runtime.handle_scope();
+            let value = v8::Local::new(scope, value);
+
+            Ok::<_, String>(value.to_rust_string_lossy(scope))
+        })
+    });
+
+    handle
+        .join()
+        .map_err(|_| "Canary isolate panicked".to_string())?
+}
+
+fn check_transpiler() -> Result<String, String> {
+    let specifier = ModuleSpecifier::parse("file:///health_check.ts").unwrap();
+
+    let (code, _) = transpile(
+        &specifier,
+        CANARY_TYPESCRIPT.to_string(),
+        MediaType::TypeScript,
+    )
+    .map_err(|e| e.to_string())?;
+
+    if code.contains(": number") {
+        return Err(format!("Type annotations were not stripped: {}", code));
+    }
+
+    Ok("TypeScript transpiled".to_string())
+}
+
+fn check_cache_dirs() -> Result<String, String> {
+    let dir = code_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    // make sure task code can actually be staged there
+    let probe = dir.join(".health_check");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("{}: {}", probe.display(), e))?;
+    std::fs::remove_file(&probe).map_err(|e| format!("{}: {}", probe.display(), e))?;
+
+    Ok(format!("{} is writable", dir.display()))
+}
+
+fn check_event_bridge(runtime: &TaskRuntime) -> Result<String, String> {
+    if !runtime.is_event_listener_running() {
+        return Err("Task events listener is not running".to_string());
+    }
+
+    Ok("Task events listener is running".to_string())
+}
@@ -0,0 +1,48 @@
+use deno_runtime::deno_core::v8;
+use deno_runtime::worker::MainWorker;
+
+use super::config::log;
+use super::TaskRuntime;
+
+// Below this the worker can't even bootstrap, and V8 aborts the process when
+// it runs out of heap before the callback is installed
+pub const MIN_HEAP_SIZE_MB: u64 = 32;
+
+/// Caps the heap of the isolate of a task at `max_heap_size_mb`.
+pub fn create_params(max_heap_size_mb: u64) -> v8::CreateParams {
+    let max_bytes = usize::try_from(max_heap_size_mb * 1024 * 1024).unwrap_or(usize::MAX);
+
+    v8::CreateParams::default().heap_limits(0, max_bytes)
+}
+
+/// Fails the task once its heap nears the limit, rather than letting V8 abort
+/// the whole app when it's reached.
+pub fn install(
+    worker: &mut MainWorker,
+    runtime: &TaskRuntime,
+    task_id: &str,
+    max_heap_size_mb: u64,
+) {
+    let isolate = worker.js_runtime.v8_isolate().thread_safe_handle();
+    let runtime = runtime.clone();
+    let task_id = task_id.to_string();
+
+    worker
+        .js_runtime
+        .add_near_heap_limit_callback(move |current_limit, _initial_limit| {
+            log!(Error, "Task {} is out of memory", task_id);
+
+            // before the termination fails it with a less telling error
+            runtime.fail_task(
+                &task_id,
+                format!(
+                    "Out of memory: the task reached its heap limit of {} MB",
+                    max_heap_size_mb
+                ),
+            );
+            isolate.terminate_execution();
+
+            // room for the script to unwind
+            current_limit * 2
+        });
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use deno_runtime::deno_fetch::{self, dns::Resolver};
+use deno_runtime::worker::MainWorker;
+use hickory_resolver::{AsyncResolver, Hosts};
+
+/// Checks a host name to address mapping, e.g. `{"api.example.com": "10.0.0.5"}`.
+pub fn validate(hosts: &HashMap<String, String>) -> Result<(), String> {
+    for (host, address) in hosts {
+        if host.is_empty() || host.contains(|c: char| c.is_whitespace() || c == '#') {
+            return Err(format!("Invalid host name {:?}", host));
+        }
+        address
+            .parse::<IpAddr>()
+            .map_err(|e| format!("Invalid address {:?} for {}: {}", address, host, e))?;
+    }
+
+    Ok(())
+}
+
+/// Resolves the mapped hosts of the `fetch` calls of `worker` to their
+/// addresses, and the rest with the system's DNS settings. The system hosts
+/// file isn't read then, like the proxy this must run before the task does.
+pub fn apply_to_worker(
+    worker: &mut MainWorker,
+    hosts: &HashMap<String, String>,
+) -> Result<(), String> {
+    // in the hosts file format, which takes care of IPv4 and IPv6
+    let conf: String = hosts
+        .iter()
+        .map(|(host, address)| format!("{} {}\n", address, host))
+        .collect();
+    let hosts = Hosts::default()
+        .read_hosts_conf(conf.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    let mut resolver = AsyncResolver::tokio_from_system_conf()
+        .map_err(|e| format!("Failed to read the DNS settings: {}", e))?;
+    resolver.set_hosts(Some(hosts));
+
+    let op_state = worker.js_runtime.op_state();
+    let mut state = op_state.borrow_mut();
+
+    let mut options = state.borrow::<deno_fetch::Options>().clone();
+    options.resolver = Resolver::hickory_from_async_resolver(resolver);
+    state.put(options);
+    state.try_take::<deno_fetch::Client>();
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::{TaskHandle, TaskRuntime, TaskState};
+
+// How long a completed task stands for its key, trigger retries come within
+// seconds
+const COMPLETED_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug)]
+struct KeyedTask {
+    task_id: String,
+    submitted_at: Instant,
+}
+
+/// Tasks by the idempotency key they were submitted with, see
+/// `RunOptions::idempotency_key`.
+#[derive(Debug, Default)]
+pub struct IdempotencyKeys {
+    keys: HashMap<String, KeyedTask>,
+}
+
+// Whether the task submitted for a key still stands for it: it's queued or
+// running, or it completed recently. Failed and stopped tasks can be retried.
+fn stands_for_key(runtime: &TaskRuntime, keyed: &KeyedTask) -> bool {
+    match runtime.get_task_state(&keyed.task_id) {
+        Some(task) if !task.is_finished() => true,
+        Some(task) => {
+            matches!(task.state(), TaskState::Completed)
+                && keyed.submitted_at.elapsed() < COMPLETED_TTL
+        }
+        None => false,
+    }
+}
+
+/// Starts a task with `start` unless one was already submitted with `key`,
+/// whose handle is returned instead.
+pub fn run_once(
+    runtime: &TaskRuntime,
+    key: &str,
+    task_id: &str,
+    start: impl FnOnce() -> Result<TaskHandle, String>,
+) -> Result<TaskHandle, String> {
+    if key.is_empty() {
+        return Err("Idempotency key is empty".to_string());
+    }
+
+    // held while starting, so a key submitted twice at once starts one task
+    let mut keys = runtime.inner.idempotency_keys.lock().unwrap();
+    keys.keys.retain(|_, keyed| stands_for_key(runtime, keyed));
+
+    if let Some(keyed) = keys.keys.get(key) {
+        return Ok(TaskHandle::new(runtime.clone(), &keyed.task_id));
+    }
+
+    let handle = start()?;
+    keys.keys.insert(
+        key.to_string(),
+        KeyedTask {
+            task_id: task_id.to_string(),
+            submitted_at: Instant::now(),
+        },
+    );
+
+    Ok(handle)
+}
+
+/// Forgets the keys whose task no longer stands for them. Returns how many
+/// were forgotten.
+pub fn prune(runtime: &TaskRuntime) -> usize {
+    let mut keys = runtime.inner.idempotency_keys.lock().unwrap();
+    let before = keys.keys.len();
+    keys.keys.retain(|_, keyed| stands_for_key(runtime, keyed));
+
+    before - keys.keys.len()
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use super::checkpoint;
+use super::config::log;
+use super::{RunOptions, TaskRuntime, TaskState};
+
+const RUNNING_TASKS_FILE: &str = "running_tasks.json";
+
+// What the next session needs to mark the task interrupted, and to run it
+// again
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct RunningTask {
+    task_id: String,
+    code: String,
+    options: RunOptions,
+}
+
+/// The tasks of a runtime whose thread is running, kept on disk so the next
+/// session knows which ones the app quit during.
+#[derive(Debug, Default)]
+pub struct RunningTasks {
+    tasks: Option<Vec<RunningTask>>,
+}
+
+fn running_tasks_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(RUNNING_TASKS_FILE)
+}
+
+impl RunningTasks {
+    // Read from disk on first use
+    fn tasks(&mut self, data_dir: &Path) -> &mut Vec<RunningTask> {
+        self.tasks.get_or_insert_with(|| {
+            std::fs::read(running_tasks_path(data_dir))
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn save(&mut self, data_dir: &Path) {
+        let json = serde_json::to_vec(self.tasks(data_dir)).map_err(|e| e.to_string());
+        let result = json.and_then(|json| {
+            std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+            std::fs::write(running_tasks_path(data_dir), json).map_err(|e| e.to_string())
+        });
+
+        if let Err(e) = result {
+            log!(Error, "Failed to save the running tasks: {}", e);
+        }
+    }
+
+    pub fn add(&mut self, data_dir: &Path, task_id: &str, code: &str, options: &RunOptions) {
+        let tasks = self.tasks(data_dir);
+        tasks.retain(|task| task.task_id != task_id);
+        tasks.push(RunningTask {
+            task_id: task_id.to_string(),
+            code: code.to_string(),
+            options: options.clone(),
+        });
+        self.save(data_dir);
+    }
+
+    pub fn remove(&mut self, data_dir: &Path, task_id: &str) {
+        self.tasks(data_dir).retain(|task| task.task_id != task_id);
+        self.save(data_dir);
+    }
+}
+
+/// Whether the app quit while tasks of the runtime at `data_dir` were
+/// running.
+pub fn has_running_tasks(data_dir: &Path) -> bool {
+    !RunningTasks::default().tasks(data_dir).is_empty()
+}
+
+/// Marks the tasks the last session quit during `interrupted`, and starts the
+/// restartable ones again. Returns the ids of the interrupted tasks.
+pub fn restore(runtime: &TaskRuntime) -> Vec<String> {
+    let data_dir = runtime.data_dir();
+
+    // the ones running in this session, if any, aren't left over
+    let interrupted: Vec<RunningTask> = {
+        let mut running = runtime.inner.running_tasks.lock().unwrap();
+        let (interrupted, alive) = running
+            .tasks(data_dir)
+            .drain(..)
+            .partition(|task| !runtime.is_task_thread_alive(&task.task_id));
+        *running.tasks(data_dir) = alive;
+        running.save(data_dir);
+        interrupted
+    };
+
+    for task in &interrupted {
+        log!(
+            Info,
+            "Task {} was interrupted by the app quitting",
+            task.task_id
+        );
+        let marked = runtime.insert_task(&task.task_id, TaskState::Interrupted, &task.options);
+        runtime.emit_task_state_changed(marked);
+
+        if !task.options.restartable {
+            continue;
+        }
+        let restarted = match checkpoint::load(data_dir, &task.task_id) {
+            Ok(_) => runtime.resume_task(&task.task_id),
+            Err(_) => runtime.run_task(&task.task_id, &task.code, task.options.clone()),
+        };
+        if let Err(e) = restarted {
+            log!(Error, "Failed to restart task {}: {}", task.task_id, e);
+        }
+    }
+
+    interrupted.into_iter().map(|task| task.task_id).collect()
+}
@@ -0,0 +1,80 @@
+// Makes `locale` and `timeZone` the defaults of `Intl` and of the
+// `toLocale*String` methods, either may be undefined. Only formatting is
+// covered, `Date`'s local time getters keep following the host.
+function installIntl(locale, timeZone) {
+  if (locale === undefined && timeZone === undefined) {
+    return;
+  }
+
+  // throws a RangeError on unknown ones
+  Intl.getCanonicalLocales(locale);
+  new Intl.DateTimeFormat(locale, { timeZone });
+
+  const withTimeZone = (options) =>
+    timeZone === undefined || options?.timeZone !== undefined
+      ? options
+      : { ...options, timeZone };
+
+  const wrapConstructor = (name, withOptions) => {
+    const Real = Intl[name];
+    if (Real === undefined) {
+      return;
+    }
+
+    function Wrapped(locales, options) {
+      const args = [locales ?? locale, withOptions(options)];
+      return new.target
+        ? Reflect.construct(Real, args, new.target)
+        : Real(...args);
+    }
+    Object.defineProperty(Wrapped, "name", { value: name });
+    Wrapped.prototype = Real.prototype;
+    Wrapped.supportedLocalesOf = Real.supportedLocalesOf;
+    Intl[name] = Wrapped;
+  };
+
+  wrapConstructor("DateTimeFormat", withTimeZone);
+  for (const name of [
+    "Collator",
+    "DisplayNames",
+    "ListFormat",
+    "NumberFormat",
+    "PluralRules",
+    "RelativeTimeFormat",
+    "Segmenter",
+  ]) {
+    wrapConstructor(name, (options) => options);
+  }
+
+  const wrapMethod = (prototype, name, withOptions) => {
+    const real = prototype[name];
+    Object.defineProperty(prototype, name, {
+      value: {
+        [name](locales, options) {
+          return real.call(this, locales ?? locale, withOptions(options));
+        },
+      }[name],
+      writable: true,
+      configurable: true,
+    });
+  };
+
+  for (const name of [
+    "toLocaleString",
+    "toLocaleDateString",
+    "toLocaleTimeString",
+  ]) {
+    wrapMethod(Date.prototype, name, withTimeZone);
+  }
+  wrapMethod(Number.prototype, "toLocaleString", (options) => options);
+  wrapMethod(BigInt.prototype, "toLocaleString", (options) => options);
+
+  const realLocaleCompare = String.prototype.localeCompare;
+  Object.defineProperty(String.prototype, "localeCompare", {
+    value: function localeCompare(that, locales, options) {
+      return realLocaleCompare.call(this, that, locales ?? locale, options);
+    },
+    writable: true,
+    configurable: true,
+  });
+}
@@ -0,0 +1,135 @@
+use std::sync::Mutex;
+
+use deno_runtime::worker::MainWorker;
+use once_cell::sync::Lazy;
+
+use super::health::evaluate_in_isolate;
+
+// Resolved by the isolate itself so it reflects what scripts will actually see
+const PROBE_SCRIPT: &str = r#"JSON.stringify({
+  locale: Intl.DateTimeFormat().resolvedOptions().locale,
+  timezone: Intl.DateTimeFormat().resolvedOptions().timeZone,
+  utc_offset_minutes: -new Date().getTimezoneOffset(),
+})"#;
+
+// The `installIntl(locale, timeZone)` function, called by `install_script`
+const INSTALL_INTL: &str = include_str!("intl.js");
+
+/// Where V8 gets its ICU data from.
+///
+/// `deno_core` is built with `include_icu_data`, so the ICU data is always the
+/// copy bundled in the binary and is identical between dev and packaged builds.
+/// Loading the system ICU is not supported: `rusty_v8` links its own ICU and
+/// `deno_core` refuses to start if other common data was registered first.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IcuDataSource {
+    Bundled,
+}
+
+/// Locale and timezone pinned for the isolates of the tasks started from now
+/// on, instead of inheriting them from the host (which is what makes `Intl`
+/// output differ between machines and between `tauri dev` and the installed
+/// app).
+///
+/// It covers formatting, `Date`'s local time getters keep following the host.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct IntlConfig {
+    locale: Option<String>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IntlInfo {
+    icu_data: IcuDataSource,
+    config: IntlConfig,
+    locale: String,
+    timezone: String,
+    utc_offset_minutes: i64,
+}
+
+static INTL_CONFIG: Lazy<Mutex<IntlConfig>> = Lazy::new(|| Mutex::new(IntlConfig::default()));
+
+// Unknown names are only caught by ICU, in the isolate
+pub(super) fn validate(locale: Option<&str>, timezone: Option<&str>) -> Result<(), String> {
+    if let Some(locale) = locale {
+        if locale.is_empty() || locale.contains('\0') {
+            return Err(format!("Invalid locale: {:?}", locale));
+        }
+    }
+
+    if let Some(timezone) = timezone {
+        if timezone.is_empty() || timezone.contains('\0') {
+            return Err(format!("Invalid timezone: {:?}", timezone));
+        }
+    }
+
+    Ok(())
+}
+
+/// Tasks already running keep the locale and timezone they started with.
+pub fn set_intl_config(config: IntlConfig) -> Result<(), String> {
+    validate(config.locale.as_deref(), config.timezone.as_deref())?;
+
+    *INTL_CONFIG.lock().unwrap() = config;
+
+    Ok(())
+}
+
+/// Reports the ICU data source and the locale/timezone a new isolate resolves.
+///
+/// Blocks while the probe isolate runs, don't call it from the main thread.
+pub fn get_intl_info() -> Result<IntlInfo, String> {
+    let config = INTL_CONFIG.lock().unwrap().clone();
+    let script = format!(
+        "{}\n{}",
+        install_script(config.locale.as_deref(), config.timezone.as_deref()),
+        PROBE_SCRIPT
+    );
+    let output = evaluate_in_isolate(script)?;
+    let output: serde_json::Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+
+    Ok(IntlInfo {
+        icu_data: IcuDataSource::Bundled,
+        config,
+        locale: output["locale"].as_str().unwrap_or_default().to_string(),
+        timezone: output["timezone"].as_str().unwrap_or_default().to_string(),
+        utc_offset_minutes: output["utc_offset_minutes"].as_i64().unwrap_or_default(),
+    })
+}
+
+/// Pins the locale and timezone for the `Intl` formatting of the task run by
+/// `worker`, the task's own if given, else those of `IntlConfig`.
+/// Process-wide settings couldn't differ between the isolates running at the
+/// same time.
+pub(super) fn apply_to_task(
+    worker: &mut MainWorker,
+    locale: Option<&str>,
+    timezone: Option<&str>,
+) -> Result<(), String> {
+    let config = INTL_CONFIG.lock().unwrap().clone();
+    let script = install_script(
+        locale.or(config.locale.as_deref()),
+        timezone.or(config.timezone.as_deref()),
+    );
+
+    worker
+        .execute_script("[task_intl]", script.into())
+        .map(drop)
+        .map_err(|e| format!("Invalid locale or timezone: {}", e))
+}
+
+fn install_script(locale: Option<&str>, timezone: Option<&str>) -> String {
+    format!(
+        "({})({}, {});",
+        INSTALL_INTL.trim_end(),
+        js_string(locale),
+        js_string(timezone)
+    )
+}
+
+fn js_string(value: Option<&str>) -> String {
+    value.map_or("undefined".to_string(), |value| {
+        serde_json::Value::String(value.to_string()).to_string()
+    })
+}
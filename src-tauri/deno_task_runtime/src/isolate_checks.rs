@@ -0,0 +1,117 @@
+use deno_runtime::deno_core::v8;
+use deno_runtime::deno_permissions::{PermissionState, PermissionsContainer};
+use deno_runtime::worker::MainWorker;
+
+const GLOBALS_SCRIPT: &str = "JSON.stringify(Object.getOwnPropertyNames(globalThis).sort())";
+
+/// What a worker looks like right after it's bootstrapped, so one handed to a
+/// task after another can be checked for what the other left behind.
+#[derive(Debug)]
+pub(crate) struct IsolateBaseline {
+    globals: Vec<String>,
+    resources: Vec<String>,
+}
+
+impl IsolateBaseline {
+    pub fn capture(worker: &mut MainWorker) -> Result<Self, String> {
+        Ok(Self {
+            globals: globals(worker)?,
+            resources: resources(worker),
+        })
+    }
+
+    /// Fails with what differs from the baseline: globals added or removed,
+    /// resources left open, or permissions granted or denied since.
+    pub fn verify(&self, worker: &mut MainWorker) -> Result<(), String> {
+        let globals = globals(worker)?;
+        let added: Vec<&str> = globals
+            .iter()
+            .filter(|name| !self.globals.contains(name))
+            .map(String::as_str)
+            .collect();
+        let removed: Vec<&str> = self
+            .globals
+            .iter()
+            .filter(|name| !globals.contains(name))
+            .map(String::as_str)
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            return Err(format!(
+                "globals differ, added: [{}], removed: [{}]",
+                added.join(", "),
+                removed.join(", ")
+            ));
+        }
+
+        let resources = resources(worker);
+        if resources != self.resources {
+            return Err(format!(
+                "resources differ, [{}] instead of [{}]",
+                resources.join(", "),
+                self.resources.join(", ")
+            ));
+        }
+
+        let permissions = permission_states(worker);
+        if let Some((name, state)) = permissions
+            .into_iter()
+            .find(|(_, state)| *state != PermissionState::Prompt)
+        {
+            return Err(format!(
+                "{} permission is {:?} instead of prompt",
+                name, state
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn globals(worker: &mut MainWorker) -> Result<Vec<String>, String> {
+    let value = worker
+        .execute_script("[isolate_checks]", GLOBALS_SCRIPT.to_string().into())
+        .map_err(|e| e.to_string())?;
+
+    let json = {
+        let scope = &mut worker.js_runtime.handle_scope();
+        let value = v8::Local::new(scope, value);
+        value.to_rust_string_lossy(scope)
+    };
+
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+// Names rather than ids, ids keep growing as resources come and go
+fn resources(worker: &mut MainWorker) -> Vec<String> {
+    let op_state = worker.js_runtime.op_state();
+    let state = op_state.borrow();
+    let mut names: Vec<String> = state
+        .resource_table
+        .names()
+        .map(|(_, name)| name.to_string())
+        .collect();
+    names.sort();
+
+    names
+}
+
+// Whole-kind queries: a grant for a single path or host shows as granted
+// partially rather than prompt
+fn permission_states(worker: &mut MainWorker) -> Vec<(&'static str, PermissionState)> {
+    let op_state = worker.js_runtime.op_state();
+    let state = op_state.borrow();
+    let permissions = state.borrow::<PermissionsContainer>();
+
+    [
+        ("read", permissions.query_read(None).ok()),
+        ("write", permissions.query_write(None).ok()),
+        ("net", permissions.query_net(None).ok()),
+        ("env", Some(permissions.query_env(None))),
+        ("sys", permissions.query_sys(None).ok()),
+        ("run", permissions.query_run(None).ok()),
+        ("ffi", permissions.query_ffi(None).ok()),
+    ]
+    .into_iter()
+    .map(|(name, state)| (name, state.unwrap_or(PermissionState::Denied)))
+    .collect()
+}
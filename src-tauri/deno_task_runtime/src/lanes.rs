@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::RunOptions;
+
+/// Which tasks go first once `max_concurrent_tasks` is reached.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskLane {
+    /// Started by the user, starts right away by taking the slot of a
+    /// background task if needed
+    #[default]
+    Interactive,
+    /// Scheduled jobs, queued until a slot is free
+    Background,
+}
+
+/// The tasks holding a slot and those waiting for one, for the UI.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskQueue {
+    running: usize,
+    max_concurrent_tasks: Option<usize>,
+    /// Queued tasks are held back, e.g. under memory pressure
+    paused: bool,
+    /// In the order they'll start
+    queued: Vec<QueueEntry>,
+    /// Running tasks by the saved script they run
+    #[ts(type = "Record<string, number>")]
+    in_flight: BTreeMap<String, usize>,
+}
+
+impl TaskQueue {
+    pub fn running(&self) -> usize {
+        self.running
+    }
+
+    pub fn queued(&self) -> &[QueueEntry] {
+        &self.queued
+    }
+
+    pub fn in_flight(&self, script: &str) -> usize {
+        self.in_flight.get(script).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct QueueEntry {
+    task_id: String,
+    priority: i32,
+    /// Saved script of the task, `None` for code run as is
+    script: Option<String>,
+}
+
+impl QueueEntry {
+    pub fn task_id(&self) -> &str {
+        &self.task_id
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct QueuedTask {
+    pub task_id: String,
+    pub code: String,
+    pub options: RunOptions,
+}
+
+impl QueuedTask {
+    fn script(&self) -> Option<&str> {
+        self.options.saved_script.as_deref()
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    lane: TaskLane,
+    script: Option<String>,
+}
+
+pub(crate) enum Admission {
+    Start,
+    Queue,
+    /// Every slot is taken by an interactive task
+    Full(usize),
+}
+
+/// Lanes of the running tasks and the background tasks waiting for a slot.
+///
+/// An interactive task preempts the slot of a running background task: the
+/// background task keeps running, but its slot only goes back to the queue
+/// once the running tasks are below the limit again.
+///
+/// Among queued tasks of the same priority, those of the saved script with
+/// the fewest running tasks go first, so a burst of runs of one script
+/// doesn't starve the others.
+#[derive(Debug, Default)]
+pub(crate) struct Lanes {
+    running: HashMap<String, Slot>,
+    queued: VecDeque<QueuedTask>,
+    // background tasks stay queued while set, e.g. under memory pressure
+    paused: bool,
+}
+
+impl Lanes {
+    /// Whether a task of `lane` can start, counted as running if so.
+    pub fn admit(
+        &mut self,
+        task_id: &str,
+        lane: TaskLane,
+        script: Option<&str>,
+        limit: Option<usize>,
+    ) -> Admission {
+        let full = limit.is_some_and(|limit| self.running.len() >= limit);
+
+        let admission = match lane {
+            // queued tasks go first, whatever the free slots
+            TaskLane::Background if full || self.paused || !self.queued.is_empty() => {
+                Admission::Queue
+            }
+            TaskLane::Interactive
+                if full
+                    && !self
+                        .running
+                        .values()
+                        .any(|slot| slot.lane == TaskLane::Background) =>
+            {
+                Admission::Full(limit.unwrap_or_default())
+            }
+            _ => Admission::Start,
+        };
+
+        if let Admission::Start = admission {
+            self.running.insert(
+                task_id.to_string(),
+                Slot {
+                    lane,
+                    script: script.map(str::to_string),
+                },
+            );
+        }
+        admission
+    }
+
+    /// Queues a background task, returns the tasks that can start now.
+    pub fn queue(&mut self, task: QueuedTask, limit: Option<usize>) -> Vec<QueuedTask> {
+        self.queued.push_back(task);
+
+        self.drain(limit)
+    }
+
+    /// Frees the slot of a task that ended, returns the tasks that can start
+    /// in its place.
+    pub fn finish(&mut self, task_id: &str, limit: Option<usize>) -> Vec<QueuedTask> {
+        self.running.remove(task_id);
+        self.drain(limit)
+    }
+
+    /// Holds back the queued tasks, or lets them start again, returns the
+    /// tasks that can start now.
+    pub fn set_paused(&mut self, paused: bool, limit: Option<usize>) -> Vec<QueuedTask> {
+        self.paused = paused;
+        self.drain(limit)
+    }
+
+    /// Takes a task out of the queue, false if it isn't queued.
+    pub fn cancel(&mut self, task_id: &str) -> bool {
+        let queued = self.queued.len();
+        self.queued.retain(|task| task.task_id != task_id);
+        self.queued.len() != queued
+    }
+
+    fn in_flight(&self) -> BTreeMap<String, usize> {
+        let mut in_flight = BTreeMap::new();
+        for script in self.running.values().filter_map(|slot| slot.script.clone()) {
+            *in_flight.entry(script).or_default() += 1;
+        }
+
+        in_flight
+    }
+
+    pub fn snapshot(&self, limit: Option<usize>) -> TaskQueue {
+        let in_flight = self.in_flight();
+
+        // the order they'd start in if none of the running tasks ended
+        let mut queued: Vec<&QueuedTask> = self.queued.iter().collect();
+        let mut started = in_flight.clone();
+        let mut order = Vec::new();
+        while let Some(index) = next_index(queued.iter().copied(), &started) {
+            let task = queued.remove(index);
+            if let Some(script) = task.script() {
+                *started.entry(script.to_string()).or_default() += 1;
+            }
+            order.push(QueueEntry {
+                task_id: task.task_id.clone(),
+                priority: task.options.priority,
+                script: task.script().map(str::to_string),
+            });
+        }
+
+        TaskQueue {
+            running: self.running.len(),
+            max_concurrent_tasks: limit,
+            paused: self.paused,
+            queued: order,
+            in_flight,
+        }
+    }
+
+    fn drain(&mut self, limit: Option<usize>) -> Vec<QueuedTask> {
+        let mut startable = Vec::new();
+        if self.paused {
+            return startable;
+        }
+
+        while limit.is_none_or(|limit| self.running.len() < limit) {
+            let Some(index) = next_index(self.queued.iter(), &self.in_flight()) else {
+                break;
+            };
+            let Some(task) = self.queued.remove(index) else {
+                break;
+            };
+            self.running.insert(
+                task.task_id.clone(),
+                Slot {
+                    lane: TaskLane::Background,
+                    script: task.script().map(str::to_string),
+                },
+            );
+            startable.push(task);
+        }
+
+        startable
+    }
+}
+
+// The queued task to start next: of the highest priority, then of the script
+// with the fewest running tasks, then the first queued. Code run as is counts
+// as a script of its own.
+fn next_index<'a>(
+    queued: impl Iterator<Item = &'a QueuedTask>,
+    in_flight: &BTreeMap<String, usize>,
+) -> Option<usize> {
+    queued
+        .enumerate()
+        .min_by_key(|(index, task)| {
+            let running = task
+                .script()
+                .and_then(|script| in_flight.get(script))
+                .copied()
+                .unwrap_or_default();
+            (std::cmp::Reverse(task.options.priority), running, *index)
+        })
+        .map(|(index, _)| index)
+}
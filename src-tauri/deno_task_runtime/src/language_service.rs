@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Mutex;
+
+use deno_ast::swc::ast::{Decl, ImportSpecifier, ModuleDecl, Pat, Stmt};
+use deno_ast::{MediaType, ModuleItemRef, ModuleSpecifier, ParseParams, SourceRangedForSpanned};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+
+use super::runtime_types::{Member, RUNTIME_EXTENSION, RUNTIME_EXTENSION_DOCS, STORE};
+
+// CompletionItemKind values of the LSP spec
+const KIND_METHOD: u32 = 2;
+const KIND_FUNCTION: u32 = 3;
+const KIND_VARIABLE: u32 = 6;
+const KIND_CLASS: u32 = 7;
+const KIND_INTERFACE: u32 = 8;
+const KIND_MODULE: u32 = 9;
+const KIND_PROPERTY: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SymbolKind {
+    Import,
+    Function,
+    Class,
+    Variable,
+    Type,
+}
+
+impl SymbolKind {
+    fn completion_kind(self) -> u32 {
+        match self {
+            SymbolKind::Import => KIND_MODULE,
+            SymbolKind::Function => KIND_FUNCTION,
+            SymbolKind::Class => KIND_CLASS,
+            SymbolKind::Variable => KIND_VARIABLE,
+            SymbolKind::Type => KIND_INTERFACE,
+        }
+    }
+}
+
+/// A name declared or imported at the top level of a script.
+#[derive(Debug, Clone)]
+struct Symbol {
+    name: String,
+    kind: SymbolKind,
+    /// Bytes of the name where it's declared
+    range: Range<usize>,
+    /// Specifier of the module it's imported from
+    import_from: Option<String>,
+    /// Source line of the declaration, shown on hover
+    declaration: String,
+}
+
+#[derive(Debug, Default)]
+struct Document {
+    text: String,
+    // from the last version that parsed, scripts don't parse mid-edit
+    symbols: Vec<Symbol>,
+}
+
+// Open documents by URI
+static DOCUMENTS: Lazy<Mutex<HashMap<String, Document>>> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct Position {
+    line: u32,
+    /// In UTF-16 code units, like editors count them
+    character: u32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentItem {
+    uri: String,
+    text: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidOpenParams {
+    text_document: TextDocumentItem,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentChange {
+    range: Option<Value>,
+    text: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChangeParams {
+    text_document: TextDocumentIdentifier,
+    content_changes: Vec<ContentChange>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidCloseParams {
+    text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionParams {
+    text_document: TextDocumentIdentifier,
+    position: Position,
+}
+
+/// Answers the language server requests of the script editor, taking and
+/// returning the params and results of the LSP spec:
+///
+/// - `textDocument/didOpen`, `textDocument/didChange` (full text only) and
+///   `textDocument/didClose` keep the documents in sync
+/// - `textDocument/completion` lists the members of `RuntimeExtension` and the
+///   names the script declares or imports
+/// - `textDocument/hover` describes them
+/// - `textDocument/definition` goes to their declaration, or to the imported
+///   module
+///
+/// There's no type checker behind it: other globals and the members of other
+/// objects get no answer.
+pub fn lsp_request(method: &str, params: Value) -> Result<Value, String> {
+    match method {
+        "textDocument/didOpen" => {
+            let params: DidOpenParams = parse_params(params)?;
+            let mut document = Document::default();
+            document.update(&params.text_document.uri, params.text_document.text);
+
+            DOCUMENTS
+                .lock()
+                .unwrap()
+                .insert(params.text_document.uri, document);
+            Ok(Value::Null)
+        }
+        "textDocument/didChange" => {
+            let params: DidChangeParams = parse_params(params)?;
+            let Some(change) = params.content_changes.into_iter().last() else {
+                return Ok(Value::Null);
+            };
+            if change.range.is_some() {
+                return Err("Only full document changes are supported".to_string());
+            }
+
+            let uri = params.text_document.uri;
+            let mut documents = DOCUMENTS.lock().unwrap();
+            documents
+                .entry(uri.clone())
+                .or_default()
+                .update(&uri, change.text);
+            Ok(Value::Null)
+        }
+        "textDocument/didClose" => {
+            let params: DidCloseParams = parse_params(params)?;
+            DOCUMENTS.lock().unwrap().remove(&params.text_document.uri);
+            Ok(Value::Null)
+        }
+        "textDocument/completion" => with_position(params, completion),
+        "textDocument/hover" => with_position(params, hover),
+        "textDocument/definition" => with_position(params, definition),
+        _ => Err(format!("Unsupported method: {}", method)),
+    }
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, String> {
+    serde_json::from_value(params).map_err(|e| format!("Invalid params: {}", e))
+}
+
+// Runs `f` on an open document and the byte offset of the position
+fn with_position(
+    params: Value,
+    f: impl FnOnce(&str, &Document, usize) -> Value,
+) -> Result<Value, String> {
+    let params: PositionParams = parse_params(params)?;
+    let documents = DOCUMENTS.lock().unwrap();
+    let Some(document) = documents.get(&params.text_document.uri) else {
+        return Err(format!("Document not open: {}", params.text_document.uri));
+    };
+
+    let offset = offset_at(&document.text, params.position);
+    Ok(f(&params.text_document.uri, document, offset))
+}
+
+impl Document {
+    fn update(&mut self, uri: &str, text: String) {
+        if let Some(symbols) = parse_symbols(uri, &text) {
+            self.symbols = symbols;
+        }
+        self.text = text;
+    }
+
+    fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+}
+
+fn completion(_uri: &str, document: &Document, offset: usize) -> Value {
+    let before = &document.text[..offset];
+    let prefix_start = identifier_start(before);
+    let prefix = &before[prefix_start..];
+
+    let items: Vec<Value> = match member_chain(&before[..prefix_start]) {
+        Some(chain) => members_of(&chain)
+            .unwrap_or_default()
+            .iter()
+            .filter(|member| member.name.starts_with(prefix))
+            .map(|member| {
+                json!({
+                    "label": member.name,
+                    "kind": if member.is_method() { KIND_METHOD } else { KIND_PROPERTY },
+                    "detail": member.signature,
+                    "documentation": member.docs,
+                })
+            })
+            .collect(),
+        None => {
+            let runtime_extension = json!({
+                "label": "RuntimeExtension",
+                "kind": KIND_VARIABLE,
+                "documentation": RUNTIME_EXTENSION_DOCS,
+            });
+
+            std::iter::once(runtime_extension)
+                .chain(document.symbols.iter().map(|symbol| {
+                    json!({
+                        "label": symbol.name,
+                        "kind": symbol.kind.completion_kind(),
+                        "detail": symbol.declaration,
+                    })
+                }))
+                .filter(|item| {
+                    item["label"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .starts_with(prefix)
+                })
+                .collect()
+        }
+    };
+
+    json!({ "isIncomplete": false, "items": items })
+}
+
+fn hover(_uri: &str, document: &Document, offset: usize) -> Value {
+    let Some(word) = word_at(&document.text, offset) else {
+        return Value::Null;
+    };
+    let name = &document.text[word.clone()];
+
+    let (declaration, docs) = match member_chain(&document.text[..word.start]) {
+        Some(chain) => {
+            let Some(member) = members_of(&chain)
+                .unwrap_or_default()
+                .iter()
+                .find(|member| member.name == name)
+            else {
+                return Value::Null;
+            };
+
+            let declaration = if member.is_method() {
+                format!("(method) {}.{}{}", chain.join("."), name, member.signature)
+            } else {
+                format!(
+                    "(property) {}.{}: {}",
+                    chain.join("."),
+                    name,
+                    member.signature
+                )
+            };
+            (declaration, member.docs)
+        }
+        None if name == "RuntimeExtension" => {
+            ("const RuntimeExtension".to_string(), RUNTIME_EXTENSION_DOCS)
+        }
+        None => match document.symbol(name) {
+            Some(symbol) => (symbol.declaration.clone(), ""),
+            None => return Value::Null,
+        },
+    };
+
+    let mut contents = format!("```ts\n{}\n```", declaration);
+    if !docs.is_empty() {
+        contents.push_str("\n\n");
+        contents.push_str(docs);
+    }
+
+    json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": range_json(&document.text, word),
+    })
+}
+
+fn definition(uri: &str, document: &Document, offset: usize) -> Value {
+    let Some(word) = word_at(&document.text, offset) else {
+        return Value::Null;
+    };
+    // members resolve to bootstrap.js, which the editor can't open
+    if member_chain(&document.text[..word.start]).is_some() {
+        return Value::Null;
+    }
+
+    let Some(symbol) = document.symbol(&document.text[word]) else {
+        return Value::Null;
+    };
+
+    let module = symbol.import_from.as_ref().and_then(|specifier| {
+        ModuleSpecifier::parse(uri)
+            .and_then(|referrer| referrer.join(specifier))
+            .ok()
+            .filter(|module| matches!(module.scheme(), "file" | "http" | "https"))
+    });
+
+    match module {
+        Some(module) => json!({
+            "uri": module.as_str(),
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 },
+            },
+        }),
+        None => json!({
+            "uri": uri,
+            "range": range_json(&document.text, symbol.range.clone()),
+        }),
+    }
+}
+
+// The objects scripts can access members of, `None` for anything else
+fn members_of(chain: &[&str]) -> Option<&'static [Member]> {
+    let chain = match chain {
+        ["globalThis", rest @ ..] => rest,
+        chain => chain,
+    };
+
+    match chain {
+        ["RuntimeExtension"] => Some(RUNTIME_EXTENSION),
+        ["RuntimeExtension", "store"] => Some(STORE),
+        _ => None,
+    }
+}
+
+// `a.b.` right before a member being typed, as `["a", "b"]`
+fn member_chain(before: &str) -> Option<Vec<&str>> {
+    let mut rest = before.strip_suffix('.')?;
+    let mut chain = Vec::new();
+
+    loop {
+        let start = identifier_start(rest);
+        if start == rest.len() {
+            return None;
+        }
+        chain.push(&rest[start..]);
+
+        match rest[..start].strip_suffix('.') {
+            Some(parent) => rest = parent,
+            None => break,
+        }
+    }
+
+    chain.reverse();
+    Some(chain)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// Start of the identifier `text` ends with, its length if there's none
+fn identifier_start(text: &str) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|(_, c)| is_identifier_char(*c))
+        .last()
+        .map_or(text.len(), |(i, _)| i)
+}
+
+// The identifier under or right before the cursor
+fn word_at(text: &str, offset: usize) -> Option<Range<usize>> {
+    let start = identifier_start(&text[..offset]);
+    let end = text[offset..]
+        .find(|c| !is_identifier_char(c))
+        .map_or(text.len(), |len| offset + len);
+
+    (start < end).then_some(start..end)
+}
+
+fn offset_at(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    line_start + line.len()
+}
+
+fn position_at(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+fn range_json(text: &str, range: Range<usize>) -> Value {
+    json!({
+        "start": position_at(text, range.start),
+        "end": position_at(text, range.end),
+    })
+}
+
+fn parse_symbols(uri: &str, text: &str) -> Option<Vec<Symbol>> {
+    let specifier = ModuleSpecifier::parse(uri)
+        .unwrap_or_else(|_| ModuleSpecifier::parse("file:///script.ts").unwrap());
+    let media_type = match MediaType::from_specifier(&specifier) {
+        MediaType::Unknown => MediaType::TypeScript,
+        media_type => media_type,
+    };
+
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier,
+        text: text.into(),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .ok()?;
+
+    let source_start = parsed.text_info_lazy().range().start;
+    let mut symbols = Vec::new();
+    let mut push = |ident: &deno_ast::swc::ast::Ident, kind, import_from: Option<String>| {
+        let range = ident.range().as_byte_range(source_start);
+        let name = ident.sym.to_string();
+
+        let declaration = match &import_from {
+            Some(specifier) => format!("(import) {} from \"{}\"", name, specifier),
+            None => declaration_line(text, range.start),
+        };
+
+        symbols.push(Symbol {
+            name,
+            kind,
+            range,
+            import_from,
+            declaration,
+        });
+    };
+
+    for item in parsed.program_ref().body() {
+        let decl = match item {
+            ModuleItemRef::ModuleDecl(ModuleDecl::Import(import)) => {
+                for specifier in &import.specifiers {
+                    let local = match specifier {
+                        ImportSpecifier::Named(named) => &named.local,
+                        ImportSpecifier::Default(default) => &default.local,
+                        ImportSpecifier::Namespace(namespace) => &namespace.local,
+                    };
+                    push(
+                        local,
+                        SymbolKind::Import,
+                        Some(import.src.value.to_string()),
+                    );
+                }
+                continue;
+            }
+            ModuleItemRef::ModuleDecl(ModuleDecl::ExportDecl(export)) => &export.decl,
+            ModuleItemRef::Stmt(Stmt::Decl(decl)) => decl,
+            _ => continue,
+        };
+
+        match decl {
+            Decl::Fn(function) => push(&function.ident, SymbolKind::Function, None),
+            Decl::Class(class) => push(&class.ident, SymbolKind::Class, None),
+            Decl::Var(var) => {
+                for declarator in &var.decls {
+                    if let Pat::Ident(binding) = &declarator.name {
+                        push(&binding.id, SymbolKind::Variable, None);
+                    }
+                }
+            }
+            Decl::TsInterface(interface) => push(&interface.id, SymbolKind::Type, None),
+            Decl::TsTypeAlias(alias) => push(&alias.id, SymbolKind::Type, None),
+            Decl::TsEnum(ts_enum) => push(&ts_enum.id, SymbolKind::Type, None),
+            _ => {}
+        }
+    }
+
+    Some(symbols)
+}
+
+// The line declaring a name, without the body it opens
+fn declaration_line(text: &str, offset: usize) -> String {
+    let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+
+    text[start..end]
+        .trim()
+        .trim_end_matches('{')
+        .trim_end()
+        .to_string()
+}
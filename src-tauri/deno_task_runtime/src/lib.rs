@@ -0,0 +1,3803 @@
+//! Runs user scripts as Deno tasks inside a Tauri app.
+//!
+//! Each task runs on its own thread with its own `MainWorker`, and reports
+//! its state, output and permission prompts as [`TaskEvent`]s emitted to the
+//! frontend. The main pieces:
+//!
+//! - [`TaskRuntime`] owns the tasks of a profile, [`Profiles`] keeps one per
+//!   profile for apps exposing several of them.
+//! - [`TaskHandle`] is a task started on a runtime.
+//! - [`PermissionBroker`] answers permission prompts without asking the user.
+//! - [`ModuleLoader`] serves task modules from outside the disk and network.
+//!
+//! The free functions ([`get_runtime_config`], [`get_storage_usage`], ...)
+//! back Tauri commands of the same name and are meant to be wrapped as-is.
+
+#![allow(clippy::print_stdout)]
+#![allow(clippy::print_stderr)]
+
+mod alert_rules;
+mod app_events;
+mod archives;
+mod artifacts;
+mod bridge;
+mod bundles;
+mod capabilities;
+mod cassette;
+mod catalog;
+mod checkpoint;
+mod client_certs;
+mod compaction;
+mod config;
+mod dead_letters;
+mod debugger;
+mod diff;
+mod event_log;
+mod event_tasks;
+mod extensions;
+mod features;
+mod fetch_interceptors;
+mod health;
+mod heap_limit;
+mod hosts;
+mod idempotency;
+mod interrupted;
+mod intl;
+mod isolate_checks;
+mod lanes;
+mod language_service;
+mod memory;
+mod messages;
+mod migrations;
+mod module_loader;
+mod op_grants;
+mod output;
+mod output_channels;
+mod overlay_fs;
+mod pause;
+mod pipelines;
+mod prewarm;
+mod profiles;
+mod progress;
+mod prompts;
+mod proxy;
+mod remote_objects;
+mod result_hooks;
+mod result_protocol;
+mod retries;
+mod runtime_snapshot;
+mod runtime_types;
+mod schedules;
+mod schemas;
+mod script_docs;
+mod script_inputs;
+mod scripts;
+mod sdk;
+mod signatures;
+mod snapshot;
+mod stack_frames;
+mod staging;
+mod stdin;
+mod storage;
+mod subscriptions;
+mod task_handle;
+mod task_history;
+mod task_ids;
+mod task_list;
+mod task_logs;
+mod task_store;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+mod threads;
+mod timings;
+mod trace;
+mod versions;
+mod watchdog;
+mod watches;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use alert_rules::AlertRules;
+use app_events::{AppEvent, AppEventListeners};
+use bridge::{BridgeCapability, BridgePermissions};
+use cassette::{Cassette, CassetteMode, CassetteOptions, RecordedResponse};
+use checkpoint::Checkpoint;
+use config::{log, PromptPolicy};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use debugger::{AttachRequests, DebugSession};
+use deno_runtime::deno_core::error::AnyError;
+use deno_runtime::deno_core::op2;
+use deno_runtime::deno_core::v8;
+use deno_runtime::deno_core::Extension;
+use deno_runtime::deno_core::ModuleSpecifier;
+use deno_runtime::deno_core::OpState;
+use deno_runtime::deno_core::ToJsBuffer;
+use deno_runtime::deno_fs::FileSystem;
+use deno_runtime::deno_fs::RealFs;
+use deno_runtime::deno_permissions::Permissions;
+use deno_runtime::deno_permissions::PermissionsContainer;
+use deno_runtime::deno_permissions::PromptResponse;
+use deno_runtime::permissions::RuntimePermissionDescriptorParser;
+use deno_runtime::worker::MainWorker;
+use deno_runtime::worker::WorkerOptions;
+use deno_runtime::worker::WorkerServiceOptions;
+use deno_runtime::BootstrapOptions;
+use event_log::EventLog;
+use event_tasks::EventTasks;
+use idempotency::IdempotencyKeys;
+use interrupted::RunningTasks;
+use isolate_checks::IsolateBaseline;
+use lanes::{Admission, Lanes, QueuedTask};
+use messages::{TaskInbox, TaskMailboxes};
+use module_loader::{ModuleLock, ModuleSources, TypescriptModuleLoader};
+use op_grants::{GrantedOps, OpAuditLog};
+use output_channels::OutputChannels;
+use overlay_fs::{FsChange, OverlayFs};
+use pause::{Pausable, PauseSwitch};
+use prewarm::{IdleWorker, PrewarmedThread, TaskStart};
+use schedules::Schedules;
+use staging::StagedCode;
+use std::io::Write;
+use stdin::{StdinWriter, TaskStdins};
+use subscriptions::Subscriptions;
+use task_history::History;
+use task_logs::TaskLogs;
+use task_store::TaskStore;
+use tauri::{AppHandle, Emitter, EventTarget};
+use tauri_plugin_store::StoreExt;
+use threads::ThreadCpuClock;
+use timings::TimingPhase;
+use versions::{PendingDiagnostics, TaskDiagnostic};
+use watchdog::Watchdog;
+use watches::Watches;
+
+pub use alert_rules::{Alert, AlertAction, AlertRule};
+pub use archives::{ArchiveEntry, ArchiveSummary, ArchivedArtifact, ArchivedTask, ArchivedTasks};
+pub use artifacts::{task_artifact_response, TASK_ARTIFACT_SCHEME};
+pub use bundles::sign_script_bundle;
+pub use capabilities::{get_runtime_capabilities, RuntimeCapabilities};
+pub use catalog::{CatalogEntry, CatalogScriptReview};
+pub use client_certs::{CertificateStore, ClientCertificate};
+pub use compaction::CompactionReport;
+pub use config::{
+    get_runtime_config, log_enabled, reload_runtime_config, set_runtime_config, AppliedConfig,
+    LogLevel, RuntimeConfig,
+};
+pub use dead_letters::DeadLetter;
+pub use debugger::{DebugCallFrame, DebugPause, DebugStep};
+pub use diff::TaskRunDiff;
+pub use event_log::SequencedEvent;
+pub use event_tasks::EventTask;
+pub use extensions::{describe_extensions, ExtensionsReport};
+pub use fetch_interceptors::{
+    list_fetch_interceptors, register_fetch_interceptor, FetchInterceptor, FetchRequest,
+};
+pub use health::{runtime_health_check, HealthReport};
+pub use intl::{get_intl_info, set_intl_config, IntlConfig, IntlInfo};
+pub use lanes::{QueueEntry, TaskLane, TaskQueue};
+pub use language_service::lsp_request;
+pub use migrations::run_migrations;
+pub use module_loader::ModuleLoader;
+pub use op_grants::OpGrant;
+pub use output::TaskOutput;
+pub use output_channels::{OutputStream, TaskOutputChunk};
+pub use pipelines::{
+    ErrorPolicy, PipelineEdge, PipelineGraph, PipelineNode, PipelineSpec, PipelineState,
+    PipelineStatus, PipelineStep, StepState, StepStatus,
+};
+pub use profiles::Profiles;
+pub use progress::TaskProgress;
+pub use proxy::ProxyOptions;
+pub use remote_objects::{EntryPreview, ObjectPreview, PropertyPreview, RemoteObject};
+pub use result_hooks::{list_result_hooks, register_result_hook, AppendToReport, ResultHook};
+pub use result_protocol::{task_result_response, TASK_RESULT_SCHEME};
+pub use retries::RetryPolicy;
+pub use runtime_snapshot::{PendingPrompt, RuntimeSnapshot};
+pub use runtime_types::runtime_types;
+pub use schedules::Schedule;
+pub use schemas::{get_json_schemas, JsonSchemas};
+pub use script_docs::{FunctionDoc, ParamDoc, ScriptDocs};
+pub use sdk::{export_typescript_sdk, typescript_sdk};
+pub use signatures::{generate_bundle_signing_key, BundleSigningKey, TrustedKey};
+pub use stack_frames::StackFrame;
+pub use storage::{get_storage_usage, set_storage_quota, StorageUsage};
+pub use subscriptions::SubscriptionFilters;
+pub use task_handle::TaskHandle;
+pub use task_history::{TaskHistory, TaskHistoryFilter, TaskHistoryPage, TaskRecord};
+pub use task_ids::{resolve_task_id, validate_task_id};
+pub use task_list::{TaskList, TaskSummary};
+pub use task_logs::{ConsoleLevel, TaskLog};
+pub use threads::pin_ui_thread;
+pub use timings::TaskTimings;
+pub use trace::{record_command, replay_trace};
+pub use versions::{get_runtime_versions, RuntimeVersions};
+pub use watches::TaskWatch;
+
+/// Events emitted to the frontend, the tag is the Tauri event name.
+#[derive(Debug, Clone, serde::Serialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(tag = "event")]
+pub enum TaskEvent {
+    #[serde(rename = "task-state-changed")]
+    StateChanged(Box<Task>),
+    #[serde(rename = "task-output-truncated")]
+    OutputTruncated { task_id: String, limit: usize },
+    #[serde(rename = "task-log")]
+    Log(TaskLog),
+    #[serde(rename = "pipeline-state-changed")]
+    PipelineStateChanged(Box<PipelineState>),
+    /// A background run failed, see `TaskRuntime::list_dead_letters`
+    #[serde(rename = "task-dead-lettered")]
+    DeadLettered(Box<DeadLetter>),
+    /// An `AlertAction::Notify` rule fired
+    #[serde(rename = "task-alert")]
+    Alert(Box<Alert>),
+    /// The task called `RuntimeExtension.reportProgress`
+    #[serde(rename = "task-progress")]
+    Progress {
+        task_id: String,
+        progress: TaskProgress,
+    },
+    /// The task called `RuntimeExtension.postMessage`
+    #[serde(rename = "task-message")]
+    Message {
+        task_id: String,
+        #[ts(type = "unknown")]
+        message: serde_json::Value,
+    },
+    /// A task run with `debug_run_task` hit a breakpoint or finished a step
+    #[serde(rename = "task-debug-paused")]
+    DebugPaused { task_id: String, pause: DebugPause },
+    #[serde(rename = "task-debug-resumed")]
+    DebugResumed { task_id: String },
+}
+
+impl TaskEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            TaskEvent::StateChanged(_) => "task-state-changed",
+            TaskEvent::OutputTruncated { .. } => "task-output-truncated",
+            TaskEvent::Log(_) => "task-log",
+            TaskEvent::PipelineStateChanged(_) => "pipeline-state-changed",
+            TaskEvent::DeadLettered(_) => "task-dead-lettered",
+            TaskEvent::Alert(_) => "task-alert",
+            TaskEvent::Progress { .. } => "task-progress",
+            TaskEvent::Message { .. } => "task-message",
+            TaskEvent::DebugPaused { .. } => "task-debug-paused",
+            TaskEvent::DebugResumed { .. } => "task-debug-resumed",
+        }
+    }
+
+    fn task_id(&self) -> &str {
+        match self {
+            TaskEvent::StateChanged(task) => &task.id,
+            TaskEvent::OutputTruncated { task_id, .. } => task_id,
+            TaskEvent::Log(log) => &log.task_id,
+            // filtered like task ids
+            TaskEvent::PipelineStateChanged(pipeline) => &pipeline.id,
+            TaskEvent::DeadLettered(letter) => &letter.task_id,
+            TaskEvent::Alert(alert) => &alert.task_id,
+            TaskEvent::Progress { task_id, .. } => task_id,
+            TaskEvent::Message { task_id, .. } => task_id,
+            TaskEvent::DebugPaused { task_id, .. } => task_id,
+            TaskEvent::DebugResumed { task_id } => task_id,
+        }
+    }
+}
+
+// Host events a task can fall behind on before missing the oldest ones
+const HOST_EVENTS_CAPACITY: usize = 16;
+
+// 2024-01-01T00:00:00Z, start of the virtual clock of deterministic runs
+const DETERMINISTIC_EPOCH_MS: f64 = 1_704_067_200_000.0;
+
+/// Tasks and everything needed to drive them, one per profile (see
+/// `Profiles`).
+///
+/// Cloning is cheap and clones share the same tasks, each `TaskRuntime::new`
+/// is an independent runtime.
+#[derive(Debug, Clone)]
+pub struct TaskRuntime {
+    inner: Arc<RuntimeState>,
+}
+
+// Tasks are owned by the store's thread. None of the remaining locks is ever
+// held while taking another one, or while emitting an event or waiting on a
+// channel.
+#[derive(Debug)]
+struct RuntimeState {
+    profile: String,
+    // cassettes, checkpoints and staged code of this runtime's tasks
+    data_dir: PathBuf,
+    tasks: TaskStore,
+    threads: Mutex<HashMap<String, thread::JoinHandle<Result<(), String>>>>,
+    lanes: Mutex<Lanes>,
+    // Whether a task thread is kept ready with an idle worker, and that thread
+    prewarm: AtomicBool,
+    prewarmed: Mutex<Option<PrewarmedThread>>,
+    memory_pressure: AtomicBool,
+    // As reported by the app, see `set_online`
+    online: AtomicBool,
+    // `queue_until_online` tasks started while offline, started once back
+    // online. Locked while `online` changes so none is left behind
+    waiting_for_network: Mutex<Vec<QueuedTask>>,
+    // Host events for the running tasks, see `RuntimeExtension.onHostEvent`
+    host_events: tokio::sync::broadcast::Sender<String>,
+    shutdown_channels: Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>,
+    permission_channels: Mutex<HashMap<String, Sender<PermissionsResponse>>>,
+    // Task events that will be received by Tauri
+    events: (Sender<TaskEvent>, Receiver<TaskEvent>),
+    // Whether the Tauri side of the events channel is being drained
+    event_listener_running: Arc<AtomicBool>,
+    // Events already sent to Tauri, for windows catching up
+    event_log: Arc<Mutex<EventLog>>,
+    subscriptions: Arc<Mutex<Subscriptions>>,
+    output_channels: Mutex<OutputChannels>,
+    task_logs: Mutex<TaskLogs>,
+    pipelines: Mutex<HashMap<String, PipelineState>>,
+    alert_rules: Mutex<AlertRules>,
+    schedules: Mutex<Schedules>,
+    event_tasks: Mutex<EventTasks>,
+    watches: Mutex<Watches>,
+    idempotency_keys: Mutex<IdempotencyKeys>,
+    history: Mutex<History>,
+    running_tasks: Mutex<RunningTasks>,
+    stdins: Mutex<TaskStdins>,
+    mailboxes: Mutex<TaskMailboxes>,
+    // For stopping tasks stuck in synchronous code, which never get to see
+    // their shutdown channel
+    isolates: Mutex<HashMap<String, v8::IsolateHandle>>,
+    debuggers: Mutex<HashMap<String, Arc<DebugSession>>>,
+    attach_requests: Mutex<HashMap<String, Arc<AttachRequests>>>,
+    pause_switches: Mutex<HashMap<String, Arc<PauseSwitch>>>,
+    // Set along with the listener, used by the bridge ops
+    app_handle: OnceLock<AppHandle>,
+    permission_broker: Mutex<Option<Arc<dyn PermissionBroker>>>,
+    module_loader: Mutex<Option<Arc<dyn ModuleLoader>>>,
+    certificate_store: Mutex<Option<Arc<dyn CertificateStore>>>,
+    // the disk when unset, dry runs always overlay the disk
+    file_system: Mutex<Option<Arc<dyn FileSystem>>>,
+    // tokio's paused clock, timers fire as soon as the task is idle
+    #[cfg(any(test, feature = "test-support"))]
+    virtual_clock: AtomicBool,
+}
+
+/// Options for a single task run, all of them optional for the frontend.
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+#[serde(default)]
+pub struct RunOptions {
+    /// Human-readable name shown instead of the task's id
+    name: Option<String>,
+    description: Option<String>,
+    /// Labels `list_tasks` and `get_task_history` filter and group tasks by,
+    /// e.g. `["reports", "nightly"]`
+    tags: Vec<String>,
+    /// Freezes the clock progression and seeds the RNGs so runs are reproducible
+    deterministic: bool,
+    /// Seed used in deterministic mode, defaults to 0
+    #[ts(type = "number | null")]
+    seed: Option<u64>,
+    /// File system writes go to an in-memory overlay and are reported on the
+    /// task instead of touching the disk
+    dry_run: bool,
+    /// Records the task's `fetch` calls to a named cassette, or replays them
+    /// from it without touching the network
+    network_cassette: Option<CassetteOptions>,
+    /// Host capabilities the task's custom ops may use, all of them if unset
+    grants: Option<Vec<OpGrant>>,
+    /// What happens to the task when the window that started it is closed
+    on_window_closed: WindowClosedPolicy,
+    /// Background tasks wait for a free slot once `max_concurrent_tasks` is
+    /// reached, interactive ones don't
+    lane: TaskLane,
+    /// Order of a queued background task, higher ones start first and ties
+    /// in the order they were queued
+    priority: i32,
+    /// Runs submitted with the key of a task still queued or running, or
+    /// completed in the last 10 minutes, get that task instead of starting
+    /// another, e.g. for triggers that may fire twice
+    idempotency_key: Option<String>,
+    /// Runs the task again when it fails, reporting the error only once the
+    /// attempts are used up
+    retry: Option<RetryPolicy>,
+    /// Runs the task's thread below the priority of the UI, for heavy scripts
+    low_priority: bool,
+    /// Defers the task while the app is offline instead of letting its
+    /// `fetch` calls fail, see `TaskRuntime::set_online`
+    queue_until_online: bool,
+    /// Runs the task again when the app quit while it was running, from its
+    /// last checkpoint if it saved one. It's only marked `interrupted`
+    /// otherwise, see `TaskRuntime::restore_interrupted_tasks`
+    restartable: bool,
+    /// Proxy of the task's `fetch` calls, the app-wide one if unset
+    proxy: Option<ProxyOptions>,
+    /// Addresses the task's `fetch` calls connect to for these host names,
+    /// e.g. to try a script against a staging server, without touching the
+    /// system hosts file
+    hosts: Option<HashMap<String, String>>,
+    /// Certificate the task's `fetch` calls present for mutual TLS
+    client_certificate: Option<ClientCertificate>,
+    /// Locale of the task's `Intl` formatting and `toLocale*String` calls,
+    /// e.g. `de-CH` for a report meant for Switzerland, the app's if unset
+    locale: Option<String>,
+    /// IANA timezone the task's dates are formatted in, e.g.
+    /// `Europe/Zurich`. `Date`'s local time getters keep the app's
+    timezone: Option<String>,
+    /// Fails the task once its JS heap grows past this many megabytes, so a
+    /// runaway script can't take the app down with it
+    #[ts(type = "number | null")]
+    max_heap_size_mb: Option<u64>,
+    /// Terminates the task once it has run for this long, counted from when
+    /// it starts rather than while it's queued
+    #[ts(type = "number | null")]
+    timeout_ms: Option<u64>,
+    /// Fails the task once its thread has spent this much CPU time on it,
+    /// unlike `timeout_ms` time spent waiting doesn't count
+    #[ts(type = "number | null")]
+    cpu_time_limit_ms: Option<u64>,
+    /// Arguments the script reads from `RuntimeExtension.args`
+    #[ts(type = "unknown")]
+    args: Option<serde_json::Value>,
+    /// Payload the script reads from `RuntimeExtension.input`, passed to
+    /// `run_task` apart from the options
+    #[ts(skip)]
+    #[schemars(skip)]
+    input: Option<serde_json::Value>,
+    /// State injected by `resume_task`, never set by the frontend
+    #[serde(skip)]
+    checkpoint_state: Option<String>,
+    /// Label of the window that started the task, set by `run_task`
+    #[serde(skip)]
+    owner_window: Option<String>,
+    /// Saved script the task runs, set by `run_saved_script`
+    #[serde(skip)]
+    saved_script: Option<String>,
+    /// Hashes its remote modules must match, from the bundle it was imported
+    /// from
+    #[serde(skip)]
+    module_lock: Option<Arc<ModuleLock>>,
+    /// Lines to pause at, set by `debug_run_task`
+    #[serde(skip)]
+    breakpoints: Option<Vec<u32>>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowClosedPolicy {
+    /// Keeps running, owned by the app from then on
+    #[default]
+    Detach,
+    /// Stops along with the window
+    Stop,
+}
+
+impl RunOptions {
+    fn rng_seed(&self) -> Option<u64> {
+        self.deterministic.then(|| self.seed.unwrap_or_default())
+    }
+
+    pub fn set_owner_window(&mut self, label: &str) {
+        self.owner_window = Some(label.to_string());
+    }
+
+    pub fn set_input(&mut self, input: serde_json::Value) {
+        self.input = Some(input);
+    }
+
+    fn grants(&self) -> &[OpGrant] {
+        self.grants.as_deref().unwrap_or(OpGrant::ALL)
+    }
+
+    // Idle workers are bootstrapped with the default options, the ones read
+    // while bootstrapping must match
+    fn can_adopt_idle_worker(&self) -> bool {
+        !self.deterministic
+            && !self.dry_run
+            && self.network_cassette.is_none()
+            && self.checkpoint_state.is_none()
+            && self.max_heap_size_mb.is_none()
+            && self.module_lock.is_none()
+    }
+}
+
+impl TaskRuntime {
+    pub fn new(profile: &str, data_dir: PathBuf) -> Self {
+        Self {
+            inner: Arc::new(RuntimeState {
+                profile: profile.to_string(),
+                data_dir,
+                tasks: TaskStore::spawn(profile),
+                threads: Mutex::new(HashMap::new()),
+                lanes: Mutex::new(Lanes::default()),
+                prewarm: AtomicBool::new(false),
+                prewarmed: Mutex::new(None),
+                memory_pressure: AtomicBool::new(false),
+                online: AtomicBool::new(true),
+                waiting_for_network: Mutex::new(Vec::new()),
+                host_events: tokio::sync::broadcast::channel(HOST_EVENTS_CAPACITY).0,
+                shutdown_channels: Mutex::new(HashMap::new()),
+                permission_channels: Mutex::new(HashMap::new()),
+                events: unbounded(),
+                event_listener_running: Arc::new(AtomicBool::new(false)),
+                event_log: Arc::new(Mutex::new(EventLog::default())),
+                subscriptions: Arc::new(Mutex::new(Subscriptions::default())),
+                output_channels: Mutex::new(OutputChannels::default()),
+                task_logs: Mutex::new(TaskLogs::default()),
+                pipelines: Mutex::new(HashMap::new()),
+                alert_rules: Mutex::new(AlertRules::default()),
+                schedules: Mutex::new(Schedules::default()),
+                event_tasks: Mutex::new(EventTasks::default()),
+                watches: Mutex::new(Watches::default()),
+                idempotency_keys: Mutex::new(IdempotencyKeys::default()),
+                history: Mutex::new(History::default()),
+                running_tasks: Mutex::new(RunningTasks::default()),
+                stdins: Mutex::new(TaskStdins::default()),
+                mailboxes: Mutex::new(TaskMailboxes::default()),
+                isolates: Mutex::new(HashMap::new()),
+                debuggers: Mutex::new(HashMap::new()),
+                attach_requests: Mutex::new(HashMap::new()),
+                pause_switches: Mutex::new(HashMap::new()),
+                app_handle: OnceLock::new(),
+                permission_broker: Mutex::new(None),
+                module_loader: Mutex::new(None),
+                certificate_store: Mutex::new(None),
+                file_system: Mutex::new(None),
+                #[cfg(any(test, feature = "test-support"))]
+                virtual_clock: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Answers the permission prompts of the tasks started from now on
+    /// before they reach the frontend.
+    pub fn set_permission_broker(&self, broker: impl PermissionBroker + 'static) {
+        *self.inner.permission_broker.lock().unwrap() = Some(Arc::new(broker));
+    }
+
+    /// Loads the modules of the tasks started from now on, before falling back
+    /// to the disk and the network.
+    pub fn set_module_loader(&self, loader: impl ModuleLoader + 'static) {
+        *self.inner.module_loader.lock().unwrap() = Some(Arc::new(loader));
+        self.refresh_prewarmed_thread();
+    }
+
+    /// Looks up the `keychain` client certificates of the tasks started from
+    /// now on.
+    pub fn set_certificate_store(&self, store: impl CertificateStore + 'static) {
+        *self.inner.certificate_store.lock().unwrap() = Some(Arc::new(store));
+    }
+
+    /// File system of the tasks started from now on, instead of the disk.
+    pub fn set_file_system(&self, fs: impl FileSystem + 'static) {
+        *self.inner.file_system.lock().unwrap() = Some(Arc::new(fs));
+        self.refresh_prewarmed_thread();
+    }
+
+    /// Keeps a worker bootstrapped ahead of the next task from now on, so it
+    /// starts without waiting for V8 and the Deno APIs to be set up. The
+    /// worker is rebootstrapped every few minutes while unused.
+    ///
+    /// Only tasks without options read while bootstrapping (`deterministic`,
+    /// `dry_run`, a network cassette, a checkpoint) adopt it.
+    pub fn prewarm_worker(&self) {
+        self.inner.prewarm.store(true, Ordering::SeqCst);
+        self.refresh_prewarmed_thread();
+    }
+
+    // replaces the prewarmed thread, the old one ends without running
+    // anything
+    fn refresh_prewarmed_thread(&self) {
+        if !self.inner.prewarm.load(Ordering::SeqCst)
+            || self.inner.memory_pressure.load(Ordering::SeqCst)
+        {
+            return;
+        }
+
+        let thread = PrewarmedThread::spawn(self.clone());
+        *self.inner.prewarmed.lock().unwrap() = Some(thread);
+    }
+
+    /// Reacts to the app running short on memory, or no longer: holds back
+    /// the queued background tasks, drops the caches and sends a
+    /// `memory-pressure` host event to the running tasks. Also driven by the
+    /// `memory_pressure_threshold_mb` setting, for apps forwarding the OS's
+    /// own signals.
+    pub fn set_memory_pressure(&self, under_pressure: bool) {
+        if self
+            .inner
+            .memory_pressure
+            .swap(under_pressure, Ordering::SeqCst)
+            == under_pressure
+        {
+            return;
+        }
+
+        let startable = self
+            .inner
+            .lanes
+            .lock()
+            .unwrap()
+            .set_paused(under_pressure, config::get().max_concurrent_tasks());
+        self.start_queued_tasks(startable);
+
+        if under_pressure {
+            module_loader::clear_transpile_cache();
+            // the idle worker is bootstrapped again once the pressure is over
+            self.inner.prewarmed.lock().unwrap().take();
+            // no task listening isn't an error
+            let _ = self.inner.host_events.send("memory-pressure".to_string());
+        } else {
+            self.refresh_prewarmed_thread();
+        }
+    }
+
+    /// Tells the runtime whether the host is online, as the app sees it. Tasks
+    /// with `queue_until_online` started while offline wait for this to be
+    /// set back, in the order they were started.
+    pub fn set_online(&self, online: bool) {
+        let waiting = {
+            let mut waiting = self.inner.waiting_for_network.lock().unwrap();
+            self.inner.online.store(online, Ordering::SeqCst);
+            if !online {
+                return;
+            }
+            std::mem::take(&mut *waiting)
+        };
+
+        for task in waiting {
+            log!(Info, "Back online, starting task {}", task.task_id);
+            if let Err(e) = self.admit_task(&task.task_id, &task.code, task.options) {
+                self.fail_task(&task.task_id, e);
+            }
+        }
+    }
+
+    fn take_prewarmed_thread(&self, options: &RunOptions) -> Option<PrewarmedThread> {
+        // idle workers run on the real clock
+        #[cfg(any(test, feature = "test-support"))]
+        if self.inner.virtual_clock.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        if !options.can_adopt_idle_worker() {
+            return None;
+        }
+
+        let thread = self.inner.prewarmed.lock().unwrap().take();
+        if thread.is_some() {
+            self.refresh_prewarmed_thread();
+        }
+        thread
+    }
+
+    /// Runs the timers of the tasks started from now on against a virtual
+    /// clock: a `setTimeout` of an hour fires as soon as nothing else is
+    /// left to do. `Date.now()` isn't affected, that's the `deterministic`
+    /// run option.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn set_virtual_clock(&self, enabled: bool) {
+        self.inner.virtual_clock.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Starts a task, or queues it when it's a background task and
+    /// `max_concurrent_tasks` is reached. With an `idempotency_key` already
+    /// submitted, returns the handle of that task instead.
+    pub fn run_task(
+        &self,
+        task_id: &str,
+        code: &str,
+        options: RunOptions,
+    ) -> Result<TaskHandle, String> {
+        match options.idempotency_key.clone() {
+            Some(key) => idempotency::run_once(self, &key, task_id, || {
+                self.start_task(task_id, code, options)
+            }),
+            None => self.start_task(task_id, code, options),
+        }
+    }
+
+    fn start_task(
+        &self,
+        task_id: &str,
+        code: &str,
+        options: RunOptions,
+    ) -> Result<TaskHandle, String> {
+        if let Some(proxy) = &options.proxy {
+            proxy.validate()?;
+        }
+        if let Some(hosts) = &options.hosts {
+            hosts::validate(hosts)?;
+        }
+        intl::validate(options.locale.as_deref(), options.timezone.as_deref())?;
+        if options
+            .max_heap_size_mb
+            .is_some_and(|mb| mb < heap_limit::MIN_HEAP_SIZE_MB)
+        {
+            return Err(format!(
+                "max_heap_size_mb must be at least {}",
+                heap_limit::MIN_HEAP_SIZE_MB
+            ));
+        }
+        if options.timeout_ms == Some(0) {
+            return Err("timeout_ms must be at least 1".to_string());
+        }
+        if options.cpu_time_limit_ms == Some(0) {
+            return Err("cpu_time_limit_ms must be at least 1".to_string());
+        }
+        if let Some(retry) = &options.retry {
+            retry.validate()?;
+        }
+        task_list::validate_tags(&options.tags)?;
+        // its thread and shutdown channel would be replaced, leaving it
+        // unstoppable and writing over the new run
+        let running = match self.get_task_state(task_id) {
+            Some(task) => !task.is_finished(),
+            // started, its thread hasn't put it in the store yet
+            None => self.inner.threads.lock().unwrap().contains_key(task_id),
+        };
+        if running {
+            return Err(format!("Task {} is already running", task_id));
+        }
+
+        if options.queue_until_online {
+            let mut waiting = self.inner.waiting_for_network.lock().unwrap();
+            if !self.inner.online.load(Ordering::SeqCst) {
+                let task = self.insert_task(task_id, TaskState::WaitingForNetwork, &options);
+                waiting.push(QueuedTask {
+                    task_id: task_id.to_string(),
+                    code: code.to_string(),
+                    options,
+                });
+                drop(waiting);
+
+                self.emit_task_state_changed(task);
+                return Ok(TaskHandle::new(self.clone(), task_id));
+            }
+        }
+
+        self.admit_task(task_id, code, options)
+    }
+
+    // Starts or queues a task already validated by `start_task`
+    fn admit_task(
+        &self,
+        task_id: &str,
+        code: &str,
+        options: RunOptions,
+    ) -> Result<TaskHandle, String> {
+        let limit = config::get().max_concurrent_tasks();
+        let admission = self.inner.lanes.lock().unwrap().admit(
+            task_id,
+            options.lane,
+            options.saved_script.as_deref(),
+            limit,
+        );
+
+        match admission {
+            Admission::Start => Ok(self.spawn_task(task_id, code, options)),
+            Admission::Queue => {
+                // in the store before it's queued, a slot may free up right away
+                self.mark_task_queued(task_id, &options);
+
+                let task = QueuedTask {
+                    task_id: task_id.to_string(),
+                    code: code.to_string(),
+                    options,
+                };
+                let startable = self.inner.lanes.lock().unwrap().queue(task, limit);
+                self.start_queued_tasks(startable);
+
+                Ok(TaskHandle::new(self.clone(), task_id))
+            }
+            Admission::Full(limit) => {
+                Err(format!("Too many tasks running, the limit is {}", limit))
+            }
+        }
+    }
+
+    /// The background tasks waiting for a slot, in the order they'll start,
+    /// see `TaskLane`.
+    pub fn get_queue(&self) -> TaskQueue {
+        self.inner
+            .lanes
+            .lock()
+            .unwrap()
+            .snapshot(config::get().max_concurrent_tasks())
+    }
+
+    fn mark_task_queued(&self, task_id: &str, options: &RunOptions) {
+        let task = self.insert_task(task_id, TaskState::Queued, options);
+
+        self.emit_task_state_changed(task);
+    }
+
+    // Replaces the previous run of the task, if any
+    fn insert_task(&self, task_id: &str, state: TaskState, options: &RunOptions) -> Task {
+        let mut task = Task::new(task_id.to_string(), self.inner.profile.clone(), state);
+        if options.dry_run {
+            task.dry_run_changes = Some(Vec::new());
+        }
+        task.name = options.name.clone();
+        task.description = options.description.clone();
+        task.tags = options.tags.clone();
+        task.owner_window = options.owner_window.clone();
+        task.on_window_closed = options.on_window_closed;
+        task.retry = options.retry.clone();
+        if matches!(task.state, TaskState::Running) {
+            task.started_at_ms = Some(task.created_at_ms);
+        }
+
+        self.inner.output_channels.lock().unwrap().restart(task_id);
+        self.inner.task_logs.lock().unwrap().restart(task_id);
+
+        let task_id = task_id.to_string();
+        self.inner.tasks.query(move |tasks| {
+            // a queued task starting was submitted when it was queued
+            if let Some(queued) = tasks.get(&task_id).filter(|queued| !queued.is_finished()) {
+                task.created_at_ms = queued.created_at_ms;
+            }
+            if let Some(started_at_ms) = task.started_at_ms {
+                let queue_wait_ms = started_at_ms.saturating_sub(task.created_at_ms);
+                task.timings.set_queue_wait(queue_wait_ms);
+            }
+            if task.is_finished() {
+                task.finish(task.created_at_ms);
+            }
+            tasks.insert(task_id, task.clone());
+            task
+        })
+    }
+
+    fn start_queued_tasks(&self, tasks: Vec<QueuedTask>) {
+        for task in tasks {
+            self.spawn_task(&task.task_id, &task.code, task.options);
+        }
+    }
+
+    // the task must have been admitted to its lane
+    fn spawn_task(&self, task_id: &str, code: &str, options: RunOptions) -> TaskHandle {
+        // running as soon as `run_task` returns, while its thread starts
+        self.insert_task(task_id, TaskState::Running, &options);
+
+        let code = code.to_string();
+
+        let task_id = task_id.to_string();
+
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+
+        self.inner
+            .shutdown_channels
+            .lock()
+            .unwrap()
+            .insert(task_id.clone(), stop_tx);
+
+        let start = Box::new(TaskStart {
+            task_id: task_id.clone(),
+            code,
+            options,
+            stop_rx,
+        });
+
+        let start = match self.take_prewarmed_thread(&start.options) {
+            Some(thread) => thread.start(start),
+            None => Err(start),
+        };
+
+        let handle = match start {
+            Ok(handle) => handle,
+            Err(start) => {
+                let runtime = self.clone();
+
+                std::thread::spawn(move || {
+                    threads::configure_task_thread(start.options.low_priority);
+
+                    log!(Info, "Starting runtime");
+
+                    let mut builder = tokio::runtime::Builder::new_current_thread();
+                    builder.enable_all();
+                    #[cfg(any(test, feature = "test-support"))]
+                    if runtime.inner.virtual_clock.load(Ordering::SeqCst) {
+                        builder.start_paused(true);
+                    }
+                    let tokio_runtime = builder.build().map_err(|e| e.to_string())?;
+
+                    runtime.run_on_thread(&tokio_runtime, *start, None);
+
+                    Ok(())
+                })
+            }
+        };
+
+        // Store the handle
+        self.inner
+            .threads
+            .lock()
+            .unwrap()
+            .insert(task_id.clone(), handle);
+
+        TaskHandle::new(self.clone(), &task_id)
+    }
+
+    // Runs a task to the end on the calling thread, which is then done with
+    // it
+    fn run_on_thread(
+        &self,
+        tokio_runtime: &tokio::runtime::Runtime,
+        start: TaskStart,
+        idle_worker: Option<IdleWorker>,
+    ) {
+        let TaskStart {
+            task_id,
+            code,
+            options,
+            mut stop_rx,
+        } = start;
+
+        log!(Info, "Starting async task");
+
+        let started_at_ms = now_ms();
+        self.inner
+            .running_tasks
+            .lock()
+            .unwrap()
+            .add(self.data_dir(), &task_id, &code, &options);
+        let cpu_clock = ThreadCpuClock::current();
+        let cpu_started = cpu_clock.as_ref().and_then(ThreadCpuClock::elapsed);
+
+        let pause_switch = Arc::new(PauseSwitch::default());
+        self.inner
+            .pause_switches
+            .lock()
+            .unwrap()
+            .insert(task_id.clone(), pause_switch.clone());
+        let _pause_switch = pause::install(pause_switch.clone());
+
+        // the worker isn't `Send`, what it spawns stays on this thread
+        let local = tokio::task::LocalSet::new();
+        local.block_on(tokio_runtime, async {
+            let mut idle_worker = idle_worker;
+            loop {
+                // a paused task can still be stopped
+                let run = Pausable::new(
+                    &pause_switch,
+                    self.run(&task_id, &code, &options, idle_worker.take()),
+                );
+
+                tokio::select! {
+                    result = run => {
+                        if let Err(e) = result {
+                            self.fail_task(&task_id, e.to_string());
+                        }
+                    },
+                    _ = &mut stop_rx => {
+                        log!(Info, "Task stopped");
+                        return;
+                    }
+                }
+
+                // on the same thread and in the same lane slot, until the
+                // attempts are used up
+                let Some(backoff) = self.retry_backoff(&task_id) else {
+                    return;
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => self.start_next_attempt(&task_id),
+                    _ = &mut stop_rx => {
+                        log!(Info, "Task stopped");
+                        return;
+                    }
+                }
+            }
+        });
+
+        log!(Info, "Runtime shutdown");
+
+        let cpu_time = cpu_clock.as_ref().and_then(ThreadCpuClock::elapsed);
+        if let Some((now, started)) = cpu_time.zip(cpu_started) {
+            self.record_cpu_time(&task_id, now.saturating_sub(started));
+        }
+
+        if options.lane == TaskLane::Background {
+            self.dead_letter_if_failed(&task_id, &code, &options);
+        }
+        if let Some(script) = &options.saved_script {
+            alert_rules::on_task_finished(self, &task_id, script);
+        }
+        self.record_history(&task_id, &code, &options, started_at_ms);
+
+        // clean up
+        self.inner
+            .shutdown_channels
+            .lock()
+            .unwrap()
+            .remove(&task_id);
+        // the script reads the end of its stdin, if it's still around
+        self.inner.stdins.lock().unwrap().remove(&task_id);
+        self.inner.mailboxes.lock().unwrap().close(&task_id);
+        self.inner.isolates.lock().unwrap().remove(&task_id);
+        self.inner.attach_requests.lock().unwrap().remove(&task_id);
+        if let Some(session) = self.inner.debuggers.lock().unwrap().remove(&task_id) {
+            session.close();
+        }
+        self.inner.pause_switches.lock().unwrap().remove(&task_id);
+        self.inner
+            .running_tasks
+            .lock()
+            .unwrap()
+            .remove(self.data_dir(), &task_id);
+        self.inner.threads.lock().unwrap().remove(&task_id);
+
+        let startable = self
+            .inner
+            .lanes
+            .lock()
+            .unwrap()
+            .finish(&task_id, config::get().max_concurrent_tasks());
+        self.start_queued_tasks(startable);
+
+        let evicted = storage::enforce_storage_quota();
+        if evicted > 0 {
+            log!(Info, "Evicted {} files over the storage quota", evicted);
+        }
+    }
+
+    /// Suspends a running task where it is, until `resume_task`. Its timers
+    /// and I/O are held back with it, a timeout still applies.
+    pub fn pause_task(&self, task_id: &str) -> Result<(), String> {
+        let switch = self
+            .inner
+            .pause_switches
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .cloned();
+        let Some(switch) = switch else {
+            return Err(format!("Task {} isn't running", task_id));
+        };
+
+        let task = self
+            .with_task(task_id, |task| {
+                (matches!(task.state, TaskState::Running) && task.transition(TaskState::Paused))
+                    .then(|| task.clone())
+            })
+            .flatten();
+        let Some(task) = task else {
+            return Err(format!("Task {} isn't running", task_id));
+        };
+
+        // a script in synchronous code only sees the pause in an interrupt
+        let isolates = self.inner.isolates.lock().unwrap();
+        switch.pause(isolates.get(task_id));
+        drop(isolates);
+
+        self.emit_task_state_changed(task);
+        Ok(())
+    }
+
+    /// Resumes a paused task, or restarts one from its last checkpoint, e.g.
+    /// after it crashed or the app was closed while it was running.
+    /// Restarting is experimental, see `Checkpoint`.
+    pub fn resume_task(&self, task_id: &str) -> Result<TaskHandle, String> {
+        let switch = self
+            .inner
+            .pause_switches
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .cloned();
+        if let Some(switch) = switch.filter(|switch| switch.is_paused()) {
+            self.update_task_state(task_id, TaskState::Running);
+            switch.resume();
+            return Ok(TaskHandle::new(self.clone(), task_id));
+        }
+
+        if self.inner.threads.lock().unwrap().contains_key(task_id) {
+            return Err(format!("Task {} is still running", task_id));
+        }
+
+        let checkpoint = checkpoint::load(self.data_dir(), task_id)
+            .map_err(|e| format!("No checkpoint to resume task {} from: {}", task_id, e))?;
+
+        let options = RunOptions {
+            checkpoint_state: Some(checkpoint.state),
+            ..checkpoint.options
+        };
+
+        self.run_task(task_id, &checkpoint.code, options)
+    }
+
+    /// Marks the tasks that were running when the app last quit as
+    /// `interrupted`, and runs the `restartable` ones again. Meant for startup,
+    /// before the runtime's first task. Returns the ids of the interrupted
+    /// tasks.
+    pub fn restore_interrupted_tasks(&self) -> Vec<String> {
+        interrupted::restore(self)
+    }
+
+    // Stopped runs aren't recorded, they're stopping until their thread is
+    // done
+    fn record_history(&self, task_id: &str, code: &str, options: &RunOptions, started_at_ms: u64) {
+        let Some(task) = self.get_task_state(task_id) else {
+            return;
+        };
+        if !task.is_finished() {
+            return;
+        }
+
+        let result = self.inner.history.lock().unwrap().record(
+            self.data_dir(),
+            &task,
+            code,
+            options.saved_script.as_deref(),
+            started_at_ms,
+            now_ms(),
+        );
+        if let Err(e) = result {
+            log!(
+                Error,
+                "Failed to record task {} in the history: {}",
+                task_id,
+                e
+            );
+        }
+    }
+
+    /// Finished runs, newest first, including the ones of earlier sessions.
+    /// Runs that completed, failed or timed out are recorded, along with the
+    /// hash of their code, their return value and permission history.
+    pub fn get_task_history(
+        &self,
+        filter: &TaskHistoryFilter,
+        page: &TaskHistoryPage,
+    ) -> TaskHistory {
+        self.inner
+            .history
+            .lock()
+            .unwrap()
+            .query(self.data_dir(), filter, page)
+    }
+
+    /// Moves the runs finished before `before_ms` out of the task history into
+    /// a compressed archive, with the logs and artifacts of the tasks that
+    /// have no later run, which are then cleared. Archived runs are found
+    /// with `search_task_archives`.
+    pub fn archive_tasks(&self, before_ms: u64) -> Result<ArchiveSummary, String> {
+        archives::archive(self, before_ms)
+    }
+
+    /// Archived runs, most recently finished first, from the archive index.
+    pub fn search_task_archives(
+        &self,
+        filter: &TaskHistoryFilter,
+        page: &TaskHistoryPage,
+    ) -> ArchivedTasks {
+        archives::search(self.data_dir(), filter, page)
+    }
+
+    /// The runs of a task in one of the archives, see `search_task_archives`.
+    pub fn get_archived_task(
+        &self,
+        archive: &str,
+        task_id: &str,
+    ) -> Result<Vec<ArchivedTask>, String> {
+        archives::read(self.data_dir(), archive, task_id)
+    }
+
+    /// Vacuums the SQLite databases tasks left in the data directory, forgets
+    /// the expired idempotency keys and removes the artifacts of tasks that
+    /// are gone from both the runtime and the history.
+    pub fn compact_storage(&self) -> CompactionReport {
+        compaction::compact(self)
+    }
+
+    fn dead_letter_if_failed(&self, task_id: &str, code: &str, options: &RunOptions) {
+        let Some(task) = self.get_task_state(task_id) else {
+            return;
+        };
+        if !matches!(task.state, TaskState::Error { .. }) {
+            return;
+        }
+
+        match dead_letters::record(self.data_dir(), task_id, code, options, task.error()) {
+            Ok(letter) => self.emit_task_event(TaskEvent::DeadLettered(Box::new(letter))),
+            Err(e) => log!(Error, "Failed to dead-letter task {}: {}", task_id, e),
+        }
+    }
+
+    /// Failed background runs, oldest first. They're kept on disk until
+    /// retried or dismissed.
+    pub fn list_dead_letters(&self) -> Vec<DeadLetter> {
+        dead_letters::list(self.data_dir())
+    }
+
+    /// Runs the task of a dead letter again, with the same code and options,
+    /// and drops the letter. A new one is added if it fails again.
+    pub fn retry_dead_letter(&self, id: &str) -> Result<TaskHandle, String> {
+        validate_task_id(id)?;
+        let entry = dead_letters::load(self.data_dir(), id)
+            .map_err(|e| format!("Dead letter not found: {}: {}", id, e))?;
+
+        if self
+            .get_task_state(&entry.letter.task_id)
+            .is_some_and(|task| !task.is_finished())
+        {
+            return Err(format!("Task {} is still running", entry.letter.task_id));
+        }
+
+        let options = RunOptions {
+            saved_script: entry.letter.script.clone(),
+            ..entry.options
+        };
+        let handle = self.run_task(&entry.letter.task_id, &entry.code, options)?;
+
+        if let Err(e) = dead_letters::remove(self.data_dir(), id) {
+            log!(Error, "Failed to remove dead letter {}: {}", id, e);
+        }
+        Ok(handle)
+    }
+
+    pub fn dismiss_dead_letter(&self, id: &str) -> Result<(), String> {
+        validate_task_id(id)?;
+
+        dead_letters::remove(self.data_dir(), id)
+            .map_err(|e| format!("Dead letter not found: {}: {}", id, e))
+    }
+
+    /// Adds a rule reacting to a saved script failing repeatedly, replacing
+    /// the one with the same id. Rules are saved in the data dir.
+    pub fn set_alert_rule(&self, rule: AlertRule) -> Result<(), String> {
+        self.inner
+            .alert_rules
+            .lock()
+            .unwrap()
+            .set(self.data_dir(), rule)
+    }
+
+    pub fn list_alert_rules(&self) -> Vec<AlertRule> {
+        self.inner.alert_rules.lock().unwrap().list(self.data_dir())
+    }
+
+    pub fn remove_alert_rule(&self, id: &str) -> Result<(), String> {
+        self.inner
+            .alert_rules
+            .lock()
+            .unwrap()
+            .remove(self.data_dir(), id)
+    }
+
+    /// Runs `code` with `options` whenever `cron` matches, e.g. `0 9 * * 1`
+    /// for Mondays at 9:00 UTC. Returns the schedule id. Schedules are saved
+    /// in the data dir, and run while the app is open.
+    pub fn schedule_task(
+        &self,
+        cron: &str,
+        code: &str,
+        options: RunOptions,
+    ) -> Result<String, String> {
+        let id = self
+            .inner
+            .schedules
+            .lock()
+            .unwrap()
+            .add(self.data_dir(), cron, code, options)?;
+        schedules::spawn_scheduler(self);
+
+        Ok(id)
+    }
+
+    pub fn list_schedules(&self) -> Vec<Schedule> {
+        self.inner.schedules.lock().unwrap().list(self.data_dir())
+    }
+
+    /// Removes a schedule, a run in progress isn't stopped.
+    pub fn cancel_schedule(&self, id: &str) -> Result<(), String> {
+        self.inner
+            .schedules
+            .lock()
+            .unwrap()
+            .remove(self.data_dir(), id)
+    }
+
+    /// Starts the runs of the schedules due at `now_ms`, in ms since the
+    /// epoch, instead of waiting for them. Returns the ids of their tasks.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn run_schedules_at(&self, now_ms: u64) -> Vec<String> {
+        schedules::run_due(self, now_ms)
+    }
+
+    /// Runs `code` with `options` whenever the Tauri event `event_name` is
+    /// emitted, by the frontend, the app or a plugin, with the payload of the
+    /// event as the task's input. With `debounce_ms`, events closer than that
+    /// are batched into a single run whose input is the list of their
+    /// distinct payloads. Returns the registration id. Registrations are
+    /// saved in the data dir.
+    pub fn register_event_task(
+        &self,
+        event_name: &str,
+        code: &str,
+        options: RunOptions,
+        debounce_ms: Option<u64>,
+    ) -> Result<String, String> {
+        event_tasks::register(self, event_name, code, options, debounce_ms)
+    }
+
+    pub fn list_event_tasks(&self) -> Vec<EventTask> {
+        self.inner.event_tasks.lock().unwrap().list(self.data_dir())
+    }
+
+    /// Stops running `code` on the event, a run in progress isn't stopped.
+    pub fn unregister_event_task(&self, id: &str) -> Result<(), String> {
+        event_tasks::unregister(self, id)
+    }
+
+    /// Starts the tasks registered on `event_name` as if it was emitted with
+    /// `payload`, for runtimes without an app handle. Returns the ids of the
+    /// ones started right away, debounced ones start once their batch is.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn run_event_tasks(&self, event_name: &str, payload: serde_json::Value) -> Vec<String> {
+        event_tasks::run_on(self, event_name, payload)
+    }
+
+    /// Runs `code` with `options` right away and again whenever one of
+    /// `paths` changes, stopping the run still going. Changes closer than
+    /// `debounce_ms`, 200 by default, are batched into a single run, which
+    /// gets the paths that changed as its input. Returns the watch id.
+    pub fn watch_task(
+        &self,
+        paths: Vec<PathBuf>,
+        code: &str,
+        options: RunOptions,
+        debounce_ms: Option<u64>,
+    ) -> Result<String, String> {
+        watches::watch(self, paths, code, options, debounce_ms)
+    }
+
+    pub fn list_watches(&self) -> Vec<TaskWatch> {
+        self.inner.watches.lock().unwrap().list()
+    }
+
+    /// Stops watching, a run in progress isn't stopped.
+    pub fn unwatch_task(&self, id: &str) -> Result<(), String> {
+        watches::unwatch(self, id)
+    }
+
+    /// Writes to the stdin of a running task, what `Deno.stdin` and
+    /// `prompt()` read. Blocks while the pipe is full.
+    pub fn write_task_stdin(&self, task_id: &str, data: &[u8]) -> Result<(), String> {
+        let stdin = self.inner.stdins.lock().unwrap().get(task_id);
+        let Some(stdin) = stdin else {
+            return Err(format!("Task {} isn't running", task_id));
+        };
+
+        stdin::write(&stdin, data).map_err(|e| e.to_string())
+    }
+
+    /// Sends a message to a running task, which its `RuntimeExtension.onMessage`
+    /// listeners get. Messages the task posts back are `task-message` events.
+    pub fn post_message_to_task(
+        &self,
+        task_id: &str,
+        message: &serde_json::Value,
+    ) -> Result<(), String> {
+        self.inner
+            .mailboxes
+            .lock()
+            .unwrap()
+            .post(task_id, message.to_string())
+    }
+
+    /// Runs a task paused at its first statement and at `breakpoints`, lines
+    /// of `code` from 1, for `debug_step`, `debug_continue` and
+    /// `debug_evaluate`. Pauses are `task-debug-paused` events.
+    pub fn debug_run_task(
+        &self,
+        task_id: &str,
+        code: &str,
+        breakpoints: &[u32],
+        mut options: RunOptions,
+    ) -> Result<TaskHandle, String> {
+        if breakpoints.contains(&0) {
+            return Err("Breakpoint lines start at 1".to_string());
+        }
+
+        options.breakpoints = Some(breakpoints.to_vec());
+        self.run_task(task_id, code, options)
+    }
+
+    /// Where a task run with `debug_run_task` is paused, `None` while it runs.
+    pub fn get_debug_pause(&self, task_id: &str) -> Option<DebugPause> {
+        self.debugger(task_id).ok()?.pause()
+    }
+
+    /// Fails unless the task is paused.
+    pub fn debug_step(&self, task_id: &str, step: DebugStep) -> Result<(), String> {
+        self.debugger(task_id)?.step(step)
+    }
+
+    /// Runs a paused task until its next breakpoint.
+    pub fn debug_continue(&self, task_id: &str) -> Result<(), String> {
+        self.debugger(task_id)?.resume()
+    }
+
+    /// Evaluates `expression` in the frame the task is paused in, or at its
+    /// top level while it runs. Blocks until the task's thread gets to it.
+    pub fn debug_evaluate(
+        &self,
+        task_id: &str,
+        expression: &str,
+    ) -> Result<serde_json::Value, String> {
+        self.debugger(task_id)?.evaluate(expression)
+    }
+
+    /// Evaluates `expression` in a running task, e.g. to see what a stuck
+    /// one is at, in the frame it's paused in if it's being debugged. Fails
+    /// on side effects, like assignments or calls to functions that have
+    /// some, unless `allow_side_effects`. Blocks until the task's thread gets
+    /// to it, a busy one is interrupted.
+    pub fn evaluate_in_task(
+        &self,
+        task_id: &str,
+        expression: &str,
+        allow_side_effects: bool,
+    ) -> Result<RemoteObject, String> {
+        let attached = self.inner.debuggers.lock().unwrap().get(task_id).cloned();
+        let session = match attached {
+            Some(session) => session,
+            // the first evaluation in a run attaches a session
+            None => {
+                let requests = self
+                    .inner
+                    .attach_requests
+                    .lock()
+                    .unwrap()
+                    .get(task_id)
+                    .cloned()
+                    .ok_or_else(|| format!("Task {} isn't running", task_id))?;
+                let isolate = self.inner.isolates.lock().unwrap().get(task_id).cloned();
+                requests
+                    .attach(isolate.as_ref())
+                    .map_err(|_| format!("Task {} didn't attach a debugger", task_id))?
+            }
+        };
+
+        session.inspect(expression, allow_side_effects)
+    }
+
+    // Of a run started with `debug_run_task`
+    fn debugger(&self, task_id: &str) -> Result<Arc<DebugSession>, String> {
+        let session = self
+            .inner
+            .debuggers
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .cloned()
+            .ok_or_else(|| format!("Task {} isn't running", task_id))?;
+        if !session.is_debug_run() {
+            return Err(format!("Task {} isn't being debugged", task_id));
+        }
+
+        Ok(session)
+    }
+
+    pub fn stop_task(&self, task_id: &str) -> Result<(), String> {
+        if self.inner.lanes.lock().unwrap().cancel(task_id) {
+            self.update_task_state(task_id, TaskState::Stopped);
+            return Ok(());
+        }
+
+        let waiting = {
+            let mut waiting = self.inner.waiting_for_network.lock().unwrap();
+            let len = waiting.len();
+            waiting.retain(|task| task.task_id != task_id);
+            waiting.len() != len
+        };
+        if waiting {
+            self.update_task_state(task_id, TaskState::Stopped);
+            return Ok(());
+        }
+
+        let handle = self.inner.threads.lock().unwrap().remove(task_id);
+
+        let task_id_clone = task_id.to_string();
+
+        if let Some(handle) = handle {
+            // Thread is already finished
+            if handle.is_finished() {
+                return Ok(());
+            }
+
+            let runtime = self.clone();
+
+            // Attempt to stop the thread
+            std::thread::spawn(move || {
+                runtime.update_task_state(&task_id_clone, TaskState::Stopping);
+
+                // a pending prompt blocks the thread, and the prompts of
+                // every other task with it
+                runtime
+                    .inner
+                    .permission_channels
+                    .lock()
+                    .unwrap()
+                    .remove(&task_id_clone);
+
+                // send shutdown message
+                let stop_tx = runtime
+                    .inner
+                    .shutdown_channels
+                    .lock()
+                    .unwrap()
+                    .remove(&task_id_clone);
+
+                if stop_tx.map(|stop_tx| stop_tx.send(())).is_none() {
+                    log!(Error, "Failed to send shutdown message");
+                }
+
+                // the message is only seen when the script yields to the
+                // event loop, a busy one is interrupted wherever it is
+                let isolate = runtime
+                    .inner
+                    .isolates
+                    .lock()
+                    .unwrap()
+                    .remove(&task_id_clone);
+                if let Some(isolate) = isolate {
+                    isolate.terminate_execution();
+                }
+                // one paused in the debugger sees neither until it goes on
+                let debugger = runtime
+                    .inner
+                    .debuggers
+                    .lock()
+                    .unwrap()
+                    .get(&task_id_clone)
+                    .cloned();
+                if let Some(debugger) = debugger {
+                    debugger.detach();
+                }
+                // a paused one is let go to stop
+                let switch = runtime
+                    .inner
+                    .pause_switches
+                    .lock()
+                    .unwrap()
+                    .get(&task_id_clone)
+                    .cloned();
+                if let Some(switch) = switch {
+                    switch.resume();
+                }
+
+                // Wait for thread to complete
+                match handle.join() {
+                    Ok(_) => {}
+                    Err(_) => {
+                        log!(Error, "Failed to stop thread");
+                    }
+                };
+
+                runtime.update_task_state(&task_id_clone, TaskState::Stopped);
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Applies the `on_window_closed` policy of the tasks started by `window`
+    /// and ends its subscriptions, called once the window is gone.
+    pub fn release_window_tasks(&self, window: &str) {
+        self.inner
+            .subscriptions
+            .lock()
+            .unwrap()
+            .remove_window(window);
+        self.inner
+            .output_channels
+            .lock()
+            .unwrap()
+            .remove_window(window);
+
+        let window = window.to_string();
+        let (to_stop, detached) = self.inner.tasks.query(move |tasks| {
+            let mut to_stop = Vec::new();
+            let mut detached = Vec::new();
+
+            for task in tasks.values_mut() {
+                if task.owner_window.as_deref() != Some(window.as_str()) {
+                    continue;
+                }
+
+                match task.on_window_closed {
+                    WindowClosedPolicy::Stop => to_stop.push(task.id.clone()),
+                    WindowClosedPolicy::Detach => {
+                        task.owner_window = None;
+                        detached.push(task.clone());
+                    }
+                }
+            }
+
+            (to_stop, detached)
+        });
+
+        for task in detached {
+            self.emit_task_state_changed(task);
+        }
+
+        // tasks that already ended have no thread, stopping them is a no-op
+        for task_id in to_stop {
+            let _ = self.stop_task(&task_id);
+        }
+    }
+
+    pub fn has_running_tasks(&self) -> bool {
+        !self.inner.threads.lock().unwrap().is_empty()
+    }
+
+    fn is_task_thread_alive(&self, task_id: &str) -> bool {
+        self.inner
+            .threads
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    pub fn get_task_state(&self, task_id: &str) -> Option<Task> {
+        let task_id = task_id.to_string();
+        self.inner
+            .tasks
+            .query(move |tasks| tasks.get(&task_id).cloned())
+    }
+
+    /// Summaries of the tasks in `state_filter` (state names, all when unset)
+    /// carrying every one of `tags` whose id, name, description or output
+    /// contains `search`, newest first. `limit` is 50 when unset and at most
+    /// 1000.
+    pub fn list_tasks(
+        &self,
+        state_filter: Option<&[String]>,
+        tags: Option<&[String]>,
+        search: Option<&str>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> TaskList {
+        // filtered in the store, the outputs aren't copied
+        let state_filter = state_filter.map(<[String]>::to_vec);
+        let tags = tags.map(<[String]>::to_vec);
+        let search = search.map(str::to_string);
+        self.inner.tasks.query(move |tasks| {
+            task_list::list(
+                tasks.values(),
+                state_filter.as_deref(),
+                tags.as_deref(),
+                search.as_deref(),
+                offset,
+                limit,
+            )
+        })
+    }
+
+    /// Saves a script under `name` for later runs, replacing the one saved
+    /// under the same name.
+    pub fn save_script(&self, name: &str, code: &str) -> Result<(), String> {
+        scripts::validate_name(name)?;
+
+        scripts::save(self.data_dir(), name, code).map_err(|e| e.to_string())?;
+        scripts::remove_catalog_pin(self.data_dir(), name).map_err(|e| e.to_string())?;
+        scripts::remove_signature(self.data_dir(), name).map_err(|e| e.to_string())
+    }
+
+    pub fn get_script(&self, name: &str) -> Result<String, String> {
+        scripts::validate_name(name)?;
+
+        scripts::load(self.data_dir(), name)
+            .map_err(|e| format!("Failed to load script {}: {}", name, e))
+    }
+
+    /// Names of the saved scripts, sorted.
+    pub fn list_scripts(&self) -> Vec<String> {
+        scripts::list(self.data_dir())
+    }
+
+    pub fn delete_script(&self, name: &str) -> Result<(), String> {
+        scripts::validate_name(name)?;
+
+        scripts::delete(self.data_dir(), name)
+            .map_err(|e| format!("Failed to delete script {}: {}", name, e))
+    }
+
+    /// Docs of the functions a saved script exports, from their JSDoc, for a
+    /// usage panel next to it.
+    pub fn generate_script_docs(&self, name: &str) -> Result<ScriptDocs, String> {
+        script_docs::script_docs(name, &self.get_script(name)?)
+    }
+
+    /// JSON schema of the args a saved script takes, from the `inputs` it
+    /// exports, for a form to fill them in. `None` when it exports none.
+    pub fn get_script_inputs(&self, name: &str) -> Result<Option<serde_json::Value>, String> {
+        script_inputs::script_inputs(name, &self.get_script(name)?)
+    }
+
+    /// Runs a saved script with `args`, checked against its `inputs` and
+    /// completed with their defaults first.
+    pub fn run_saved_script(
+        &self,
+        task_id: &str,
+        name: &str,
+        args: Option<serde_json::Value>,
+        mut options: RunOptions,
+    ) -> Result<TaskHandle, String> {
+        let code = self.get_script(name)?;
+        bundles::check_signature(self.data_dir(), name, &code)?;
+
+        options.args = match script_inputs::script_inputs(name, &code)? {
+            Some(schema) => Some(script_inputs::validate_args(&schema, args)?),
+            None => args,
+        };
+
+        options.saved_script = Some(name.to_string());
+        if options.grants.is_none() {
+            options.grants = scripts::load_grants(self.data_dir(), name);
+        }
+        let lock = scripts::load_lock(self.data_dir(), name);
+        options.module_lock = (!lock.is_empty()).then(|| Arc::new(lock));
+
+        self.run_task(task_id, &code, options)
+    }
+
+    /// Runs saved scripts one after the other, or as a graph of dependencies
+    /// with up to `max_parallel` steps at once, passing return values along
+    /// as args and input. Each step is a task started with `options`, the
+    /// pipeline reports its progress with
+    /// `pipeline-state-changed` events. Returns the pipeline id.
+    pub fn run_pipeline(&self, spec: PipelineSpec, options: RunOptions) -> Result<String, String> {
+        pipelines::start(self, spec, options)
+    }
+
+    /// The steps of a pipeline as a graph, with their state and how long they
+    /// ran. Pipelines are kept until `clear_completed_tasks` once finished.
+    pub fn get_pipeline_state(&self, pipeline_id: &str) -> Option<PipelineGraph> {
+        self.inner
+            .pipelines
+            .lock()
+            .unwrap()
+            .get(pipeline_id)
+            .map(PipelineState::graph)
+    }
+
+    /// Enables the result hooks named in `hooks` for a saved script, run in
+    /// that order after each completed run of it. See `ResultHook`.
+    pub fn set_script_hooks(&self, name: &str, hooks: Vec<String>) -> Result<(), String> {
+        scripts::validate_name(name)?;
+        if let Some(unknown) = hooks.iter().find(|hook| !result_hooks::is_registered(hook)) {
+            return Err(format!("Result hook not registered: {}", unknown));
+        }
+
+        scripts::save_hooks(self.data_dir(), name, &hooks).map_err(|e| e.to_string())
+    }
+
+    pub fn get_script_hooks(&self, name: &str) -> Result<Vec<String>, String> {
+        scripts::validate_name(name)?;
+
+        Ok(scripts::load_hooks(self.data_dir(), name))
+    }
+
+    /// Enables the fetch interceptors named in `interceptors` for a saved
+    /// script, run in that order on each request of its runs. See
+    /// `FetchInterceptor`.
+    pub fn set_script_fetch_interceptors(
+        &self,
+        name: &str,
+        interceptors: Vec<String>,
+    ) -> Result<(), String> {
+        scripts::validate_name(name)?;
+        if let Some(unknown) = interceptors
+            .iter()
+            .find(|interceptor| !fetch_interceptors::is_registered(interceptor))
+        {
+            return Err(format!("Fetch interceptor not registered: {}", unknown));
+        }
+
+        scripts::save_interceptors(self.data_dir(), name, &interceptors).map_err(|e| e.to_string())
+    }
+
+    pub fn get_script_fetch_interceptors(&self, name: &str) -> Result<Vec<String>, String> {
+        scripts::validate_name(name)?;
+
+        Ok(scripts::load_interceptors(self.data_dir(), name))
+    }
+
+    /// Grants the runs of a saved script get unless they set their own, e.g.
+    /// the permission profile it's shared with. `None` gives them all.
+    pub fn set_script_grants(
+        &self,
+        name: &str,
+        grants: Option<Vec<OpGrant>>,
+    ) -> Result<(), String> {
+        scripts::validate_name(name)?;
+
+        scripts::save_grants(self.data_dir(), name, grants.as_deref())
+            .and_then(|_| scripts::remove_signature(self.data_dir(), name))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get_script_grants(&self, name: &str) -> Result<Option<Vec<OpGrant>>, String> {
+        scripts::validate_name(name)?;
+
+        Ok(scripts::load_grants(self.data_dir(), name))
+    }
+
+    /// Writes a saved script to a `.denotask` bundle at `path`: its source,
+    /// input schema and grants, with a lockfile of the remote modules it
+    /// imports. Blocks while they're fetched to be hashed.
+    pub fn export_script_bundle(&self, name: &str, path: &Path) -> Result<(), String> {
+        let module_loader = self.inner.module_loader.lock().unwrap().clone();
+        bundles::export_script_bundle(self.data_dir(), name, path, module_loader.as_deref())
+    }
+
+    /// Saves the script of a `.denotask` bundle, its runs only load the
+    /// remote modules matching its lockfile. The signature of a signed bundle
+    /// is checked against the trusted keys, and again before each run.
+    /// Returns its name.
+    pub fn import_script_bundle(&self, path: &Path) -> Result<String, String> {
+        bundles::import_script_bundle(self.data_dir(), path)
+    }
+
+    /// Trusts the bundles signed with the private key of `public_key`, in
+    /// base64. Replaces the key trusted under the same name.
+    pub fn add_trusted_key(&self, name: &str, public_key: &str) -> Result<(), String> {
+        signatures::add(self.data_dir(), name, public_key)
+    }
+
+    pub fn list_trusted_keys(&self) -> Vec<TrustedKey> {
+        signatures::list(self.data_dir())
+    }
+
+    /// Scripts imported from bundles signed with the key no longer run.
+    pub fn remove_trusted_key(&self, name: &str) -> Result<(), String> {
+        signatures::remove(self.data_dir(), name)
+    }
+
+    /// Scripts of the curated catalog whose index is at `url`, see
+    /// `CatalogEntry`. Blocks while it's fetched.
+    pub fn list_catalog(&self, url: &str) -> Result<Vec<CatalogEntry>, String> {
+        catalog::list_catalog(self.data_dir(), url)
+    }
+
+    /// Source of a catalog script for the user to review, it's neither saved
+    /// nor run.
+    pub fn review_catalog_script(
+        &self,
+        url: &str,
+        name: &str,
+    ) -> Result<CatalogScriptReview, String> {
+        catalog::review_catalog_script(self.data_dir(), url, name)
+    }
+
+    /// Installs a reviewed catalog script into the saved scripts, pinned to
+    /// the hash of the source the user read. It's only run when asked to,
+    /// like any saved script.
+    pub fn install_catalog_script(
+        &self,
+        url: &str,
+        name: &str,
+        reviewed_sha256: &str,
+    ) -> Result<(), String> {
+        scripts::validate_name(name)?;
+
+        catalog::install_catalog_script(self.data_dir(), url, name, reviewed_sha256)
+    }
+
+    /// Compares the results of two runs, e.g. two runs of the same script on
+    /// different days.
+    pub fn diff_task_runs(&self, run_a: &str, run_b: &str) -> Result<TaskRunDiff, String> {
+        let find = |task_id: &str| {
+            self.get_task_state(task_id)
+                .ok_or_else(|| format!("Task not found: {}", task_id))
+        };
+
+        Ok(diff::diff_task_runs(&find(run_a)?, &find(run_b)?))
+    }
+
+    pub fn clear_completed_tasks(&self) {
+        let removed = self.inner.tasks.query(|tasks| {
+            let mut removed = Vec::new();
+            tasks.retain(|task_id, task| {
+                let keep = !task.is_finished();
+                if !keep {
+                    removed.push(task_id.clone());
+                }
+                keep
+            });
+            removed
+        });
+
+        for task_id in &removed {
+            artifacts::remove_all(self.data_dir(), task_id);
+        }
+        self.inner.event_log.lock().unwrap().forget(&removed);
+        self.inner.output_channels.lock().unwrap().forget(&removed);
+        self.inner.task_logs.lock().unwrap().forget(&removed);
+        self.inner
+            .pipelines
+            .lock()
+            .unwrap()
+            .retain(|_, pipeline| !pipeline.is_finished());
+    }
+
+    // Drops archived tasks from memory, unless they're running again
+    fn forget_finished_tasks(&self, task_ids: &[String]) {
+        let task_ids = task_ids.to_vec();
+        let removed: Vec<String> = self.inner.tasks.query(move |tasks| {
+            task_ids
+                .into_iter()
+                .filter(|task_id| {
+                    tasks.get(task_id).is_some_and(Task::is_finished)
+                        && tasks.remove(task_id).is_some()
+                })
+                .collect()
+        });
+
+        self.inner.event_log.lock().unwrap().forget(&removed);
+        self.inner.output_channels.lock().unwrap().forget(&removed);
+        self.inner.task_logs.lock().unwrap().forget(&removed);
+    }
+
+    /// The `console` calls of the current run of a task, also emitted as
+    /// `task-log` events as they happen.
+    pub fn get_task_logs(&self, task_id: &str) -> Result<Vec<TaskLog>, String> {
+        if self.get_task_state(task_id).is_none() {
+            return Err(format!("Task not found: {}", task_id));
+        }
+
+        Ok(self.inner.task_logs.lock().unwrap().get(task_id))
+    }
+
+    /// Sends the console output of a task through `channel` as it's printed,
+    /// starting with what it printed so far, and the output of its next runs.
+    ///
+    /// Ends when the window is closed or the task is cleared.
+    pub fn subscribe_task_output(
+        &self,
+        task_id: &str,
+        channel: tauri::ipc::Channel<TaskOutputChunk>,
+        window: Option<&str>,
+    ) -> Result<(), String> {
+        if self.get_task_state(task_id).is_none() {
+            return Err(format!("Task not found: {}", task_id));
+        }
+
+        self.inner
+            .output_channels
+            .lock()
+            .unwrap()
+            .subscribe(task_id, channel, window);
+
+        Ok(())
+    }
+
+    /// Sends the events matching `filters` through `channel` from now on,
+    /// returns the id to `unsubscribe` with. Windows with many tasks should
+    /// subscribe instead of listening to the events, which get every event of
+    /// every task. A window with a subscription no longer gets the events
+    /// as Tauri events, only through its channels.
+    ///
+    /// The subscriptions of a window end when it's closed.
+    pub fn subscribe(
+        &self,
+        filters: SubscriptionFilters,
+        channel: tauri::ipc::Channel<SequencedEvent>,
+        window: Option<&str>,
+    ) -> u64 {
+        self.inner
+            .subscriptions
+            .lock()
+            .unwrap()
+            .add(filters, channel, window)
+    }
+
+    pub fn unsubscribe(&self, subscription_id: u64) -> Result<(), String> {
+        if self
+            .inner
+            .subscriptions
+            .lock()
+            .unwrap()
+            .remove(subscription_id)
+        {
+            Ok(())
+        } else {
+            Err(format!("Subscription not found: {}", subscription_id))
+        }
+    }
+
+    /// Events emitted after `since_seq`, for a window that (re)subscribes and
+    /// may have missed some. Pass 0 to get everything still buffered.
+    pub fn sync_task_events(&self, since_seq: u64) -> Vec<SequencedEvent> {
+        self.inner.event_log.lock().unwrap().since(since_seq)
+    }
+
+    /// The tasks, pending prompts, queue, schedules and config at once, for a
+    /// window (re)building its state. Events after its `seq` are then synced
+    /// with `sync_task_events` rather than read again one getter at a time.
+    pub fn get_runtime_snapshot(&self) -> RuntimeSnapshot {
+        runtime_snapshot::take(self)
+    }
+
+    /// Events of a task after its `task_seq`, to fill a gap in the `task_seq`s
+    /// the frontend received.
+    pub fn get_events_since(&self, task_id: &str, task_seq: u64) -> Vec<SequencedEvent> {
+        self.inner
+            .event_log
+            .lock()
+            .unwrap()
+            .task_events_since(task_id, task_seq)
+    }
+
+    pub fn respond_to_permission_prompt(&self, task_id: &str, response: PermissionsResponse) {
+        log!(Debug, "Responding to permission prompt --");
+
+        let tx = self
+            .inner
+            .permission_channels
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .cloned();
+
+        let Some(tx) = tx else {
+            log!(
+                Debug,
+                "No permission channel found for task_id: {} --",
+                task_id
+            );
+            return;
+        };
+
+        let recorded = response.clone();
+        self.with_task(task_id, move |task| {
+            // Update the pending prompt with the response
+            if let TaskState::WaitingForPermission { prompt } = &mut task.state {
+                prompt.response = Some(recorded.clone());
+            }
+
+            // Update the permission history
+            if let Some(last) = task.permission_history.last_mut() {
+                last.response = Some(recorded);
+            }
+        });
+
+        let _ = tx.send(response);
+        log!(Debug, "Sent response --");
+    }
+
+    pub fn init_listener(&self, app_handle: AppHandle) {
+        let _ = self.inner.app_handle.set(app_handle.clone());
+
+        // the listener must not keep the runtime alive, it stops once every
+        // clone of the runtime (and so the events sender) is dropped
+        let events = self.inner.events.1.clone();
+        let running = self.inner.event_listener_running.clone();
+        let event_log = self.inner.event_log.clone();
+        let subscriptions = self.inner.subscriptions.clone();
+        let profile = self.inner.profile.clone();
+
+        // a thread of its own, the blocking recv would hold a worker of
+        // Tauri's async runtime for good, one per profile
+        std::thread::Builder::new()
+            .name(format!("task-events-{}", profile))
+            .spawn(move || {
+                running.store(true, Ordering::SeqCst);
+
+                while let Ok(event) = events.recv() {
+                    // sequenced here, the listener is the only consumer so the
+                    // order of the seqs is the order of the emits
+                    let event = event_log.lock().unwrap().record(event);
+                    trace::record_event(&profile, &event);
+
+                    let subscribed = subscriptions.lock().unwrap().windows();
+                    let result =
+                        app_handle.emit_filter(
+                            event.event().name(),
+                            &event,
+                            |target| match target {
+                                EventTarget::Window { label }
+                                | EventTarget::Webview { label }
+                                | EventTarget::WebviewWindow { label }
+                                | EventTarget::AnyLabel { label } => !subscribed.contains(label),
+                                _ => true,
+                            },
+                        );
+                    if result.is_err() {
+                        log!(
+                            Error,
+                            "Failed to emit task event {}, kept for sync_task_events",
+                            event.seq()
+                        );
+                    }
+
+                    let subscribers = subscriptions.lock().unwrap().matching(&event);
+                    for (id, channel) in subscribers {
+                        if channel.send(event.clone()).is_err() {
+                            // the webview went away without unsubscribing
+                            subscriptions.lock().unwrap().remove(id);
+                        }
+                    }
+                }
+
+                running.store(false, Ordering::SeqCst);
+            })
+            .expect("failed to spawn the task events thread");
+    }
+
+    /// Events emitted since the last call, for runtimes without a listener.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn take_events(&self) -> Vec<TaskEvent> {
+        self.inner.events.1.try_iter().collect()
+    }
+
+    fn is_event_listener_running(&self) -> bool {
+        self.inner.event_listener_running.load(Ordering::SeqCst)
+    }
+
+    async fn run(
+        &self,
+        task_id: &str,
+        code: &str,
+        options: &RunOptions,
+        idle_worker: Option<IdleWorker>,
+    ) -> Result<(), AnyError> {
+        let attempt = self.get_task_state(task_id).map_or(1, |task| task.attempt);
+        let prefix = format!(
+            "globalThis.RuntimeExtension.taskId = \"{task_id}\";\nglobalThis.RuntimeExtension.attempt = {attempt};\n\n"
+        );
+        let prefix_lines = prefix.matches('\n').count() as u32;
+        let augmented_code = format!("{prefix}{code}");
+
+        // removed from disk when dropped, whichever way the task ends
+        let staged_code = StagedCode::write(self.data_dir(), task_id, &augmented_code)?;
+
+        let main_module = ModuleSpecifier::from_file_path(staged_code.path()).unwrap();
+
+        // prompts raised on this thread are answered for this task
+        let _prompter = prompts::install(self, task_id);
+
+        let bootstrap_started = Instant::now();
+
+        // dropped right away otherwise, isolates go in the reverse order they
+        // were created in
+        let idle_worker = idle_worker.and_then(|mut idle_worker| match idle_worker.verify() {
+            Ok(()) => Some(idle_worker),
+            Err(e) => {
+                log!(
+                    Error,
+                    "Discarding the prewarmed worker of task {}: {}",
+                    task_id,
+                    e
+                );
+                None
+            }
+        });
+
+        let prewarmed = idle_worker.is_some();
+        self.with_task(task_id, move |task| task.timings.set_prewarmed(prewarmed));
+
+        let (mut worker, diagnostics) = match idle_worker {
+            Some(idle_worker) => {
+                self.adopt_idle_worker(idle_worker, task_id, code, options, &main_module)
+            }
+            None => {
+                let file_system = self.inner.file_system.lock().unwrap().clone();
+                let fs: Arc<dyn FileSystem> = match file_system {
+                    _ if options.dry_run => Arc::new(OverlayFs::new(self.clone(), task_id)),
+                    Some(fs) => fs,
+                    None => Arc::new(RealFs),
+                };
+
+                let cassette = options
+                    .network_cassette
+                    .as_ref()
+                    .map(|cassette| Cassette::open(self.data_dir(), cassette));
+
+                let cassette = match cassette {
+                    Some(Ok(cassette)) => Some(cassette),
+                    Some(Err(e)) => {
+                        self.fail_task(task_id, e.to_string());
+                        return Ok(());
+                    }
+                    None => None,
+                };
+
+                let diagnostics = PendingDiagnostics::default();
+                let module_loader = self.inner.module_loader.lock().unwrap().clone();
+
+                let worker = create_worker(
+                    &main_module,
+                    fs,
+                    Permissions::none_with_prompt(),
+                    vec![runtime_extension::init_ops_and_esm(
+                        self.clone(),
+                        task_id.to_string(),
+                        code.to_string(),
+                        options.clone(),
+                        cassette,
+                    )],
+                    options,
+                    diagnostics.clone(),
+                    module_loader,
+                );
+
+                (worker, diagnostics)
+            }
+        };
+
+        // unknown locales and timezones are only caught by ICU, in the isolate
+        if let Err(e) = intl::apply_to_task(
+            &mut worker,
+            options.locale.as_deref(),
+            options.timezone.as_deref(),
+        ) {
+            self.fail_task(task_id, e);
+            return Ok(());
+        }
+
+        let stdin = worker
+            .js_runtime
+            .op_state()
+            .borrow_mut()
+            .try_take::<StdinWriter>();
+        if let Some(stdin) = stdin {
+            self.inner.stdins.lock().unwrap().insert(task_id, stdin);
+        }
+        let inbox = self.inner.mailboxes.lock().unwrap().open(task_id);
+        worker.js_runtime.op_state().borrow_mut().put(inbox);
+        self.inner.isolates.lock().unwrap().insert(
+            task_id.to_string(),
+            worker.js_runtime.v8_isolate().thread_safe_handle(),
+        );
+        // of an earlier attempt
+        let previous = self.inner.debuggers.lock().unwrap().remove(task_id);
+        if let Some(previous) = previous {
+            previous.close();
+        }
+        if let Some(breakpoints) = &options.breakpoints {
+            let session = DebugSession::attach(
+                self,
+                &mut worker,
+                task_id,
+                &main_module,
+                prefix_lines,
+                breakpoints,
+            );
+            self.inner
+                .debuggers
+                .lock()
+                .unwrap()
+                .insert(task_id.to_string(), session);
+        }
+        let requests = Arc::new(AttachRequests::default());
+        self.inner
+            .attach_requests
+            .lock()
+            .unwrap()
+            .insert(task_id.to_string(), requests.clone());
+        // declared after the worker to be dropped before it
+        let _attach_requests = debugger::answer_attach_requests(
+            self,
+            &mut worker,
+            task_id,
+            &main_module,
+            prefix_lines,
+            requests,
+        );
+
+        if let Some(proxy) = &options.proxy {
+            proxy::apply_to_worker(&mut worker, proxy);
+        }
+        if let Some(hosts) = &options.hosts {
+            if let Err(e) = hosts::apply_to_worker(&mut worker, hosts) {
+                self.fail_task(task_id, e);
+                return Ok(());
+            }
+        }
+        if let Some(certificate) = &options.client_certificate {
+            let store = self.inner.certificate_store.lock().unwrap().clone();
+            if let Err(e) =
+                client_certs::apply_to_worker(&mut worker, certificate, store.as_deref())
+            {
+                self.fail_task(task_id, e);
+                return Ok(());
+            }
+        }
+        let interceptors = options
+            .saved_script
+            .as_ref()
+            .map(|script| (script, scripts::load_interceptors(self.data_dir(), script)))
+            .filter(|(_, names)| !names.is_empty());
+        let _interceptors = interceptors
+            .map(|(script, names)| fetch_interceptors::install(&mut worker, script, names));
+        if let Some(max_heap_size_mb) = options.max_heap_size_mb {
+            heap_limit::install(&mut worker, self, task_id, max_heap_size_mb);
+        }
+
+        let _watchdog = options.timeout_ms.map(|timeout_ms| {
+            let isolate = worker.js_runtime.v8_isolate().thread_safe_handle();
+            Watchdog::arm(self, task_id, isolate, timeout_ms)
+        });
+        let _cpu_watchdog = match options.cpu_time_limit_ms {
+            Some(limit) => {
+                let isolate = worker.js_runtime.v8_isolate().thread_safe_handle();
+                match Watchdog::arm_cpu_budget(self, task_id, isolate, limit) {
+                    Ok(watchdog) => Some(watchdog),
+                    Err(e) => {
+                        self.fail_task(task_id, e);
+                        return Ok(());
+                    }
+                }
+            }
+            None => None,
+        };
+        self.record_timing(task_id, TimingPhase::Bootstrap, bootstrap_started);
+
+        // the staged module starts with the task id, so the directive is
+        // looked up in the code as written
+        if let Some(diagnostic) = versions::check_min_deno_version(&main_module, code) {
+            diagnostics.borrow_mut().push(diagnostic);
+        }
+
+        // loaded first so the diagnostics of the module graph show up before
+        // the task starts running
+        let load_started = Instant::now();
+        let result = worker.preload_main_module(&main_module).await;
+        self.record_timing(task_id, TimingPhase::ModuleLoad, load_started);
+        self.record_diagnostics(task_id, &diagnostics);
+        let result = match result {
+            Ok(module_id) => {
+                let execution_started = Instant::now();
+                let result = worker.evaluate_module(module_id).await;
+                self.record_timing(task_id, TimingPhase::Execution, execution_started);
+                result
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            self.record_diagnostics(task_id, &diagnostics);
+            self.fail_task_with_frames(task_id, &e, &mut worker, &main_module, prefix_lines);
+            return Ok(());
+        }
+
+        let event_loop_started = Instant::now();
+        let result = worker.run_event_loop(false).await;
+        self.record_timing(task_id, TimingPhase::EventLoop, event_loop_started);
+
+        // dynamic imports are loaded while running
+        self.record_diagnostics(task_id, &diagnostics);
+
+        if let Err(e) = result {
+            self.fail_task_with_frames(task_id, &e, &mut worker, &main_module, prefix_lines);
+            return Ok(());
+        }
+
+        drop(staged_code);
+        // a completed task has nothing left to resume
+        checkpoint::remove(self.data_dir(), task_id);
+        self.update_task_state(task_id, TaskState::Completed);
+
+        if let Some(script) = &options.saved_script {
+            let hooks = scripts::load_hooks(self.data_dir(), script);
+            let task = self.get_task_state(task_id);
+            // not when it was stopped meanwhile
+            if let Some(task) = task.filter(|task| matches!(task.state, TaskState::Completed)) {
+                result_hooks::run(&hooks, script, &task);
+            }
+        }
+
+        Ok(())
+    }
+
+    // A worker for the next task that can adopt it, set up like `run` does
+    // for the default options
+    fn bootstrap_idle_worker(&self) -> IdleWorker {
+        let file_system = self.inner.file_system.lock().unwrap().clone();
+        let fs: Arc<dyn FileSystem> = file_system.unwrap_or_else(|| Arc::new(RealFs));
+        let module_loader = self.inner.module_loader.lock().unwrap().clone();
+        let diagnostics = PendingDiagnostics::default();
+
+        let mut worker = create_worker(
+            &prewarm::placeholder_main_module(),
+            fs,
+            Permissions::none_with_prompt(),
+            vec![runtime_extension::init_ops_and_esm(
+                self.clone(),
+                String::new(),
+                String::new(),
+                RunOptions::default(),
+                None,
+            )],
+            &RunOptions::default(),
+            diagnostics.clone(),
+            module_loader,
+        );
+
+        let baseline = IsolateBaseline::capture(&mut worker)
+            .inspect_err(|e| log!(Error, "Failed to capture the idle worker's baseline: {}", e))
+            .ok();
+
+        IdleWorker {
+            worker,
+            diagnostics,
+            baseline,
+        }
+    }
+
+    // Gives an idle worker the state `runtime_extension` would have been
+    // initialized with for the task
+    fn adopt_idle_worker(
+        &self,
+        idle_worker: IdleWorker,
+        task_id: &str,
+        code: &str,
+        options: &RunOptions,
+        main_module: &ModuleSpecifier,
+    ) -> (MainWorker, PendingDiagnostics) {
+        let IdleWorker {
+            mut worker,
+            diagnostics,
+            ..
+        } = idle_worker;
+
+        let op_state = worker.js_runtime.op_state();
+        {
+            let mut state = op_state.borrow_mut();
+            state.put(TaskId(task_id.to_string()));
+            state.put(TaskCode(code.to_string()));
+            state.put(GrantedOps::new(options));
+            state.put(HostEvents::new(self));
+            state.put(options.clone());
+            // `Deno.mainModule`
+            state.put(main_module.clone());
+        }
+
+        (worker, diagnostics)
+    }
+
+    fn data_dir(&self) -> &Path {
+        &self.inner.data_dir
+    }
+
+    /// Runs `f` on a task in the task store, `f` must not block or emit.
+    fn with_task<R: Send + 'static>(
+        &self,
+        task_id: &str,
+        f: impl FnOnce(&mut Task) -> R + Send + 'static,
+    ) -> Option<R> {
+        let task_id = task_id.to_string();
+        self.inner
+            .tasks
+            .query(move |tasks| tasks.get_mut(&task_id).map(f))
+    }
+
+    // Runs `f` on the return value of a task, or its rendered output, without
+    // copying the rest of the task
+    fn with_task_result<R: Send + 'static>(
+        &self,
+        task_id: &str,
+        output: bool,
+        f: impl FnOnce(&[u8]) -> R + Send + 'static,
+    ) -> Option<R> {
+        self.with_task(task_id, move |task| {
+            if output {
+                f(task.output.to_string().as_bytes())
+            } else {
+                // empty when the task didn't return anything
+                let value = task.return_value.as_ref().map(|value| value.to_string());
+                f(value.unwrap_or_default().as_bytes())
+            }
+        })
+    }
+
+    fn record_timing(&self, task_id: &str, phase: TimingPhase, since: Instant) {
+        self.with_task(task_id, move |task| task.timings.record(phase, since));
+    }
+
+    fn record_fs_change(&self, task_id: &str, change: FsChange) {
+        self.with_task(task_id, move |task| {
+            if let Some(changes) = &mut task.dry_run_changes {
+                changes.push(change);
+            }
+        });
+    }
+
+    fn record_cpu_time(&self, task_id: &str, cpu_time: Duration) {
+        let cpu_time_ms = cpu_time.as_millis() as u64;
+        let task = self.with_task(task_id, move |task| {
+            task.cpu_time_ms = Some(cpu_time_ms);
+            task.clone()
+        });
+
+        if let Some(task) = task {
+            self.emit_task_state_changed(task);
+        }
+    }
+
+    fn record_diagnostics(&self, task_id: &str, diagnostics: &PendingDiagnostics) {
+        let new_diagnostics: Vec<TaskDiagnostic> = diagnostics.borrow_mut().drain(..).collect();
+        if new_diagnostics.is_empty() {
+            return;
+        }
+
+        let task = self.with_task(task_id, move |task| {
+            task.diagnostics.extend(new_diagnostics);
+            task.clone()
+        });
+
+        if let Some(task) = task {
+            self.emit_task_state_changed(task);
+        }
+    }
+
+    fn fail_task(&self, task_id: &str, error: String) {
+        self.update_task_state(task_id, TaskState::error(error, Vec::new()));
+    }
+
+    // Wait before the next attempt of a task whose last one failed
+    fn retry_backoff(&self, task_id: &str) -> Option<Duration> {
+        let task = self.get_task_state(task_id)?;
+
+        match task.state {
+            TaskState::Retrying { backoff_ms, .. } => Some(Duration::from_millis(backoff_ms)),
+            _ => None,
+        }
+    }
+
+    fn start_next_attempt(&self, task_id: &str) {
+        let task = self
+            .with_task(task_id, |task| {
+                task.transition(TaskState::Running).then(|| {
+                    task.attempt += 1;
+                    // set again by the attempt if it gets that far, the
+                    // output is kept to see why the last ones failed
+                    task.return_value = None;
+                    task.diagnostics.clear();
+                    task.progress = None;
+                    task.timings.restart();
+                    task.clone()
+                })
+            })
+            .flatten();
+
+        if let Some(task) = task {
+            log!(Info, "Retrying task {}, attempt {}", task_id, task.attempt);
+            self.emit_task_state_changed(task);
+        }
+    }
+
+    // Keeps where a JS error was thrown in the code as written
+    fn fail_task_with_frames(
+        &self,
+        task_id: &str,
+        error: &AnyError,
+        worker: &mut MainWorker,
+        main_module: &ModuleSpecifier,
+        prefix_lines: u32,
+    ) {
+        let sources = worker
+            .js_runtime
+            .op_state()
+            .borrow()
+            .try_borrow::<ModuleSources>()
+            .cloned();
+        let frames = stack_frames::stack_frames(error, sources.as_ref(), main_module, prefix_lines);
+
+        self.update_task_state(task_id, TaskState::error(error.to_string(), frames));
+    }
+
+    fn update_task_state(&self, task_id: &str, state: TaskState) {
+        log!(Debug, "Updating task state --");
+
+        let task = self
+            .with_task(task_id, move |task| {
+                task.transition(state).then(|| task.clone())
+            })
+            .flatten();
+
+        if let Some(task) = task {
+            self.emit_task_state_changed(task);
+            log!(Debug, "Emitted task state changed --");
+        }
+    }
+
+    fn emit_task_state_changed(&self, task: Task) {
+        self.emit_task_event(TaskEvent::StateChanged(Box::new(task)));
+    }
+
+    fn emit_task_event(&self, event: TaskEvent) {
+        let result = self.inner.events.0.send(event);
+        if result.is_err() {
+            log!(Error, "Failed to send task event");
+        }
+    }
+
+    // Called on the task thread by its prompter, blocks until the frontend
+    // responds or the task is stopped
+    fn prompt(
+        &self,
+        task_id: &str,
+        prompt: PermissionPrompt,
+        responses: &Receiver<PermissionsResponse>,
+    ) -> PromptResponse {
+        // still recorded so the denial shows up in the history
+        if config::get().prompt_policy() == PromptPolicy::Deny {
+            self.with_task(task_id, move |task| task.permission_history.push(prompt));
+            return PromptResponse::Deny;
+        }
+
+        // asked once the task is resumed, its state is the pause meanwhile
+        pause::wait_while_paused();
+
+        let broker = self.inner.permission_broker.lock().unwrap().clone();
+        let brokered = broker.and_then(|broker| broker.prompt(task_id, &prompt));
+        if let Some(response) = brokered {
+            let prompt = PermissionPrompt {
+                response: Some(response.clone()),
+                ..prompt
+            };
+            self.with_task(task_id, move |task| task.permission_history.push(prompt));
+            return response.to_prompt_response();
+        }
+
+        let task = self.with_task(task_id, move |task| {
+            task.permission_history.push(prompt.clone());
+            task.transition(TaskState::WaitingForPermission { prompt })
+                .then(|| task.clone())
+        });
+
+        // gone, or being stopped
+        let Some(Some(task)) = task else {
+            log!(Debug, "No task waiting for permission --");
+            return PromptResponse::Deny;
+        };
+
+        self.emit_task_state_changed(task);
+
+        log!(Debug, "Waiting for response --");
+        match responses.recv() {
+            Ok(response) => {
+                log!(Debug, "Received response --");
+                self.update_task_state(task_id, TaskState::Running);
+                response.to_prompt_response()
+            }
+            // the task is being stopped
+            Err(_) => PromptResponse::Deny,
+        }
+    }
+}
+
+// serialized as the variant name, like `ts_rs` and `schemars` declare it
+#[derive(Debug, Clone, ts_rs::TS, schemars::JsonSchema)]
+pub enum PermissionsResponse {
+    Allow,
+    Deny,
+    AllowAll,
+}
+
+impl PermissionsResponse {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PermissionsResponse::Allow => "Allow",
+            PermissionsResponse::Deny => "Deny",
+            PermissionsResponse::AllowAll => "AllowAll",
+        }
+    }
+
+    pub fn to_prompt_response(&self) -> PromptResponse {
+        match self {
+            PermissionsResponse::Allow => PromptResponse::Allow,
+            PermissionsResponse::Deny => PromptResponse::Deny,
+            PermissionsResponse::AllowAll => PromptResponse::AllowAll,
+        }
+    }
+}
+
+impl std::str::FromStr for PermissionsResponse {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Allow" => Ok(PermissionsResponse::Allow),
+            "Deny" => Ok(PermissionsResponse::Deny),
+            "AllowAll" => Ok(PermissionsResponse::AllowAll),
+            _ => Err(format!("Invalid permissions response: {}", s)),
+        }
+    }
+}
+
+impl serde::Serialize for PermissionsResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PermissionsResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PermissionPrompt {
+    message: String,
+    name: String,
+    api_name: Option<String>,
+    is_unary: bool,
+    response: Option<PermissionsResponse>,
+}
+
+impl PermissionPrompt {
+    fn new(message: String, name: &str, api_name: Option<&str>, is_unary: bool) -> Self {
+        Self {
+            message,
+            name: name.to_string(),
+            api_name: api_name.map(|s| s.to_string()),
+            is_unary,
+            response: None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Permission asked for, e.g. `read`, `net` or `bridge`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// API that needs it, e.g. `Deno.readFile()`
+    pub fn api_name(&self) -> Option<&str> {
+        self.api_name.as_deref()
+    }
+
+    pub fn is_unary(&self) -> bool {
+        self.is_unary
+    }
+}
+
+/// Answers permission prompts on behalf of the user, e.g. from an allowlist
+/// kept by the app or in tests.
+///
+/// Asked before a prompt reaches the frontend, on the task's thread. Answers
+/// are recorded in the task's permission history like the user's.
+pub trait PermissionBroker: Send + Sync {
+    /// `None` leaves the prompt to the user.
+    fn prompt(&self, task_id: &str, prompt: &PermissionPrompt) -> Option<PermissionsResponse>;
+}
+
+impl fmt::Debug for dyn PermissionBroker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PermissionBroker")
+    }
+}
+
+/// Where a task is at, the variants carry what only makes sense in them.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TaskState {
+    /// Waiting for a free slot, see `TaskLane`
+    Queued,
+    /// Started while offline with `queue_until_online`, waiting to be back
+    /// online
+    WaitingForNetwork,
+    Running,
+    /// Failed with `message` on `attempt`, runs again after `backoff_ms`, see
+    /// `RunOptions::retry`
+    Retrying {
+        attempt: u32,
+        message: String,
+        #[ts(type = "number")]
+        backoff_ms: u64,
+    },
+    /// Waiting for the user to answer `prompt`
+    WaitingForPermission {
+        prompt: PermissionPrompt,
+    },
+    /// Suspended by `pause_task` until `resume_task`
+    Paused,
+    /// Asked to stop, until its thread is done
+    Stopping,
+    Stopped,
+    /// Terminated after running for longer than its `timeout_ms`
+    TimedOut {
+        #[ts(type = "number")]
+        timeout_ms: u64,
+    },
+    Completed,
+    /// The app quit while it was running, see `RunOptions::restartable`
+    Interrupted,
+    Error {
+        message: String,
+        stack: Option<String>,
+        /// Empty when the task didn't fail with a JS error
+        frames: Vec<StackFrame>,
+    },
+}
+
+impl TaskState {
+    // deno's errors end with their stack, a `    at ...` line per frame
+    fn error(error: String, frames: Vec<StackFrame>) -> Self {
+        match error.find("\n    at ") {
+            Some(i) => TaskState::Error {
+                message: error[..i].to_string(),
+                stack: Some(error[i + 1..].to_string()),
+                frames,
+            },
+            None => TaskState::Error {
+                message: error,
+                stack: None,
+                frames,
+            },
+        }
+    }
+
+    /// The `kind` it's serialized with, e.g. `waiting_for_permission`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskState::Queued => "queued",
+            TaskState::WaitingForNetwork => "waiting_for_network",
+            TaskState::Running => "running",
+            TaskState::Retrying { .. } => "retrying",
+            TaskState::WaitingForPermission { .. } => "waiting_for_permission",
+            TaskState::Paused => "paused",
+            TaskState::Stopping => "stopping",
+            TaskState::Stopped => "stopped",
+            TaskState::TimedOut { .. } => "timed_out",
+            TaskState::Completed => "completed",
+            TaskState::Interrupted => "interrupted",
+            TaskState::Error { .. } => "error",
+        }
+    }
+
+    fn waiting_on(&self) -> Option<WaitingOn> {
+        match self {
+            TaskState::Queued => {
+                let detail = match config::get().max_concurrent_tasks() {
+                    Some(limit) => {
+                        format!("Queued until one of the {} background slots is free", limit)
+                    }
+                    None => "Queued until a background slot is free".to_string(),
+                };
+                Some(WaitingOn::new(WaitingKind::Queue, detail))
+            }
+            TaskState::WaitingForNetwork => Some(WaitingOn::new(
+                WaitingKind::Network,
+                "Starts once the app is back online",
+            )),
+            TaskState::WaitingForPermission { prompt } => Some(WaitingOn::new(
+                WaitingKind::Permission,
+                prompt.message.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Whether the task completed, failed, timed out, was stopped or
+    /// interrupted
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TaskState::Completed
+                | TaskState::Error { .. }
+                | TaskState::Stopped
+                | TaskState::TimedOut { .. }
+                | TaskState::Interrupted
+        )
+    }
+
+    // Finished tasks stay as they are, and a task asked to stop ends stopped
+    // whatever it does meanwhile
+    fn can_become(&self, next: &TaskState) -> bool {
+        use TaskState::*;
+
+        match self {
+            Queued | WaitingForNetwork => matches!(next, Running | Stopped | Error { .. }),
+            Running | WaitingForPermission { .. } | Paused => matches!(
+                next,
+                Running
+                    | WaitingForPermission { .. }
+                    | Paused
+                    | Stopping
+                    | Stopped
+                    | TimedOut { .. }
+                    | Completed
+                    | Error { .. }
+                    | Retrying { .. }
+            ),
+            // the error ending the attempt may be reported more than once
+            Retrying { .. } => matches!(next, Running | Stopping | Stopped),
+            Stopping => matches!(next, Stopped),
+            Stopped | TimedOut { .. } | Completed | Interrupted | Error { .. } => false,
+        }
+    }
+}
+
+/// Why a task isn't progressing, see `Task::waiting_on`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct WaitingOn {
+    kind: WaitingKind,
+    /// What it's waiting for, in words the UI can show as is
+    detail: String,
+}
+
+impl WaitingOn {
+    fn new(kind: WaitingKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn kind(&self) -> WaitingKind {
+        self.kind
+    }
+
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitingKind {
+    /// The user's answer to a permission prompt
+    Permission,
+    /// The app to be back online, see `RunOptions::queue_until_online`
+    Network,
+    /// A line of stdin, for `prompt`, `confirm` or `alert`
+    UserInput,
+    /// A free background slot, see `TaskLane`
+    Queue,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct Task {
+    id: String,
+    profile: String,
+    /// See `RunOptions::name`
+    name: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    state: TaskState,
+    /// Why it isn't progressing, `None` while it is. Set with the state,
+    /// except for `user_input` which a running task waits on
+    waiting_on: Option<WaitingOn>,
+    /// Value passed to `RuntimeExtension.returnValue`
+    #[ts(type = "unknown")]
+    return_value: Option<serde_json::Value>,
+    permission_history: Vec<PermissionPrompt>,
+    #[serde(flatten)]
+    #[ts(flatten)]
+    output: TaskOutput,
+    dry_run_changes: Option<Vec<FsChange>>,
+    diagnostics: Vec<TaskDiagnostic>,
+    op_audit: OpAuditLog,
+    /// Names of the files saved with `RuntimeExtension.saveArtifact`
+    artifacts: Vec<String>,
+    /// Window that started the task, `None` once the app owns it
+    owner_window: Option<String>,
+    on_window_closed: WindowClosedPolicy,
+    /// CPU time its thread spent on the task, set once the thread is done.
+    /// `None` where it can't be measured
+    #[ts(type = "number | null")]
+    cpu_time_ms: Option<u64>,
+    /// Run of the task it's at, from 1, see `RunOptions::retry`
+    attempt: u32,
+    /// Latest progress the script reported, `None` if it never did
+    progress: Option<TaskProgress>,
+    timings: TaskTimings,
+    /// Milliseconds since the Unix epoch, from when this run was submitted
+    #[ts(type = "number")]
+    created_at_ms: u64,
+    /// From when it started running, `None` while it's queued
+    #[ts(type = "number | null")]
+    started_at_ms: Option<u64>,
+    #[ts(type = "number | null")]
+    finished_at_ms: Option<u64>,
+    /// Time from its start, or its submission when it never started, to its
+    /// end, retries included. `None` until it finishes
+    #[ts(type = "number | null")]
+    duration_ms: Option<u64>,
+    #[serde(skip)]
+    retry: Option<RetryPolicy>,
+}
+
+impl Task {
+    fn new(id: String, profile: String, initial_state: TaskState) -> Self {
+        Self {
+            id,
+            profile,
+            name: None,
+            description: None,
+            tags: Vec::new(),
+            waiting_on: initial_state.waiting_on(),
+            state: initial_state,
+            return_value: None,
+            permission_history: Vec::new(),
+            output: TaskOutput::default(),
+            dry_run_changes: None,
+            diagnostics: Vec::new(),
+            op_audit: OpAuditLog::default(),
+            artifacts: Vec::new(),
+            owner_window: None,
+            on_window_closed: WindowClosedPolicy::default(),
+            cpu_time_ms: None,
+            attempt: 1,
+            progress: None,
+            timings: TaskTimings::default(),
+            created_at_ms: now_ms(),
+            started_at_ms: None,
+            finished_at_ms: None,
+            duration_ms: None,
+            retry: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn state(&self) -> &TaskState {
+        &self.state
+    }
+
+    pub fn waiting_on(&self) -> Option<&WaitingOn> {
+        self.waiting_on.as_ref()
+    }
+
+    /// Whether the task completed, failed, timed out, was stopped or
+    /// interrupted
+    pub fn is_finished(&self) -> bool {
+        self.state.is_finished()
+    }
+
+    /// Message of the error the task failed with, empty otherwise
+    pub fn error(&self) -> &str {
+        match &self.state {
+            TaskState::Error { message, .. } => message,
+            _ => "",
+        }
+    }
+
+    // Invalid transitions are ignored, they come from a task finishing or
+    // being stopped meanwhile. An error with attempts left is a retry
+    fn transition(&mut self, next: TaskState) -> bool {
+        let running = matches!(
+            self.state,
+            TaskState::Running | TaskState::WaitingForPermission { .. } | TaskState::Paused
+        );
+        let backoff = match &next {
+            TaskState::Error { message, .. } if running => self.retry_backoff(message),
+            _ => None,
+        };
+        let next = match (next, backoff) {
+            (TaskState::Error { message, .. }, Some(backoff)) => TaskState::Retrying {
+                attempt: self.attempt,
+                message,
+                backoff_ms: backoff.as_millis() as u64,
+            },
+            (next, _) => next,
+        };
+        if !self.state.can_become(&next) {
+            log!(
+                Debug,
+                "Task {} can't go from {} to {}",
+                self.id,
+                self.state.name(),
+                next.name()
+            );
+            return false;
+        }
+
+        if next.is_finished() && !self.state.is_finished() {
+            self.finish(now_ms());
+        }
+        self.waiting_on = next.waiting_on();
+        self.state = next;
+        true
+    }
+
+    fn finish(&mut self, finished_at_ms: u64) {
+        let started_at_ms = self.started_at_ms.unwrap_or(self.created_at_ms);
+        self.finished_at_ms = Some(finished_at_ms);
+        self.duration_ms = Some(finished_at_ms.saturating_sub(started_at_ms));
+    }
+
+    pub fn cpu_time_ms(&self) -> Option<u64> {
+        self.cpu_time_ms
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn progress(&self) -> Option<&TaskProgress> {
+        self.progress.as_ref()
+    }
+
+    pub fn timings(&self) -> &TaskTimings {
+        &self.timings
+    }
+
+    pub fn created_at_ms(&self) -> u64 {
+        self.created_at_ms
+    }
+
+    pub fn started_at_ms(&self) -> Option<u64> {
+        self.started_at_ms
+    }
+
+    pub fn finished_at_ms(&self) -> Option<u64> {
+        self.finished_at_ms
+    }
+
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.duration_ms
+    }
+
+    fn retry_backoff(&self, error: &str) -> Option<Duration> {
+        self.retry.as_ref()?.backoff(self.attempt, error)
+    }
+
+    /// Value passed to `RuntimeExtension.returnValue`, `None` when unset
+    pub fn return_value(&self) -> Option<&serde_json::Value> {
+        self.return_value.as_ref()
+    }
+
+    pub fn output(&self) -> &TaskOutput {
+        &self.output
+    }
+
+    pub fn permission_history(&self) -> &[PermissionPrompt] {
+        &self.permission_history
+    }
+}
+
+#[op2(fast)]
+fn return_value(state: &mut OpState, #[string] value: &str) -> Result<(), AnyError> {
+    op_grants::check(state, "return_value")?;
+
+    // serialized by the script, so `toJSON` and friends apply
+    let value: serde_json::Value = serde_json::from_str(value)?;
+    state
+        .borrow::<TaskRuntime>()
+        .with_task(&state.borrow::<TaskId>().0, move |task| {
+            task.return_value = Some(value);
+        });
+
+    Ok(())
+}
+
+#[op2]
+#[string]
+fn document_dir(state: &mut OpState) -> Result<Option<String>, AnyError> {
+    op_grants::check(state, "document_dir")?;
+
+    Ok(dirs::document_dir().map(|path| path.to_string_lossy().to_string()))
+}
+
+struct TaskId(String);
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct JsRunOptions {
+    // the clock starts at a fixed instant and moves 1ms per read
+    deterministic_epoch_ms: Option<f64>,
+    rng_seed: Option<u64>,
+    cassette_mode: Option<CassetteMode>,
+    checkpoint_state: Option<String>,
+}
+
+#[op2]
+#[serde]
+fn run_options(state: &mut OpState) -> JsRunOptions {
+    let options = state.borrow::<RunOptions>();
+
+    JsRunOptions {
+        deterministic_epoch_ms: options.deterministic.then_some(DETERMINISTIC_EPOCH_MS),
+        rng_seed: options.rng_seed(),
+        cassette_mode: options.network_cassette.as_ref().map(|c| c.mode()),
+        checkpoint_state: options.checkpoint_state.clone(),
+    }
+}
+
+// Read when the script asks for them, idle workers are bootstrapped before
+// their task is known
+#[op2]
+#[serde]
+fn task_args(state: &mut OpState) -> Option<serde_json::Value> {
+    state.borrow::<RunOptions>().args.clone()
+}
+
+#[op2]
+#[serde]
+fn task_input(state: &mut OpState) -> Option<serde_json::Value> {
+    state.borrow::<RunOptions>().input.clone()
+}
+
+// Called by `prompt`, `confirm` and `alert` around reading stdin, with what
+// they ask and then an empty message
+#[op2(fast)]
+fn waiting_for_input(state: &mut OpState, #[string] message: String) {
+    let task_id = state.borrow::<TaskId>().0.clone();
+    let runtime = state.borrow::<TaskRuntime>();
+
+    let task = runtime
+        .with_task(&task_id, move |task| {
+            // the other states have their own reason, or none
+            if !matches!(task.state, TaskState::Running) {
+                return None;
+            }
+            task.waiting_on =
+                (!message.is_empty()).then(|| WaitingOn::new(WaitingKind::UserInput, message));
+            Some(task.clone())
+        })
+        .flatten();
+
+    if let Some(task) = task {
+        runtime.emit_task_state_changed(task);
+    }
+}
+
+// Called by `RuntimeExtension.reportProgress`, an empty message is none
+#[op2(fast)]
+fn report_progress(
+    state: &mut OpState,
+    percent: f64,
+    #[string] message: String,
+) -> Result<(), AnyError> {
+    let progress = TaskProgress::new(percent, message).map_err(AnyError::msg)?;
+    let task_id = state.borrow::<TaskId>().0.clone();
+    let runtime = state.borrow::<TaskRuntime>();
+
+    let reported = progress.clone();
+    let updated = runtime
+        .with_task(&task_id, move |task| {
+            // e.g. a callback running after the task was stopped
+            if task.is_finished() {
+                return false;
+            }
+            task.progress = Some(reported);
+            true
+        })
+        .unwrap_or(false);
+    if updated {
+        runtime.emit_task_event(TaskEvent::Progress { task_id, progress });
+    }
+
+    Ok(())
+}
+
+struct TaskCode(String);
+
+// Host events sent since the task started and not picked up yet
+struct HostEvents(Rc<tokio::sync::Mutex<tokio::sync::broadcast::Receiver<String>>>);
+
+impl HostEvents {
+    fn new(runtime: &TaskRuntime) -> Self {
+        Self(Rc::new(tokio::sync::Mutex::new(
+            runtime.inner.host_events.subscribe(),
+        )))
+    }
+}
+
+#[op2(async)]
+#[string]
+async fn next_host_event(state: Rc<RefCell<OpState>>) -> Option<String> {
+    let events = state.borrow().borrow::<HostEvents>().0.clone();
+    let mut events = events.lock().await;
+
+    loop {
+        match events.recv().await {
+            Ok(event) => return Some(event),
+            // the most recent ones are still worth handling
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+// Next message posted to the task, `None` once it can't get any more
+#[op2(async)]
+#[string]
+async fn next_task_message(state: Rc<RefCell<OpState>>) -> Option<String> {
+    let inbox = state.borrow().borrow::<TaskInbox>().0.clone();
+    let mut inbox = inbox.lock().await;
+
+    inbox.recv().await
+}
+
+// Called by `RuntimeExtension.postMessage` with the message as JSON
+#[op2(fast)]
+fn post_message(state: &mut OpState, #[string] message: &str) -> Result<(), AnyError> {
+    let task_id = state.borrow::<TaskId>().0.clone();
+    let message = serde_json::from_str(message)?;
+
+    state
+        .borrow::<TaskRuntime>()
+        .emit_task_event(TaskEvent::Message { task_id, message });
+
+    Ok(())
+}
+
+// Called by `RuntimeExtension.emit`, the payload as JSON
+#[op2(fast)]
+fn emit_event(
+    state: &mut OpState,
+    #[string] event: &str,
+    #[string] payload: &str,
+) -> Result<(), AnyError> {
+    op_grants::check(state, "emit_event")?;
+    validate_event_name(event).map_err(AnyError::msg)?;
+    bridge::check(state, BridgeCapability::Emit, "RuntimeExtension.emit")?;
+
+    let task_id = state.borrow::<TaskId>().0.clone();
+    let payload: serde_json::Value = serde_json::from_str(payload)?;
+    let runtime = state.borrow::<TaskRuntime>();
+    let Some(app_handle) = runtime.inner.app_handle.get() else {
+        return Err(AnyError::msg("The app bridge is not available"));
+    };
+
+    app_handle.emit(
+        event,
+        serde_json::json!({ "task_id": task_id, "payload": payload }),
+    )?;
+
+    Ok(())
+}
+
+// Called by `RuntimeExtension.listen` for the first listener of `event`
+#[op2(fast)]
+fn listen_app_event(state: &mut OpState, #[string] event: &str) -> Result<(), AnyError> {
+    op_grants::check(state, "listen_app_event")?;
+    validate_event_name(event).map_err(AnyError::msg)?;
+    bridge::check(state, BridgeCapability::Listen, "RuntimeExtension.listen")?;
+
+    let runtime = state.borrow::<TaskRuntime>();
+    let Some(app_handle) = runtime.inner.app_handle.get().cloned() else {
+        return Err(AnyError::msg("The app bridge is not available"));
+    };
+    state
+        .borrow_mut::<AppEventListeners>()
+        .listen(&app_handle, event);
+
+    Ok(())
+}
+
+// Once the last listener of `event` is removed
+#[op2(fast)]
+fn unlisten_app_event(state: &mut OpState, #[string] event: &str) {
+    state.borrow_mut::<AppEventListeners>().unlisten(event);
+}
+
+// Next app event the task listens to, as it's emitted
+#[op2(async)]
+#[serde]
+async fn next_app_event(state: Rc<RefCell<OpState>>) -> Option<AppEvent> {
+    let receiver = state.borrow().borrow::<AppEventListeners>().receiver();
+    let mut receiver = receiver.lock().await;
+
+    receiver.recv().await
+}
+
+// Tauri panics on names it doesn't accept, and neither its own events, like
+// `tauri://close-requested`, nor the runtime's can be faked or overheard
+fn validate_event_name(event: &str) -> Result<(), String> {
+    let is_valid = !event.is_empty()
+        && event
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '/' | ':' | '_'));
+    if !is_valid {
+        return Err(format!(
+            "Invalid event name {:?}, use letters, digits, `-`, `/`, `:` and `_`",
+            event
+        ));
+    }
+    if sdk::EVENTS.contains(&event) {
+        return Err(format!("{} is an event of the runtime", event));
+    }
+    if event.starts_with("tauri://") {
+        return Err(format!("{} is an event of Tauri", event));
+    }
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn save_checkpoint(
+    state: &mut OpState,
+    #[string] checkpoint_state: String,
+) -> Result<(), AnyError> {
+    op_grants::check(state, "save_checkpoint")?;
+
+    let checkpoint = Checkpoint {
+        code: state.borrow::<TaskCode>().0.clone(),
+        options: state.borrow::<RunOptions>().clone(),
+        state: checkpoint_state,
+    };
+
+    checkpoint::save(
+        state.borrow::<TaskRuntime>().data_dir(),
+        &state.borrow::<TaskId>().0,
+        &checkpoint,
+    )?;
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn save_artifact(
+    state: &mut OpState,
+    #[string] name: String,
+    #[buffer] data: &[u8],
+) -> Result<(), AnyError> {
+    op_grants::check(state, "save_artifact")?;
+    artifacts::validate_name(&name).map_err(AnyError::msg)?;
+
+    let task_id = &state.borrow::<TaskId>().0;
+    let runtime = state.borrow::<TaskRuntime>();
+
+    artifacts::save(runtime.data_dir(), task_id, &name, data)?;
+    runtime.with_task(task_id, move |task| {
+        if !task.artifacts.contains(&name) {
+            task.artifacts.push(name);
+        }
+    });
+
+    Ok(())
+}
+
+#[op2]
+fn cassette_record(
+    state: &mut OpState,
+    #[serde] response: RecordedResponse,
+    #[buffer] body: &[u8],
+) -> Result<(), AnyError> {
+    op_grants::check(state, "cassette_record")?;
+
+    state.borrow_mut::<Cassette>().record(response, body)
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ReplayedResponse {
+    #[serde(flatten)]
+    response: RecordedResponse,
+    body: ToJsBuffer,
+}
+
+#[op2]
+#[serde]
+fn cassette_replay(
+    state: &mut OpState,
+    #[string] method: &str,
+    #[string] url: &str,
+) -> Result<Option<ReplayedResponse>, AnyError> {
+    op_grants::check(state, "cassette_replay")?;
+
+    let replayed = state.borrow_mut::<Cassette>().replay(method, url)?;
+
+    Ok(replayed.map(|(response, body)| ReplayedResponse {
+        response,
+        body: body.into(),
+    }))
+}
+
+// Milliseconds since the Unix epoch
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+// Per profile, in the app data directory
+fn task_store_path(profile: &str) -> String {
+    format!("task_store_{}.json", profile)
+}
+
+#[op2]
+#[serde]
+fn bridge_store_get(
+    state: &mut OpState,
+    #[string] key: &str,
+) -> Result<Option<serde_json::Value>, AnyError> {
+    op_grants::check(state, "bridge_store_get")?;
+    bridge::check(state, BridgeCapability::Store, "RuntimeExtension.store.get")?;
+
+    let runtime = state.borrow::<TaskRuntime>();
+    let Some(app_handle) = runtime.inner.app_handle.get() else {
+        return Ok(None);
+    };
+
+    let store = app_handle.store(task_store_path(&runtime.inner.profile))?;
+
+    Ok(store.get(key))
+}
+
+#[op2]
+fn bridge_store_set(
+    state: &mut OpState,
+    #[string] key: String,
+    #[serde] value: serde_json::Value,
+) -> Result<(), AnyError> {
+    op_grants::check(state, "bridge_store_set")?;
+    bridge::check(state, BridgeCapability::Store, "RuntimeExtension.store.set")?;
+
+    let runtime = state.borrow::<TaskRuntime>();
+    let Some(app_handle) = runtime.inner.app_handle.get() else {
+        return Err(AnyError::msg("The app bridge is not available"));
+    };
+
+    let store = app_handle.store(task_store_path(&runtime.inner.profile))?;
+    store.set(key, value);
+
+    Ok(())
+}
+
+// Replaces `op_print` so console output is captured on the task instead of
+// only going to the host stdout
+#[op2(fast)]
+fn capture_print(state: &mut OpState, #[string] msg: &str, is_err: bool) -> Result<(), AnyError> {
+    let task_id = &state.borrow::<TaskId>().0;
+    let runtime = state.borrow::<TaskRuntime>();
+
+    let chunk = msg.to_string();
+    let limit = config::get().max_task_output_bytes();
+    let Some((kept_len, started_truncating)) = runtime.with_task(task_id, move |task| {
+        let (kept, started_truncating) = task.output.push(&chunk, limit);
+        (kept.len(), started_truncating)
+    }) else {
+        return Ok(());
+    };
+    let kept = &msg[..kept_len];
+
+    if is_err {
+        std::io::stderr().write_all(kept.as_bytes())?;
+    } else {
+        std::io::stdout().write_all(kept.as_bytes())?;
+    }
+
+    if !kept.is_empty() {
+        let stream = if is_err {
+            OutputStream::Stderr
+        } else {
+            OutputStream::Stdout
+        };
+        runtime
+            .inner
+            .output_channels
+            .lock()
+            .unwrap()
+            .publish(task_id, stream, kept);
+    }
+
+    if started_truncating {
+        runtime.emit_task_event(TaskEvent::OutputTruncated {
+            task_id: task_id.clone(),
+            limit,
+        });
+    }
+
+    Ok(())
+}
+
+// Called by the `console` methods along with the printing, with the args
+// serialized to a JSON array
+#[op2(fast)]
+fn capture_log(
+    state: &mut OpState,
+    #[string] level: &str,
+    #[string] args: &str,
+) -> Result<(), AnyError> {
+    let task_id = &state.borrow::<TaskId>().0;
+    let runtime = state.borrow::<TaskRuntime>();
+
+    let level = serde_json::from_value(serde_json::Value::String(level.to_string()))?;
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let log = TaskLog::new(task_id, level, serde_json::from_str(args)?, timestamp_ms);
+
+    let limit = config::get().max_task_output_bytes();
+    let kept = runtime
+        .inner
+        .task_logs
+        .lock()
+        .unwrap()
+        .push(log.clone(), args.len(), limit);
+    if kept {
+        runtime.emit_task_event(TaskEvent::Log(log));
+    }
+
+    Ok(())
+}
+
+deno_runtime::deno_core::extension!(
+  runtime_extension,
+  ops = [
+    return_value,
+    document_dir,
+    capture_print,
+    capture_log,
+    run_options,
+    task_args,
+    task_input,
+    cassette_record,
+    cassette_replay,
+    save_checkpoint,
+    save_artifact,
+    bridge_store_get,
+    bridge_store_set,
+    next_host_event,
+    waiting_for_input,
+    report_progress,
+    next_task_message,
+    post_message,
+    emit_event,
+    listen_app_event,
+    unlisten_app_event,
+    next_app_event,
+  ],
+  esm_entry_point = "ext:runtime_extension/bootstrap.js",
+  esm = [dir "src", "bootstrap.js"],
+  options = {
+    runtime: TaskRuntime,
+    task_id: String,
+    code: String,
+    run_options: RunOptions,
+    cassette: Option<Cassette>,
+  },
+  middleware = |op| match op.name {
+    "op_print" => op.with_implementation_from(&capture_print()),
+    _ => op,
+  },
+  state = |state, options| {
+    state.put(HostEvents::new(&options.runtime));
+    state.put(options.runtime);
+    state.put(TaskId(options.task_id));
+    state.put(TaskCode(options.code));
+    state.put(GrantedOps::new(&options.run_options));
+    state.put(BridgePermissions::default());
+    state.put(AppEventListeners::default());
+    state.put(options.run_options);
+    if let Some(cassette) = options.cassette {
+      state.put(cassette);
+    }
+  },
+);
+
+fn create_worker(
+    main_module: &ModuleSpecifier,
+    fs: Arc<dyn FileSystem>,
+    permissions: Permissions,
+    extensions: Vec<Extension>,
+    options: &RunOptions,
+    diagnostics: PendingDiagnostics,
+    custom_loader: Option<Arc<dyn ModuleLoader>>,
+) -> MainWorker {
+    let permission_desc_parser = Arc::new(RuntimePermissionDescriptorParser::new(fs.clone()));
+
+    let source_map_store = Rc::new(RefCell::new(HashMap::new()));
+    let sources = ModuleSources::default();
+
+    let permission_container = PermissionsContainer::new(permission_desc_parser, permissions);
+
+    let (stdio, stdin) = stdin::stdio();
+
+    threads::init_v8_platform();
+
+    let mut worker = MainWorker::bootstrap_from_options(
+        main_module.clone(),
+        WorkerServiceOptions {
+            module_loader: Rc::new(TypescriptModuleLoader {
+                source_maps: source_map_store,
+                sources: sources.clone(),
+                diagnostics,
+                custom_loader,
+                lock: options.module_lock.clone(),
+            }),
+            // File only loader
+            // module_loader: Rc::new(FsModuleLoader),
+            permissions: permission_container,
+            blob_store: Default::default(),
+            broadcast_channel: Default::default(),
+            feature_checker: features::feature_checker(),
+            node_services: Default::default(),
+            npm_process_state_provider: Default::default(),
+            root_cert_store_provider: Default::default(),
+            shared_array_buffer_store: Default::default(),
+            compiled_wasm_module_store: Default::default(),
+            v8_code_cache: Default::default(),
+            fetch_dns_resolver: Default::default(),
+            fs,
+        },
+        WorkerOptions {
+            extensions,
+            // seeds the crypto RNG
+            seed: options.rng_seed(),
+            bootstrap: BootstrapOptions {
+                unstable_features: features::unstable_feature_ids(),
+                ..Default::default()
+            },
+            maybe_inspector_server: features::inspector_server(),
+            startup_snapshot: snapshot::startup_snapshot(),
+            create_params: options.max_heap_size_mb.map(heap_limit::create_params),
+            stdio,
+            ..Default::default()
+        },
+    );
+
+    // for the stack frames of the errors tasks fail with
+    worker.js_runtime.op_state().borrow_mut().put(sources);
+
+    stdin::install_prompts(&mut worker);
+    if let Some(stdin) = stdin {
+        // taken by the task adopting the worker
+        worker.js_runtime.op_state().borrow_mut().put(stdin);
+    }
+
+    worker
+}
+
+pub fn sweep_orphaned_code() -> usize {
+    profiles::data_dirs()
+        .iter()
+        .map(|dir| staging::sweep_orphaned_code(dir))
+        .sum()
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use super::config::{self, log};
+use super::TaskRuntime;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// Pressure ends below this share of the threshold, so memory hovering around
+// it doesn't pause and resume the queue every poll
+const RELEASE_PERCENT: u64 = 90;
+
+/// Polls the resident memory of the process against the
+/// `memory_pressure_threshold_mb` setting and tells the runtimes when it
+/// crosses it. Stops once the runtimes are dropped.
+pub fn spawn_monitor(runtimes: Weak<Mutex<HashMap<String, TaskRuntime>>>) {
+    thread::spawn(move || {
+        let mut under_pressure = false;
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let Some(runtimes) = runtimes.upgrade() else {
+                return;
+            };
+
+            let threshold = config::get()
+                .memory_pressure_threshold_mb()
+                .map(|mb| mb * 1024 * 1024);
+
+            let pressure = match (threshold, resident_set_bytes()) {
+                (Some(threshold), Some(rss)) if under_pressure => {
+                    rss * 100 >= threshold * RELEASE_PERCENT
+                }
+                (Some(threshold), Some(rss)) => rss >= threshold,
+                _ => false,
+            };
+
+            if pressure == under_pressure {
+                continue;
+            }
+            under_pressure = pressure;
+
+            log!(
+                Info,
+                "Memory pressure {}",
+                if pressure { "started" } else { "ended" }
+            );
+
+            let runtimes: Vec<TaskRuntime> = runtimes.lock().unwrap().values().cloned().collect();
+            for runtime in runtimes {
+                runtime.set_memory_pressure(pressure);
+            }
+        }
+    });
+}
+
+/// Resident memory of the process, `None` where it can't be read.
+pub fn resident_set_bytes() -> Option<u64> {
+    sys::resident_set_bytes()
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    pub fn resident_set_bytes() -> Option<u64> {
+        // size and resident, in pages
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+
+        // SAFETY: no pointers involved
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+        Some(pages * u64::try_from(page_size).ok()?)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    pub fn resident_set_bytes() -> Option<u64> {
+        // SAFETY: the buffer is a zeroed `proc_taskinfo` of the size passed
+        unsafe {
+            let mut info: libc::proc_taskinfo = std::mem::zeroed();
+            let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+            let written = libc::proc_pidinfo(
+                std::process::id() as libc::c_int,
+                libc::PROC_PIDTASKINFO,
+                0,
+                &mut info as *mut _ as *mut libc::c_void,
+                size,
+            );
+
+            (written == size).then_some(info.pti_resident_size)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use windows_sys::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    pub fn resident_set_bytes() -> Option<u64> {
+        let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+
+        // SAFETY: the counters are zeroed and of the size passed, the pseudo
+        // handle of the current process is always valid
+        unsafe {
+            let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+            counters.cb = size;
+
+            let ok = GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size);
+
+            (ok != 0).then_some(counters.WorkingSetSize as u64)
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod sys {
+    pub fn resident_set_bytes() -> Option<u64> {
+        None
+    }
+}
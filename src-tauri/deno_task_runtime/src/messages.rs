@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Where `TaskRuntime::post_message_to_task` sends the messages of the
+/// running tasks, read with `RuntimeExtension.onMessage`.
+#[derive(Debug, Default)]
+pub struct TaskMailboxes {
+    senders: HashMap<String, UnboundedSender<String>>,
+}
+
+impl TaskMailboxes {
+    /// Opens the inbox of a task's run. Messages still unread by its previous
+    /// attempt are dropped along with it.
+    pub fn open(&mut self, task_id: &str) -> TaskInbox {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.senders.insert(task_id.to_string(), sender);
+
+        TaskInbox(Rc::new(tokio::sync::Mutex::new(receiver)))
+    }
+
+    pub fn post(&self, task_id: &str, message: String) -> Result<(), String> {
+        self.senders
+            .get(task_id)
+            .and_then(|sender| sender.send(message).ok())
+            .ok_or_else(|| format!("Task {} isn't running", task_id))
+    }
+
+    /// Ends the task's `onMessage` loop, once its thread is done.
+    pub fn close(&mut self, task_id: &str) {
+        self.senders.remove(task_id);
+    }
+}
+
+/// Messages posted to a task, JSON encoded, in its worker's `OpState`.
+pub struct TaskInbox(pub Rc<tokio::sync::Mutex<UnboundedReceiver<String>>>);
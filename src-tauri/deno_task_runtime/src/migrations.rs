@@ -0,0 +1,158 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::config::log;
+use super::staging::{code_dir, is_staged_code};
+
+const VERSION_FILE: &str = "storage_version";
+const BACKUPS_DIR: &str = "backups";
+
+/// A step that upgrades the persisted state (checkpoints, cassettes, ...) in
+/// the app directory from `version - 1` to `version`.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub run: fn(&Path) -> std::io::Result<()>,
+}
+
+// Append only, every migration must keep working on any older layout and be
+// a no-op on an empty directory
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    name: "baseline",
+    run: |_| Ok(()),
+}];
+
+/// Brings the persisted state up to the current version, must be called
+/// before any task runs. `Profiles` does it before starting the runtimes.
+///
+/// The directory is backed up to `backups/` before the first migration runs,
+/// and restored from that backup if any of them fails.
+///
+/// Returns the version the state is at.
+pub fn run_migrations() -> Result<u32, String> {
+    migrate(&code_dir(), MIGRATIONS)
+}
+
+/// `run_migrations` with `migrations` on the app directory at `dir`.
+pub fn migrate(dir: &Path, migrations: &[Migration]) -> Result<u32, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    let current = read_version(dir)?;
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+
+    if current > latest {
+        return Err(format!(
+            "Persisted state is at version {} but this build only knows up to {}, not touching it",
+            current, latest
+        ));
+    }
+
+    let pending: Vec<&Migration> = migrations
+        .iter()
+        .filter(|migration| migration.version > current)
+        .collect();
+
+    if pending.is_empty() {
+        return Ok(current);
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let backup = dir
+        .join(BACKUPS_DIR)
+        .join(format!("v{}-{}", current, timestamp));
+
+    copy_state(dir, &backup).map_err(|e| format!("Failed to back up {}: {}", dir.display(), e))?;
+    log!(Info, "Backed up persisted state to {}", backup.display());
+
+    for migration in pending {
+        log!(
+            Info,
+            "Migrating persisted state to version {} ({})",
+            migration.version,
+            migration.name
+        );
+
+        let result = (migration.run)(dir).and_then(|_| write_version(dir, migration.version));
+
+        if let Err(e) = result {
+            // what the failed migrations wrote goes too, the version included
+            let restored = clear_state(dir).and_then(|_| copy_state(&backup, dir));
+            let restored = match restored {
+                Ok(_) => "restored the backup".to_string(),
+                Err(e) => format!("restoring the backup failed too: {}", e),
+            };
+
+            return Err(format!(
+                "Migration {} ({}) failed: {}, {}",
+                migration.version, migration.name, e, restored
+            ));
+        }
+    }
+
+    Ok(latest)
+}
+
+fn read_version(dir: &Path) -> Result<u32, String> {
+    match std::fs::read_to_string(dir.join(VERSION_FILE)) {
+        Ok(version) => version
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid storage version: {:?}", version)),
+        // state written before migrations existed
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn write_version(dir: &Path, version: u32) -> std::io::Result<()> {
+    std::fs::write(dir.join(VERSION_FILE), version.to_string())
+}
+
+// Removes the persisted state, leaving out the backups and the code of running
+// tasks like `copy_state`
+fn clear_state(dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.file_name().is_some_and(|name| name == BACKUPS_DIR) || is_staged_code(&path) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Copies the persisted state, leaving out the backups themselves and the code
+// of running tasks
+fn copy_state(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if path.file_name().is_some_and(|name| name == BACKUPS_DIR) || is_staged_code(&path) {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            copy_state(&path, &to.join(entry.file_name()))?;
+        } else if file_type.is_file() {
+            std::fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc, sync::Arc};
+
+use deno_ast::MediaType;
+use deno_ast::ModuleSpecifier;
+use deno_ast::ParseParams;
+use deno_ast::SourceMapOption;
+use deno_core::ModuleSourceCode;
+use deno_core::ModuleType;
+use deno_core::{
+    error::AnyError, resolve_import, ModuleLoadResponse, ModuleLoader as CoreModuleLoader,
+    ModuleSource, RequestedModuleType, ResolutionKind,
+};
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Error;
+use once_cell::sync::Lazy;
+
+use super::catalog::sha256_hex;
+use super::config::log;
+use super::versions::{check_min_deno_version, PendingDiagnostics};
+
+/// Serves the source of task modules from somewhere else than the disk or
+/// the network, e.g. scripts bundled with the app or kept in a database.
+///
+/// Asked first for every module a task loads, including its main module.
+/// Sources are transpiled based on the extension of the specifier, like the
+/// files on disk.
+pub trait ModuleLoader: Send + Sync {
+    /// `None` falls back to loading `file:` and `https:` modules as usual.
+    fn load_source(&self, specifier: &ModuleSpecifier) -> Result<Option<String>, AnyError>;
+}
+
+impl fmt::Debug for dyn ModuleLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ModuleLoader")
+    }
+}
+
+/// Transpiles TypeScript/JSX to JavaScript, returns the code and its source map.
+pub fn transpile(
+    module_specifier: &ModuleSpecifier,
+    code: String,
+    media_type: MediaType,
+) -> Result<(String, Vec<u8>), AnyError> {
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier: module_specifier.clone(),
+        text: code.into(),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })?;
+    let res = parsed.transpile(
+        &deno_ast::TranspileOptions {
+            imports_not_used_as_values: deno_ast::ImportsNotUsedAsValues::Remove,
+            use_decorators_proposal: true,
+            ..Default::default()
+        },
+        &deno_ast::TranspileModuleOptions::default(),
+        &deno_ast::EmitOptions {
+            source_map: SourceMapOption::Separate,
+            inline_sources: true,
+            ..Default::default()
+        },
+    )?;
+    let res = res.into_source();
+    let source_map = res.source_map.unwrap();
+    Ok((
+        String::from_utf8(res.text.into_bytes()).unwrap(),
+        source_map.into_bytes(),
+    ))
+}
+
+// Transpiled code and source map by specifier and hash of the source (the
+// media type follows from the specifier), so modules imported by several
+// tasks and the code of resumed tasks are transpiled once
+type TranspileCache = HashMap<(ModuleSpecifier, u64), (String, Vec<u8>)>;
+
+static TRANSPILE_CACHE: Lazy<Mutex<TranspileCache>> = Lazy::new(Default::default);
+
+// Dropped all at once when full, tasks are edited and re-run far more often
+// than this many modules are used
+const TRANSPILE_CACHE_ENTRIES: usize = 256;
+
+/// Drops every cached transpilation, e.g. to give memory back.
+pub fn clear_transpile_cache() {
+    TRANSPILE_CACHE.lock().unwrap().clear();
+}
+
+fn transpile_cached(
+    module_specifier: &ModuleSpecifier,
+    code: String,
+    media_type: MediaType,
+) -> Result<(String, Vec<u8>), AnyError> {
+    let mut hasher = DefaultHasher::new();
+    code.hash(&mut hasher);
+    let key = (module_specifier.clone(), hasher.finish());
+
+    if let Some(transpiled) = TRANSPILE_CACHE.lock().unwrap().get(&key) {
+        return Ok(transpiled.clone());
+    }
+
+    let transpiled = transpile(module_specifier, code, media_type)?;
+
+    let mut cache = TRANSPILE_CACHE.lock().unwrap();
+    if cache.len() >= TRANSPILE_CACHE_ENTRIES {
+        cache.clear();
+    }
+    cache.insert(key, transpiled.clone());
+
+    Ok(transpiled)
+}
+
+type SourceMapStore = Rc<RefCell<HashMap<String, Vec<u8>>>>;
+
+/// Hex SHA-256 of remote modules by URL, those a saved script imported when
+/// it was bundled. A module not matching its hash isn't loaded.
+pub type ModuleLock = BTreeMap<String, String>;
+
+/// Source of a remote module, from the app's `ModuleLoader` or the network.
+pub fn load_remote_source(
+    custom_loader: Option<&dyn ModuleLoader>,
+    specifier: &ModuleSpecifier,
+) -> Result<String, AnyError> {
+    if let Some(code) = custom_loader
+        .map(|loader| loader.load_source(specifier))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(code);
+    }
+
+    if specifier.scheme() != "https" {
+        bail!("Unknown scheme {:?}", specifier.scheme())
+    }
+    Ok(ureq::get(specifier.as_str()).call()?.into_string()?)
+}
+
+/// Code of the modules a worker loaded as written, before transpiling, by
+/// specifier. Kept in the worker's `OpState`.
+pub type ModuleSources = Rc<RefCell<HashMap<String, String>>>;
+
+pub struct TypescriptModuleLoader {
+    pub source_maps: SourceMapStore,
+    pub sources: ModuleSources,
+    pub diagnostics: PendingDiagnostics,
+    /// Set with `TaskRuntime::set_module_loader`
+    pub custom_loader: Option<Arc<dyn ModuleLoader>>,
+    pub lock: Option<Arc<ModuleLock>>,
+}
+
+// How a module is evaluated and whether it needs transpiling
+fn module_type(media_type: MediaType) -> Option<(ModuleType, bool)> {
+    match media_type {
+        MediaType::JavaScript | MediaType::Mjs | MediaType::Cjs => {
+            Some((ModuleType::JavaScript, false))
+        }
+        MediaType::Jsx => Some((ModuleType::JavaScript, true)),
+        MediaType::TypeScript
+        | MediaType::Mts
+        | MediaType::Cts
+        | MediaType::Dts
+        | MediaType::Dmts
+        | MediaType::Dcts
+        | MediaType::Tsx => Some((ModuleType::JavaScript, true)),
+        MediaType::Json => Some((ModuleType::Json, false)),
+        _ => None,
+    }
+}
+
+impl CoreModuleLoader for TypescriptModuleLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        _kind: ResolutionKind,
+    ) -> Result<ModuleSpecifier, Error> {
+        Ok(resolve_import(specifier, referrer)?)
+    }
+
+    fn load(
+        &self,
+        module_specifier: &ModuleSpecifier,
+        _maybe_referrer: Option<&ModuleSpecifier>,
+        _is_dyn_import: bool,
+        _requested_module_type: RequestedModuleType,
+    ) -> ModuleLoadResponse {
+        let source_maps = self.source_maps.clone();
+        let sources = self.sources.clone();
+        let diagnostics = self.diagnostics.clone();
+        let custom_loader = self.custom_loader.clone();
+        let lock = self.lock.clone();
+        fn load(
+            source_maps: SourceMapStore,
+            sources: ModuleSources,
+            diagnostics: PendingDiagnostics,
+            custom_loader: Option<Arc<dyn ModuleLoader>>,
+            lock: Option<Arc<ModuleLock>>,
+            module_specifier: &ModuleSpecifier,
+        ) -> Result<ModuleSource, AnyError> {
+            let custom_source = match &custom_loader {
+                Some(loader) => loader.load_source(module_specifier)?,
+                None => None,
+            };
+
+            let (code, should_transpile, media_type, module_type) =
+                if let Some(code) = custom_source {
+                    let media_type = MediaType::from_specifier(module_specifier);
+                    let Some((module_type, should_transpile)) = module_type(media_type) else {
+                        bail!("Unknown media type of {}", module_specifier)
+                    };
+
+                    (code, should_transpile, media_type, module_type)
+                } else if module_specifier.scheme() == "file" {
+                    let path = module_specifier.to_file_path().map_err(|_| {
+                        anyhow!("There was an error converting the module specifier to a file path")
+                    })?;
+
+                    let media_type = MediaType::from_path(&path);
+                    let Some((module_type, should_transpile)) = module_type(media_type) else {
+                        bail!("Unknown extension {:?}", path.extension())
+                    };
+
+                    (
+                        std::fs::read_to_string(&path)?,
+                        should_transpile,
+                        media_type,
+                        module_type,
+                    )
+                } else if module_specifier.scheme() == "https" {
+                    let url = module_specifier.to_string();
+
+                    let response = ureq::get(&url).call()?.into_string()?;
+
+                    (
+                        response,
+                        false,
+                        MediaType::JavaScript,
+                        ModuleType::JavaScript,
+                    )
+                } else {
+                    log!(Error, "Unknown scheme {:?}", module_specifier.scheme());
+                    bail!("Unknown scheme {:?}", module_specifier.scheme())
+                };
+
+            let locked = lock
+                .as_ref()
+                .and_then(|lock| lock.get(module_specifier.as_str()));
+            if locked.is_some_and(|sha256| !sha256.eq_ignore_ascii_case(&sha256_hex(&code))) {
+                bail!(
+                    "{} doesn't match the hash in the lockfile",
+                    module_specifier
+                )
+            }
+
+            if let Some(diagnostic) = check_min_deno_version(module_specifier, &code) {
+                diagnostics.borrow_mut().push(diagnostic);
+            }
+
+            sources
+                .borrow_mut()
+                .insert(module_specifier.to_string(), code.clone());
+
+            let code = if should_transpile {
+                let (code, source_map) = transpile_cached(module_specifier, code, media_type)?;
+                source_maps
+                    .borrow_mut()
+                    .insert(module_specifier.to_string(), source_map);
+                code
+            } else {
+                code
+            };
+            Ok(ModuleSource::new(
+                module_type,
+                ModuleSourceCode::String(code.into()),
+                module_specifier,
+                None,
+            ))
+        }
+
+        ModuleLoadResponse::Sync(load(
+            source_maps,
+            sources,
+            diagnostics,
+            custom_loader,
+            lock,
+            module_specifier,
+        ))
+    }
+
+    fn get_source_map(&self, specifier: &str) -> Option<Vec<u8>> {
+        self.source_maps.borrow().get(specifier).cloned()
+    }
+}
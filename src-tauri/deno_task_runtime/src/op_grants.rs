@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+
+use deno_runtime::deno_core::error::{custom_error, AnyError};
+use deno_runtime::deno_core::OpState;
+
+use super::{RunOptions, TaskId, TaskRuntime};
+
+// Oldest entries are dropped past this, an op called in a loop would
+// otherwise grow the task without bounds
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// A host capability behind one or more custom ops.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OpGrant {
+    /// Setting the task's return value
+    TaskResult,
+    /// Reading the path of the user's documents directory
+    DocumentDir,
+    /// Writing checkpoints to disk
+    Checkpoint,
+    /// Recording and replaying fetch calls
+    NetworkCassette,
+    /// Reading and writing the app's key-value store, also confirmed by the
+    /// user on first use
+    Store,
+    /// Saving files the app's windows can display
+    Artifacts,
+    /// Sending custom events to the app's windows and listening to the app's,
+    /// also confirmed by the user on first use
+    Events,
+}
+
+impl OpGrant {
+    pub const ALL: &'static [OpGrant] = &[
+        OpGrant::TaskResult,
+        OpGrant::DocumentDir,
+        OpGrant::Checkpoint,
+        OpGrant::NetworkCassette,
+        OpGrant::Store,
+        OpGrant::Artifacts,
+        OpGrant::Events,
+    ];
+}
+
+// What each custom op needs, ops that aren't listed are plumbing scripts
+// can't misuse (console capture, run options)
+const OP_GRANTS: &[(&str, &[OpGrant])] = &[
+    ("return_value", &[OpGrant::TaskResult]),
+    ("document_dir", &[OpGrant::DocumentDir]),
+    ("save_checkpoint", &[OpGrant::Checkpoint]),
+    ("cassette_record", &[OpGrant::NetworkCassette]),
+    ("cassette_replay", &[OpGrant::NetworkCassette]),
+    ("bridge_store_get", &[OpGrant::Store]),
+    ("bridge_store_set", &[OpGrant::Store]),
+    ("save_artifact", &[OpGrant::Artifacts]),
+    ("emit_event", &[OpGrant::Events]),
+    ("listen_app_event", &[OpGrant::Events]),
+];
+
+/// A gated op call, kept on the task.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct OpAuditEntry {
+    op: String,
+    grants: Vec<OpGrant>,
+    allowed: bool,
+}
+
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+pub struct OpAuditLog {
+    entries: Vec<OpAuditEntry>,
+    /// Entries dropped to stay under the limit
+    dropped: usize,
+}
+
+impl OpAuditLog {
+    fn record(&mut self, entry: OpAuditEntry) {
+        if self.entries.len() == MAX_AUDIT_ENTRIES {
+            self.entries.remove(0);
+            self.dropped += 1;
+        }
+
+        self.entries.push(entry);
+    }
+}
+
+/// Grants of the task running on an `OpState`.
+pub struct GrantedOps(HashSet<OpGrant>);
+
+impl GrantedOps {
+    pub fn new(options: &RunOptions) -> Self {
+        Self(options.grants().iter().copied().collect())
+    }
+}
+
+/// Checks the grants `op` declares in `OP_GRANTS` and records the call in the
+/// task's audit log. Called first thing by every gated op.
+pub fn check(state: &OpState, op: &'static str) -> Result<(), AnyError> {
+    let required = OP_GRANTS
+        .iter()
+        .find(|(name, _)| *name == op)
+        .map(|(_, grants)| *grants)
+        .unwrap_or_default();
+
+    let granted = state.borrow::<GrantedOps>();
+    let missing: Vec<OpGrant> = required
+        .iter()
+        .filter(|grant| !granted.0.contains(grant))
+        .copied()
+        .collect();
+
+    let entry = OpAuditEntry {
+        op: op.to_string(),
+        grants: required.to_vec(),
+        allowed: missing.is_empty(),
+    };
+    let task_id = &state.borrow::<TaskId>().0;
+    state
+        .borrow::<TaskRuntime>()
+        .with_task(task_id, move |task| task.op_audit.record(entry));
+
+    if !missing.is_empty() {
+        return Err(custom_error(
+            "PermissionDenied",
+            format!("{} requires the {:?} grant(s)", op, missing),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+// Default amount of console output kept in memory for a single task, see
+// `max_task_output_bytes` in the runtime config
+pub const DEFAULT_MAX_TASK_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Captured console output of a task.
+///
+/// Output is kept up to a byte budget, everything past it is dropped and only
+/// counted, so a script printing gigabytes can't exhaust the host memory. The
+/// rendered output ends with a `…N bytes truncated…` marker when that happens.
+///
+/// Flattened into `Task` as `output` and `output_truncated_bytes`.
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+pub struct TaskOutput {
+    #[serde(rename = "output")]
+    text: String,
+    /// Bytes dropped past the budget, 0 unless the output was truncated
+    #[serde(rename = "output_truncated_bytes")]
+    truncated_bytes: usize,
+}
+
+impl TaskOutput {
+    /// The output kept, without the truncation marker.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Bytes of output dropped past the budget.
+    pub fn truncated_bytes(&self) -> usize {
+        self.truncated_bytes
+    }
+
+    /// Appends a chunk of output, returns the part that fit in the budget and
+    /// whether this chunk is the one that started the truncation.
+    pub fn push<'a>(&mut self, chunk: &'a str, limit: usize) -> (&'a str, bool) {
+        // what comes after dropped output would read as if it came before it
+        if self.truncated_bytes > 0 {
+            self.truncated_bytes += chunk.len();
+            return ("", false);
+        }
+
+        let available = limit.saturating_sub(self.text.len());
+
+        if chunk.len() <= available {
+            self.text.push_str(chunk);
+            return (chunk, false);
+        }
+
+        // cut on a char boundary so the kept text stays valid utf-8
+        let mut cut = available;
+        while !chunk.is_char_boundary(cut) {
+            cut -= 1;
+        }
+
+        let kept = &chunk[..cut];
+
+        self.text.push_str(kept);
+        self.truncated_bytes += chunk.len() - cut;
+
+        (kept, true)
+    }
+}
+
+impl fmt::Display for TaskOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)?;
+
+        if self.truncated_bytes > 0 {
+            write!(f, "\n…{} bytes truncated…\n", self.truncated_bytes)?;
+        }
+
+        Ok(())
+    }
+}
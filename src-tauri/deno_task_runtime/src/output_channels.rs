@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use tauri::ipc::Channel;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// What a task printed at once, usually a line with its `\n`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskOutputChunk {
+    stream: OutputStream,
+    text: String,
+}
+
+struct Subscriber {
+    channel: Channel<TaskOutputChunk>,
+    // dropped along with the window
+    window: Option<String>,
+}
+
+#[derive(Default)]
+struct TaskChannel {
+    // the output of the current run, within `max_task_output_bytes`
+    chunks: Vec<TaskOutputChunk>,
+    subscribers: Vec<Subscriber>,
+}
+
+/// Console output of the tasks, streamed to their subscribers as it's
+/// printed and kept so the ones subscribing late get it from the start.
+#[derive(Default)]
+pub struct OutputChannels {
+    by_task: HashMap<String, TaskChannel>,
+}
+
+impl fmt::Debug for OutputChannels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputChannels")
+            .field("tasks", &self.by_task.len())
+            .finish()
+    }
+}
+
+// Chunks are sent with the lock held, so the ones a subscriber catches up on
+// and the ones printed meanwhile arrive in order
+impl OutputChannels {
+    pub fn publish(&mut self, task_id: &str, stream: OutputStream, text: &str) {
+        let channel = self.by_task.entry(task_id.to_string()).or_default();
+        let chunk = TaskOutputChunk {
+            stream,
+            text: text.to_string(),
+        };
+
+        // the ones failing belong to webviews that went away
+        channel
+            .subscribers
+            .retain(|subscriber| subscriber.channel.send(chunk.clone()).is_ok());
+        channel.chunks.push(chunk);
+    }
+
+    pub fn subscribe(
+        &mut self,
+        task_id: &str,
+        channel: Channel<TaskOutputChunk>,
+        window: Option<&str>,
+    ) {
+        let task_channel = self.by_task.entry(task_id.to_string()).or_default();
+
+        for chunk in &task_channel.chunks {
+            if channel.send(chunk.clone()).is_err() {
+                return;
+            }
+        }
+
+        task_channel.subscribers.push(Subscriber {
+            channel,
+            window: window.map(|label| label.to_string()),
+        });
+    }
+
+    /// Drops the output of the previous run, the subscribers get the new one.
+    pub fn restart(&mut self, task_id: &str) {
+        if let Some(channel) = self.by_task.get_mut(task_id) {
+            channel.chunks.clear();
+        }
+    }
+
+    pub fn remove_window(&mut self, window: &str) {
+        for channel in self.by_task.values_mut() {
+            channel
+                .subscribers
+                .retain(|subscriber| subscriber.window.as_deref() != Some(window));
+        }
+    }
+
+    pub fn forget(&mut self, task_ids: &[String]) {
+        for task_id in task_ids {
+            self.by_task.remove(task_id);
+        }
+    }
+}
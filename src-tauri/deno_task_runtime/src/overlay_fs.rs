@@ -0,0 +1,655 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use deno_runtime::deno_fs::{
+    AccessCheckCb, FileSystem, FsDirEntry, FsFileType, OpenOptions, RealFs,
+};
+use deno_runtime::deno_io::fs::{File, FsError, FsResult, FsStat};
+
+use super::TaskRuntime;
+
+/// A change a dry run task would have made to the disk.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FsChange {
+    WriteFile {
+        path: PathBuf,
+        bytes: usize,
+        append: bool,
+    },
+    CreateDir {
+        path: PathBuf,
+    },
+    Remove {
+        path: PathBuf,
+        recursive: bool,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Truncate {
+        path: PathBuf,
+        len: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    File(Vec<u8>),
+    Dir,
+    Removed,
+}
+
+enum Lookup {
+    Entry(Entry),
+    Removed,
+    // not touched by the task, read from disk
+    Disk,
+}
+
+/// Copy-on-write overlay over the real file system used by dry runs.
+///
+/// Reads fall through to the disk until the task "writes" a path, after that
+/// the in-memory copy is served. Nothing is ever written to disk, the changes
+/// are recorded instead so they can be reviewed on the task.
+///
+/// Without a disk below it (`in_memory`) it's a file system of its own, used
+/// by the test harness. Clones share the same files.
+///
+/// Whole-file operations are supported (`Deno.writeFile`, `Deno.mkdir`,
+/// `Deno.remove`, `Deno.rename`, ...). Opening a file handle for writing,
+/// links and metadata changes are not, and fail with "not supported".
+#[derive(Debug, Clone)]
+pub struct OverlayFs {
+    // task of a dry run, its changes are recorded on it
+    recorder: Option<(TaskRuntime, String)>,
+    disk: Option<RealFs>,
+    entries: Arc<Mutex<HashMap<PathBuf, Entry>>>,
+}
+
+impl OverlayFs {
+    pub fn new(runtime: TaskRuntime, task_id: &str) -> Self {
+        Self {
+            recorder: Some((runtime, task_id.to_string())),
+            disk: Some(RealFs),
+            entries: Default::default(),
+        }
+    }
+
+    /// Starts with nothing but the root directory.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn in_memory() -> Self {
+        Self {
+            recorder: None,
+            disk: None,
+            entries: Default::default(),
+        }
+    }
+
+    /// Writes a file as if it was there before the task started, creating
+    /// its parent directories.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn seed_file(&self, path: &Path, data: &[u8]) {
+        let mut entries = self.entries.lock().unwrap();
+
+        for ancestor in path.ancestors().skip(1) {
+            entries.insert(ancestor.to_path_buf(), Entry::Dir);
+        }
+        entries.insert(path.to_path_buf(), Entry::File(data.to_vec()));
+    }
+
+    /// Contents of a file written to the overlay, files still only on disk
+    /// aren't returned.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn written_file(&self, path: &Path) -> Option<Vec<u8>> {
+        match self.entries.lock().unwrap().get(path) {
+            Some(Entry::File(data)) => Some(data.clone()),
+            _ => None,
+        }
+    }
+
+    // changes go straight to the task so they survive the task being stopped
+    fn record(&self, change: FsChange) {
+        if let Some((runtime, task_id)) = &self.recorder {
+            runtime.record_fs_change(task_id, change);
+        }
+    }
+
+    // only fails in memory, lookups never fall through to the disk there
+    fn disk(&self) -> FsResult<&RealFs> {
+        self.disk.as_ref().ok_or(FsError::NotSupported)
+    }
+
+    fn lookup(&self, path: &Path) -> Lookup {
+        let entries = self.entries.lock().unwrap();
+
+        if let Some(entry) = entries.get(path) {
+            return match entry {
+                Entry::Removed => Lookup::Removed,
+                entry => Lookup::Entry(entry.clone()),
+            };
+        }
+
+        // everything below a removed directory is gone too
+        let removed_ancestor = path
+            .ancestors()
+            .skip(1)
+            .any(|ancestor| matches!(entries.get(ancestor), Some(Entry::Removed)));
+
+        if removed_ancestor {
+            Lookup::Removed
+        } else if self.disk.is_some() {
+            Lookup::Disk
+        } else if path.parent().is_none() {
+            Lookup::Entry(Entry::Dir)
+        } else {
+            Lookup::Removed
+        }
+    }
+
+    fn read(&self, path: &Path) -> FsResult<Vec<u8>> {
+        match self.lookup(path) {
+            Lookup::Entry(Entry::File(data)) => Ok(data),
+            Lookup::Entry(_) => Err(is_a_directory()),
+            Lookup::Removed => Err(not_found()),
+            Lookup::Disk => Ok(self.disk()?.read_file_sync(path, None)?.into_owned()),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        match self.lookup(path) {
+            Lookup::Entry(_) => true,
+            Lookup::Removed => false,
+            Lookup::Disk => self
+                .disk
+                .as_ref()
+                .is_some_and(|disk| disk.exists_sync(path)),
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.lookup(path) {
+            Lookup::Entry(Entry::Dir) => true,
+            Lookup::Entry(_) | Lookup::Removed => false,
+            Lookup::Disk => self
+                .disk
+                .as_ref()
+                .is_some_and(|disk| disk.is_dir_sync(path)),
+        }
+    }
+
+    fn put(&self, path: &Path, entry: Entry) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), entry);
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl FileSystem for OverlayFs {
+    fn cwd(&self) -> FsResult<PathBuf> {
+        self.disk()?.cwd()
+    }
+
+    fn tmp_dir(&self) -> FsResult<PathBuf> {
+        self.disk()?.tmp_dir()
+    }
+
+    fn chdir(&self, _path: &Path) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+
+    fn umask(&self, mask: Option<u32>) -> FsResult<u32> {
+        match mask {
+            None => self.disk()?.umask(None),
+            Some(_) => Err(FsError::NotSupported),
+        }
+    }
+
+    fn open_sync(
+        &self,
+        path: &Path,
+        options: OpenOptions,
+        access_check: Option<AccessCheckCb>,
+    ) -> FsResult<Rc<dyn File>> {
+        let writes = options.write || options.append || options.create || options.truncate;
+
+        if writes || !matches!(self.lookup(path), Lookup::Disk) {
+            return Err(FsError::NotSupported);
+        }
+
+        self.disk()?.open_sync(path, options, access_check)
+    }
+    async fn open_async<'a>(
+        &'a self,
+        path: PathBuf,
+        options: OpenOptions,
+        access_check: Option<AccessCheckCb<'a>>,
+    ) -> FsResult<Rc<dyn File>> {
+        self.open_sync(&path, options, access_check)
+    }
+
+    fn mkdir_sync(&self, path: &Path, recursive: bool, _mode: Option<u32>) -> FsResult<()> {
+        if self.is_dir(path) {
+            return if recursive {
+                Ok(())
+            } else {
+                Err(already_exists())
+            };
+        }
+
+        if self.exists(path) {
+            return Err(already_exists());
+        }
+
+        if let Some(parent) = path.parent() {
+            if !self.is_dir(parent) {
+                if !recursive {
+                    return Err(not_found());
+                }
+                self.mkdir_sync(parent, true, None)?;
+            }
+        }
+
+        self.put(path, Entry::Dir);
+        self.record(FsChange::CreateDir {
+            path: path.to_path_buf(),
+        });
+
+        Ok(())
+    }
+    async fn mkdir_async(&self, path: PathBuf, recursive: bool, mode: Option<u32>) -> FsResult<()> {
+        self.mkdir_sync(&path, recursive, mode)
+    }
+
+    fn chmod_sync(&self, _path: &Path, _mode: u32) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn chmod_async(&self, path: PathBuf, mode: u32) -> FsResult<()> {
+        self.chmod_sync(&path, mode)
+    }
+
+    fn chown_sync(&self, _path: &Path, _uid: Option<u32>, _gid: Option<u32>) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn chown_async(&self, path: PathBuf, uid: Option<u32>, gid: Option<u32>) -> FsResult<()> {
+        self.chown_sync(&path, uid, gid)
+    }
+
+    fn lchown_sync(&self, _path: &Path, _uid: Option<u32>, _gid: Option<u32>) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn lchown_async(
+        &self,
+        path: PathBuf,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> FsResult<()> {
+        self.lchown_sync(&path, uid, gid)
+    }
+
+    fn remove_sync(&self, path: &Path, recursive: bool) -> FsResult<()> {
+        if !self.exists(path) {
+            return Err(not_found());
+        }
+
+        if !recursive && self.is_dir(path) && !self.read_dir_sync(path)?.is_empty() {
+            return Err(FsError::Io(Error::other("Directory not empty")));
+        }
+
+        {
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|entry_path, _| !entry_path.starts_with(path));
+            entries.insert(path.to_path_buf(), Entry::Removed);
+        }
+
+        self.record(FsChange::Remove {
+            path: path.to_path_buf(),
+            recursive,
+        });
+
+        Ok(())
+    }
+    async fn remove_async(&self, path: PathBuf, recursive: bool) -> FsResult<()> {
+        self.remove_sync(&path, recursive)
+    }
+
+    fn copy_file_sync(&self, oldpath: &Path, newpath: &Path) -> FsResult<()> {
+        let data = self.read(oldpath)?;
+        self.put(newpath, Entry::File(data));
+        self.record(FsChange::Copy {
+            from: oldpath.to_path_buf(),
+            to: newpath.to_path_buf(),
+        });
+
+        Ok(())
+    }
+    async fn copy_file_async(&self, oldpath: PathBuf, newpath: PathBuf) -> FsResult<()> {
+        self.copy_file_sync(&oldpath, &newpath)
+    }
+
+    fn cp_sync(&self, path: &Path, new_path: &Path) -> FsResult<()> {
+        if self.is_dir(path) {
+            return Err(FsError::NotSupported);
+        }
+
+        self.copy_file_sync(path, new_path)
+    }
+    async fn cp_async(&self, path: PathBuf, new_path: PathBuf) -> FsResult<()> {
+        self.cp_sync(&path, &new_path)
+    }
+
+    fn stat_sync(&self, path: &Path) -> FsResult<FsStat> {
+        match self.lookup(path) {
+            Lookup::Entry(Entry::File(data)) => Ok(overlay_stat(false, data.len() as u64)),
+            Lookup::Entry(_) => Ok(overlay_stat(true, 0)),
+            Lookup::Removed => Err(not_found()),
+            Lookup::Disk => self.disk()?.stat_sync(path),
+        }
+    }
+    async fn stat_async(&self, path: PathBuf) -> FsResult<FsStat> {
+        self.stat_sync(&path)
+    }
+
+    fn lstat_sync(&self, path: &Path) -> FsResult<FsStat> {
+        match self.lookup(path) {
+            Lookup::Disk => self.disk()?.lstat_sync(path),
+            _ => self.stat_sync(path),
+        }
+    }
+    async fn lstat_async(&self, path: PathBuf) -> FsResult<FsStat> {
+        self.lstat_sync(&path)
+    }
+
+    fn realpath_sync(&self, path: &Path) -> FsResult<PathBuf> {
+        match self.lookup(path) {
+            Lookup::Entry(_) => Ok(path.to_path_buf()),
+            Lookup::Removed => Err(not_found()),
+            Lookup::Disk => self.disk()?.realpath_sync(path),
+        }
+    }
+    async fn realpath_async(&self, path: PathBuf) -> FsResult<PathBuf> {
+        self.realpath_sync(&path)
+    }
+
+    fn read_dir_sync(&self, path: &Path) -> FsResult<Vec<FsDirEntry>> {
+        let mut dir_entries = match self.lookup(path) {
+            Lookup::Entry(Entry::Dir) => Vec::new(),
+            Lookup::Entry(_) => return Err(not_a_directory()),
+            Lookup::Removed => return Err(not_found()),
+            Lookup::Disk => self.disk()?.read_dir_sync(path)?,
+        };
+
+        let entries = self.entries.lock().unwrap();
+
+        // hide what was removed or replaced, the overlay entries are added below
+        dir_entries.retain(|dir_entry| !entries.contains_key(&path.join(&dir_entry.name)));
+
+        for (entry_path, entry) in entries.iter() {
+            if entry_path.parent() != Some(path) {
+                continue;
+            }
+
+            let Some(name) = entry_path.file_name() else {
+                continue;
+            };
+
+            let (is_file, is_directory) = match entry {
+                Entry::File(_) => (true, false),
+                Entry::Dir => (false, true),
+                Entry::Removed => continue,
+            };
+
+            dir_entries.push(FsDirEntry {
+                name: name.to_string_lossy().to_string(),
+                is_file,
+                is_directory,
+                is_symlink: false,
+            });
+        }
+
+        Ok(dir_entries)
+    }
+    async fn read_dir_async(&self, path: PathBuf) -> FsResult<Vec<FsDirEntry>> {
+        self.read_dir_sync(&path)
+    }
+
+    fn rename_sync(&self, oldpath: &Path, newpath: &Path) -> FsResult<()> {
+        if self.is_dir(oldpath) {
+            return Err(FsError::NotSupported);
+        }
+
+        let data = self.read(oldpath)?;
+        self.put(newpath, Entry::File(data));
+        self.put(oldpath, Entry::Removed);
+        self.record(FsChange::Rename {
+            from: oldpath.to_path_buf(),
+            to: newpath.to_path_buf(),
+        });
+
+        Ok(())
+    }
+    async fn rename_async(&self, oldpath: PathBuf, newpath: PathBuf) -> FsResult<()> {
+        self.rename_sync(&oldpath, &newpath)
+    }
+
+    fn link_sync(&self, _oldpath: &Path, _newpath: &Path) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn link_async(&self, oldpath: PathBuf, newpath: PathBuf) -> FsResult<()> {
+        self.link_sync(&oldpath, &newpath)
+    }
+
+    fn symlink_sync(
+        &self,
+        _oldpath: &Path,
+        _newpath: &Path,
+        _file_type: Option<FsFileType>,
+    ) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn symlink_async(
+        &self,
+        oldpath: PathBuf,
+        newpath: PathBuf,
+        file_type: Option<FsFileType>,
+    ) -> FsResult<()> {
+        self.symlink_sync(&oldpath, &newpath, file_type)
+    }
+
+    fn read_link_sync(&self, path: &Path) -> FsResult<PathBuf> {
+        match self.lookup(path) {
+            Lookup::Disk => self.disk()?.read_link_sync(path),
+            _ => Err(FsError::NotSupported),
+        }
+    }
+    async fn read_link_async(&self, path: PathBuf) -> FsResult<PathBuf> {
+        self.read_link_sync(&path)
+    }
+
+    fn truncate_sync(&self, path: &Path, len: u64) -> FsResult<()> {
+        let mut data = self.read(path)?;
+        data.resize(len as usize, 0);
+        self.put(path, Entry::File(data));
+        self.record(FsChange::Truncate {
+            path: path.to_path_buf(),
+            len,
+        });
+
+        Ok(())
+    }
+    async fn truncate_async(&self, path: PathBuf, len: u64) -> FsResult<()> {
+        self.truncate_sync(&path, len)
+    }
+
+    fn utime_sync(
+        &self,
+        _path: &Path,
+        _atime_secs: i64,
+        _atime_nanos: u32,
+        _mtime_secs: i64,
+        _mtime_nanos: u32,
+    ) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn utime_async(
+        &self,
+        path: PathBuf,
+        atime_secs: i64,
+        atime_nanos: u32,
+        mtime_secs: i64,
+        mtime_nanos: u32,
+    ) -> FsResult<()> {
+        self.utime_sync(&path, atime_secs, atime_nanos, mtime_secs, mtime_nanos)
+    }
+
+    fn lutime_sync(
+        &self,
+        _path: &Path,
+        _atime_secs: i64,
+        _atime_nanos: u32,
+        _mtime_secs: i64,
+        _mtime_nanos: u32,
+    ) -> FsResult<()> {
+        Err(FsError::NotSupported)
+    }
+    async fn lutime_async(
+        &self,
+        path: PathBuf,
+        atime_secs: i64,
+        atime_nanos: u32,
+        mtime_secs: i64,
+        mtime_nanos: u32,
+    ) -> FsResult<()> {
+        self.lutime_sync(&path, atime_secs, atime_nanos, mtime_secs, mtime_nanos)
+    }
+
+    fn write_file_sync(
+        &self,
+        path: &Path,
+        options: OpenOptions,
+        access_check: Option<AccessCheckCb>,
+        data: &[u8],
+    ) -> FsResult<()> {
+        // the permission check normally happens when the real file is opened
+        if let Some(access_check) = access_check {
+            (*access_check)(false, path, &options)?;
+        }
+
+        let exists = self.exists(path);
+
+        if options.create_new && exists {
+            return Err(already_exists());
+        }
+
+        if !exists && !options.create {
+            return Err(not_found());
+        }
+
+        if !path.parent().is_some_and(|parent| self.is_dir(parent)) {
+            return Err(not_found());
+        }
+
+        let contents = if options.append && exists {
+            let mut contents = self.read(path)?;
+            contents.extend_from_slice(data);
+            contents
+        } else {
+            data.to_vec()
+        };
+
+        self.put(path, Entry::File(contents));
+        self.record(FsChange::WriteFile {
+            path: path.to_path_buf(),
+            bytes: data.len(),
+            append: options.append,
+        });
+
+        Ok(())
+    }
+    async fn write_file_async<'a>(
+        &'a self,
+        path: PathBuf,
+        options: OpenOptions,
+        access_check: Option<AccessCheckCb<'a>>,
+        data: Vec<u8>,
+    ) -> FsResult<()> {
+        self.write_file_sync(&path, options, access_check, &data)
+    }
+
+    fn read_file_sync(
+        &self,
+        path: &Path,
+        access_check: Option<AccessCheckCb>,
+    ) -> FsResult<Cow<'static, [u8]>> {
+        match self.lookup(path) {
+            Lookup::Disk => self.disk()?.read_file_sync(path, access_check),
+            _ => {
+                if let Some(access_check) = access_check {
+                    (*access_check)(false, path, &OpenOptions::read())?;
+                }
+
+                Ok(Cow::Owned(self.read(path)?))
+            }
+        }
+    }
+    async fn read_file_async<'a>(
+        &'a self,
+        path: PathBuf,
+        access_check: Option<AccessCheckCb<'a>>,
+    ) -> FsResult<Cow<'static, [u8]>> {
+        self.read_file_sync(&path, access_check)
+    }
+}
+
+fn overlay_stat(is_directory: bool, size: u64) -> FsStat {
+    FsStat {
+        is_file: !is_directory,
+        is_directory,
+        is_symlink: false,
+        size,
+        mtime: None,
+        atime: None,
+        birthtime: None,
+        ctime: None,
+        dev: 0,
+        ino: 0,
+        mode: 0,
+        nlink: 0,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        blksize: 0,
+        blocks: 0,
+        is_block_device: false,
+        is_char_device: false,
+        is_fifo: false,
+        is_socket: false,
+    }
+}
+
+fn not_found() -> FsError {
+    FsError::Io(Error::new(ErrorKind::NotFound, "Not found"))
+}
+
+fn already_exists() -> FsError {
+    FsError::Io(Error::new(ErrorKind::AlreadyExists, "Already exists"))
+}
+
+fn is_a_directory() -> FsError {
+    FsError::Io(Error::new(ErrorKind::InvalidInput, "Is a directory"))
+}
+
+fn not_a_directory() -> FsError {
+    FsError::Io(Error::new(ErrorKind::InvalidInput, "Not a directory"))
+}
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use deno_runtime::deno_core::v8;
+
+/// Pauses the thread of a task: its event loop stops being polled, and a
+/// script busy running JS is held in an interrupt until it's resumed.
+#[derive(Debug, Default)]
+pub struct PauseSwitch {
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    // of the task's future while it's held back
+    waker: Mutex<Option<Waker>>,
+}
+
+impl PauseSwitch {
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap()
+    }
+
+    /// The event loop is paused right away, the JS code running meanwhile at
+    /// its next interrupt check when `isolate` is given.
+    pub fn pause(&self, isolate: Option<&v8::IsolateHandle>) {
+        *self.paused.lock().unwrap() = true;
+
+        if let Some(isolate) = isolate {
+            isolate.request_interrupt(hold_while_paused, std::ptr::null_mut());
+        }
+    }
+
+    /// Also how a paused task is let go to be stopped.
+    pub fn resume(&self) {
+        *self.paused.lock().unwrap() = false;
+        self.resumed.notify_all();
+
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    fn wait_while_paused(&self) {
+        let paused = self.paused.lock().unwrap();
+        let _resumed = self.resumed.wait_while(paused, |paused| *paused).unwrap();
+    }
+}
+
+thread_local! {
+    // Switch of the task running on this thread, for the interrupt
+    static TASK_SWITCH: RefCell<Option<Arc<PauseSwitch>>> = const { RefCell::new(None) };
+}
+
+/// Installs the switch of a task on the current thread, the one running its
+/// worker, until the guard is dropped.
+pub fn install(switch: Arc<PauseSwitch>) -> PauseSwitchGuard {
+    TASK_SWITCH.set(Some(switch));
+
+    PauseSwitchGuard
+}
+
+pub struct PauseSwitchGuard;
+
+impl Drop for PauseSwitchGuard {
+    fn drop(&mut self) {
+        TASK_SWITCH.take();
+    }
+}
+
+/// Blocks while the task running on the current thread is paused.
+pub fn wait_while_paused() {
+    let switch = TASK_SWITCH.with_borrow(Option::clone);
+    if let Some(switch) = switch {
+        switch.wait_while_paused();
+    }
+}
+
+extern "C" fn hold_while_paused(_isolate: &mut v8::Isolate, _data: *mut c_void) {
+    wait_while_paused();
+}
+
+/// `future`, not polled while `switch` is paused.
+pub struct Pausable<'a, F> {
+    switch: &'a PauseSwitch,
+    future: Pin<Box<F>>,
+}
+
+impl<'a, F: Future> Pausable<'a, F> {
+    pub fn new(switch: &'a PauseSwitch, future: F) -> Self {
+        Self {
+            switch,
+            future: Box::pin(future),
+        }
+    }
+}
+
+impl<F: Future> Future for Pausable<'_, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.switch.is_paused() {
+            *self.switch.waker.lock().unwrap() = Some(cx.waker().clone());
+            // resumed before the waker was in
+            if self.switch.is_paused() {
+                return Poll::Pending;
+            }
+        }
+
+        self.future.as_mut().poll(cx)
+    }
+}
@@ -0,0 +1,566 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Map, Value};
+
+use super::config::log;
+use super::task_ids::{resolve_task_id, validate_task_id};
+use super::{scripts, RunOptions, TaskEvent, TaskRuntime, TaskState};
+
+// Steps are followed through the task store, like `TaskHandle::wait` does
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Saved scripts to run one after the other, each one taking the return
+/// values of the steps it depends on as args. See `TaskRuntime::run_pipeline`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PipelineSpec {
+    /// Generated when unset
+    #[serde(default)]
+    id: Option<String>,
+    steps: Vec<PipelineStep>,
+    #[serde(default)]
+    on_error: ErrorPolicy,
+    /// Steps running at once, the ones ready first in the order they're
+    /// declared. Every ready step starts when unset
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    max_parallel: Option<usize>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PipelineStep {
+    /// Unique in the pipeline, the step's task is `<pipeline id>-<step id>`
+    id: String,
+    /// Name of the saved script
+    script: String,
+    /// Args of the script, an object when the step has dependencies. Their
+    /// return values are merged in first: the fields of the ones returning
+    /// an object, the others under the id of their step. The script also
+    /// finds them as they are in `RuntimeExtension.input`, by step id.
+    #[serde(default)]
+    #[ts(type = "unknown")]
+    args: Option<Value>,
+    /// Steps whose return values this one takes, only steps declared before
+    /// it. The previous step when unset, `[]` for none.
+    #[serde(default)]
+    depends_on: Option<Vec<String>>,
+    /// Overrides the `on_error` of the pipeline for this step
+    #[serde(default)]
+    on_error: Option<ErrorPolicy>,
+}
+
+/// What a failing step does to the rest of the pipeline.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// Stops the running steps and skips the ones left
+    #[default]
+    Abort,
+    /// Skips the steps depending on it, the others still run
+    ContinueOnError,
+}
+
+/// Where a pipeline is at, emitted as `pipeline-state-changed` on every
+/// change.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PipelineState {
+    pub(crate) id: String,
+    state: PipelineStatus,
+    steps: Vec<StepState>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStatus {
+    Running,
+    Completed,
+    /// Some steps failed and the others ran, see `ErrorPolicy::ContinueOnError`
+    CompletedWithErrors,
+    /// Aborted by a failing step
+    Failed,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct StepState {
+    id: String,
+    script: String,
+    task_id: String,
+    depends_on: Vec<String>,
+    state: StepStatus,
+    error: Option<String>,
+    /// Milliseconds since the Unix epoch
+    #[ts(type = "number | null")]
+    started_at_ms: Option<u64>,
+    #[ts(type = "number | null")]
+    finished_at_ms: Option<u64>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    /// Not run, a step it depends on failed or the pipeline was aborted
+    Skipped,
+}
+
+/// A pipeline as a graph of its steps, for rendering it while it runs. See
+/// `TaskRuntime::get_pipeline_state`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PipelineGraph {
+    id: String,
+    state: PipelineStatus,
+    /// In the order the steps are declared
+    nodes: Vec<PipelineNode>,
+    edges: Vec<PipelineEdge>,
+    /// Ids of the steps running or ready to start
+    frontier: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PipelineNode {
+    /// The step id
+    id: String,
+    script: String,
+    task_id: String,
+    state: StepStatus,
+    error: Option<String>,
+    /// How long the step ran, so far while it's running. Unset for steps that
+    /// haven't started.
+    #[ts(type = "number | null")]
+    duration_ms: Option<u64>,
+}
+
+/// `to` takes the return value of `from`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PipelineEdge {
+    from: String,
+    to: String,
+}
+
+impl PipelineState {
+    pub fn is_finished(&self) -> bool {
+        self.state != PipelineStatus::Running
+    }
+
+    pub fn graph(&self) -> PipelineGraph {
+        let now = now_ms();
+
+        let nodes = self
+            .steps
+            .iter()
+            .map(|step| PipelineNode {
+                id: step.id.clone(),
+                script: step.script.clone(),
+                task_id: step.task_id.clone(),
+                state: step.state,
+                error: step.error.clone(),
+                duration_ms: step
+                    .started_at_ms
+                    .map(|started| step.finished_at_ms.unwrap_or(now).saturating_sub(started)),
+            })
+            .collect();
+
+        let edges = self
+            .steps
+            .iter()
+            .flat_map(|step| {
+                step.depends_on.iter().map(|dep| PipelineEdge {
+                    from: dep.clone(),
+                    to: step.id.clone(),
+                })
+            })
+            .collect();
+
+        let frontier = self
+            .steps
+            .iter()
+            .filter(|step| match step.state {
+                StepStatus::Running => true,
+                StepStatus::Pending => step.depends_on.iter().all(|dep| {
+                    self.steps
+                        .iter()
+                        .any(|other| &other.id == dep && other.state == StepStatus::Completed)
+                }),
+                _ => false,
+            })
+            .map(|step| step.id.clone())
+            .collect();
+
+        PipelineGraph {
+            id: self.id.clone(),
+            state: self.state,
+            nodes,
+            edges,
+            frontier,
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Checks `spec` and starts running it on its own thread, returns the
+/// pipeline id.
+pub fn start(
+    runtime: &TaskRuntime,
+    spec: PipelineSpec,
+    options: RunOptions,
+) -> Result<String, String> {
+    let id = resolve_task_id(spec.id.as_deref(), None)?;
+    let state = initial_state(&id, &spec)?;
+
+    {
+        let mut pipelines = runtime.inner.pipelines.lock().unwrap();
+        if pipelines
+            .get(&id)
+            .is_some_and(|pipeline| !pipeline.is_finished())
+        {
+            return Err(format!("Pipeline {} is still running", id));
+        }
+        pipelines.insert(id.clone(), state.clone());
+    }
+    runtime.emit_task_event(TaskEvent::PipelineStateChanged(Box::new(state.clone())));
+
+    let runner = Runner {
+        runtime: runtime.clone(),
+        spec,
+        state,
+        return_values: Vec::new(),
+        options,
+    };
+    thread::Builder::new()
+        .name(format!("pipeline-{}", id))
+        .spawn(move || runner.run())
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+fn initial_state(id: &str, spec: &PipelineSpec) -> Result<PipelineState, String> {
+    if spec.steps.is_empty() {
+        return Err("Pipeline has no steps".to_string());
+    }
+    if spec.max_parallel == Some(0) {
+        return Err("max_parallel must be at least 1".to_string());
+    }
+
+    let mut declared = HashSet::new();
+    let mut steps = Vec::new();
+
+    for (i, step) in spec.steps.iter().enumerate() {
+        scripts::validate_name(&step.script)?;
+        let task_id = format!("{}-{}", id, step.id);
+        validate_task_id(&step.id)
+            .and_then(|_| validate_task_id(&task_id))
+            .map_err(|e| format!("Invalid step id {:?}: {}", step.id, e))?;
+
+        let depends_on = match &step.depends_on {
+            Some(depends_on) => depends_on.clone(),
+            None if i == 0 => Vec::new(),
+            None => vec![spec.steps[i - 1].id.clone()],
+        };
+        if let Some(unknown) = depends_on.iter().find(|dep| !declared.contains(*dep)) {
+            return Err(format!(
+                "Step {} depends on {}, which isn't a step declared before it",
+                step.id, unknown
+            ));
+        }
+        if !depends_on.is_empty() && step.args.as_ref().is_some_and(|args| !args.is_object()) {
+            return Err(format!("Args of step {} must be an object", step.id));
+        }
+
+        if !declared.insert(step.id.clone()) {
+            return Err(format!("Duplicate step id {}", step.id));
+        }
+        steps.push(StepState {
+            id: step.id.clone(),
+            script: step.script.clone(),
+            task_id,
+            depends_on,
+            state: StepStatus::Pending,
+            error: None,
+            started_at_ms: None,
+            finished_at_ms: None,
+        });
+    }
+
+    Ok(PipelineState {
+        id: id.to_string(),
+        state: PipelineStatus::Running,
+        steps,
+    })
+}
+
+struct Runner {
+    runtime: TaskRuntime,
+    spec: PipelineSpec,
+    state: PipelineState,
+    // by step id, once completed
+    return_values: Vec<(String, Value)>,
+    options: RunOptions,
+}
+
+impl Runner {
+    fn run(mut self) {
+        while !self.state.is_finished() {
+            let changed = self.start_ready_steps() | self.follow_running_steps();
+
+            if !changed {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            self.skip_unreachable_steps();
+            let done = !self.has(StepStatus::Running) && !self.has(StepStatus::Pending);
+            if done && !self.state.is_finished() {
+                self.state.state = if self.has(StepStatus::Failed) {
+                    PipelineStatus::CompletedWithErrors
+                } else {
+                    PipelineStatus::Completed
+                };
+            }
+            self.publish();
+        }
+
+        log!(
+            Info,
+            "Pipeline {} finished: {:?}",
+            self.state.id,
+            self.state.state
+        );
+    }
+
+    fn has(&self, status: StepStatus) -> bool {
+        self.state.steps.iter().any(|step| step.state == status)
+    }
+
+    fn start_ready_steps(&mut self) -> bool {
+        let mut changed = false;
+        let mut running = self
+            .state
+            .steps
+            .iter()
+            .filter(|step| step.state == StepStatus::Running)
+            .count();
+
+        for i in 0..self.state.steps.len() {
+            if self
+                .spec
+                .max_parallel
+                .is_some_and(|max_parallel| running >= max_parallel)
+            {
+                break;
+            }
+
+            let step = &self.state.steps[i];
+            let ready = step.state == StepStatus::Pending
+                && step
+                    .depends_on
+                    .iter()
+                    .all(|dep| self.return_values.iter().any(|(id, _)| id == dep));
+            if !ready {
+                continue;
+            }
+
+            let spec = &self.spec.steps[i];
+            let args = self.args(spec, &step.depends_on);
+            let mut options = self.options.clone();
+            options.set_input(self.input(&step.depends_on));
+            let result = self
+                .runtime
+                .run_saved_script(&step.task_id, &spec.script, args, options);
+
+            changed = true;
+            match result {
+                Ok(_) => {
+                    let step = &mut self.state.steps[i];
+                    step.state = StepStatus::Running;
+                    step.started_at_ms = Some(now_ms());
+                    running += 1;
+                }
+                Err(e) => self.fail(i, e),
+            }
+            if self.state.is_finished() {
+                break;
+            }
+        }
+
+        changed
+    }
+
+    fn args(&self, step: &PipelineStep, depends_on: &[String]) -> Option<Value> {
+        if depends_on.is_empty() {
+            return step.args.clone();
+        }
+
+        let mut args = Map::new();
+        for (id, value) in &self.return_values {
+            if !depends_on.contains(id) {
+                continue;
+            }
+
+            match value {
+                Value::Object(fields) => args.extend(fields.clone()),
+                value => {
+                    args.insert(id.clone(), value.clone());
+                }
+            }
+        }
+        if let Some(Value::Object(fields)) = &step.args {
+            args.extend(fields.clone());
+        }
+
+        Some(Value::Object(args))
+    }
+
+    // The return values of the dependencies by step id, unlike the args
+    // nothing is merged
+    fn input(&self, depends_on: &[String]) -> Value {
+        let input = self
+            .return_values
+            .iter()
+            .filter(|(id, _)| depends_on.contains(id))
+            .map(|(id, value)| (id.clone(), value.clone()))
+            .collect();
+
+        Value::Object(input)
+    }
+
+    fn follow_running_steps(&mut self) -> bool {
+        let mut changed = false;
+
+        for i in 0..self.state.steps.len() {
+            let step = &self.state.steps[i];
+            if step.state != StepStatus::Running {
+                continue;
+            }
+
+            let task = match self.runtime.get_task_state(&step.task_id) {
+                Some(task) if !task.is_finished() => continue,
+                Some(task) => task,
+                // cleared meanwhile
+                None => {
+                    self.fail(i, format!("Task not found: {}", step.task_id));
+                    return true;
+                }
+            };
+
+            changed = true;
+            if matches!(task.state(), TaskState::Completed) {
+                // a script that returned nothing passes `null` along
+                let value = task.return_value().cloned().unwrap_or(Value::Null);
+                self.return_values.push((step.id.clone(), value));
+                let step = &mut self.state.steps[i];
+                step.state = StepStatus::Completed;
+                step.finished_at_ms = Some(now_ms());
+            } else if matches!(task.state(), TaskState::Stopped) {
+                self.fail(i, "Stopped".to_string());
+            } else if let TaskState::TimedOut { timeout_ms } = task.state() {
+                self.fail(i, format!("Timed out after {}ms", timeout_ms));
+            } else {
+                self.fail(i, task.error().to_string());
+            }
+            if self.state.is_finished() {
+                break;
+            }
+        }
+
+        changed
+    }
+
+    fn fail(&mut self, i: usize, error: String) {
+        let step = &mut self.state.steps[i];
+        step.state = StepStatus::Failed;
+        step.error = Some(error);
+        step.finished_at_ms = Some(now_ms());
+
+        let policy = self.spec.steps[i].on_error.unwrap_or(self.spec.on_error);
+        if policy == ErrorPolicy::ContinueOnError {
+            return;
+        }
+
+        for step in &mut self.state.steps {
+            match step.state {
+                StepStatus::Pending => step.state = StepStatus::Skipped,
+                StepStatus::Running => {
+                    // reported as stopped by its task, the pipeline is done
+                    // with it either way
+                    if let Err(e) = self.runtime.stop_task(&step.task_id) {
+                        log!(Error, "Failed to stop step {}: {}", step.id, e);
+                    }
+                    step.state = StepStatus::Skipped;
+                    step.finished_at_ms = Some(now_ms());
+                }
+                _ => {}
+            }
+        }
+        self.state.state = PipelineStatus::Failed;
+    }
+
+    // Pending steps depending on a step that won't complete
+    fn skip_unreachable_steps(&mut self) {
+        // declared after their dependencies, one pass reaches the whole chain
+        for i in 0..self.state.steps.len() {
+            let (before, after) = self.state.steps.split_at_mut(i);
+            let step = &mut after[0];
+            let unreachable = step.state == StepStatus::Pending
+                && before.iter().any(|dep| {
+                    step.depends_on.contains(&dep.id)
+                        && matches!(dep.state, StepStatus::Failed | StepStatus::Skipped)
+                });
+            if unreachable {
+                step.state = StepStatus::Skipped;
+            }
+        }
+    }
+
+    fn publish(&self) {
+        self.runtime
+            .inner
+            .pipelines
+            .lock()
+            .unwrap()
+            .insert(self.state.id.clone(), self.state.clone());
+
+        self.runtime
+            .emit_task_event(TaskEvent::PipelineStateChanged(Box::new(
+                self.state.clone(),
+            )));
+    }
+}
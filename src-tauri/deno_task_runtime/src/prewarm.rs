@@ -0,0 +1,116 @@
+use std::fmt;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use deno_runtime::deno_core::ModuleSpecifier;
+use deno_runtime::worker::MainWorker;
+use tokio::sync::oneshot;
+
+use super::config::log;
+use super::isolate_checks::IsolateBaseline;
+use super::versions::PendingDiagnostics;
+use super::{threads, RunOptions, TaskRuntime};
+
+// Rebootstrapped this often while nothing runs, so the idle worker doesn't
+// keep e.g. an outdated timezone or root certificates around for hours
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A worker bootstrapped before its task is known, adopted by the next task
+/// started with options it was bootstrapped for.
+pub(crate) struct IdleWorker {
+    pub worker: MainWorker,
+    pub diagnostics: PendingDiagnostics,
+    /// `None` when it couldn't be captured, the worker isn't adopted then
+    pub baseline: Option<IsolateBaseline>,
+}
+
+impl IdleWorker {
+    /// Checks that nothing ran in the worker since it was bootstrapped, so a
+    /// task adopting it can't see what another left behind.
+    pub fn verify(&mut self) -> Result<(), String> {
+        match &self.baseline {
+            Some(baseline) => baseline.verify(&mut self.worker),
+            None => Err("its baseline couldn't be captured".to_string()),
+        }
+    }
+}
+
+/// Main module of an idle worker, replaced by the task's when it's adopted.
+pub(crate) fn placeholder_main_module() -> ModuleSpecifier {
+    ModuleSpecifier::parse("file:///prewarmed_worker.ts").unwrap()
+}
+
+/// What a task thread needs to run a task.
+pub(crate) struct TaskStart {
+    pub task_id: String,
+    pub code: String,
+    pub options: RunOptions,
+    pub stop_rx: oneshot::Receiver<()>,
+}
+
+/// A task thread with an idle worker, parked until it's given a task.
+///
+/// The thread ends without running anything once dropped. Until then the
+/// idle worker keeps its runtime alive, which is meant to live as long as the
+/// app anyway.
+pub(crate) struct PrewarmedThread {
+    thread: JoinHandle<Result<(), String>>,
+    start: oneshot::Sender<Box<TaskStart>>,
+}
+
+impl fmt::Debug for PrewarmedThread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrewarmedThread")
+            .field("finished", &self.thread.is_finished())
+            .finish()
+    }
+}
+
+impl PrewarmedThread {
+    pub fn spawn(runtime: TaskRuntime) -> Self {
+        let (start_tx, mut start_rx) = oneshot::channel::<Box<TaskStart>>();
+
+        let thread = thread::spawn(move || {
+            let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())?;
+
+            let adopted = tokio_runtime.block_on(async {
+                loop {
+                    let idle = runtime.bootstrap_idle_worker();
+
+                    tokio::select! {
+                        start = &mut start_rx => return start.ok().map(|start| (idle, start)),
+                        _ = tokio::time::sleep(REFRESH_INTERVAL) => {
+                            log!(Debug, "Refreshing the prewarmed worker");
+                        }
+                    }
+                }
+            });
+
+            if let Some((idle, start)) = adopted {
+                threads::configure_task_thread(start.options.low_priority);
+                runtime.run_on_thread(&tokio_runtime, *start, Some(idle));
+            }
+
+            Ok(())
+        });
+
+        Self {
+            thread,
+            start: start_tx,
+        }
+    }
+
+    /// Hands a task to the thread, returns the thread to join, or the task
+    /// back if the thread is gone.
+    pub fn start(
+        self,
+        start: Box<TaskStart>,
+    ) -> Result<JoinHandle<Result<(), String>>, Box<TaskStart>> {
+        self.start.send(start)?;
+
+        Ok(self.thread)
+    }
+}
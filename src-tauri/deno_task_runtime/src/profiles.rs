@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tauri::AppHandle;
+
+use super::config::{self, log};
+use super::event_tasks;
+use super::interrupted;
+use super::memory;
+use super::migrations::run_migrations;
+use super::schedules;
+use super::staging::code_dir;
+use super::{sweep_orphaned_code, TaskRuntime};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+
+type Runtimes = Mutex<HashMap<String, TaskRuntime>>;
+
+/// Named runtimes that share nothing but the process: each profile has its own
+/// tasks, permission prompts, events and data directory (cassettes,
+/// checkpoints, ...), so e.g. "work" and "personal" automations stay apart.
+///
+/// The default profile keeps its data in the app directory itself, the others
+/// in `profiles/<name>`. Profiles are the directories found there, so they
+/// survive restarts.
+///
+/// Nothing is started until the first call that needs a runtime, so apps
+/// whose users rarely run scripts don't pay for it on startup. If the
+/// persisted state can't be migrated none starts, and every call needing one
+/// fails with the reason.
+pub struct Profiles {
+    app_handle: AppHandle,
+    runtimes: OnceLock<Result<Arc<Runtimes>, String>>,
+    // for the runtimes of profiles created later
+    online: AtomicBool,
+}
+
+impl Profiles {
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self {
+            app_handle,
+            runtimes: OnceLock::new(),
+            online: AtomicBool::new(true),
+        }
+    }
+
+    /// Migrates the persisted state and starts the runtimes, for apps that
+    /// would rather do it ahead of their first task. Does nothing the second
+    /// time.
+    pub fn init(&self) -> Result<(), String> {
+        self.runtimes().map(|_| ())
+    }
+
+    /// Starts the runtimes right away when the last session left schedules
+    /// behind or quit while tasks were running, so they don't wait for the
+    /// first command. For the app's startup.
+    pub fn restore(&self) {
+        let pending = data_dirs()
+            .iter()
+            .any(|dir| schedules::has_schedules(dir) || interrupted::has_running_tasks(dir));
+
+        if pending {
+            if let Err(e) = self.init() {
+                log!(Error, "{}", e);
+            }
+        }
+    }
+
+    fn runtimes(&self) -> Result<&Runtimes, String> {
+        let runtimes = self.runtimes.get_or_init(|| {
+            // before any task runs, nothing runs on state left half migrated
+            let version =
+                run_migrations().map_err(|e| format!("Can't start the runtimes: {}", e))?;
+            log!(Info, "Persisted state is at version {}", version);
+
+            let removed = sweep_orphaned_code();
+            log!(Info, "Removed {} orphaned code files", removed);
+
+            let mut runtimes = HashMap::new();
+            runtimes.insert(DEFAULT_PROFILE.to_string(), self.start(DEFAULT_PROFILE));
+
+            if let Ok(entries) = std::fs::read_dir(code_dir().join(PROFILES_DIR)) {
+                for entry in entries.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if entry.path().is_dir() && validate_name(&name).is_ok() {
+                        let runtime = self.start(&name);
+                        runtimes.insert(name, runtime);
+                    }
+                }
+            }
+
+            let runtimes = Arc::new(Mutex::new(runtimes));
+            memory::spawn_monitor(Arc::downgrade(&runtimes));
+            Ok(runtimes)
+        });
+
+        runtimes.as_deref().map_err(Clone::clone)
+    }
+
+    /// Runtime of a profile, `None` is the default profile.
+    pub fn get(&self, profile: Option<&str>) -> Result<TaskRuntime, String> {
+        let profile = profile.unwrap_or(DEFAULT_PROFILE);
+
+        self.runtimes()?
+            .lock()
+            .unwrap()
+            .get(profile)
+            .cloned()
+            .ok_or_else(|| format!("Profile not found: {}", profile))
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, String> {
+        let mut names: Vec<String> = self.runtimes()?.lock().unwrap().keys().cloned().collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    pub fn release_window_tasks(&self, window: &str) {
+        // no runtime started, no task either
+        let Some(Ok(runtimes)) = self.runtimes.get() else {
+            return;
+        };
+
+        for runtime in runtimes.lock().unwrap().values() {
+            runtime.release_window_tasks(window);
+        }
+    }
+
+    /// See `TaskRuntime::set_online`, applies to every profile.
+    pub fn set_online(&self, online: bool) {
+        self.online.store(online, Ordering::SeqCst);
+
+        let Ok(runtimes) = self.runtimes() else {
+            return;
+        };
+        let runtimes: Vec<TaskRuntime> = runtimes.lock().unwrap().values().cloned().collect();
+        for runtime in runtimes {
+            runtime.set_online(online);
+        }
+    }
+
+    pub fn create(&self, name: &str) -> Result<(), String> {
+        validate_name(name)?;
+
+        if self.runtimes()?.lock().unwrap().contains_key(name) {
+            return Err(format!("Profile already exists: {}", name));
+        }
+
+        std::fs::create_dir_all(data_dir(name)).map_err(|e| e.to_string())?;
+        let runtime = self.start(name);
+        self.runtimes()?
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), runtime);
+
+        Ok(())
+    }
+
+    /// Runtime of a run context, which is the profile of the same name with
+    /// its own permission prompts and data directory. Unlike `get`, the
+    /// profile is created the first time it's used.
+    pub fn context(&self, name: &str) -> Result<TaskRuntime, String> {
+        validate_name(name)?;
+
+        // held while starting, so a context used twice at once starts once
+        let mut runtimes = self.runtimes()?.lock().unwrap();
+        if let Some(runtime) = runtimes.get(name) {
+            return Ok(runtime.clone());
+        }
+
+        std::fs::create_dir_all(data_dir(name)).map_err(|e| e.to_string())?;
+        let runtime = self.start(name);
+        runtimes.insert(name.to_string(), runtime.clone());
+        log!(Info, "Created profile {} for its first run", name);
+
+        Ok(runtime)
+    }
+
+    /// Deletes a profile and all its data, its tasks must not be running.
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        if name == DEFAULT_PROFILE {
+            return Err("The default profile can't be deleted".to_string());
+        }
+
+        let mut runtimes = self.runtimes()?.lock().unwrap();
+
+        let Some(runtime) = runtimes.get(name) else {
+            return Err(format!("Profile not found: {}", name));
+        };
+
+        if runtime.has_running_tasks() {
+            return Err(format!("Profile {} has running tasks", name));
+        }
+
+        runtimes.remove(name);
+        drop(runtimes);
+
+        match std::fs::remove_dir_all(data_dir(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn start(&self, name: &str) -> TaskRuntime {
+        let runtime = TaskRuntime::new(name, data_dir(name));
+        runtime.init_listener(self.app_handle.clone());
+        runtime.set_online(self.online.load(Ordering::SeqCst));
+        schedules::spawn_scheduler(&runtime);
+        event_tasks::listen(&runtime);
+
+        let interrupted = runtime.restore_interrupted_tasks();
+        if !interrupted.is_empty() {
+            log!(
+                Info,
+                "Profile {} restored {} interrupted tasks",
+                name,
+                interrupted.len()
+            );
+        }
+
+        if name == DEFAULT_PROFILE && config::prewarm_worker() {
+            runtime.prewarm_worker();
+        }
+
+        runtime
+    }
+}
+
+fn data_dir(profile: &str) -> PathBuf {
+    if profile == DEFAULT_PROFILE {
+        code_dir()
+    } else {
+        code_dir().join(PROFILES_DIR).join(profile)
+    }
+}
+
+/// Whether `dir` is the data directory of a profile.
+pub fn is_data_dir(dir: &Path) -> bool {
+    dir == code_dir() || dir.parent() == Some(&code_dir().join(PROFILES_DIR))
+}
+
+/// Every directory holding staged code, for the sweep at startup.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![code_dir()];
+
+    if let Ok(entries) = std::fs::read_dir(code_dir().join(PROFILES_DIR)) {
+        dirs.extend(entries.flatten().map(|entry| entry.path()));
+    }
+
+    dirs
+}
+
+// Names end up in paths, keep them to a safe charset
+fn validate_name(name: &str) -> Result<(), String> {
+    let is_valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !is_valid {
+        return Err(format!("Invalid profile name: {:?}", name));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use super::now_ms;
+
+/// What a task last reported with `RuntimeExtension.reportProgress`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskProgress {
+    /// From 0 to 100
+    percent: f64,
+    /// `None` when the task reported a percentage only
+    message: Option<String>,
+    #[ts(type = "number")]
+    reported_at_ms: u64,
+}
+
+impl TaskProgress {
+    pub fn new(percent: f64, message: String) -> Result<Self, String> {
+        // NaN isn't in the range either
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!(
+                "Progress must be a percentage from 0 to 100, got {}",
+                percent
+            ));
+        }
+
+        Ok(Self {
+            percent,
+            message: (!message.is_empty()).then_some(message),
+            reported_at_ms: now_ms(),
+        })
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.percent
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
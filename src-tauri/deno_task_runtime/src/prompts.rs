@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::sync::Once;
+use std::thread;
+
+use crossbeam_channel::{unbounded, Receiver};
+use deno_runtime::deno_core::error::JsStackFrame;
+use deno_runtime::deno_permissions::{set_prompter, PermissionPrompter, PromptResponse};
+
+use super::config::log;
+use super::{PermissionPrompt, PermissionsResponse, TaskRuntime};
+
+/// Prompter of one task, asks the permission broker or the user on its
+/// behalf and waits for the answer sent with
+/// `TaskRuntime::respond_to_permission_prompt`.
+struct TaskPrompter {
+    runtime: TaskRuntime,
+    task_id: String,
+    responses: Receiver<PermissionsResponse>,
+}
+
+thread_local! {
+    // Prompter of the task whose worker runs on this thread, deno checks
+    // permissions on the thread of the op that needs them
+    static TASK_PROMPTER: RefCell<Option<TaskPrompter>> = const { RefCell::new(None) };
+}
+
+/// Installs the prompter of a task on the current thread, the one running
+/// its worker, until the guard is dropped. Also when the task is stopped and
+/// the future running it is dropped.
+pub(crate) fn install(runtime: &TaskRuntime, task_id: &str) -> TaskPrompterGuard {
+    static ROUTER_SET: Once = Once::new();
+    ROUTER_SET.call_once(|| set_prompter(Box::new(PromptRouter)));
+
+    let (tx, rx) = unbounded();
+    runtime
+        .inner
+        .permission_channels
+        .lock()
+        .unwrap()
+        .insert(task_id.to_string(), tx);
+
+    TASK_PROMPTER.set(Some(TaskPrompter {
+        runtime: runtime.clone(),
+        task_id: task_id.to_string(),
+        responses: rx,
+    }));
+
+    TaskPrompterGuard
+}
+
+pub(crate) struct TaskPrompterGuard;
+
+impl Drop for TaskPrompterGuard {
+    fn drop(&mut self) {
+        if let Some(prompter) = TASK_PROMPTER.take() {
+            prompter
+                .runtime
+                .inner
+                .permission_channels
+                .lock()
+                .unwrap()
+                .remove(&prompter.task_id);
+        }
+    }
+}
+
+/// deno_permissions takes a single prompter for the whole process, this one
+/// hands each prompt to the prompter of the task that raised it.
+///
+/// deno holds a process-wide lock while the prompter runs, so the prompts
+/// other tasks raise meanwhile wait until the pending one is answered, or
+/// its task is stopped.
+struct PromptRouter;
+
+impl PermissionPrompter for PromptRouter {
+    fn prompt(
+        &mut self,
+        message: &str,
+        name: &str,
+        api_name: Option<&str>,
+        is_unary: bool,
+        _: Option<Vec<JsStackFrame>>, // stack frames
+    ) -> PromptResponse {
+        let prompt = PermissionPrompt::new(message.to_string(), name, api_name, is_unary);
+
+        prompt_current_task(prompt)
+    }
+}
+
+/// Asks the user on behalf of the task running on this thread.
+pub(crate) fn prompt_current_task(prompt: PermissionPrompt) -> PromptResponse {
+    log!(Info, "Prompting for permission: {:?}", prompt);
+
+    TASK_PROMPTER.with_borrow(|prompter| match prompter {
+        Some(prompter) => prompter
+            .runtime
+            .prompt(&prompter.task_id, prompt, &prompter.responses),
+        None => {
+            log!(
+                Error,
+                "No task found for thread {:?}",
+                thread::current().id()
+            );
+            PromptResponse::Deny
+        }
+    })
+}
@@ -0,0 +1,53 @@
+use deno_runtime::deno_core::url::Url;
+use deno_runtime::deno_fetch;
+use deno_runtime::deno_tls::{BasicAuth, Proxy};
+use deno_runtime::worker::MainWorker;
+
+const SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Proxy the `fetch` calls of a single task go through, in place of the
+/// app-wide one (the `*_PROXY` environment variables).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ProxyOptions {
+    /// e.g. `http://proxy.local:8080` or `socks5://127.0.0.1:1080`
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl ProxyOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        let url = Url::parse(&self.url).map_err(|e| format!("Invalid proxy url: {}", e))?;
+        if !SCHEMES.contains(&url.scheme()) {
+            return Err(format!(
+                "Unsupported proxy scheme {:?}, expected one of {}",
+                url.scheme(),
+                SCHEMES.join(", ")
+            ));
+        }
+        if self.password.is_some() && self.username.is_none() {
+            return Err("A proxy password needs a username".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Points the `fetch` client of `worker` at `proxy`. The client is created on
+/// the first `fetch`, so this must run before the task does.
+pub fn apply_to_worker(worker: &mut MainWorker, proxy: &ProxyOptions) {
+    let op_state = worker.js_runtime.op_state();
+    let mut state = op_state.borrow_mut();
+
+    let mut options = state.borrow::<deno_fetch::Options>().clone();
+    options.proxy = Some(Proxy {
+        url: proxy.url.clone(),
+        basic_auth: proxy.username.clone().map(|username| BasicAuth {
+            username,
+            password: proxy.password.clone().unwrap_or_default(),
+        }),
+    });
+    state.put(options);
+    // in case an idle worker created it with the app-wide settings
+    state.try_take::<deno_fetch::Client>();
+}
@@ -0,0 +1,158 @@
+use serde_json::Value;
+
+/// A value in a task as the inspector shows it, see
+/// `TaskRuntime::evaluate_in_task`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct RemoteObject {
+    /// `typeof` the value, e.g. `object` or `number`
+    kind: String,
+    /// Kind of object, e.g. `array`, `map`, `promise`, `error` or `null`
+    subtype: Option<String>,
+    /// Constructor name of objects, e.g. `Map`
+    class_name: Option<String>,
+    /// Primitives only, `undefined` and non-finite numbers have their
+    /// `description` instead
+    #[ts(type = "unknown")]
+    value: Option<Value>,
+    /// How the console would print it, e.g. `Map(2)` or `Error: boom\n at ...`
+    description: Option<String>,
+    /// First properties of objects, `None` for primitives and functions
+    preview: Option<ObjectPreview>,
+}
+
+impl RemoteObject {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn subtype(&self) -> Option<&str> {
+        self.subtype.as_deref()
+    }
+
+    pub fn class_name(&self) -> Option<&str> {
+        self.class_name.as_deref()
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn preview(&self) -> Option<&ObjectPreview> {
+        self.preview.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct ObjectPreview {
+    /// Whether there were more properties or entries than shown
+    overflow: bool,
+    properties: Vec<PropertyPreview>,
+    /// Of maps and sets
+    entries: Vec<EntryPreview>,
+}
+
+impl ObjectPreview {
+    pub fn overflow(&self) -> bool {
+        self.overflow
+    }
+
+    pub fn properties(&self) -> &[PropertyPreview] {
+        &self.properties
+    }
+
+    pub fn entries(&self) -> &[EntryPreview] {
+        &self.entries
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PropertyPreview {
+    name: String,
+    kind: String,
+    subtype: Option<String>,
+    /// Abbreviated, e.g. `"abc"`, `42` or `Object` for a nested object
+    value: String,
+}
+
+impl PropertyPreview {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn subtype(&self) -> Option<&str> {
+        self.subtype.as_deref()
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct EntryPreview {
+    /// `None` for sets
+    key: Option<String>,
+    value: String,
+}
+
+impl EntryPreview {
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+fn string(value: &Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+/// From a `Runtime.RemoteObject` of the DevTools protocol, evaluated with
+/// `generatePreview`.
+pub fn from_protocol(object: &Value) -> RemoteObject {
+    RemoteObject {
+        kind: string(object, "type").unwrap_or_default(),
+        subtype: string(object, "subtype"),
+        class_name: string(object, "className"),
+        value: object.get("value").cloned(),
+        description: string(object, "description"),
+        preview: object.get("preview").map(preview),
+    }
+}
+
+fn preview(preview: &Value) -> ObjectPreview {
+    let list = |key: &str| preview[key].as_array().cloned().unwrap_or_default();
+
+    ObjectPreview {
+        overflow: preview["overflow"].as_bool().unwrap_or(false),
+        properties: list("properties")
+            .iter()
+            .map(|property| PropertyPreview {
+                name: string(property, "name").unwrap_or_default(),
+                kind: string(property, "type").unwrap_or_default(),
+                subtype: string(property, "subtype"),
+                // nested objects only have a preview of their own
+                value: string(property, "value")
+                    .or_else(|| string(&property["valuePreview"], "description"))
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        entries: list("entries")
+            .iter()
+            .map(|entry| EntryPreview {
+                key: string(&entry["key"], "description"),
+                value: string(&entry["value"], "description").unwrap_or_default(),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+use super::config::log;
+use super::Task;
+
+/// A follow-up action on the result of a saved script, e.g. indexing it or
+/// sending a notification, so it doesn't need a task of its own.
+///
+/// Registered by the app with `register_result_hook` and enabled per script
+/// with `TaskRuntime::set_script_hooks`. Runs on the task's thread once the
+/// task has completed, errors are logged.
+pub trait ResultHook: Send + Sync {
+    fn run(&self, script: &str, task: &Task) -> Result<(), String>;
+}
+
+impl fmt::Debug for dyn ResultHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResultHook")
+    }
+}
+
+// Shared by the runtimes of every profile
+static HOOKS: Lazy<Mutex<BTreeMap<String, Arc<dyn ResultHook>>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Registers `hook` under `name`, replacing the one registered under the same
+/// name.
+pub fn register_result_hook(name: &str, hook: impl ResultHook + 'static) {
+    HOOKS
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), Arc::new(hook));
+}
+
+/// Names of the registered hooks, sorted.
+pub fn list_result_hooks() -> Vec<String> {
+    HOOKS.lock().unwrap().keys().cloned().collect()
+}
+
+pub fn is_registered(name: &str) -> bool {
+    HOOKS.lock().unwrap().contains_key(name)
+}
+
+/// Runs the hooks named in `names` in order, a failing one doesn't stop the
+/// next ones.
+pub fn run(names: &[String], script: &str, task: &Task) {
+    for name in names {
+        // not held while the hook runs, it may take a while
+        let hook = HOOKS.lock().unwrap().get(name).cloned();
+        let Some(hook) = hook else {
+            log!(
+                Error,
+                "Result hook {} of script {} isn't registered",
+                name,
+                script
+            );
+            continue;
+        };
+
+        if let Err(e) = hook.run(script, task) {
+            log!(
+                Error,
+                "Result hook {} failed on task {}: {}",
+                name,
+                task.id(),
+                e
+            );
+        }
+    }
+}
+
+/// Appends a JSON line with the script, task id and return value of each
+/// result to a report file.
+#[derive(Debug, Clone)]
+pub struct AppendToReport {
+    path: PathBuf,
+}
+
+impl AppendToReport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ResultHook for AppendToReport {
+    fn run(&self, script: &str, task: &Task) -> Result<(), String> {
+        let line = serde_json::json!({
+            "script": script,
+            "task_id": task.id(),
+            "return_value": task.return_value(),
+        });
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| e.to_string())?;
+
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use tauri::http::{header, HeaderValue, Request, Response, StatusCode};
+
+use super::task_ids::validate_task_id;
+use super::Profiles;
+
+/// Scheme to register `task_result_response` under.
+pub const TASK_RESULT_SCHEME: &str = "task-result";
+
+const OUTPUT_PART: &str = "output";
+
+/// Serves the return value of a task, or its console output, as raw bytes so
+/// big results don't go through JSON events. The frontend gets the URL with
+/// `convertFileSrc("<task_id>", "task-result")`, which differs per platform:
+///
+/// - `<task_id>`: the return value, as JSON
+/// - `<task_id>/output`: the console output, as text
+///
+/// Add `?profile=<name>` for tasks of another profile than the default one.
+/// A single byte range can be requested with the `Range` header.
+pub fn task_result_response(profiles: &Profiles, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let path = request.uri().path().trim_matches('/');
+    let (task_id, output) = match path.split_once('/') {
+        Some((task_id, OUTPUT_PART)) => (task_id, true),
+        Some(_) => return error(StatusCode::NOT_FOUND, format!("No such result: {}", path)),
+        None => (path, false),
+    };
+
+    if let Err(e) = validate_task_id(task_id) {
+        return error(StatusCode::BAD_REQUEST, e);
+    }
+
+    let runtime = match profiles.get(profile_param(request)) {
+        Ok(runtime) => runtime,
+        Err(e) => return error(StatusCode::NOT_FOUND, e),
+    };
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .map(|range| range.to_str().unwrap_or_default().to_string());
+
+    let content_type = if output {
+        "text/plain; charset=utf-8"
+    } else {
+        "application/json"
+    };
+
+    // only the requested bytes are copied out of the task
+    let response = runtime.with_task_result(task_id, output, move |bytes| {
+        let Some(range) = range else {
+            return respond(StatusCode::OK, content_type)
+                .header(header::CONTENT_LENGTH, bytes.len())
+                .body(bytes.to_vec());
+        };
+
+        match parse_range(&range, bytes.len()) {
+            Some(range) => respond(StatusCode::PARTIAL_CONTENT, content_type)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", range.start, range.end - 1, bytes.len()),
+                )
+                .header(header::CONTENT_LENGTH, range.len())
+                .body(bytes[range].to_vec()),
+            None => respond(StatusCode::RANGE_NOT_SATISFIABLE, content_type)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", bytes.len()))
+                .body(Vec::new()),
+        }
+    });
+
+    match response {
+        Some(Ok(response)) => response,
+        Some(Err(e)) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        None => error(
+            StatusCode::NOT_FOUND,
+            format!("Task not found: {}", task_id),
+        ),
+    }
+}
+
+// `?profile=<name>`, the default profile if unset
+pub(super) fn profile_param(request: &Request<Vec<u8>>) -> Option<&str> {
+    request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|param| param.strip_prefix("profile="))
+    })
+}
+
+pub(super) fn respond(status: StatusCode, content_type: &str) -> tauri::http::response::Builder {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+        // fetched from the app's own origin, which differs from the scheme's
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "Content-Range")
+}
+
+pub(super) fn error(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    let mut response = Response::new(message.into_bytes());
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    response
+}
+
+// `bytes=start-end`, `bytes=start-` or `bytes=-suffix_length`, end included.
+// `None` for anything else, several ranges included.
+pub(super) fn parse_range(range: &str, len: usize) -> Option<Range<usize>> {
+    let (start, end) = range.strip_prefix("bytes=")?.trim().split_once('-')?;
+
+    let range = match (start, end) {
+        ("", suffix) => {
+            let suffix: usize = suffix.parse().ok()?;
+            len.saturating_sub(suffix)..len
+        }
+        (start, "") => start.parse().ok()?..len,
+        (start, end) => {
+            let end: usize = end.parse().ok()?;
+            start.parse().ok()?..end.saturating_add(1).min(len)
+        }
+    };
+
+    (range.start < range.end).then_some(range)
+}
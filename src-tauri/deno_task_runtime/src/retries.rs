@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+/// How a failed task is run again, see `RunOptions::retry`. The task reports
+/// `error` once the last attempt failed, `retrying` in between.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Runs in total, the first one included
+    max_attempts: u32,
+    /// Wait before the second attempt
+    #[ts(type = "number")]
+    backoff_ms: u64,
+    /// What the wait is multiplied by after each attempt
+    backoff_multiplier: f64,
+    /// Longest wait between attempts, whatever the multiplier
+    #[ts(type = "number | null")]
+    max_backoff_ms: Option<u64>,
+    /// Only errors whose message contains one of these are retried, e.g.
+    /// `"ECONNRESET"`. Any error is when unset
+    retry_on: Option<Vec<String>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff_ms: 1000,
+            backoff_multiplier: 2.0,
+            max_backoff_ms: None,
+            retry_on: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        if !(self.backoff_multiplier >= 1.0 && self.backoff_multiplier.is_finite()) {
+            return Err("backoff_multiplier must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Wait before the attempt after `attempt`, `None` when the error it
+    /// failed with is the last one.
+    pub fn backoff(&self, attempt: u32, error: &str) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if let Some(retry_on) = &self.retry_on {
+            if !retry_on
+                .iter()
+                .any(|pattern| error.contains(pattern.as_str()))
+            {
+                return None;
+            }
+        }
+
+        let backoff_ms = self.backoff_ms as f64 * self.backoff_multiplier.powi(attempt as i32 - 1);
+        let backoff_ms = match self.max_backoff_ms {
+            Some(max_backoff_ms) => backoff_ms.min(max_backoff_ms as f64),
+            None => backoff_ms,
+        };
+
+        // saturates on overflow
+        Some(Duration::from_millis(backoff_ms as u64))
+    }
+}
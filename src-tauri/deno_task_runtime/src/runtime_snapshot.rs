@@ -0,0 +1,92 @@
+use super::config::{self, AppliedConfig};
+use super::lanes::TaskQueue;
+use super::schedules::Schedule;
+use super::{PermissionPrompt, Task, TaskRuntime, TaskState};
+
+/// Everything a window shows of a runtime, taken in one call, see
+/// `TaskRuntime::get_runtime_snapshot`.
+#[derive(Debug, Clone, serde::Serialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct RuntimeSnapshot {
+    /// Seq of the last event the snapshot reflects, pass it to
+    /// `sync_task_events` to catch up on the ones emitted since
+    #[ts(type = "number")]
+    seq: u64,
+    /// Ordered by id
+    tasks: Vec<Task>,
+    /// Permission prompts waiting for an answer
+    pending_prompts: Vec<PendingPrompt>,
+    queue: TaskQueue,
+    schedules: Vec<Schedule>,
+    config: AppliedConfig,
+}
+
+impl RuntimeSnapshot {
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    pub fn pending_prompts(&self) -> &[PendingPrompt] {
+        &self.pending_prompts
+    }
+
+    pub fn queue(&self) -> &TaskQueue {
+        &self.queue
+    }
+
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct PendingPrompt {
+    task_id: String,
+    prompt: PermissionPrompt,
+}
+
+impl PendingPrompt {
+    pub fn task_id(&self) -> &str {
+        &self.task_id
+    }
+
+    pub fn prompt(&self) -> &PermissionPrompt {
+        &self.prompt
+    }
+}
+
+pub fn take(runtime: &TaskRuntime) -> RuntimeSnapshot {
+    // read before the tasks: a task is updated before its event is sequenced,
+    // so the events up to it are all reflected, the ones after are replayed
+    // on top, which is harmless
+    let seq = runtime.inner.event_log.lock().unwrap().last_seq();
+
+    let mut tasks: Vec<Task> = runtime
+        .inner
+        .tasks
+        .query(|tasks| tasks.values().cloned().collect());
+    tasks.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let pending_prompts = tasks
+        .iter()
+        .filter_map(|task| match &task.state {
+            TaskState::WaitingForPermission { prompt } => Some(PendingPrompt {
+                task_id: task.id.clone(),
+                prompt: prompt.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+
+    RuntimeSnapshot {
+        seq,
+        tasks,
+        pending_prompts,
+        queue: runtime.get_queue(),
+        schedules: runtime.list_schedules(),
+        config: config::get_runtime_config(),
+    }
+}
@@ -0,0 +1,152 @@
+const HEADER: &str = "\
+// Generated by `deno_task_runtime::runtime_types`, don't edit by hand. Declares
+// the host APIs the runtime gives every task.
+";
+
+/// A member of an object the runtime gives scripts, as they see it.
+pub(crate) struct Member {
+    pub name: &'static str,
+    /// Parameters and return type for methods, the type for properties
+    pub signature: &'static str,
+    pub docs: &'static str,
+}
+
+impl Member {
+    pub fn is_method(&self) -> bool {
+        self.signature.starts_with('(')
+    }
+}
+
+// Mirrors `globalThis.RuntimeExtension` in bootstrap.js
+pub(crate) const RUNTIME_EXTENSION: &[Member] = &[
+    Member {
+        name: "returnValue",
+        signature: "(value: unknown): void",
+        docs: "Sets the return value of the task, serialized as JSON like `JSON.stringify` does.",
+    },
+    Member {
+        name: "onHostEvent",
+        signature: "(event: string, listener: (event: string) => void): () => void",
+        docs: "Calls `listener` on events from the app, e.g. `\"memory-pressure\"`. Returns a function removing the listener.",
+    },
+    Member {
+        name: "onMessage",
+        signature: "(listener: (message: unknown) => void): () => void",
+        docs: "Calls `listener` with the messages the app posts with `post_message_to_task`. Returns a function removing the listener. Listening doesn't keep the task running.",
+    },
+    Member {
+        name: "postMessage",
+        signature: "(message: unknown): void",
+        docs: "Sends a message, serialized as JSON, to the app's windows as a `task-message` event.",
+    },
+    Member {
+        name: "emit",
+        signature: "(event: string, payload?: unknown): void",
+        docs: "Emits a custom event to the app's windows, with `{ task_id, payload }` as its payload. The first use in a task asks the user for the `emit` permission.",
+    },
+    Member {
+        name: "listen",
+        signature: "(event: string, listener: (payload: unknown) => void): () => void",
+        docs: "Calls `listener` with the payload of `event` each time the app emits it. Returns a function removing the listener. The first use in a task asks the user for the `listen` permission, listening doesn't keep the task running.",
+    },
+    Member {
+        name: "documentDir",
+        signature: "(): string | null",
+        docs: "Path of the user's documents directory.",
+    },
+    Member {
+        name: "checkpoint",
+        signature: "(state: unknown): void",
+        docs: "Saves state a resumed run finds in `RuntimeExtension.checkpointState`.",
+    },
+    Member {
+        name: "saveArtifact",
+        signature: "(name: string, data: string | Uint8Array): void",
+        docs: "Saves a file the app's windows can show, e.g. an image, under `task-artifact://<task_id>/<name>`.",
+    },
+    Member {
+        name: "reportProgress",
+        signature: "(percent: number, message?: string): void",
+        docs: "Reports how far along the task is, from 0 to 100, for the app's progress bars. The app keeps the latest report.",
+    },
+    Member {
+        name: "store",
+        signature: "RuntimeExtensionStore",
+        docs: "Key-value store of the app, the first use in a task asks the user.",
+    },
+    Member {
+        name: "checkpointState",
+        signature: "unknown",
+        docs: "State of the last checkpoint when the task was resumed, `undefined` otherwise.",
+    },
+    Member {
+        name: "args",
+        signature: "unknown",
+        docs: "Arguments the task was started with, `undefined` if none.",
+    },
+    Member {
+        name: "input",
+        signature: "unknown",
+        docs: "Payload passed to `run_task`, `undefined` if none.",
+    },
+    Member {
+        name: "taskId",
+        signature: "string",
+        docs: "Id of the running task.",
+    },
+    Member {
+        name: "attempt",
+        signature: "number",
+        docs: "Run of the task it is, from 1, more than one with a retry policy.",
+    },
+];
+
+pub(crate) const STORE: &[Member] = &[
+    Member {
+        name: "get",
+        signature: "(key: string): unknown",
+        docs: "Value stored under `key`, `undefined` if there's none.",
+    },
+    Member {
+        name: "set",
+        signature: "(key: string, value: unknown): void",
+        docs: "Stores `value`, which must be serializable as JSON, under `key`.",
+    },
+];
+
+pub(crate) const RUNTIME_EXTENSION_DOCS: &str = "Host APIs of the app, available to every task.";
+
+const STORE_DOCS: &str = "Key-value store of the app, see `RuntimeExtension.store`.";
+
+fn interface(name: &str, docs: &str, members: &[Member]) -> String {
+    let mut declaration = format!("/** {} */\ninterface {} {{\n", docs, name);
+
+    for member in members {
+        let separator = if member.is_method() { "" } else { ": " };
+        declaration.push_str(&format!(
+            "  /** {} */\n  {}{}{};\n",
+            member.docs, member.name, separator, member.signature
+        ));
+    }
+
+    declaration.push_str("}\n");
+    declaration
+}
+
+/// TypeScript declarations of what the runtime injects in scripts, for the
+/// script editor to type check them against.
+pub fn runtime_types() -> String {
+    let mut types = HEADER.to_string();
+
+    types.push('\n');
+    types.push_str(&interface("RuntimeExtensionStore", STORE_DOCS, STORE));
+    types.push('\n');
+    types.push_str(&interface(
+        "RuntimeExtension",
+        RUNTIME_EXTENSION_DOCS,
+        RUNTIME_EXTENSION,
+    ));
+    types.push_str("\ndeclare var RuntimeExtension: RuntimeExtension;\n");
+
+    types
+}
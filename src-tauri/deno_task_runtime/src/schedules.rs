@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+
+use super::config::log;
+use super::task_ids::resolve_task_id;
+use super::{RunOptions, RuntimeState, TaskRuntime};
+
+const SCHEDULES_FILE: &str = "schedules.json";
+
+// Cron expressions have a resolution of a minute
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A task run on a cron cadence, added with `TaskRuntime::schedule_task`.
+/// Each run is a task of its own, with the schedule id as its namespace.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct Schedule {
+    id: String,
+    /// `minute hour day-of-month month day-of-week`, in UTC
+    cron: String,
+    code: String,
+    options: RunOptions,
+    /// When it runs next, in ms since the epoch, set when listed
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    next_run_ms: Option<u64>,
+    /// Task of the last run, `None` until it has run
+    last_task_id: Option<String>,
+}
+
+impl Schedule {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn next_run_ms(&self) -> Option<u64> {
+        self.next_run_ms
+    }
+
+    pub fn last_task_id(&self) -> Option<&str> {
+        self.last_task_id.as_deref()
+    }
+}
+
+fn parse_cron(cron: &str) -> Result<saffron::Cron, String> {
+    cron.parse::<saffron::Cron>()
+        .map_err(|e| format!("Invalid cron expression {:?}: {}", cron, e))
+}
+
+fn now() -> DateTime<Utc> {
+    let ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+
+    DateTime::from_timestamp_millis(ms).unwrap_or_default()
+}
+
+/// The schedules of a runtime and when each of them runs next. Runs missed
+/// while the app was closed are skipped.
+#[derive(Debug, Default)]
+pub struct Schedules {
+    schedules: Option<Vec<Schedule>>,
+    next_runs: HashMap<String, DateTime<Utc>>,
+    scheduler_started: bool,
+}
+
+fn schedules_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SCHEDULES_FILE)
+}
+
+impl Schedules {
+    // Read from disk on first use
+    fn schedules(&mut self, data_dir: &Path) -> &mut Vec<Schedule> {
+        self.schedules.get_or_insert_with(|| {
+            std::fs::read(schedules_path(data_dir))
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn save(&mut self, data_dir: &Path) -> Result<(), String> {
+        let json =
+            serde_json::to_vec_pretty(self.schedules(data_dir)).map_err(|e| e.to_string())?;
+
+        std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+        std::fs::write(schedules_path(data_dir), json).map_err(|e| e.to_string())
+    }
+
+    fn next_run(&mut self, schedule: &Schedule, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some(next) = self.next_runs.get(&schedule.id) {
+            return Some(*next);
+        }
+
+        let next = parse_cron(&schedule.cron).ok()?.next_after(after)?;
+        self.next_runs.insert(schedule.id.clone(), next);
+        Some(next)
+    }
+
+    pub fn add(
+        &mut self,
+        data_dir: &Path,
+        cron: &str,
+        code: &str,
+        options: RunOptions,
+    ) -> Result<String, String> {
+        let next = parse_cron(cron)?
+            .next_after(now())
+            .ok_or_else(|| format!("Cron expression {:?} never matches", cron))?;
+        let id = resolve_task_id(None, None)?;
+        self.next_runs.insert(id.clone(), next);
+
+        self.schedules(data_dir).push(Schedule {
+            id: id.clone(),
+            cron: cron.to_string(),
+            code: code.to_string(),
+            options,
+            next_run_ms: None,
+            last_task_id: None,
+        });
+        self.save(data_dir)?;
+
+        Ok(id)
+    }
+
+    pub fn list(&mut self, data_dir: &Path) -> Vec<Schedule> {
+        let now = now();
+        let mut schedules = self.schedules(data_dir).clone();
+        for schedule in &mut schedules {
+            schedule.next_run_ms = self
+                .next_run(schedule, now)
+                .map(|next| next.timestamp_millis() as u64);
+        }
+
+        schedules
+    }
+
+    pub fn remove(&mut self, data_dir: &Path, id: &str) -> Result<(), String> {
+        let schedules = self.schedules(data_dir);
+        let len = schedules.len();
+        schedules.retain(|schedule| schedule.id != id);
+        if schedules.len() == len {
+            return Err(format!("Schedule not found: {}", id));
+        }
+
+        self.next_runs.remove(id);
+        self.save(data_dir)
+    }
+
+    // Schedules due at `now`, moved on to their next run
+    fn take_due(&mut self, data_dir: &Path, now: DateTime<Utc>) -> Vec<Schedule> {
+        let mut due = Vec::new();
+
+        for schedule in self.schedules(data_dir).clone() {
+            if self
+                .next_run(&schedule, now)
+                .is_some_and(|next| next <= now)
+            {
+                self.next_runs.remove(&schedule.id);
+                self.next_run(&schedule, now);
+                due.push(schedule);
+            }
+        }
+
+        due
+    }
+
+    fn record_run(&mut self, data_dir: &Path, id: &str, task_id: &str) {
+        let schedule = self
+            .schedules(data_dir)
+            .iter_mut()
+            .find(|schedule| schedule.id == id);
+        // cancelled meanwhile
+        let Some(schedule) = schedule else {
+            return;
+        };
+
+        schedule.last_task_id = Some(task_id.to_string());
+        if let Err(e) = self.save(data_dir) {
+            log!(Error, "Failed to save schedule {}: {}", id, e);
+        }
+    }
+}
+
+/// Whether the runtime at `data_dir` has schedules saved.
+pub fn has_schedules(data_dir: &Path) -> bool {
+    !Schedules::default().schedules(data_dir).is_empty()
+}
+
+/// Starts the runs of the schedules due at `now_ms`, through `run_task` like
+/// any task. Returns the ids of the tasks started.
+pub fn run_due(runtime: &TaskRuntime, now_ms: u64) -> Vec<String> {
+    let now = DateTime::from_timestamp_millis(now_ms as i64).unwrap_or_default();
+    let data_dir = runtime.data_dir();
+
+    let due = runtime
+        .inner
+        .schedules
+        .lock()
+        .unwrap()
+        .take_due(data_dir, now);
+
+    let mut started = Vec::new();
+    for schedule in due {
+        let result = resolve_task_id(None, Some(&schedule.id)).and_then(|task_id| {
+            runtime
+                .run_task(&task_id, &schedule.code, schedule.options)
+                .map(|_| task_id)
+        });
+
+        match result {
+            Ok(task_id) => {
+                log!(Info, "Schedule {} started task {}", schedule.id, task_id);
+                runtime.inner.schedules.lock().unwrap().record_run(
+                    data_dir,
+                    &schedule.id,
+                    &task_id,
+                );
+                started.push(task_id);
+            }
+            Err(e) => log!(Error, "Schedule {} failed to start: {}", schedule.id, e),
+        }
+    }
+
+    started
+}
+
+/// Runs the schedules of `runtime` as they come due, until it's dropped.
+pub fn spawn_scheduler(runtime: &TaskRuntime) {
+    {
+        let mut schedules = runtime.inner.schedules.lock().unwrap();
+        if schedules.scheduler_started {
+            return;
+        }
+        schedules.scheduler_started = true;
+    }
+
+    // tests drive the schedules themselves, see `run_schedules_at`
+    #[cfg(any(test, feature = "test-support"))]
+    if runtime
+        .inner
+        .virtual_clock
+        .load(std::sync::atomic::Ordering::SeqCst)
+    {
+        return;
+    }
+
+    let inner: Weak<RuntimeState> = Arc::downgrade(&runtime.inner);
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        run_due(&TaskRuntime { inner }, now().timestamp_millis() as u64);
+    });
+}
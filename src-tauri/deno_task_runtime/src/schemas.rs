@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use super::alert_rules::{Alert, AlertRule};
+use super::dead_letters::DeadLetter;
+use super::event_log::SequencedEvent;
+use super::output_channels::TaskOutputChunk;
+use super::pipelines::{PipelineGraph, PipelineSpec, PipelineState};
+use super::runtime_snapshot::RuntimeSnapshot;
+use super::subscriptions::SubscriptionFilters;
+use super::task_logs::TaskLog;
+use super::{PermissionPrompt, PermissionsResponse, RunOptions, Task, TaskEvent, TaskState};
+
+/// JSON Schemas of the types the frontend exchanges with the runtime, by type
+/// name, for consumers that can't use the TypeScript SDK. Events are emitted
+/// as `SequencedEvent`s.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(transparent)]
+pub struct JsonSchemas(BTreeMap<&'static str, RootSchema>);
+
+pub fn get_json_schemas() -> JsonSchemas {
+    JsonSchemas(BTreeMap::from([
+        ("Task", schema_for!(Task)),
+        ("TaskState", schema_for!(TaskState)),
+        ("PermissionPrompt", schema_for!(PermissionPrompt)),
+        ("PermissionsResponse", schema_for!(PermissionsResponse)),
+        ("RunOptions", schema_for!(RunOptions)),
+        ("TaskEvent", schema_for!(TaskEvent)),
+        ("SequencedEvent", schema_for!(SequencedEvent)),
+        ("RuntimeSnapshot", schema_for!(RuntimeSnapshot)),
+        ("SubscriptionFilters", schema_for!(SubscriptionFilters)),
+        ("TaskOutputChunk", schema_for!(TaskOutputChunk)),
+        ("TaskLog", schema_for!(TaskLog)),
+        ("PipelineSpec", schema_for!(PipelineSpec)),
+        ("PipelineState", schema_for!(PipelineState)),
+        ("PipelineGraph", schema_for!(PipelineGraph)),
+        ("DeadLetter", schema_for!(DeadLetter)),
+        ("AlertRule", schema_for!(AlertRule)),
+        ("Alert", schema_for!(Alert)),
+    ]))
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use deno_ast::swc::ast::{Decl, DefaultDecl, Expr, Function, ModuleDecl, Pat, TsTypeAnn};
+use deno_ast::swc::common::comments::CommentKind;
+use deno_ast::{ModuleItemRef, ParsedSource, SourceRangedForSpanned};
+
+use super::scripts;
+
+/// Usage of a saved script, from the JSDoc of the functions it exports.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScriptDocs {
+    name: String,
+    functions: Vec<FunctionDoc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FunctionDoc {
+    /// `default` for the default export
+    name: String,
+    description: String,
+    params: Vec<ParamDoc>,
+    /// Declared return type, or the one of the `@returns` tag
+    return_type: Option<String>,
+    /// Text of the `@returns` tag
+    returns: Option<String>,
+    is_async: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ParamDoc {
+    name: String,
+    /// Declared type, or the one of the `@param` tag
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    optional: bool,
+    description: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct JsDoc {
+    description: String,
+    // type and text by name
+    params: HashMap<String, (Option<String>, String)>,
+    returns: Option<(Option<String>, String)>,
+}
+
+/// Extracts the docs of the functions `code` exports, fails when it doesn't
+/// parse.
+pub fn script_docs(name: &str, code: &str) -> Result<ScriptDocs, String> {
+    let parsed = scripts::parse(name, code)?;
+
+    let mut functions = Vec::new();
+
+    for item in parsed.program_ref().body() {
+        let ModuleItemRef::ModuleDecl(decl) = item else {
+            continue;
+        };
+
+        match decl {
+            ModuleDecl::ExportDecl(export) => {
+                let jsdoc = jsdoc(&parsed, export);
+
+                match &export.decl {
+                    Decl::Fn(function) => functions.push(function_doc(
+                        &parsed,
+                        function.ident.sym.to_string(),
+                        &function.function,
+                        jsdoc,
+                    )),
+                    // `export const name = (...) => ...`
+                    Decl::Var(var) => {
+                        let [declarator] = var.decls.as_slice() else {
+                            continue;
+                        };
+                        let (Pat::Ident(binding), Some(init)) =
+                            (&declarator.name, &declarator.init)
+                        else {
+                            continue;
+                        };
+                        let name = binding.id.sym.to_string();
+
+                        match &**init {
+                            Expr::Fn(function) => functions.push(function_doc(
+                                &parsed,
+                                name,
+                                &function.function,
+                                jsdoc,
+                            )),
+                            Expr::Arrow(arrow) => functions.push(doc(
+                                &parsed,
+                                name,
+                                arrow.params.iter(),
+                                arrow.return_type.as_deref(),
+                                arrow.is_async,
+                                jsdoc,
+                            )),
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ModuleDecl::ExportDefaultDecl(export) => {
+                if let DefaultDecl::Fn(function) = &export.decl {
+                    functions.push(function_doc(
+                        &parsed,
+                        "default".to_string(),
+                        &function.function,
+                        jsdoc(&parsed, export),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ScriptDocs {
+        name: name.to_string(),
+        functions,
+    })
+}
+
+fn function_doc(
+    parsed: &ParsedSource,
+    name: String,
+    function: &Function,
+    jsdoc: JsDoc,
+) -> FunctionDoc {
+    doc(
+        parsed,
+        name,
+        function.params.iter().map(|param| &param.pat),
+        function.return_type.as_deref(),
+        function.is_async,
+        jsdoc,
+    )
+}
+
+fn doc<'a>(
+    parsed: &ParsedSource,
+    name: String,
+    params: impl Iterator<Item = &'a Pat>,
+    return_type: Option<&TsTypeAnn>,
+    is_async: bool,
+    mut jsdoc: JsDoc,
+) -> FunctionDoc {
+    let params = params
+        .map(|pat| {
+            let (name, ty, optional) = param(parsed, pat);
+            let tag = jsdoc.params.remove(name.trim_start_matches("..."));
+            let (tag_type, description) = tag.unzip();
+
+            ParamDoc {
+                name,
+                ty: ty.or(tag_type.flatten()),
+                optional,
+                description: description.filter(|description| !description.is_empty()),
+            }
+        })
+        .collect();
+
+    let (returns_type, returns) = jsdoc.returns.unzip();
+
+    FunctionDoc {
+        name,
+        description: jsdoc.description,
+        params,
+        return_type: return_type
+            .map(|ann| source(parsed, &*ann.type_ann))
+            .or(returns_type.flatten()),
+        returns: returns.filter(|returns| !returns.is_empty()),
+        is_async,
+    }
+}
+
+// Name, declared type and whether it's optional
+fn param(parsed: &ParsedSource, pat: &Pat) -> (String, Option<String>, bool) {
+    let ty = |ann: &Option<Box<TsTypeAnn>>| ann.as_ref().map(|ann| source(parsed, &*ann.type_ann));
+
+    match pat {
+        Pat::Ident(binding) => (
+            binding.id.sym.to_string(),
+            ty(&binding.type_ann),
+            binding.id.optional,
+        ),
+        Pat::Assign(assign) => {
+            let (name, ty, _) = param(parsed, &assign.left);
+            (name, ty, true)
+        }
+        Pat::Rest(rest) => {
+            let (name, arg_ty, _) = param(parsed, &rest.arg);
+            (format!("...{}", name), ty(&rest.type_ann).or(arg_ty), true)
+        }
+        // destructured, named after the pattern as written
+        Pat::Object(object) => (
+            pattern_name(parsed, pat, &object.type_ann),
+            ty(&object.type_ann),
+            object.optional,
+        ),
+        Pat::Array(array) => (
+            pattern_name(parsed, pat, &array.type_ann),
+            ty(&array.type_ann),
+            array.optional,
+        ),
+        _ => (source(parsed, pat), None, false),
+    }
+}
+
+fn pattern_name(parsed: &ParsedSource, pat: &Pat, type_ann: &Option<Box<TsTypeAnn>>) -> String {
+    let name = source(parsed, pat);
+    let Some(type_ann) = type_ann else {
+        return name;
+    };
+
+    // the type annotation is part of the pattern
+    let source_start = parsed.text_info_lazy().range().start;
+    let type_start =
+        type_ann.start().as_byte_index(source_start) - pat.start().as_byte_index(source_start);
+    name[..type_start.min(name.len())]
+        .trim_end()
+        .trim_end_matches(':')
+        .trim_end()
+        .to_string()
+}
+
+fn source(parsed: &ParsedSource, node: &impl SourceRangedForSpanned) -> String {
+    let range = node
+        .range()
+        .as_byte_range(parsed.text_info_lazy().range().start);
+
+    parsed.text()[range].to_string()
+}
+
+// The `/** ... */` comment right before `node`
+fn jsdoc(parsed: &ParsedSource, node: &impl SourceRangedForSpanned) -> JsDoc {
+    parsed
+        .comments()
+        .get_leading(node.start())
+        .and_then(|comments| {
+            comments
+                .iter()
+                .rev()
+                .find(|comment| comment.kind == CommentKind::Block && comment.text.starts_with('*'))
+        })
+        .map(|comment| parse_jsdoc(&comment.text))
+        .unwrap_or_default()
+}
+
+fn parse_jsdoc(text: &str) -> JsDoc {
+    let lines = text.lines().map(|line| {
+        let line = line.trim_start().trim_start_matches('*');
+        line.strip_prefix(' ').unwrap_or(line).trim_end()
+    });
+
+    // the description, then one entry per tag with its continuation lines
+    let mut sections: Vec<String> = vec![String::new()];
+    for line in lines {
+        if line.starts_with('@') {
+            sections.push(line.to_string());
+        } else {
+            let section = sections.last_mut().unwrap();
+            if !section.is_empty() {
+                section.push('\n');
+            }
+            section.push_str(line);
+        }
+    }
+
+    let mut jsdoc = JsDoc {
+        description: sections[0].trim().to_string(),
+        ..Default::default()
+    };
+
+    for tag in &sections[1..] {
+        let (name, rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let (ty, rest) = tag_type(rest.trim_start());
+
+        match name {
+            "@param" | "@arg" | "@argument" => {
+                let (param, text) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                // `[name]` and `[name=default]` are optional
+                let param = param.trim_start_matches('[').trim_end_matches(']');
+                let param = param.split('=').next().unwrap_or(param);
+                let text = text.trim_start().trim_start_matches("- ").trim();
+
+                jsdoc
+                    .params
+                    .insert(param.to_string(), (ty, text.to_string()));
+            }
+            "@returns" | "@return" => jsdoc.returns = Some((ty, rest.trim().to_string())),
+            _ => {}
+        }
+    }
+
+    jsdoc
+}
+
+// `{type} rest`, braces can nest in types
+fn tag_type(text: &str) -> (Option<String>, &str) {
+    if !text.starts_with('{') {
+        return (None, text);
+    }
+
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => continue,
+        }
+        if depth == 0 {
+            return (
+                Some(text[1..i].trim().to_string()),
+                text[i + 1..].trim_start(),
+            );
+        }
+    }
+
+    (None, text)
+}
@@ -0,0 +1,245 @@
+use deno_ast::swc::ast::{Decl, Expr, Lit, ModuleDecl, Pat, Prop, PropName, PropOrSpread, UnaryOp};
+use deno_ast::ModuleItemRef;
+use serde_json::{Map, Number, Value};
+
+use super::scripts;
+
+/// The JSON schema of the args a script takes, from the `inputs` it exports:
+///
+/// ```ts
+/// export const inputs = {
+///   type: "object",
+///   properties: { city: { type: "string" }, days: { type: "integer", default: 7 } },
+///   required: ["city"],
+/// } as const;
+/// ```
+///
+/// It's read from the source without running the script, so it must be a
+/// literal. `None` when the script exports no `inputs`.
+pub fn script_inputs(name: &str, code: &str) -> Result<Option<Value>, String> {
+    let parsed = scripts::parse(name, code)?;
+
+    for item in parsed.program_ref().body() {
+        let ModuleItemRef::ModuleDecl(ModuleDecl::ExportDecl(export)) = item else {
+            continue;
+        };
+        let Decl::Var(var) = &export.decl else {
+            continue;
+        };
+
+        for declarator in &var.decls {
+            let Pat::Ident(binding) = &declarator.name else {
+                continue;
+            };
+            if binding.id.sym != "inputs" {
+                continue;
+            }
+
+            let schema = declarator
+                .init
+                .as_deref()
+                .ok_or_else(|| "no value".to_string())
+                .and_then(literal)
+                .map_err(|e| format!("Invalid inputs: {}", e))?;
+            if !schema.is_object() {
+                return Err("Invalid inputs: must be an object".to_string());
+            }
+
+            return Ok(Some(schema));
+        }
+    }
+
+    Ok(None)
+}
+
+// The value of a JSON-like literal, anything else would need running the
+// script
+fn literal(expr: &Expr) -> Result<Value, String> {
+    match expr {
+        Expr::Paren(paren) => literal(&paren.expr),
+        // `as const`, `satisfies ...`
+        Expr::TsConstAssertion(assertion) => literal(&assertion.expr),
+        Expr::TsAs(as_expr) => literal(&as_expr.expr),
+        Expr::TsSatisfies(satisfies) => literal(&satisfies.expr),
+        Expr::Lit(Lit::Str(string)) => Ok(Value::String(string.value.to_string())),
+        Expr::Lit(Lit::Bool(boolean)) => Ok(Value::Bool(boolean.value)),
+        Expr::Lit(Lit::Null(_)) => Ok(Value::Null),
+        Expr::Lit(Lit::Num(number)) => to_number(number.value),
+        Expr::Unary(unary) if unary.op == UnaryOp::Minus => match &*unary.arg {
+            Expr::Lit(Lit::Num(number)) => to_number(-number.value),
+            _ => Err("only numbers can be negated".to_string()),
+        },
+        Expr::Tpl(template) if template.exprs.is_empty() => Ok(Value::String(
+            template
+                .quasis
+                .iter()
+                .filter_map(|quasi| quasi.cooked.as_ref())
+                .map(|cooked| cooked.to_string())
+                .collect(),
+        )),
+        Expr::Array(array) => array
+            .elems
+            .iter()
+            .map(|element| match element {
+                Some(element) if element.spread.is_none() => literal(&element.expr),
+                Some(_) => Err("spreads aren't supported".to_string()),
+                None => Err("arrays can't have holes".to_string()),
+            })
+            .collect(),
+        Expr::Object(object) => {
+            let mut map = Map::new();
+            for prop in &object.props {
+                let PropOrSpread::Prop(prop) = prop else {
+                    return Err("spreads aren't supported".to_string());
+                };
+                let Prop::KeyValue(key_value) = &**prop else {
+                    return Err("only `key: value` properties are supported".to_string());
+                };
+
+                let key = match &key_value.key {
+                    PropName::Ident(ident) => ident.sym.to_string(),
+                    PropName::Str(string) => string.value.to_string(),
+                    PropName::Num(number) => number.value.to_string(),
+                    _ => return Err("computed keys aren't supported".to_string()),
+                };
+                map.insert(key, literal(&key_value.value)?);
+            }
+
+            Ok(Value::Object(map))
+        }
+        _ => Err("only literals are supported".to_string()),
+    }
+}
+
+fn to_number(value: f64) -> Result<Value, String> {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        return Ok(Value::from(value as i64));
+    }
+
+    Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| format!("{} isn't a JSON number", value))
+}
+
+/// Checks `args` against `schema`, filling in the defaults of the missing
+/// properties. Handles the keywords a form needs: `type`, `enum`,
+/// `properties`, `required`, `additionalProperties: false`, `items`,
+/// `minimum`, `maximum`, `minLength` and `maxLength`.
+pub fn validate_args(schema: &Value, args: Option<Value>) -> Result<Value, String> {
+    let mut args = args.unwrap_or_else(|| Value::Object(Map::new()));
+
+    let mut errors = Vec::new();
+    check(schema, &mut args, "args", &mut errors);
+
+    if errors.is_empty() {
+        Ok(args)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn check(schema: &Value, value: &mut Value, path: &str, errors: &mut Vec<String>) {
+    // `true` and the other non-object schemas accept anything
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+        errors.push(format!("{} must be of type {}", path, types.join(" or ")));
+        return;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            let options: Vec<String> = options.iter().map(Value::to_string).collect();
+            errors.push(format!("{} must be one of {}", path, options.join(", ")));
+        }
+    }
+
+    let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = bound("minimum").filter(|minimum| number < *minimum) {
+            errors.push(format!("{} must be at least {}", path, minimum));
+        }
+        if let Some(maximum) = bound("maximum").filter(|maximum| number > *maximum) {
+            errors.push(format!("{} must be at most {}", path, maximum));
+        }
+    }
+
+    if let Some(string) = value.as_str() {
+        let length = string.chars().count() as f64;
+        if let Some(min_length) = bound("minLength").filter(|min_length| length < *min_length) {
+            errors.push(format!(
+                "{} must be at least {} characters",
+                path, min_length
+            ));
+        }
+        if let Some(max_length) = bound("maxLength").filter(|max_length| length > *max_length) {
+            errors.push(format!(
+                "{} must be at most {} characters",
+                path, max_length
+            ));
+        }
+    }
+
+    match value {
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter_mut().enumerate() {
+                    check(item_schema, item, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+
+            for (name, property) in properties.into_iter().flatten() {
+                match object.get_mut(name) {
+                    Some(value) => check(property, value, &format!("{}.{}", path, name), errors),
+                    None => {
+                        if let Some(default) = property.get("default") {
+                            object.insert(name.clone(), default.clone());
+                        }
+                    }
+                }
+            }
+
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        errors.push(format!("{}.{} is required", path, name));
+                    }
+                }
+            }
+
+            if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                for name in object.keys() {
+                    if !properties.is_some_and(|properties| properties.contains_key(name)) {
+                        errors.push(format!("{}.{} isn't an input", path, name));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        // not ours to reject
+        _ => true,
+    }
+}
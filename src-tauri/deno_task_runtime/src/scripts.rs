@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+
+use deno_ast::{MediaType, ModuleSpecifier, ParseParams, ParsedSource};
+
+use super::module_loader::ModuleLock;
+use super::op_grants::OpGrant;
+use super::profiles;
+use super::signatures::BundleSignature;
+use super::task_ids::validate_task_id;
+
+const SCRIPTS_DIR: &str = "scripts";
+
+const SCRIPT_EXTENSION: &str = "ts";
+
+// Result hooks and fetch interceptors of the script, where it was installed
+// from, its default grants, lockfile and signature, next to it
+const HOOKS_EXTENSION: &str = "hooks.json";
+const INTERCEPTORS_EXTENSION: &str = "interceptors.json";
+const CATALOG_PIN_EXTENSION: &str = "catalog.json";
+const GRANTS_EXTENSION: &str = "grants.json";
+const LOCK_EXTENSION: &str = "lock.json";
+const SIGNATURE_EXTENSION: &str = "signature.json";
+
+fn script_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, SCRIPT_EXTENSION))
+}
+
+fn hooks_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, HOOKS_EXTENSION))
+}
+
+fn interceptors_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, INTERCEPTORS_EXTENSION))
+}
+
+fn catalog_pin_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, CATALOG_PIN_EXTENSION))
+}
+
+fn grants_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, GRANTS_EXTENSION))
+}
+
+fn lock_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, LOCK_EXTENSION))
+}
+
+fn signature_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir
+        .join(SCRIPTS_DIR)
+        .join(format!("{}.{}", name, SIGNATURE_EXTENSION))
+}
+
+/// Names end up in paths, they follow the rules of task ids.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    validate_task_id(name).map_err(|_| format!("Invalid script name {:?}", name))
+}
+
+pub fn save(data_dir: &Path, name: &str, code: &str) -> std::io::Result<()> {
+    let path = script_path(data_dir, name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, code)
+}
+
+pub fn load(data_dir: &Path, name: &str) -> std::io::Result<String> {
+    std::fs::read_to_string(script_path(data_dir, name))
+}
+
+pub fn delete(data_dir: &Path, name: &str) -> std::io::Result<()> {
+    std::fs::remove_file(script_path(data_dir, name))?;
+
+    remove_if_exists(&hooks_path(data_dir, name))?;
+    remove_if_exists(&interceptors_path(data_dir, name))?;
+    remove_if_exists(&catalog_pin_path(data_dir, name))?;
+    remove_if_exists(&grants_path(data_dir, name))?;
+    remove_if_exists(&lock_path(data_dir, name))?;
+    remove_if_exists(&signature_path(data_dir, name))
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+pub fn save_hooks(data_dir: &Path, name: &str, hooks: &[String]) -> std::io::Result<()> {
+    let path = hooks_path(data_dir, name);
+    if hooks.is_empty() {
+        return remove_if_exists(&path);
+    }
+
+    std::fs::write(path, serde_json::to_vec(hooks)?)
+}
+
+/// Names of the result hooks enabled for the script, none when unset.
+pub fn load_hooks(data_dir: &Path, name: &str) -> Vec<String> {
+    std::fs::read(hooks_path(data_dir, name))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_interceptors(
+    data_dir: &Path,
+    name: &str,
+    interceptors: &[String],
+) -> std::io::Result<()> {
+    let path = interceptors_path(data_dir, name);
+    if interceptors.is_empty() {
+        return remove_if_exists(&path);
+    }
+
+    std::fs::write(path, serde_json::to_vec(interceptors)?)
+}
+
+/// Names of the fetch interceptors enabled for the script, none when unset.
+pub fn load_interceptors(data_dir: &Path, name: &str) -> Vec<String> {
+    std::fs::read(interceptors_path(data_dir, name))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Catalog a script was installed from and the hash of the source reviewed
+/// then.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CatalogPin {
+    pub catalog_url: String,
+    pub sha256: String,
+}
+
+pub fn save_catalog_pin(data_dir: &Path, name: &str, pin: &CatalogPin) -> std::io::Result<()> {
+    std::fs::write(catalog_pin_path(data_dir, name), serde_json::to_vec(pin)?)
+}
+
+/// Dropped when the script is saved over, it's no longer what was reviewed.
+pub fn remove_catalog_pin(data_dir: &Path, name: &str) -> std::io::Result<()> {
+    remove_if_exists(&catalog_pin_path(data_dir, name))
+}
+
+/// `None` when the script wasn't installed from a catalog.
+pub fn load_catalog_pin(data_dir: &Path, name: &str) -> Option<CatalogPin> {
+    std::fs::read(catalog_pin_path(data_dir, name))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+}
+
+/// `None` removes them, runs of the script get all the grants again.
+pub fn save_grants(data_dir: &Path, name: &str, grants: Option<&[OpGrant]>) -> std::io::Result<()> {
+    let path = grants_path(data_dir, name);
+    match grants {
+        Some(grants) => std::fs::write(path, serde_json::to_vec(grants)?),
+        None => remove_if_exists(&path),
+    }
+}
+
+/// Grants the script runs with when the run doesn't set its own.
+pub fn load_grants(data_dir: &Path, name: &str) -> Option<Vec<OpGrant>> {
+    std::fs::read(grants_path(data_dir, name))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+}
+
+pub fn save_lock(data_dir: &Path, name: &str, lock: &ModuleLock) -> std::io::Result<()> {
+    let path = lock_path(data_dir, name);
+    if lock.is_empty() {
+        return remove_if_exists(&path);
+    }
+
+    std::fs::write(path, serde_json::to_vec_pretty(lock)?)
+}
+
+/// Hashes the remote modules of the script are checked against, empty when
+/// it wasn't imported from a bundle.
+pub fn load_lock(data_dir: &Path, name: &str) -> ModuleLock {
+    std::fs::read(lock_path(data_dir, name))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_signature(
+    data_dir: &Path,
+    name: &str,
+    signature: &BundleSignature,
+) -> std::io::Result<()> {
+    std::fs::write(
+        signature_path(data_dir, name),
+        serde_json::to_vec(signature)?,
+    )
+}
+
+/// Dropped when the script or its grants change, it no longer runs what was
+/// signed.
+pub fn remove_signature(data_dir: &Path, name: &str) -> std::io::Result<()> {
+    remove_if_exists(&signature_path(data_dir, name))
+}
+
+/// `None` when the script wasn't imported from a signed bundle.
+pub fn load_signature(data_dir: &Path, name: &str) -> Option<BundleSignature> {
+    std::fs::read(signature_path(data_dir, name))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+}
+
+/// Names of the saved scripts, sorted.
+pub fn list(data_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(data_dir.join(SCRIPTS_DIR)) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != SCRIPT_EXTENSION {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+
+    names.sort();
+    names
+}
+
+/// Whether `path` is a script saved by the user or its settings, which the
+/// storage quota never evicts.
+pub fn is_saved_script(path: &Path) -> bool {
+    path.parent()
+        .filter(|dir| dir.ends_with(SCRIPTS_DIR))
+        .and_then(Path::parent)
+        .is_some_and(profiles::is_data_dir)
+}
+
+/// Parses a script without running it, for what the host reads from its
+/// source.
+pub fn parse(name: &str, code: &str) -> Result<ParsedSource, String> {
+    deno_ast::parse_module(ParseParams {
+        specifier: ModuleSpecifier::parse(&format!("file:///{}.{}", name, SCRIPT_EXTENSION))
+            .map_err(|e| e.to_string())?,
+        text: code.into(),
+        media_type: MediaType::TypeScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,548 @@
+use std::path::Path;
+
+use ts_rs::TS;
+
+use super::alert_rules::{Alert, AlertAction, AlertRule};
+use super::archives::{
+    ArchiveEntry, ArchiveSummary, ArchivedArtifact, ArchivedTask, ArchivedTasks,
+};
+use super::client_certs::ClientCertificate;
+use super::compaction::CompactionReport;
+use super::config::{AppliedConfig, LogLevel, PromptPolicy, RuntimeConfig};
+use super::dead_letters::DeadLetter;
+use super::debugger::{DebugCallFrame, DebugPause, DebugStep};
+use super::event_log::SequencedEvent;
+use super::event_tasks::EventTask;
+use super::lanes::{QueueEntry, TaskQueue};
+use super::op_grants::{OpAuditEntry, OpAuditLog, OpGrant};
+use super::output_channels::{OutputStream, TaskOutputChunk};
+use super::overlay_fs::FsChange;
+use super::pipelines::{
+    ErrorPolicy, PipelineEdge, PipelineGraph, PipelineNode, PipelineSpec, PipelineState,
+    PipelineStatus, PipelineStep, StepState, StepStatus,
+};
+use super::progress::TaskProgress;
+use super::proxy::ProxyOptions;
+use super::remote_objects::{EntryPreview, ObjectPreview, PropertyPreview, RemoteObject};
+use super::retries::RetryPolicy;
+use super::runtime_snapshot::{PendingPrompt, RuntimeSnapshot};
+use super::schedules::Schedule;
+use super::stack_frames::StackFrame;
+use super::subscriptions::SubscriptionFilters;
+use super::task_history::{TaskHistory, TaskHistoryFilter, TaskHistoryPage, TaskRecord};
+use super::task_list::{TaskList, TaskSummary};
+use super::task_logs::{ConsoleLevel, TaskLog};
+use super::timings::TaskTimings;
+use super::versions::TaskDiagnostic;
+use super::watches::TaskWatch;
+use super::{
+    CassetteMode, CassetteOptions, PermissionPrompt, PermissionsResponse, RunOptions, Task,
+    TaskEvent, TaskLane, TaskState, WaitingKind, WaitingOn, WindowClosedPolicy,
+};
+
+const HEADER: &str = "\
+// Generated from the Rust types by `deno_task_runtime::typescript_sdk`, don't
+// edit by hand. Debug builds of the app keep it up to date.
+
+import { invoke, type Channel } from \"@tauri-apps/api/core\";
+import { listen, type EventCallback, type UnlistenFn } from \"@tauri-apps/api/event\";
+";
+
+struct Command {
+    name: &'static str,
+    docs: &'static str,
+    // as the frontend passes them (camelCase), with their TS type
+    args: Vec<(&'static str, String, bool)>,
+    returns: String,
+}
+
+impl Command {
+    // every task command takes the profile, the default one if unset
+    fn new(name: &'static str, docs: &'static str, returns: impl Into<String>) -> Self {
+        Self {
+            name,
+            docs,
+            args: vec![("profile", "string".to_string(), true)],
+            returns: returns.into(),
+        }
+    }
+
+    fn arg(mut self, name: &'static str, ty: impl Into<String>) -> Self {
+        self.args.push((name, ty.into(), false));
+        self
+    }
+
+    fn optional_arg(mut self, name: &'static str, ty: impl Into<String>) -> Self {
+        self.args.push((name, ty.into(), true));
+        self
+    }
+
+    fn to_ts(&self) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|(name, ty, optional)| {
+                format!("{}{}: {}", name, if *optional { "?" } else { "" }, ty)
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        format!(
+            "/** {} */\nexport function {}(args: {{ {} }}): Promise<{}> {{\n  return invoke(\"{}\", args);\n}}\n",
+            self.docs,
+            camel_case(self.name, '_'),
+            args,
+            self.returns,
+            self.name
+        )
+    }
+}
+
+// The task commands of the app, which wraps the runtime's methods as-is
+fn commands() -> Vec<Command> {
+    let events = format!("{}[]", SequencedEvent::name());
+
+    vec![
+        Command::new(
+            "run_task",
+            "Returns the task id, generated when none is given, or the id of the task already submitted with the same idempotency key. A `context` runs it in the profile of that name, created on first use, instead of `profile`.",
+            "string",
+        )
+        .optional_arg("context", "string")
+        .optional_arg("taskId", "string")
+        .arg("code", "string")
+        .optional_arg("options", format!("Partial<{}>", RunOptions::name()))
+        .optional_arg("namespaceByWindow", "boolean")
+        .optional_arg("input", "unknown"),
+        Command::new(
+            "post_message_to_task",
+            "Sends a message to the `RuntimeExtension.onMessage` listeners of a running task.",
+            "void",
+        )
+        .arg("taskId", "string")
+        .arg("message", "unknown"),
+        Command::new(
+            "debug_run_task",
+            "Runs a task paused at its first statement and at the given lines of `code`, from 1. Pauses are `task-debug-paused` events.",
+            "string",
+        )
+        .optional_arg("taskId", "string")
+        .arg("code", "string")
+        .arg("breakpoints", "number[]")
+        .optional_arg("options", format!("Partial<{}>", RunOptions::name()))
+        .optional_arg("namespaceByWindow", "boolean"),
+        Command::new(
+            "debug_step",
+            "Steps a paused task, over function calls by default.",
+            "void",
+        )
+        .arg("taskId", "string")
+        .optional_arg("step", DebugStep::name()),
+        Command::new(
+            "debug_continue",
+            "Runs a paused task until its next breakpoint.",
+            "void",
+        )
+        .arg("taskId", "string"),
+        Command::new(
+            "debug_evaluate",
+            "Evaluates an expression in the frame a debugged task is paused in, or at its top level while it runs.",
+            "unknown",
+        )
+        .arg("taskId", "string")
+        .arg("expression", "string"),
+        Command::new(
+            "get_debug_pause",
+            "Where a debugged task is paused, `null` while it runs.",
+            format!("{} | null", DebugPause::name()),
+        )
+        .arg("taskId", "string"),
+        Command::new(
+            "evaluate_in_task",
+            "Previews the value of an expression in a running task, read-only unless `allowSideEffects`.",
+            RemoteObject::name(),
+        )
+        .arg("taskId", "string")
+        .arg("expression", "string")
+        .optional_arg("allowSideEffects", "boolean"),
+        Command::new(
+            "pause_task",
+            "Suspends a running task until it's resumed.",
+            "void",
+        )
+        .arg("taskId", "string"),
+        Command::new(
+            "resume_task",
+            "Resumes a paused task, or restarts one from its last checkpoint.",
+            "void",
+        )
+        .arg("taskId", "string"),
+        Command::new("stop_task", "Stops a running task.", "void").arg("taskId", "string"),
+        Command::new(
+            "write_task_stdin",
+            "Writes to the stdin of a running task, what `Deno.stdin` and `prompt()` read.",
+            "void",
+        )
+        .arg("taskId", "string")
+        .arg("data", "string"),
+        Command::new(
+            "get_task_state",
+            "Fails when the task doesn't exist.",
+            <Task as TS>::name(),
+        )
+        .arg("taskId", "string"),
+        Command::new(
+            "get_queue",
+            "The background tasks waiting for a slot, in the order they'll start.",
+            TaskQueue::name(),
+        ),
+        Command::new(
+            "respond_to_permission_prompt",
+            "Answers the permission prompt the task is waiting on.",
+            "void",
+        )
+        .arg("taskId", "string")
+        .arg("response", PermissionsResponse::name()),
+        Command::new(
+            "get_runtime_snapshot",
+            "Everything a window shows at once, sync the events after its `seq` to stay current.",
+            RuntimeSnapshot::name(),
+        ),
+        Command::new(
+            "sync_task_events",
+            "Events emitted after `sinceSeq`, 0 for everything still buffered.",
+            events.clone(),
+        )
+        .arg("sinceSeq", "number"),
+        Command::new(
+            "get_events_since",
+            "Events of a task after the given `task_seq`.",
+            events,
+        )
+        .arg("taskId", "string")
+        .arg("seq", "number"),
+        Command::new(
+            "subscribe",
+            "Sends the events matching `filters` through `onEvent`, returns the subscription id. The window stops getting the events with `listen`.",
+            "number",
+        )
+        .arg("filters", SubscriptionFilters::name())
+        .arg("onEvent", format!("Channel<{}>", SequencedEvent::name())),
+        Command::new("unsubscribe", "Ends a subscription.", "void").arg("subscriptionId", "number"),
+        Command::new(
+            "subscribe_task_output",
+            "Sends the console output of a task through `onOutput`, starting with what it printed so far.",
+            "void",
+        )
+        .arg("taskId", "string")
+        .arg("onOutput", format!("Channel<{}>", TaskOutputChunk::name())),
+        Command::new(
+            "run_pipeline",
+            "Runs saved scripts in steps passing return values along, returns the pipeline id.",
+            "string",
+        )
+        .arg("spec", PipelineSpec::name())
+        .optional_arg("options", format!("Partial<{}>", RunOptions::name())),
+        Command::new(
+            "get_pipeline_state",
+            "The steps of a pipeline as a graph, fails when the pipeline doesn't exist.",
+            PipelineGraph::name(),
+        )
+        .arg("pipelineId", "string"),
+        Command::new(
+            "list_dead_letters",
+            "Failed background runs, oldest first.",
+            format!("{}[]", DeadLetter::name()),
+        ),
+        Command::new(
+            "retry_dead_letter",
+            "Runs the task of a dead letter again and drops the letter.",
+            "void",
+        )
+        .arg("id", "string"),
+        Command::new("dismiss_dead_letter", "Drops a dead letter.", "void").arg("id", "string"),
+        Command::new(
+            "set_alert_rule",
+            "Adds a rule reacting to a saved script failing repeatedly, replacing the one with the same id.",
+            "void",
+        )
+        .arg("rule", AlertRule::name()),
+        Command::new(
+            "list_alert_rules",
+            "The alert rules, in the order they were added.",
+            format!("{}[]", AlertRule::name()),
+        ),
+        Command::new("remove_alert_rule", "Removes an alert rule.", "void").arg("id", "string"),
+        Command::new(
+            "schedule_task",
+            "Runs the code whenever the cron expression matches, in UTC. Returns the schedule id.",
+            "string",
+        )
+        .arg("cron", "string")
+        .arg("code", "string")
+        .optional_arg("options", format!("Partial<{}>", RunOptions::name())),
+        Command::new(
+            "list_schedules",
+            "The schedules, in the order they were added.",
+            format!("{}[]", Schedule::name()),
+        ),
+        Command::new(
+            "cancel_schedule",
+            "Removes a schedule, a run in progress goes on.",
+            "void",
+        )
+        .arg("id", "string"),
+        Command::new(
+            "register_event_task",
+            "Runs the code whenever the Tauri event is emitted, with its payload as the input, or the payloads of a batch with `debounceMs`. Returns the registration id.",
+            "string",
+        )
+        .arg("eventName", "string")
+        .arg("code", "string")
+        .optional_arg("options", format!("Partial<{}>", RunOptions::name()))
+        .optional_arg("debounceMs", "number"),
+        Command::new(
+            "list_event_tasks",
+            "The event tasks, in the order they were registered.",
+            format!("{}[]", EventTask::name()),
+        ),
+        Command::new(
+            "unregister_event_task",
+            "Removes an event task, a run in progress goes on.",
+            "void",
+        )
+        .arg("id", "string"),
+        Command::new(
+            "watch_task",
+            "Runs the code now and whenever one of the paths changes, once per `debounceMs` of changes, with the changed paths as the input. Returns the watch id.",
+            "string",
+        )
+        .arg("paths", "string[]")
+        .arg("code", "string")
+        .optional_arg("options", format!("Partial<{}>", RunOptions::name()))
+        .optional_arg("debounceMs", "number"),
+        Command::new(
+            "list_watches",
+            "The watches, in the order they were started.",
+            format!("{}[]", TaskWatch::name()),
+        ),
+        Command::new(
+            "unwatch_task",
+            "Stops watching, a run in progress goes on.",
+            "void",
+        )
+        .arg("id", "string"),
+        Command::new(
+            "get_task_logs",
+            "The `console` calls of the current run of a task.",
+            format!("{}[]", TaskLog::name()),
+        )
+        .arg("taskId", "string"),
+        Command::new(
+            "clear_completed_tasks",
+            "Forgets the tasks that are done.",
+            "void",
+        ),
+        Command::new(
+            "list_tasks",
+            "Summaries of the tasks, newest first. `stateFilter` takes state names, `tags` keeps the tasks carrying all of them, `search` matches ids, names, descriptions and output.",
+            TaskList::name(),
+        )
+        .optional_arg("stateFilter", "string[]")
+        .optional_arg("tags", "string[]")
+        .optional_arg("search", "string")
+        .optional_arg("offset", "number")
+        .optional_arg("limit", "number"),
+        Command::new(
+            "get_task_history",
+            "Finished runs, of earlier sessions too, newest first.",
+            TaskHistory::name(),
+        )
+        .optional_arg("filter", format!("Partial<{}>", TaskHistoryFilter::name()))
+        .optional_arg("page", format!("Partial<{}>", TaskHistoryPage::name())),
+        Command::new(
+            "archive_tasks",
+            "Moves the runs finished before `beforeMs` from the history to a compressed archive, with the logs and artifacts of their tasks.",
+            ArchiveSummary::name(),
+        )
+        .arg("beforeMs", "number"),
+        Command::new(
+            "search_task_archives",
+            "Archived runs, most recently finished first.",
+            ArchivedTasks::name(),
+        )
+        .optional_arg("filter", format!("Partial<{}>", TaskHistoryFilter::name()))
+        .optional_arg("page", format!("Partial<{}>", TaskHistoryPage::name())),
+        Command::new(
+            "get_archived_task",
+            "The runs of a task in an archive found with `searchTaskArchives`.",
+            format!("{}[]", ArchivedTask::name()),
+        )
+        .arg("archive", "string")
+        .arg("taskId", "string"),
+        Command::new(
+            "compact_storage",
+            "Vacuums the SQLite stores, forgets expired idempotency keys and removes orphaned artifacts.",
+            CompactionReport::name(),
+        ),
+    ]
+}
+
+// Tauri event names, see `TaskEvent`
+pub(crate) const EVENTS: &[&str] = &[
+    "task-state-changed",
+    "task-output-truncated",
+    "task-log",
+    "pipeline-state-changed",
+    "task-dead-lettered",
+    "task-alert",
+    "task-progress",
+    "task-message",
+    "task-debug-paused",
+    "task-debug-resumed",
+];
+
+fn declarations() -> Vec<(Option<&'static str>, String)> {
+    fn declaration<T: TS>() -> (Option<&'static str>, String) {
+        (T::DOCS, T::decl())
+    }
+
+    vec![
+        declaration::<Task>(),
+        declaration::<TaskState>(),
+        declaration::<WaitingOn>(),
+        declaration::<TaskProgress>(),
+        declaration::<TaskTimings>(),
+        declaration::<DebugPause>(),
+        declaration::<DebugCallFrame>(),
+        declaration::<DebugStep>(),
+        declaration::<RemoteObject>(),
+        declaration::<ObjectPreview>(),
+        declaration::<PropertyPreview>(),
+        declaration::<EntryPreview>(),
+        declaration::<WaitingKind>(),
+        declaration::<StackFrame>(),
+        declaration::<PermissionPrompt>(),
+        declaration::<PermissionsResponse>(),
+        declaration::<TaskDiagnostic>(),
+        declaration::<FsChange>(),
+        declaration::<OpAuditLog>(),
+        declaration::<OpAuditEntry>(),
+        declaration::<OpGrant>(),
+        declaration::<WindowClosedPolicy>(),
+        declaration::<TaskLane>(),
+        declaration::<TaskQueue>(),
+        declaration::<QueueEntry>(),
+        declaration::<RunOptions>(),
+        declaration::<CassetteOptions>(),
+        declaration::<CassetteMode>(),
+        declaration::<ProxyOptions>(),
+        declaration::<RetryPolicy>(),
+        declaration::<ClientCertificate>(),
+        declaration::<TaskEvent>(),
+        declaration::<SequencedEvent>(),
+        declaration::<RuntimeSnapshot>(),
+        declaration::<PendingPrompt>(),
+        declaration::<AppliedConfig>(),
+        declaration::<RuntimeConfig>(),
+        declaration::<PromptPolicy>(),
+        declaration::<LogLevel>(),
+        declaration::<SubscriptionFilters>(),
+        declaration::<OutputStream>(),
+        declaration::<TaskOutputChunk>(),
+        declaration::<ConsoleLevel>(),
+        declaration::<TaskLog>(),
+        declaration::<TaskList>(),
+        declaration::<TaskSummary>(),
+        declaration::<TaskHistory>(),
+        declaration::<TaskRecord>(),
+        declaration::<TaskHistoryFilter>(),
+        declaration::<TaskHistoryPage>(),
+        declaration::<ArchiveSummary>(),
+        declaration::<ArchivedTasks>(),
+        declaration::<ArchiveEntry>(),
+        declaration::<ArchivedTask>(),
+        declaration::<ArchivedArtifact>(),
+        declaration::<CompactionReport>(),
+        declaration::<PipelineSpec>(),
+        declaration::<PipelineStep>(),
+        declaration::<ErrorPolicy>(),
+        declaration::<PipelineState>(),
+        declaration::<PipelineStatus>(),
+        declaration::<StepState>(),
+        declaration::<StepStatus>(),
+        declaration::<PipelineGraph>(),
+        declaration::<PipelineNode>(),
+        declaration::<PipelineEdge>(),
+        declaration::<DeadLetter>(),
+        declaration::<AlertRule>(),
+        declaration::<Schedule>(),
+        declaration::<EventTask>(),
+        declaration::<TaskWatch>(),
+        declaration::<AlertAction>(),
+        declaration::<Alert>(),
+    ]
+}
+
+/// TypeScript client for the task commands and events, along with the types
+/// they use, generated from the Rust types so the frontend can't drift from
+/// them.
+pub fn typescript_sdk() -> String {
+    let mut sdk = HEADER.to_string();
+
+    for (docs, declaration) in declarations() {
+        sdk.push('\n');
+        sdk.push_str(docs.unwrap_or_default());
+        sdk.push_str("export ");
+        sdk.push_str(&declaration);
+        sdk.push('\n');
+    }
+
+    for command in commands() {
+        sdk.push('\n');
+        sdk.push_str(&command.to_ts());
+    }
+
+    for event in EVENTS {
+        sdk.push_str(&format!(
+            "\nexport function on{}(handler: EventCallback<Extract<{}, {{ event: \"{}\" }}>>): Promise<UnlistenFn> {{\n  return listen(\"{}\", handler);\n}}\n",
+            camel_case(&format!("-{}", event), '-'),
+            SequencedEvent::name(),
+            event,
+            event
+        ));
+    }
+
+    sdk
+}
+
+/// Writes `typescript_sdk` to `path`, unless it's already up to date (so dev
+/// servers watching it don't reload for nothing).
+pub fn export_typescript_sdk(path: &Path) -> std::io::Result<()> {
+    let sdk = typescript_sdk();
+
+    if std::fs::read_to_string(path).is_ok_and(|current| current == sdk) {
+        return Ok(());
+    }
+
+    std::fs::write(path, sdk)
+}
+
+// `run_task` -> `runTask`, a leading separator capitalizes the first word
+fn camel_case(name: &str, separator: char) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+
+    for c in name.chars() {
+        if c == separator {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+
+    camel
+}
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use super::catalog::sha256_hex;
+use super::module_loader::ModuleLock;
+use super::op_grants::OpGrant;
+
+const TRUSTED_KEYS_FILE: &str = "trusted_keys.json";
+
+/// A public key whose signed bundles are trusted, added with
+/// `TaskRuntime::add_trusted_key`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrustedKey {
+    /// Whose key it is, e.g. the team distributing the scripts
+    name: String,
+    /// Base64 of the 32 bytes of the ed25519 public key
+    public_key: String,
+}
+
+impl TrustedKey {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+}
+
+/// A new key pair to sign bundles with, base64 encoded. The private key is
+/// kept by whoever signs, the public one is added to the trusted keys of
+/// those importing the bundles.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BundleSigningKey {
+    pub private_key: String,
+    pub public_key: String,
+}
+
+/// Signature of a bundle, kept next to the script it was imported as to
+/// check it again before each run.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BundleSignature {
+    public_key: String,
+    signature: String,
+}
+
+// What a signature covers, the input schema comes with the source
+#[derive(serde::Serialize)]
+struct SignedContent<'a> {
+    format: &'static str,
+    name: &'a str,
+    source_sha256: String,
+    lockfile: &'a ModuleLock,
+    grants: Option<&'a [OpGrant]>,
+}
+
+/// The bytes signed for a script, the same whether they're read from a
+/// bundle or from the saved script.
+pub fn signed_message(
+    name: &str,
+    source: &str,
+    lockfile: &ModuleLock,
+    grants: Option<&[OpGrant]>,
+) -> Vec<u8> {
+    serde_json::to_vec(&SignedContent {
+        format: "denotask",
+        name,
+        source_sha256: sha256_hex(source),
+        lockfile,
+        grants,
+    })
+    .unwrap_or_default()
+}
+
+fn decode_key<const N: usize>(key: &str, what: &str) -> Result<[u8; N], String> {
+    BASE64_STANDARD
+        .decode(key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("Invalid {}, expected {} bytes in base64", what, N))
+}
+
+fn verifying_key(public_key: &str) -> Result<VerifyingKey, String> {
+    VerifyingKey::from_bytes(&decode_key(public_key, "public key")?)
+        .map_err(|e| format!("Invalid public key: {}", e))
+}
+
+pub fn generate_bundle_signing_key() -> BundleSigningKey {
+    let key = SigningKey::generate(&mut rand::rngs::OsRng);
+
+    BundleSigningKey {
+        private_key: BASE64_STANDARD.encode(key.to_bytes()),
+        public_key: BASE64_STANDARD.encode(key.verifying_key().to_bytes()),
+    }
+}
+
+pub fn sign(private_key: &str, message: &[u8]) -> Result<BundleSignature, String> {
+    let key = SigningKey::from_bytes(&decode_key(private_key, "private key")?);
+
+    Ok(BundleSignature {
+        public_key: BASE64_STANDARD.encode(key.verifying_key().to_bytes()),
+        signature: BASE64_STANDARD.encode(key.sign(message).to_bytes()),
+    })
+}
+
+/// Checks that `signature` is of `message` and by a trusted key, returns the
+/// name of the key.
+pub fn verify(
+    data_dir: &Path,
+    signature: &BundleSignature,
+    message: &[u8],
+) -> Result<String, String> {
+    let trusted = list(data_dir)
+        .into_iter()
+        .find(|key| key.public_key == signature.public_key)
+        .ok_or_else(|| "It's signed by a key that isn't trusted".to_string())?;
+
+    let bytes = decode_key(&signature.signature, "signature")?;
+    verifying_key(&signature.public_key)?
+        .verify_strict(message, &Signature::from_bytes(&bytes))
+        .map_err(|_| {
+            "Its signature doesn't match, it was changed since it was signed".to_string()
+        })?;
+
+    Ok(trusted.name)
+}
+
+fn trusted_keys_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(TRUSTED_KEYS_FILE)
+}
+
+pub fn list(data_dir: &Path) -> Vec<TrustedKey> {
+    std::fs::read(trusted_keys_path(data_dir))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save(data_dir: &Path, keys: &[TrustedKey]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(keys).map_err(|e| e.to_string())?;
+
+    std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    std::fs::write(trusted_keys_path(data_dir), json).map_err(|e| e.to_string())
+}
+
+/// Replaces the key trusted under the same name.
+pub fn add(data_dir: &Path, name: &str, public_key: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Trusted keys need a name".to_string());
+    }
+    let public_key = BASE64_STANDARD.encode(verifying_key(public_key)?.to_bytes());
+
+    let mut keys = list(data_dir);
+    keys.retain(|key| key.name != name);
+    keys.push(TrustedKey {
+        name: name.to_string(),
+        public_key,
+    });
+
+    save(data_dir, &keys)
+}
+
+/// Scripts imported from bundles signed with it no longer run.
+pub fn remove(data_dir: &Path, name: &str) -> Result<(), String> {
+    let mut keys = list(data_dir);
+    let len = keys.len();
+    keys.retain(|key| key.name != name);
+    if keys.len() == len {
+        return Err(format!("Trusted key not found: {}", name));
+    }
+
+    save(data_dir, &keys)
+}
@@ -0,0 +1,37 @@
+use std::sync::Mutex;
+
+// Workers bootstrap from it when set, otherwise the JS sources of every
+// extension are evaluated again for each task
+static STARTUP_SNAPSHOT: Mutex<Option<&'static [u8]>> = Mutex::new(None);
+
+pub fn startup_snapshot() -> Option<&'static [u8]> {
+    *STARTUP_SNAPSHOT.lock().unwrap()
+}
+
+/// Startup snapshot the workers of every runtime bootstrap from, `None` to
+/// go back to evaluating the JS sources of the extensions.
+#[cfg(any(test, feature = "test-support"))]
+pub fn set_startup_snapshot(snapshot: Option<&'static [u8]>) {
+    *STARTUP_SNAPSHOT.lock().unwrap() = snapshot;
+}
+
+/// Snapshot of the extensions of `deno_runtime` for `set_startup_snapshot`,
+/// the task extensions aren't part of it and still load on bootstrap. Slow,
+/// take it once per process.
+#[cfg(any(test, feature = "test-support"))]
+pub fn create_startup_snapshot() -> &'static [u8] {
+    use deno_runtime::ops::bootstrap::SnapshotOptions;
+    use deno_runtime::snapshot::create_runtime_snapshot;
+
+    // only writes to a file
+    let path =
+        std::env::temp_dir().join(format!("deno_task_runtime_{}.snapshot", std::process::id()));
+
+    create_runtime_snapshot(path.clone(), SnapshotOptions::default(), vec![]);
+
+    let snapshot = std::fs::read(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    // workers borrow it for as long as the process runs
+    Box::leak(snapshot.into_boxed_slice())
+}
@@ -0,0 +1,69 @@
+use deno_runtime::deno_core::error::{AnyError, JsError};
+use deno_runtime::deno_core::ModuleSpecifier;
+
+use super::module_loader::ModuleSources;
+
+/// A frame of the stack a task failed with, pointing at the code as written.
+/// Frames of transpiled modules are mapped back through their source maps.
+#[derive(
+    Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+pub struct StackFrame {
+    /// Unset for the task's own code
+    file_name: Option<String>,
+    function_name: Option<String>,
+    /// 1-based
+    line: Option<u32>,
+    /// 1-based
+    column: Option<u32>,
+    /// The line of code the frame points at
+    snippet: Option<String>,
+}
+
+/// The frames of `error` when it's a JS error, innermost first.
+///
+/// `main_module` is the staged code of the task, whose first `prefix_lines`
+/// lines were added to the code as written.
+pub fn stack_frames(
+    error: &AnyError,
+    sources: Option<&ModuleSources>,
+    main_module: &ModuleSpecifier,
+    prefix_lines: u32,
+) -> Vec<StackFrame> {
+    let Some(error) = error.downcast_ref::<JsError>() else {
+        return Vec::new();
+    };
+
+    error
+        .frames
+        .iter()
+        .map(|frame| {
+            let line = frame.line_number.and_then(|line| u32::try_from(line).ok());
+            let snippet = frame
+                .file_name
+                .as_ref()
+                .zip(line)
+                .and_then(|(file_name, line)| {
+                    let sources = sources?.borrow();
+                    let code = sources.get(file_name)?;
+                    code.lines()
+                        .nth(line.checked_sub(1)? as usize)
+                        .map(|snippet| snippet.trim_end().to_string())
+                });
+
+            let is_task_code = frame.file_name.as_deref() == Some(main_module.as_str());
+            StackFrame {
+                file_name: frame.file_name.clone().filter(|_| !is_task_code),
+                function_name: frame.function_name.clone(),
+                line: match line {
+                    Some(line) if is_task_code => line.checked_sub(prefix_lines),
+                    line => line,
+                },
+                column: frame
+                    .column_number
+                    .and_then(|column| u32::try_from(column).ok()),
+                snippet,
+            }
+        })
+        .collect()
+}
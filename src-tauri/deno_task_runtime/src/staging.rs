@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use super::config::{self, log};
+
+const STAGED_CODE_PREFIX: &str = "temp_code_";
+const STAGED_CODE_EXTENSION: &str = "ts";
+
+// Directory where task code is written before being loaded as the main module
+pub fn code_dir() -> PathBuf {
+    if let Some(dir) = config::data_dir() {
+        return dir;
+    }
+
+    // path of user directory
+    let user_dir = dirs::home_dir().unwrap();
+
+    user_dir.join(".tauri_deno_example")
+}
+
+/// Task code staged on disk for the module loader.
+///
+/// The file is removed when the guard is dropped, which also covers tasks that
+/// are stopped (the `run` future is dropped) or that panic.
+pub struct StagedCode {
+    path: PathBuf,
+}
+
+impl StagedCode {
+    pub fn write(dir: &Path, task_id: &str, code: &str) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let path = dir.join(format!(
+            "{STAGED_CODE_PREFIX}{task_id}.{STAGED_CODE_EXTENSION}"
+        ));
+
+        log!(Debug, "Writing code to {}", path.display());
+
+        std::fs::write(&path, code)?;
+
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagedCode {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log!(Error, "Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Removes staged code files left behind by a previous run of the app (e.g. it
+/// was killed while tasks were running) from `dir`. Must be called before any
+/// task starts.
+///
+/// Returns how many files were removed.
+pub fn sweep_orphaned_code(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+
+    let mut removed = 0;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if !is_staged_code(&path) {
+            continue;
+        }
+
+        match std::fs::remove_file(&path) {
+            Ok(_) => removed += 1,
+            Err(e) => log!(Error, "Failed to remove {}: {}", path.display(), e),
+        }
+    }
+
+    removed
+}
+
+pub fn is_staged_code(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(STAGED_CODE_PREFIX))
+        && path
+            .extension()
+            .is_some_and(|extension| extension == STAGED_CODE_EXTENSION)
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use deno_runtime::deno_io::{self, PipeWrite, Stdio, StdioPipe};
+use deno_runtime::worker::MainWorker;
+
+use super::config::log;
+
+// See `installPrompts` in bootstrap.js
+const INSTALL_PROMPTS: &str = r#"
+const key = Symbol.for("runtimeExtension.installPrompts");
+globalThis[key]();
+delete globalThis[key];
+"#;
+
+/// Write end of a worker's stdin, in its `OpState` until a task adopts it.
+pub struct StdinWriter(PipeWrite);
+
+/// Stdio of a worker, with stdin as a pipe so the app can write to it. Falls
+/// back to the process' stdin when the pipe can't be created.
+pub fn stdio() -> (Stdio, Option<StdinWriter>) {
+    match deno_io::pipe() {
+        Ok((read, write)) => (
+            Stdio {
+                stdin: StdioPipe::file(read),
+                ..Default::default()
+            },
+            Some(StdinWriter(write)),
+        ),
+        Err(e) => {
+            log!(Error, "Failed to create the stdin pipe of a worker: {}", e);
+            (Stdio::default(), None)
+        }
+    }
+}
+
+/// Makes `alert`, `confirm` and `prompt` read from the stdin pipe, to run
+/// once the worker is bootstrapped.
+pub fn install_prompts(worker: &mut MainWorker) {
+    if let Err(e) = worker.execute_script("[stdin_prompts]", INSTALL_PROMPTS.to_string().into()) {
+        log!(Error, "Failed to install the stdin prompts: {}", e);
+    }
+}
+
+/// Stdin of the running tasks, closed when they're done.
+#[derive(Default)]
+pub struct TaskStdins {
+    writers: HashMap<String, Arc<Mutex<PipeWrite>>>,
+}
+
+impl fmt::Debug for TaskStdins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskStdins")
+            .field("tasks", &self.writers.len())
+            .finish()
+    }
+}
+
+impl TaskStdins {
+    pub fn insert(&mut self, task_id: &str, writer: StdinWriter) {
+        self.writers
+            .insert(task_id.to_string(), Arc::new(Mutex::new(writer.0)));
+    }
+
+    pub fn get(&self, task_id: &str) -> Option<Arc<Mutex<PipeWrite>>> {
+        self.writers.get(task_id).cloned()
+    }
+
+    pub fn remove(&mut self, task_id: &str) {
+        self.writers.remove(task_id);
+    }
+}
+
+/// Blocks until the task reads what doesn't fit in the pipe.
+pub fn write(writer: &Mutex<PipeWrite>, data: &[u8]) -> std::io::Result<()> {
+    let mut writer = writer.lock().unwrap();
+
+    writer.write_all(data)?;
+    writer.flush()
+}
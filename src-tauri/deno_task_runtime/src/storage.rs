@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::config::{self, log};
+use super::profiles;
+use super::scripts::is_saved_script;
+use super::staging::{code_dir, is_staged_code};
+
+pub const DEFAULT_STORAGE_QUOTA_BYTES: u64 = 1024 * 1024 * 1024;
+
+// Staged code belongs to tasks that are running, it's never evicted
+const STAGED_CODE_CATEGORY: &str = "staged_code";
+
+// Saved by the user, never evicted either
+const SAVED_SCRIPTS_CATEGORY: &str = "saved_scripts";
+
+// What the runtime can do without: recorded fetches, saved artifacts and
+// archived runs. The rest, like checkpoints, is the user's state and only
+// counts towards the usage.
+const EVICTABLE_CATEGORIES: &[&str] = &["cassettes", "artifacts", "archives"];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StorageCategory {
+    name: String,
+    bytes: u64,
+    files: usize,
+}
+
+/// Disk used by the deno subsystem, grouped by the top level directory of the
+/// app directory (`cassettes`, `checkpoints`, ...).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StorageUsage {
+    dir: PathBuf,
+    total_bytes: u64,
+    quota_bytes: Option<u64>,
+    categories: Vec<StorageCategory>,
+}
+
+struct StoredFile {
+    path: PathBuf,
+    category: String,
+    bytes: u64,
+    last_used: SystemTime,
+}
+
+pub fn get_storage_usage() -> StorageUsage {
+    let mut categories: BTreeMap<String, StorageCategory> = BTreeMap::new();
+
+    for file in stored_files() {
+        let category = categories
+            .entry(file.category.clone())
+            .or_insert_with(|| StorageCategory {
+                name: file.category,
+                bytes: 0,
+                files: 0,
+            });
+        category.bytes += file.bytes;
+        category.files += 1;
+    }
+
+    StorageUsage {
+        dir: code_dir(),
+        total_bytes: categories.values().map(|category| category.bytes).sum(),
+        quota_bytes: config::get().storage_quota_bytes(),
+        categories: categories.into_values().collect(),
+    }
+}
+
+/// Sets the cap on disk usage, `None` removes it. The cap is saved in the
+/// runtime config and enforced right away and after every task.
+pub fn set_storage_quota(quota_bytes: Option<u64>) -> Result<(), String> {
+    config::update(|config| config.set_storage_quota_bytes(quota_bytes))?;
+
+    Ok(())
+}
+
+/// Removes the least recently used cached files until the usage fits the
+/// quota.
+///
+/// Returns how many files were removed.
+pub fn enforce_storage_quota() -> usize {
+    let Some(quota_bytes) = config::get().storage_quota_bytes() else {
+        return 0;
+    };
+
+    evict_over_quota(&code_dir(), quota_bytes)
+}
+
+/// `enforce_storage_quota` for the app directory at `root`.
+pub fn evict_over_quota(root: &Path, quota_bytes: u64) -> usize {
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files);
+    let mut total_bytes: u64 = files.iter().map(|file| file.bytes).sum();
+
+    if total_bytes <= quota_bytes {
+        return 0;
+    }
+
+    files.retain(|file| EVICTABLE_CATEGORIES.contains(&file.category.as_str()));
+    files.sort_by_key(|file| file.last_used);
+
+    let mut removed = 0;
+
+    for file in files {
+        if total_bytes <= quota_bytes {
+            break;
+        }
+
+        match std::fs::remove_file(&file.path) {
+            Ok(_) => {
+                log!(
+                    Info,
+                    "Evicted {} ({} bytes)",
+                    file.path.display(),
+                    file.bytes
+                );
+                total_bytes -= file.bytes;
+                removed += 1;
+            }
+            Err(e) => log!(Error, "Failed to remove {}: {}", file.path.display(), e),
+        }
+    }
+
+    removed
+}
+
+fn stored_files() -> Vec<StoredFile> {
+    let root = code_dir();
+    let mut files = Vec::new();
+
+    collect_files(&root, &root, &mut files);
+
+    files
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<StoredFile>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // symlinks are not followed, whatever they point to isn't ours
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            let root = if profiles::is_data_dir(&path) {
+                &path
+            } else {
+                root
+            };
+            collect_files(root, &path, files);
+            continue;
+        }
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let category = if is_staged_code(&path) {
+            STAGED_CODE_CATEGORY.to_string()
+        } else if is_saved_script(&path) {
+            SAVED_SCRIPTS_CATEGORY.to_string()
+        } else if relative.components().count() > 1 {
+            relative
+                .components()
+                .next()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_default()
+        } else {
+            "other".to_string()
+        };
+
+        // access times may be disabled or coarse, a write also counts as a use
+        let last_used = [metadata.accessed().ok(), metadata.modified().ok()]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        files.push(StoredFile {
+            path,
+            category,
+            bytes: metadata.len(),
+            last_used,
+        });
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use tauri::ipc::Channel;
+
+use super::event_log::SequencedEvent;
+use super::TaskEvent;
+
+/// Which events a subscriber gets, each filter narrows them down and the
+/// ones left unset match everything.
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+#[serde(default)]
+pub struct SubscriptionFilters {
+    task_ids: Option<Vec<String>>,
+    /// States the tasks of `task-state-changed` events must be in, other
+    /// events aren't filtered by state
+    states: Option<Vec<String>>,
+    /// Event names, e.g. `task-output-truncated`
+    events: Option<Vec<String>>,
+}
+
+impl SubscriptionFilters {
+    fn matches(&self, event: &TaskEvent) -> bool {
+        let matches = |filter: &Option<Vec<String>>, value: &str| {
+            filter
+                .as_ref()
+                .is_none_or(|values| values.iter().any(|v| v == value))
+        };
+
+        let state_matches = match event {
+            TaskEvent::StateChanged(task) => matches(&self.states, task.state.name()),
+            _ => true,
+        };
+
+        matches(&self.task_ids, event.task_id())
+            && matches(&self.events, event.name())
+            && state_matches
+    }
+}
+
+struct Subscription {
+    filters: SubscriptionFilters,
+    channel: Channel<SequencedEvent>,
+    // dropped along with the window
+    window: Option<String>,
+}
+
+/// Subscribers of a runtime, each one gets the events matching its filters
+/// through its own channel, so events are filtered before they cross the
+/// IPC instead of in every window.
+#[derive(Default)]
+pub struct Subscriptions {
+    last_id: u64,
+    by_id: HashMap<u64, Subscription>,
+}
+
+impl fmt::Debug for Subscriptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscriptions")
+            .field("last_id", &self.last_id)
+            .field("count", &self.by_id.len())
+            .finish()
+    }
+}
+
+impl Subscriptions {
+    pub fn add(
+        &mut self,
+        filters: SubscriptionFilters,
+        channel: Channel<SequencedEvent>,
+        window: Option<&str>,
+    ) -> u64 {
+        self.last_id += 1;
+        self.by_id.insert(
+            self.last_id,
+            Subscription {
+                filters,
+                channel,
+                window: window.map(|label| label.to_string()),
+            },
+        );
+
+        self.last_id
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        self.by_id.remove(&id).is_some()
+    }
+
+    pub fn remove_window(&mut self, window: &str) {
+        self.by_id
+            .retain(|_, subscription| subscription.window.as_deref() != Some(window));
+    }
+
+    /// Windows with a subscription, left out of the broadcast of the events
+    /// so they don't get them twice.
+    pub fn windows(&self) -> HashSet<String> {
+        self.by_id
+            .values()
+            .filter_map(|subscription| subscription.window.clone())
+            .collect()
+    }
+
+    /// Channels of the subscriptions `event` matches, to send it through once
+    /// the lock is released.
+    pub fn matching(&self, event: &SequencedEvent) -> Vec<(u64, Channel<SequencedEvent>)> {
+        self.by_id
+            .iter()
+            .filter(|(_, subscription)| subscription.filters.matches(event.event()))
+            .map(|(id, subscription)| (*id, subscription.channel.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,72 @@
+use std::thread;
+use std::time::Duration;
+
+use super::{PermissionsResponse, Task, TaskRuntime};
+
+// Tasks live on their own threads and report through the task store, waiting
+// on one is polling its state
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A task started on a `TaskRuntime`, returned by `run_task` and
+/// `resume_task`.
+///
+/// The task keeps running when the handle is dropped, it's only a way to
+/// reach it without going through the task id.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    runtime: TaskRuntime,
+    task_id: String,
+}
+
+impl TaskHandle {
+    pub(crate) fn new(runtime: TaskRuntime, task_id: &str) -> Self {
+        Self {
+            runtime,
+            task_id: task_id.to_string(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.task_id
+    }
+
+    /// Current state, `None` once the task is cleared.
+    pub fn state(&self) -> Option<Task> {
+        self.runtime.get_task_state(&self.task_id)
+    }
+
+    pub fn stop(&self) -> Result<(), String> {
+        self.runtime.stop_task(&self.task_id)
+    }
+
+    pub fn pause(&self) -> Result<(), String> {
+        self.runtime.pause_task(&self.task_id)
+    }
+
+    /// Resumes the task once paused.
+    pub fn resume(&self) -> Result<(), String> {
+        self.runtime.resume_task(&self.task_id).map(drop)
+    }
+
+    pub fn respond_to_permission_prompt(&self, response: PermissionsResponse) {
+        self.runtime
+            .respond_to_permission_prompt(&self.task_id, response)
+    }
+
+    /// Blocks until the task completes, fails or is stopped, and returns its
+    /// final state. `None` when it was cleared in the meantime.
+    ///
+    /// Pending permission prompts must be answered from another thread (or
+    /// by a `PermissionBroker`) for the task to end.
+    pub fn wait(&self) -> Option<Task> {
+        loop {
+            let task = self.state();
+
+            match &task {
+                Some(task) if task.is_finished() => return Some(task.clone()),
+                None if !self.runtime.is_task_thread_alive(&self.task_id) => return None,
+                _ => thread::sleep(WAIT_POLL_INTERVAL),
+            }
+        }
+    }
+}
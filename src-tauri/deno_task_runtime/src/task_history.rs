@@ -0,0 +1,305 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::catalog::sha256_hex;
+use super::task_list::has_tags;
+use super::{PermissionPrompt, Task, TaskState};
+
+const HISTORY_FILE: &str = "task_history.jsonl";
+
+// The oldest records are dropped past this many, the file is rewritten once
+// it has a tenth more so it isn't on every run
+const MAX_RECORDS: usize = 10_000;
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// A finished run of a task, kept on disk across restarts. See
+/// `TaskRuntime::get_task_history`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskRecord {
+    task_id: String,
+    // missing from the records of earlier versions
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Saved script it ran, if any
+    script: Option<String>,
+    /// Hex SHA-256 of the code it ran, the same for runs of the same code
+    code_sha256: String,
+    /// How it ended, `completed`, `error` or `timed_out`
+    state: TaskState,
+    #[ts(type = "unknown")]
+    return_value: Option<serde_json::Value>,
+    permission_history: Vec<PermissionPrompt>,
+    /// Attempts it took, see `RunOptions::retry`
+    attempt: u32,
+    /// Milliseconds since the Unix epoch, from when its thread picked it up
+    #[ts(type = "number")]
+    started_at_ms: u64,
+    #[ts(type = "number")]
+    finished_at_ms: u64,
+    #[ts(type = "number | null")]
+    cpu_time_ms: Option<u64>,
+}
+
+impl TaskRecord {
+    pub fn task_id(&self) -> &str {
+        &self.task_id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn state(&self) -> &TaskState {
+        &self.state
+    }
+
+    pub fn return_value(&self) -> Option<&serde_json::Value> {
+        self.return_value.as_ref()
+    }
+
+    pub fn script(&self) -> Option<&str> {
+        self.script.as_deref()
+    }
+
+    pub fn code_sha256(&self) -> &str {
+        &self.code_sha256
+    }
+
+    pub fn finished_at_ms(&self) -> u64 {
+        self.finished_at_ms
+    }
+}
+
+/// Which records `get_task_history` returns, all of them when empty.
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+#[serde(default)]
+pub struct TaskHistoryFilter {
+    task_id: Option<String>,
+    script: Option<String>,
+    /// Names of the states the runs ended in, e.g. `["error", "timed_out"]`
+    states: Option<Vec<String>>,
+    /// Runs of tasks carrying every one of these tags
+    tags: Option<Vec<String>>,
+    /// Runs finished at or after this, in ms since the epoch
+    #[ts(type = "number | null")]
+    since_ms: Option<u64>,
+    /// Runs finished before this, in ms since the epoch
+    #[ts(type = "number | null")]
+    until_ms: Option<u64>,
+}
+
+impl TaskHistoryFilter {
+    fn matches(&self, record: &TaskRecord) -> bool {
+        self.matches_run(
+            &record.task_id,
+            record.script.as_deref(),
+            &record.tags,
+            record.state.name(),
+            record.finished_at_ms,
+        )
+    }
+
+    /// Whether a run with these fields is one the filter returns.
+    pub fn matches_run(
+        &self,
+        task_id: &str,
+        script: Option<&str>,
+        tags: &[String],
+        state: &str,
+        finished_at_ms: u64,
+    ) -> bool {
+        self.task_id.as_deref().is_none_or(|id| id == task_id)
+            && self
+                .script
+                .as_deref()
+                .is_none_or(|name| script == Some(name))
+            && has_tags(tags, self.tags.as_deref())
+            && self
+                .states
+                .as_ref()
+                .is_none_or(|states| states.iter().any(|name| name == state))
+            && self
+                .since_ms
+                .is_none_or(|since_ms| finished_at_ms >= since_ms)
+            && self
+                .until_ms
+                .is_none_or(|until_ms| finished_at_ms < until_ms)
+    }
+}
+
+/// A page of the history, newest runs first.
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+#[serde(default)]
+pub struct TaskHistoryPage {
+    /// Records to skip
+    offset: usize,
+    /// Records to return, 50 when unset and at most 1000
+    limit: Option<usize>,
+}
+
+impl TaskHistoryPage {
+    /// Records to skip and to return.
+    pub fn bounds(&self) -> (usize, usize) {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+        (self.offset, limit)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskHistory {
+    records: Vec<TaskRecord>,
+    /// Records matching the filter, across all pages
+    total: usize,
+}
+
+impl TaskHistory {
+    pub fn records(&self) -> &[TaskRecord] {
+        &self.records
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+/// The task history of a runtime, appended to as tasks finish.
+#[derive(Debug, Default)]
+pub struct History {
+    records: Option<Vec<TaskRecord>>,
+}
+
+fn history_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(HISTORY_FILE)
+}
+
+impl History {
+    // Read from disk on first use, a line cut short by a crash is skipped
+    fn records(&mut self, data_dir: &Path) -> &mut Vec<TaskRecord> {
+        self.records.get_or_insert_with(|| {
+            std::fs::read_to_string(history_path(data_dir))
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+    }
+
+    /// Appends the run of a task that just finished.
+    pub fn record(
+        &mut self,
+        data_dir: &Path,
+        task: &Task,
+        code: &str,
+        script: Option<&str>,
+        started_at_ms: u64,
+        finished_at_ms: u64,
+    ) -> Result<(), String> {
+        let record = TaskRecord {
+            task_id: task.id.clone(),
+            name: task.name.clone(),
+            tags: task.tags.clone(),
+            script: script.map(str::to_string),
+            code_sha256: sha256_hex(code),
+            state: task.state.clone(),
+            return_value: task.return_value.clone(),
+            permission_history: task.permission_history.clone(),
+            attempt: task.attempt,
+            started_at_ms,
+            finished_at_ms,
+            cpu_time_ms: task.cpu_time_ms,
+        };
+        let mut line = serde_json::to_vec(&record).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+
+        let records = self.records(data_dir);
+        records.push(record);
+        if records.len() > MAX_RECORDS + MAX_RECORDS / 10 {
+            records.drain(..records.len() - MAX_RECORDS);
+            return self.save(data_dir);
+        }
+
+        std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(history_path(data_dir))
+            .and_then(|mut file| file.write_all(&line))
+            .map_err(|e| e.to_string())
+    }
+
+    fn save(&mut self, data_dir: &Path) -> Result<(), String> {
+        let mut lines = Vec::new();
+        for record in self.records(data_dir).iter() {
+            serde_json::to_writer(&mut lines, record).map_err(|e| e.to_string())?;
+            lines.push(b'\n');
+        }
+
+        std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+        std::fs::write(history_path(data_dir), lines).map_err(|e| e.to_string())
+    }
+
+    /// Records of the runs finished before `before_ms`, oldest first.
+    pub fn finished_before(&mut self, data_dir: &Path, before_ms: u64) -> Vec<TaskRecord> {
+        self.records(data_dir)
+            .iter()
+            .filter(|record| record.finished_at_ms < before_ms)
+            .cloned()
+            .collect()
+    }
+
+    pub fn remove_finished_before(
+        &mut self,
+        data_dir: &Path,
+        before_ms: u64,
+    ) -> Result<(), String> {
+        self.records(data_dir)
+            .retain(|record| record.finished_at_ms >= before_ms);
+
+        self.save(data_dir)
+    }
+
+    /// Whether a run of `task_id` finished at or after `since_ms`.
+    pub fn has_run_since(&mut self, data_dir: &Path, task_id: &str, since_ms: u64) -> bool {
+        self.records(data_dir)
+            .iter()
+            .any(|record| record.task_id == task_id && record.finished_at_ms >= since_ms)
+    }
+
+    pub fn query(
+        &mut self,
+        data_dir: &Path,
+        filter: &TaskHistoryFilter,
+        page: &TaskHistoryPage,
+    ) -> TaskHistory {
+        let (offset, limit) = page.bounds();
+        let matching: Vec<&TaskRecord> = self
+            .records(data_dir)
+            .iter()
+            .rev()
+            .filter(|record| filter.matches(record))
+            .collect();
+
+        TaskHistory {
+            total: matching.len(),
+            records: matching
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+}
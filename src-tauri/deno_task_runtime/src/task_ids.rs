@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MAX_TASK_ID_LEN: usize = 128;
+
+// Separates the window label from the task id in namespaced ids
+const NAMESPACE_SEPARATOR: char = '.';
+
+/// Task ids end up in file names (staged code, checkpoints) and in the code
+/// injected into the task, so only a safe charset is accepted.
+pub fn validate_task_id(task_id: &str) -> Result<(), String> {
+    if task_id.is_empty() {
+        return Err("Task id is empty".to_string());
+    }
+
+    if task_id.len() > MAX_TASK_ID_LEN {
+        return Err(format!(
+            "Task id is longer than {} characters",
+            MAX_TASK_ID_LEN
+        ));
+    }
+
+    if let Some(c) = task_id.chars().find(|c| !is_task_id_char(*c)) {
+        return Err(format!("Invalid character {:?} in task id", c));
+    }
+
+    if task_id.starts_with(NAMESPACE_SEPARATOR) || task_id.contains("..") {
+        return Err(format!("Invalid task id {:?}", task_id));
+    }
+
+    Ok(())
+}
+
+/// Id for `run_task` when the caller doesn't pick one, optionally namespaced
+/// by the window that started the task so ids picked by different UI
+/// components can't collide.
+pub fn resolve_task_id(task_id: Option<&str>, namespace: Option<&str>) -> Result<String, String> {
+    let task_id = match task_id {
+        Some(task_id) => task_id.to_string(),
+        None => generate_task_id(),
+    };
+
+    let task_id = match namespace {
+        Some(namespace) => format!(
+            "{}{}{}",
+            sanitize_namespace(namespace),
+            NAMESPACE_SEPARATOR,
+            task_id
+        ),
+        None => task_id,
+    };
+
+    validate_task_id(&task_id)?;
+
+    Ok(task_id)
+}
+
+fn is_task_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == NAMESPACE_SEPARATOR
+}
+
+// Window labels may contain `/` and `:`, which aren't valid in task ids
+fn sanitize_namespace(namespace: &str) -> String {
+    namespace
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn generate_task_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+
+    format!(
+        "task-{:x}-{:x}",
+        millis,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use super::task_history::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use super::{Task, TaskState, WaitingOn};
+
+/// What a task dashboard shows of a task, see `TaskRuntime::list_tasks`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskSummary {
+    id: String,
+    name: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    state: TaskState,
+    waiting_on: Option<WaitingOn>,
+    attempt: u32,
+    #[ts(type = "number")]
+    created_at_ms: u64,
+    #[ts(type = "number | null")]
+    started_at_ms: Option<u64>,
+    #[ts(type = "number | null")]
+    finished_at_ms: Option<u64>,
+    #[ts(type = "number | null")]
+    duration_ms: Option<u64>,
+    /// Window that started the task, `None` once the app owns it
+    owner_window: Option<String>,
+}
+
+impl TaskSummary {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn state(&self) -> &TaskState {
+        &self.state
+    }
+
+    pub fn created_at_ms(&self) -> u64 {
+        self.created_at_ms
+    }
+
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.duration_ms
+    }
+}
+
+/// A page of the tasks, newest first.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskList {
+    tasks: Vec<TaskSummary>,
+    /// Tasks matching the filters, across all pages
+    total: usize,
+    /// How many of those carry each tag, to group them by
+    tag_counts: BTreeMap<String, usize>,
+}
+
+impl TaskList {
+    pub fn tasks(&self) -> &[TaskSummary] {
+        &self.tasks
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn tag_counts(&self) -> &BTreeMap<String, usize> {
+        &self.tag_counts
+    }
+}
+
+pub fn validate_tags(tags: &[String]) -> Result<(), String> {
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.trim().is_empty() {
+            return Err("Tags can't be empty".to_string());
+        }
+        if tags[..i].contains(tag) {
+            return Err(format!("Duplicate tag {:?}", tag));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a task or run tagged `tags` carries every one of `wanted`.
+pub fn has_tags(tags: &[String], wanted: Option<&[String]>) -> bool {
+    wanted.is_none_or(|wanted| wanted.iter().all(|tag| tags.contains(tag)))
+}
+
+pub fn list<'a>(
+    tasks: impl Iterator<Item = &'a Task>,
+    state_filter: Option<&[String]>,
+    tags: Option<&[String]>,
+    search: Option<&str>,
+    offset: usize,
+    limit: Option<usize>,
+) -> TaskList {
+    let search = search.map(str::to_lowercase);
+    let mut matching: Vec<&Task> = tasks
+        .filter(|task| {
+            state_filter.is_none_or(|states| states.iter().any(|state| state == task.state.name()))
+        })
+        .filter(|task| has_tags(&task.tags, tags))
+        .filter(|task| {
+            search.as_deref().is_none_or(|search| {
+                let contains = |text: &str| text.to_lowercase().contains(search);
+                contains(&task.id)
+                    || task.name.as_deref().is_some_and(contains)
+                    || task.description.as_deref().is_some_and(contains)
+                    || contains(task.output.text())
+            })
+        })
+        .collect();
+    // ids break ties, tasks submitted in the same millisecond keep an order
+    matching.sort_by(|a, b| {
+        b.created_at_ms
+            .cmp(&a.created_at_ms)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    let mut tag_counts = BTreeMap::new();
+    for tag in matching.iter().flat_map(|task| &task.tags) {
+        *tag_counts.entry(tag.clone()).or_default() += 1;
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    TaskList {
+        total: matching.len(),
+        tag_counts,
+        tasks: matching
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|task| TaskSummary {
+                id: task.id.clone(),
+                name: task.name.clone(),
+                description: task.description.clone(),
+                tags: task.tags.clone(),
+                state: task.state.clone(),
+                waiting_on: task.waiting_on.clone(),
+                attempt: task.attempt,
+                created_at_ms: task.created_at_ms,
+                started_at_ms: task.started_at_ms,
+                finished_at_ms: task.finished_at_ms,
+                duration_ms: task.duration_ms,
+                owner_window: task.owner_window.clone(),
+            })
+            .collect(),
+    }
+}
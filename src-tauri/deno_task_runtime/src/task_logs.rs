@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    ts_rs::TS,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsoleLevel {
+    Debug,
+    Log,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A `console` call of a task, with its arguments as JSON. Errors become
+/// `{ name, message, stack }`, and what JSON can't represent (cycles,
+/// functions, ...) the string `Deno.inspect` renders it as.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskLog {
+    pub(crate) task_id: String,
+    level: ConsoleLevel,
+    #[ts(type = "unknown[]")]
+    args: Vec<serde_json::Value>,
+    /// Milliseconds since the Unix epoch, from the host clock
+    #[ts(type = "number")]
+    timestamp_ms: u64,
+}
+
+impl TaskLog {
+    pub fn new(
+        task_id: &str,
+        level: ConsoleLevel,
+        args: Vec<serde_json::Value>,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self {
+            task_id: task_id.to_string(),
+            level,
+            args,
+            timestamp_ms,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Logs {
+    logs: Vec<TaskLog>,
+    bytes: usize,
+}
+
+/// Structured console logs of the current run of each task, kept within the
+/// output budget of the task (`max_task_output_bytes`), the calls past it are
+/// dropped.
+#[derive(Debug, Default)]
+pub struct TaskLogs {
+    by_task: HashMap<String, Logs>,
+}
+
+impl TaskLogs {
+    /// Keeps `log` if it fits in the budget, `bytes` being the size of its
+    /// arguments.
+    pub fn push(&mut self, log: TaskLog, bytes: usize, limit: usize) -> bool {
+        let logs = self.by_task.entry(log.task_id.clone()).or_default();
+        if logs.bytes + bytes > limit {
+            return false;
+        }
+
+        logs.bytes += bytes;
+        logs.logs.push(log);
+        true
+    }
+
+    pub fn get(&self, task_id: &str) -> Vec<TaskLog> {
+        self.by_task
+            .get(task_id)
+            .map(|logs| logs.logs.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn restart(&mut self, task_id: &str) {
+        self.by_task.remove(task_id);
+    }
+
+    pub fn forget(&mut self, task_ids: &[String]) {
+        for task_id in task_ids {
+            self.by_task.remove(task_id);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use crossbeam_channel::{bounded, unbounded, Sender};
+
+use super::Task;
+
+type Job = Box<dyn FnOnce(&mut HashMap<String, Task>) + Send>;
+
+/// Owns the tasks of a runtime on a dedicated thread.
+///
+/// Reads and writes are jobs sent to that thread and run one after the other,
+/// so there is a single writer and nothing else ever locks the tasks: an op or
+/// the prompter can't end up waiting on a lock it (indirectly) holds. Jobs
+/// must be quick and must not call back into the store.
+#[derive(Debug)]
+pub struct TaskStore {
+    jobs: Sender<Job>,
+}
+
+impl TaskStore {
+    pub fn spawn(name: &str) -> Self {
+        let (jobs, receiver) = unbounded::<Job>();
+
+        // the thread ends once the store (the only sender) is dropped
+        std::thread::Builder::new()
+            .name(format!("task-store-{}", name))
+            .spawn(move || {
+                let mut tasks = HashMap::new();
+
+                while let Ok(job) = receiver.recv() {
+                    job(&mut tasks);
+                }
+            })
+            .expect("failed to spawn the task store thread");
+
+        Self { jobs }
+    }
+
+    /// Runs `f` on the tasks and waits for its result.
+    pub fn query<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut HashMap<String, Task>) -> R + Send + 'static,
+    ) -> R {
+        let (reply_tx, reply_rx) = bounded(1);
+
+        self.jobs
+            .send(Box::new(move |tasks| {
+                let _ = reply_tx.send(f(tasks));
+            }))
+            .expect("task store thread stopped");
+
+        reply_rx.recv().expect("task store job panicked")
+    }
+}
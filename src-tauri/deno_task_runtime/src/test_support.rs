@@ -0,0 +1,260 @@
+//! Deterministic stand-ins for the user, the disk and the clock, to cover
+//! task execution, permission flows and timeouts in tests. Enabled with the
+//! `test-support` feature.
+//!
+//! `TestHarness` wires all of them into a runtime of its own:
+//!
+//! ```ignore
+//! let harness = TestHarness::new("greeting");
+//! harness.prompter().answer("read", PermissionsResponse::Allow);
+//! harness.fs().seed_file("/data/name.txt", "Ada");
+//!
+//! let task = harness.run("RuntimeExtension.returnValue(Deno.readTextFileSync(\"/data/name.txt\"));");
+//! assert_eq!(task.return_value(), Some(&json!("Ada")));
+//! ```
+
+use std::collections::VecDeque;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::overlay_fs::OverlayFs;
+use super::{
+    resolve_task_id, PermissionBroker, PermissionPrompt, PermissionsResponse, RunOptions, Task,
+    TaskEvent, TaskHandle, TaskRuntime,
+};
+
+/// The loader every task's worker gets, to test how specifiers are resolved
+/// and loaded without running a task.
+pub use super::module_loader::TypescriptModuleLoader;
+
+pub use super::snapshot::{create_startup_snapshot, set_startup_snapshot};
+
+pub use super::migrations::{migrate, Migration};
+
+pub use super::storage::evict_over_quota;
+
+pub use super::trace::TraceEntry;
+
+/// Answers permission prompts from a script set up by the test, in order.
+///
+/// A prompt for another permission than the next scripted one, or past the
+/// end of the script, is denied. Every prompt is kept for assertions. Clones
+/// share the same script.
+///
+/// Scripted prompts can also be left to `respond_to_permission_prompt`, to go
+/// through the same flow as with the frontend.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedPrompter {
+    script: Arc<Mutex<Script>>,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    // `None` leaves the prompt to `respond_to_permission_prompt`
+    answers: VecDeque<(String, Option<PermissionsResponse>)>,
+    prompts: Vec<PermissionPrompt>,
+}
+
+impl ScriptedPrompter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the answer to the next prompt, expected to ask for the `name`
+    /// permission (`read`, `net`, `bridge`, ...).
+    pub fn answer(&self, name: &str, response: PermissionsResponse) -> &Self {
+        self.push(name, Some(response))
+    }
+
+    /// Lets the next prompt, expected to ask for `name`, wait for an answer
+    /// from `respond_to_permission_prompt` like it would from the user.
+    pub fn defer(&self, name: &str) -> &Self {
+        self.push(name, None)
+    }
+
+    fn push(&self, name: &str, response: Option<PermissionsResponse>) -> &Self {
+        self.script
+            .lock()
+            .unwrap()
+            .answers
+            .push_back((name.to_string(), response));
+        self
+    }
+
+    /// Prompts seen so far, oldest first.
+    pub fn prompts(&self) -> Vec<PermissionPrompt> {
+        self.script.lock().unwrap().prompts.clone()
+    }
+
+    /// Scripted answers no prompt consumed.
+    pub fn pending_answers(&self) -> usize {
+        self.script.lock().unwrap().answers.len()
+    }
+}
+
+impl PermissionBroker for ScriptedPrompter {
+    // only deferred prompts are left to the frontend, nothing else answers
+    // them in tests
+    fn prompt(&self, _task_id: &str, prompt: &PermissionPrompt) -> Option<PermissionsResponse> {
+        let mut script = self.script.lock().unwrap();
+        script.prompts.push(prompt.clone());
+
+        match script.answers.front() {
+            Some((name, _)) if name == prompt.name() => script
+                .answers
+                .pop_front()
+                .and_then(|(_, response)| response),
+            _ => Some(PermissionsResponse::Deny),
+        }
+    }
+}
+
+/// A file system that only exists in memory, starting with nothing but the
+/// root directory. Clones share the same files.
+#[derive(Debug, Clone)]
+pub struct InMemoryFs {
+    fs: OverlayFs,
+}
+
+impl Default for InMemoryFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryFs {
+    pub fn new() -> Self {
+        Self {
+            fs: OverlayFs::in_memory(),
+        }
+    }
+
+    /// Adds a file along with its parent directories.
+    pub fn seed_file(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) {
+        self.fs.seed_file(path.as_ref(), contents.as_ref());
+    }
+
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.fs.written_file(path.as_ref())
+    }
+
+    /// Makes the tasks `runtime` starts from now on use this file system.
+    pub fn install(&self, runtime: &TaskRuntime) {
+        runtime.set_file_system(self.fs.clone());
+    }
+}
+
+// Parallel tests must not share a data directory
+static NEXT_DATA_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of its own under the temp directory, removed on drop. Tests
+/// start their runtimes on one, or keep the files they write outside of a
+/// runtime there.
+#[derive(Debug)]
+pub struct TempDataDir(PathBuf);
+
+impl TempDataDir {
+    pub fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!(
+            "deno_task_runtime_{}_{}_{}",
+            name,
+            std::process::id(),
+            NEXT_DATA_DIR.fetch_add(1, Ordering::SeqCst)
+        )))
+    }
+}
+
+impl Deref for TempDataDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDataDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDataDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A runtime with a `ScriptedPrompter`, an `InMemoryFs` and the virtual
+/// clock, in a temporary data directory removed on drop. No Tauri app is
+/// needed, task events stay in the runtime.
+#[derive(Debug)]
+pub struct TestHarness {
+    runtime: TaskRuntime,
+    prompter: ScriptedPrompter,
+    fs: InMemoryFs,
+    // after the runtime, to be removed once it's gone
+    data_dir: TempDataDir,
+}
+
+impl TestHarness {
+    pub fn new(name: &str) -> Self {
+        let data_dir = TempDataDir::new(name);
+
+        let runtime = TaskRuntime::new(name, data_dir.to_path_buf());
+        let prompter = ScriptedPrompter::new();
+        let fs = InMemoryFs::new();
+
+        runtime.set_permission_broker(prompter.clone());
+        fs.install(&runtime);
+        runtime.set_virtual_clock(true);
+
+        Self {
+            runtime,
+            prompter,
+            fs,
+            data_dir,
+        }
+    }
+
+    pub fn runtime(&self) -> &TaskRuntime {
+        &self.runtime
+    }
+
+    pub fn prompter(&self) -> &ScriptedPrompter {
+        &self.prompter
+    }
+
+    pub fn fs(&self) -> &InMemoryFs {
+        &self.fs
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Events emitted since the last call.
+    pub fn take_events(&self) -> Vec<TaskEvent> {
+        self.runtime.take_events()
+    }
+
+    /// Sends `event` like a task does, to be picked up by `take_events`.
+    pub fn emit(&self, event: TaskEvent) {
+        self.runtime.emit_task_event(event);
+    }
+
+    /// Starts `code` under a generated task id.
+    pub fn start(&self, code: &str, options: RunOptions) -> TaskHandle {
+        let task_id = resolve_task_id(None, None).unwrap();
+
+        self.runtime.run_task(&task_id, code, options).unwrap()
+    }
+
+    /// Runs `code` to the end and returns the final state of the task.
+    pub fn run(&self, code: &str) -> Task {
+        self.start(code, RunOptions::default())
+            .wait()
+            .expect("the task never started")
+    }
+}
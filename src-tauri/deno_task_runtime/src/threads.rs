@@ -0,0 +1,323 @@
+// Where and how eagerly task threads are scheduled, so heavy scripts don't
+// make the UI stutter
+
+use std::sync::Once;
+use std::time::Duration;
+
+use deno_runtime::deno_core::{v8, JsRuntime};
+
+use super::config::{self, log};
+
+// Where the UI thread goes when `reserve_ui_core` is set
+const UI_CORE: usize = 0;
+
+/// Starts V8 with `executor_threads` worker threads, before the first task
+/// does with the default. Later calls do nothing.
+pub fn init_v8_platform() {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        // 0 is one per core
+        let threads = config::executor_threads().unwrap_or(0);
+        let platform = v8::new_default_platform(threads as u32, false).make_shared();
+
+        // import assertions stay disabled, like `MainWorker` initializes V8
+        JsRuntime::init_platform(Some(platform), false);
+    });
+}
+
+/// Pins the calling thread, the UI thread, to its own core when
+/// `reserve_ui_core` is set. Called once on startup.
+pub fn pin_ui_thread() {
+    if !config::reserve_ui_core() {
+        return;
+    }
+
+    if let Err(e) = sys::set_affinity(&[UI_CORE]) {
+        log!(Error, "Failed to pin the UI thread: {}", e);
+    }
+}
+
+/// Applies the scheduling settings to the calling task thread.
+pub fn configure_task_thread(low_priority: bool) {
+    if config::reserve_ui_core() {
+        let cores: Vec<usize> = (0..core_count()).filter(|core| *core != UI_CORE).collect();
+
+        // a single core is shared anyway
+        if !cores.is_empty() {
+            if let Err(e) = sys::set_affinity(&cores) {
+                log!(Error, "Failed to keep the task off the UI core: {}", e);
+            }
+        }
+    }
+
+    if low_priority {
+        if let Err(e) = sys::lower_priority() {
+            log!(Error, "Failed to lower the task priority: {}", e);
+        }
+    }
+}
+
+fn core_count() -> usize {
+    std::thread::available_parallelism().map_or(1, |count| count.get())
+}
+
+/// CPU time used by a thread, readable from other threads while it's alive.
+#[derive(Debug)]
+pub struct ThreadCpuClock(sys::ThreadClock);
+
+impl ThreadCpuClock {
+    /// The clock of the calling thread, `None` where it can't be read.
+    pub fn current() -> Option<Self> {
+        sys::current_thread_clock().ok().map(Self)
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.0.elapsed().ok()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+    use std::time::Duration;
+
+    pub fn set_affinity(cores: &[usize]) -> io::Result<()> {
+        // SAFETY: the set is a plain bitmask, 0 is the calling thread
+        let result = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for core in cores {
+                libc::CPU_SET(*core, &mut set);
+            }
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct ThreadClock(libc::clockid_t);
+
+    pub fn current_thread_clock() -> io::Result<ThreadClock> {
+        let mut clock: libc::clockid_t = 0;
+        // SAFETY: `pthread_self` is the calling thread, alive for the call
+        let result = unsafe { libc::pthread_getcpuclockid(libc::pthread_self(), &mut clock) };
+
+        if result == 0 {
+            Ok(ThreadClock(clock))
+        } else {
+            Err(io::Error::from_raw_os_error(result))
+        }
+    }
+
+    impl ThreadClock {
+        // fails once the thread is gone
+        pub fn elapsed(&self) -> io::Result<Duration> {
+            // SAFETY: the timespec is zeroed and written by the call
+            let (result, time) = unsafe {
+                let mut time: libc::timespec = std::mem::zeroed();
+                (libc::clock_gettime(self.0, &mut time), time)
+            };
+
+            if result == 0 {
+                Ok(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+    }
+
+    pub fn lower_priority() -> io::Result<()> {
+        // the nice value is per thread on Linux, 0 is the calling thread
+        // SAFETY: no pointers involved
+        let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 10) };
+
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use std::io;
+    use std::time::Duration;
+
+    // macOS has no affinity, cores are picked by QoS
+    pub fn set_affinity(_cores: &[usize]) -> io::Result<()> {
+        Ok(())
+    }
+
+    // the Mach port of the thread
+    #[derive(Debug)]
+    pub struct ThreadClock(libc::mach_port_t);
+
+    pub fn current_thread_clock() -> io::Result<ThreadClock> {
+        // SAFETY: `pthread_self` is the calling thread, alive for the call
+        let port = unsafe { libc::pthread_mach_thread_np(libc::pthread_self()) };
+
+        Ok(ThreadClock(port))
+    }
+
+    impl ThreadClock {
+        pub fn elapsed(&self) -> io::Result<Duration> {
+            let mut count = libc::THREAD_BASIC_INFO_COUNT;
+            // SAFETY: the info is zeroed and `count` is its size in integers
+            let (result, info) = unsafe {
+                let mut info: libc::thread_basic_info = std::mem::zeroed();
+                let result = libc::thread_info(
+                    self.0,
+                    libc::THREAD_BASIC_INFO as libc::thread_flavor_t,
+                    &mut info as *mut _ as libc::thread_info_t,
+                    &mut count,
+                );
+                (result, info)
+            };
+
+            if result != libc::KERN_SUCCESS {
+                return Err(io::Error::other(format!("thread_info failed: {}", result)));
+            }
+
+            let time = |time: libc::time_value_t| {
+                Duration::new(time.seconds as u64, time.microseconds as u32 * 1000)
+            };
+            Ok(time(info.user_time) + time(info.system_time))
+        }
+    }
+
+    pub fn lower_priority() -> io::Result<()> {
+        // SAFETY: applies to the calling thread, no pointers involved
+        let result =
+            unsafe { libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_UTILITY, 0) };
+
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::from_raw_os_error(result))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::io;
+    use std::time::Duration;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, HANDLE};
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, GetCurrentThreadId, GetThreadTimes, OpenThread, SetThreadAffinityMask,
+        SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL, THREAD_QUERY_LIMITED_INFORMATION,
+    };
+
+    // a real handle, the pseudo one is the calling thread wherever it's used
+    #[derive(Debug)]
+    pub struct ThreadClock(HANDLE);
+
+    // SAFETY: thread handles can be used from any thread
+    unsafe impl Send for ThreadClock {}
+    unsafe impl Sync for ThreadClock {}
+
+    pub fn current_thread_clock() -> io::Result<ThreadClock> {
+        // SAFETY: no pointers involved
+        let handle =
+            unsafe { OpenThread(THREAD_QUERY_LIMITED_INFORMATION, 0, GetCurrentThreadId()) };
+
+        if handle.is_null() {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ThreadClock(handle))
+        }
+    }
+
+    impl ThreadClock {
+        pub fn elapsed(&self) -> io::Result<Duration> {
+            // SAFETY: the handle is open until dropped, the times are zeroed
+            // and written by the call
+            let (result, kernel, user) = unsafe {
+                let mut times: [FILETIME; 4] = std::mem::zeroed();
+                let [creation, exit, kernel, user] = &mut times;
+                let result = GetThreadTimes(self.0, creation, exit, kernel, user);
+                (result, times[2], times[3])
+            };
+
+            if result == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            // in 100ns units
+            let time = |time: FILETIME| {
+                let ticks = (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64;
+                Duration::from_nanos(ticks * 100)
+            };
+            Ok(time(kernel) + time(user))
+        }
+    }
+
+    impl Drop for ThreadClock {
+        fn drop(&mut self) {
+            // SAFETY: opened by `current_thread_clock`, closed once
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    pub fn set_affinity(cores: &[usize]) -> io::Result<()> {
+        // only the first 64 cores, the ones of the thread's processor group
+        let mask = cores
+            .iter()
+            .filter(|core| **core < usize::BITS as usize)
+            .fold(0usize, |mask, core| mask | 1 << *core);
+
+        // SAFETY: the pseudo handle of the current thread is always valid
+        let previous = unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) };
+
+        if previous != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub fn lower_priority() -> io::Result<()> {
+        // SAFETY: the pseudo handle of the current thread is always valid
+        let result = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL) };
+
+        if result != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod sys {
+    use std::io;
+    use std::time::Duration;
+
+    #[derive(Debug)]
+    pub struct ThreadClock;
+
+    pub fn current_thread_clock() -> io::Result<ThreadClock> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    impl ThreadClock {
+        pub fn elapsed(&self) -> io::Result<Duration> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+    }
+
+    pub fn set_affinity(_cores: &[usize]) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn lower_priority() -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::Instant;
+
+/// Where the wall time of a task's latest attempt went, to tell the runtime's
+/// share from the script's. A phase is `None` until the attempt gets through
+/// it.
+#[derive(
+    Debug, Clone, Default, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema,
+)]
+pub struct TaskTimings {
+    /// From its submission to its start, 0 unless it waited for a lane slot
+    #[ts(type = "number")]
+    queue_wait_ms: u64,
+    /// Creating the worker and applying the run options to it
+    #[ts(type = "number | null")]
+    bootstrap_ms: Option<u64>,
+    /// Whether the worker was prewarmed, its bootstrap then only adopts it
+    prewarmed: bool,
+    /// Resolving, fetching and transpiling the module graph
+    #[ts(type = "number | null")]
+    module_load_ms: Option<u64>,
+    /// Evaluating the main module
+    #[ts(type = "number | null")]
+    execution_ms: Option<u64>,
+    /// Running the timers, promises and ops left pending once evaluated
+    #[ts(type = "number | null")]
+    event_loop_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TimingPhase {
+    Bootstrap,
+    ModuleLoad,
+    Execution,
+    EventLoop,
+}
+
+impl TaskTimings {
+    pub fn queue_wait_ms(&self) -> u64 {
+        self.queue_wait_ms
+    }
+
+    pub fn bootstrap_ms(&self) -> Option<u64> {
+        self.bootstrap_ms
+    }
+
+    pub fn prewarmed(&self) -> bool {
+        self.prewarmed
+    }
+
+    pub fn module_load_ms(&self) -> Option<u64> {
+        self.module_load_ms
+    }
+
+    pub fn execution_ms(&self) -> Option<u64> {
+        self.execution_ms
+    }
+
+    pub fn event_loop_ms(&self) -> Option<u64> {
+        self.event_loop_ms
+    }
+
+    pub(crate) fn set_queue_wait(&mut self, queue_wait_ms: u64) {
+        self.queue_wait_ms = queue_wait_ms;
+    }
+
+    pub(crate) fn set_prewarmed(&mut self, prewarmed: bool) {
+        self.prewarmed = prewarmed;
+    }
+
+    pub(crate) fn record(&mut self, phase: TimingPhase, since: Instant) {
+        let elapsed_ms = Some(since.elapsed().as_millis() as u64);
+
+        match phase {
+            TimingPhase::Bootstrap => self.bootstrap_ms = elapsed_ms,
+            TimingPhase::ModuleLoad => self.module_load_ms = elapsed_ms,
+            TimingPhase::Execution => self.execution_ms = elapsed_ms,
+            TimingPhase::EventLoop => self.event_loop_ms = elapsed_ms,
+        }
+    }
+
+    // A retry goes through the phases again, its queue wait was the first's
+    pub(crate) fn restart(&mut self) {
+        *self = Self {
+            queue_wait_ms: self.queue_wait_ms,
+            ..Self::default()
+        };
+    }
+}
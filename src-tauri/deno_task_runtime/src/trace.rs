@@ -0,0 +1,165 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Emitter};
+
+use super::config::{self, log};
+use super::event_log::SequencedEvent;
+
+// Idle stretches of a trace are shortened to this when replaying, what the
+// UI does between two events doesn't depend on how long it waited
+const MAX_REPLAY_GAP: Duration = Duration::from_secs(1);
+
+// Arguments left out of traces, which are collected from users' bug reports,
+// by command. Named as the frontend sends them, in camelCase
+const REDACTED_ARGS: &[(&str, &[&str])] = &[
+    ("sign_script_bundle", &["privateKey"]),
+    ("write_task_stdin", &["data"]),
+];
+
+/// A line of a trace file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceEntry {
+    Command {
+        at_ms: u64,
+        command: String,
+        args: serde_json::Value,
+    },
+    Event {
+        at_ms: u64,
+        profile: String,
+        name: String,
+        payload: serde_json::Value,
+    },
+}
+
+impl TraceEntry {
+    /// An invocation of `command`, its arguments in `REDACTED_ARGS` replaced.
+    pub fn command(command: &str, args: &serde_json::Value) -> Self {
+        let redacted = REDACTED_ARGS
+            .iter()
+            .find(|(name, _)| *name == command)
+            .map_or(&[][..], |(_, args)| args);
+
+        let mut args = args.clone();
+        if let Some(args) = args.as_object_mut() {
+            for name in redacted {
+                if let Some(value) = args.get_mut(*name) {
+                    *value = "[redacted]".into();
+                }
+            }
+        }
+
+        TraceEntry::Command {
+            at_ms: now_ms(),
+            command: command.to_string(),
+            args,
+        }
+    }
+
+    fn at_ms(&self) -> u64 {
+        match self {
+            TraceEntry::Command { at_ms, .. } | TraceEntry::Event { at_ms, .. } => *at_ms,
+        }
+    }
+}
+
+// Entries of concurrent commands and listeners must not interleave
+static TRACE_WRITES: Mutex<()> = Mutex::new(());
+
+/// Appends a command invocation to the trace file, if one is configured.
+pub fn record_command(command: &str, args: &serde_json::Value) {
+    record(|| TraceEntry::command(command, args));
+}
+
+pub fn record_event(profile: &str, event: &SequencedEvent) {
+    record(|| TraceEntry::Event {
+        at_ms: now_ms(),
+        profile: profile.to_string(),
+        name: event.event().name().to_string(),
+        payload: serde_json::to_value(event).unwrap_or_default(),
+    });
+}
+
+fn record(entry: impl FnOnce() -> TraceEntry) {
+    let Some(path) = config::get().trace_file().map(Path::to_path_buf) else {
+        return;
+    };
+
+    let mut line = serde_json::to_string(&entry()).unwrap();
+    line.push('\n');
+
+    let _guard = TRACE_WRITES.lock().unwrap();
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+
+    if let Err(e) = result {
+        log!(
+            Error,
+            "Failed to write to the trace {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// Emits the events of a trace to the frontend again, in order and with the
+/// gaps between them (see `MAX_REPLAY_GAP`). Commands are only there for
+/// context, they aren't invoked again.
+///
+/// Returns the number of events replayed.
+pub async fn replay_trace(app_handle: &AppHandle, path: &Path) -> Result<usize, String> {
+    let entries = read_trace(path)?;
+
+    let mut replayed = 0;
+    let mut last_at_ms = None;
+
+    for entry in entries {
+        let at_ms = entry.at_ms();
+        let TraceEntry::Event { name, payload, .. } = entry else {
+            continue;
+        };
+
+        if let Some(last_at_ms) = last_at_ms {
+            let gap = Duration::from_millis(at_ms.saturating_sub(last_at_ms));
+            tokio::time::sleep(gap.min(MAX_REPLAY_GAP)).await;
+        }
+        last_at_ms = Some(at_ms);
+
+        app_handle.emit(&name, payload).map_err(|e| e.to_string())?;
+        replayed += 1;
+    }
+
+    Ok(replayed)
+}
+
+fn read_trace(path: &Path) -> Result<Vec<TraceEntry>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut entries = vec![];
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| format!("{}:{}: {}", path.display(), index + 1, e))?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
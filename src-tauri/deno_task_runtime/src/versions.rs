@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use deno_runtime::deno_core::v8;
+use deno_runtime::deno_core::ModuleSpecifier;
+
+// The Deno release built on the embedded deno_runtime, keep in sync when
+// bumping it in Cargo.toml
+pub const DENO_VERSION: &str = "2.1.2";
+
+// e.g. `// @min-deno-version 2.1.0`, in the comments at the top of a module
+const MIN_DENO_VERSION_DIRECTIVE: &str = "@min-deno-version";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RuntimeVersions {
+    /// Deno release the embedded runtime matches
+    deno: String,
+    deno_runtime: String,
+    v8: String,
+}
+
+pub fn get_runtime_versions() -> RuntimeVersions {
+    RuntimeVersions {
+        deno: DENO_VERSION.to_string(),
+        deno_runtime: env!("DENO_RUNTIME_VERSION").to_string(),
+        v8: v8::V8::get_version().to_string(),
+    }
+}
+
+/// A problem found with a task's code that doesn't stop it from running.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskDiagnostic {
+    specifier: String,
+    message: String,
+}
+
+/// Diagnostics found by the module loader, drained into the task by the runtime.
+pub type PendingDiagnostics = Rc<RefCell<Vec<TaskDiagnostic>>>;
+
+/// Warns when a module declares a minimum Deno version newer than the
+/// embedded one. The module still runs, it may just fail on a missing API.
+pub fn check_min_deno_version(specifier: &ModuleSpecifier, code: &str) -> Option<TaskDiagnostic> {
+    let required = code
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with("//"))
+        .find_map(|line| {
+            line.trim_start_matches('/')
+                .trim()
+                .strip_prefix(MIN_DENO_VERSION_DIRECTIVE)
+                .map(|version| version.trim().to_string())
+        })?;
+
+    let message = match (parse_version(&required), parse_version(DENO_VERSION)) {
+        (Some(required_version), Some(current)) if required_version <= current => return None,
+        (Some(_), _) => format!(
+            "requires Deno {} but the embedded runtime matches Deno {}",
+            required, DENO_VERSION
+        ),
+        (None, _) => format!("invalid minimum Deno version {:?}", required),
+    };
+
+    Some(TaskDiagnostic {
+        specifier: specifier.to_string(),
+        message,
+    })
+}
+
+// "2", "2.1" and "v2.1.0" are all accepted, missing parts are zero
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+    let patch = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((major, minor, patch))
+}
@@ -0,0 +1,139 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use deno_runtime::deno_core::v8::IsolateHandle;
+
+use super::config::log;
+use super::threads::ThreadCpuClock;
+use super::{TaskRuntime, TaskState};
+
+// How often the CPU time of a task with a budget is checked
+const CPU_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Ends a task that runs for longer than its `timeout_ms`, or uses more CPU
+/// time than its `cpu_time_limit_ms`. Disarmed when dropped.
+#[derive(Debug)]
+pub struct Watchdog {
+    _disarm: mpsc::Sender<()>,
+}
+
+impl Watchdog {
+    pub fn arm(
+        runtime: &TaskRuntime,
+        task_id: &str,
+        isolate: IsolateHandle,
+        timeout_ms: u64,
+    ) -> Self {
+        let (disarm, disarmed) = mpsc::channel::<()>();
+        let runtime = runtime.clone();
+        let task_id = task_id.to_string();
+
+        thread::spawn(move || {
+            // dropping the sender disconnects it
+            if disarmed.recv_timeout(Duration::from_millis(timeout_ms))
+                != Err(RecvTimeoutError::Timeout)
+            {
+                return;
+            }
+
+            log!(Info, "Task {} timed out after {}ms", task_id, timeout_ms);
+            end_task(
+                &runtime,
+                &task_id,
+                &isolate,
+                TaskState::TimedOut { timeout_ms },
+            );
+        });
+
+        Self { _disarm: disarm }
+    }
+
+    /// Watches the CPU clock of the calling thread, which must be the task's.
+    pub fn arm_cpu_budget(
+        runtime: &TaskRuntime,
+        task_id: &str,
+        isolate: IsolateHandle,
+        cpu_time_limit_ms: u64,
+    ) -> Result<Self, String> {
+        let clock =
+            ThreadCpuClock::current().ok_or("CPU time limits aren't supported on this platform")?;
+        // the thread may have run other tasks before
+        let started = clock.elapsed().unwrap_or_default();
+        let limit = Duration::from_millis(cpu_time_limit_ms);
+
+        let (disarm, disarmed) = mpsc::channel::<()>();
+        let runtime = runtime.clone();
+        let task_id = task_id.to_string();
+
+        thread::spawn(move || loop {
+            if disarmed.recv_timeout(CPU_POLL_INTERVAL) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+            let Some(elapsed) = clock.elapsed() else {
+                return;
+            };
+            if elapsed.saturating_sub(started) < limit {
+                continue;
+            }
+
+            log!(Info, "Task {} used up its CPU time", task_id);
+            let error = format!("CPU time limit of {}ms exceeded", cpu_time_limit_ms);
+            end_task(
+                &runtime,
+                &task_id,
+                &isolate,
+                TaskState::error(error, Vec::new()),
+            );
+            return;
+        });
+
+        Ok(Self { _disarm: disarm })
+    }
+}
+
+fn end_task(runtime: &TaskRuntime, task_id: &str, isolate: &IsolateHandle, state: TaskState) {
+    let task = runtime
+        .with_task(task_id, move |task| {
+            task.transition(state).then(|| task.clone())
+        })
+        .flatten();
+    // finished or stopped meanwhile
+    let Some(task) = task else {
+        return;
+    };
+
+    runtime.emit_task_state_changed(task);
+
+    // a script stuck in a loop never yields to the event loop, and one
+    // waiting on a prompt is blocked until it's answered
+    isolate.terminate_execution();
+    runtime
+        .inner
+        .permission_channels
+        .lock()
+        .unwrap()
+        .remove(task_id);
+
+    let stop_tx = runtime
+        .inner
+        .shutdown_channels
+        .lock()
+        .unwrap()
+        .remove(task_id);
+    if let Some(stop_tx) = stop_tx {
+        let _ = stop_tx.send(());
+    }
+
+    // held in an interrupt until resumed
+    let switch = runtime
+        .inner
+        .pause_switches
+        .lock()
+        .unwrap()
+        .get(task_id)
+        .cloned();
+    if let Some(switch) = switch {
+        switch.resume();
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::config::log;
+use super::task_ids::resolve_task_id;
+use super::{RunOptions, RuntimeState, TaskRuntime};
+
+// Quiet time after a change before the script runs again when none is
+// given, editors save in several writes
+const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
+/// Paths watched with `TaskRuntime::watch_task`, and the runs of its script.
+/// Each run is a task of its own with the watch id as its namespace.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ts_rs::TS, schemars::JsonSchema)]
+pub struct TaskWatch {
+    id: String,
+    #[ts(type = "string[]")]
+    paths: Vec<PathBuf>,
+    /// Changes closer than this are batched into a single run
+    #[ts(type = "number")]
+    debounce_ms: u64,
+    /// Task of the current or last run
+    last_task_id: Option<String>,
+    runs: usize,
+}
+
+impl TaskWatch {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn last_task_id(&self) -> Option<&str> {
+        self.last_task_id.as_deref()
+    }
+
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    pub fn debounce_ms(&self) -> u64 {
+        self.debounce_ms
+    }
+}
+
+#[derive(Debug)]
+struct Watch {
+    info: TaskWatch,
+    code: String,
+    options: RunOptions,
+    // unwatched when dropped, which ends the debounce thread
+    _watcher: RecommendedWatcher,
+}
+
+/// The watches of a runtime, they last until unwatched or the app closes.
+#[derive(Debug, Default)]
+pub struct Watches {
+    watches: HashMap<String, Watch>,
+}
+
+impl Watches {
+    /// Sorted by id, so in the order they were started.
+    pub fn list(&self) -> Vec<TaskWatch> {
+        let mut watches: Vec<TaskWatch> = self
+            .watches
+            .values()
+            .map(|watch| watch.info.clone())
+            .collect();
+        watches.sort_by(|a, b| a.id.cmp(&b.id));
+
+        watches
+    }
+}
+
+/// Runs `code` once and again whenever one of `paths` changes, directories
+/// recursively, once per `debounce_ms` of changes. Returns the watch id.
+pub fn watch(
+    runtime: &TaskRuntime,
+    paths: Vec<PathBuf>,
+    code: &str,
+    options: RunOptions,
+    debounce_ms: Option<u64>,
+) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No paths to watch".to_string());
+    }
+    let debounce_ms = debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS);
+    if debounce_ms == 0 {
+        return Err("debounce_ms must be at least 1".to_string());
+    }
+    let id = resolve_task_id(None, None)?;
+
+    let (changes_tx, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // reads, the script's own included, don't count
+        let Ok(event) = event else {
+            return;
+        };
+        if matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            let _ = changes_tx.send(event.paths);
+        }
+    })
+    .map_err(|e| format!("Failed to start watching: {}", e))?;
+
+    for path in &paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+    }
+
+    runtime.inner.watches.lock().unwrap().watches.insert(
+        id.clone(),
+        Watch {
+            info: TaskWatch {
+                id: id.clone(),
+                paths,
+                debounce_ms,
+                last_task_id: None,
+                runs: 0,
+            },
+            code: code.to_string(),
+            options,
+            _watcher: watcher,
+        },
+    );
+
+    run(runtime, &id, Vec::new());
+
+    let inner: Weak<RuntimeState> = Arc::downgrade(&runtime.inner);
+    let watch_id = id.clone();
+    let debounce = Duration::from_millis(debounce_ms);
+    thread::spawn(move || {
+        while let Ok(paths) = changes.recv() {
+            // a path changed several times is in the batch once
+            let mut changed: BTreeSet<PathBuf> = paths.into_iter().collect();
+            while let Ok(paths) = changes.recv_timeout(debounce) {
+                changed.extend(paths);
+            }
+
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            run(
+                &TaskRuntime { inner },
+                &watch_id,
+                changed.into_iter().collect(),
+            );
+        }
+    });
+
+    Ok(id)
+}
+
+pub fn unwatch(runtime: &TaskRuntime, id: &str) -> Result<(), String> {
+    let watch = runtime.inner.watches.lock().unwrap().watches.remove(id);
+
+    match watch {
+        Some(_) => Ok(()),
+        None => Err(format!("Watch not found: {}", id)),
+    }
+}
+
+// Starts the next run of the watch with the paths that changed as its input,
+// stopping the last one if it's still going
+fn run(runtime: &TaskRuntime, id: &str, changed: Vec<PathBuf>) {
+    let next = {
+        let watches = runtime.inner.watches.lock().unwrap();
+        watches.watches.get(id).map(|watch| {
+            (
+                watch.code.clone(),
+                watch.options.clone(),
+                watch.info.last_task_id.clone(),
+            )
+        })
+    };
+    // unwatched meanwhile
+    let Some((code, mut options, last_task_id)) = next else {
+        return;
+    };
+
+    if let Some(last_task_id) = last_task_id {
+        let running = runtime
+            .get_task_state(&last_task_id)
+            .is_some_and(|task| !task.is_finished());
+        if running {
+            let _ = runtime.stop_task(&last_task_id);
+        }
+    }
+
+    options.set_input(serde_json::json!({ "changed": changed }));
+    let result = resolve_task_id(None, Some(id))
+        .and_then(|task_id| runtime.run_task(&task_id, &code, options).map(|_| task_id));
+
+    match result {
+        Ok(task_id) => {
+            if let Some(watch) = runtime.inner.watches.lock().unwrap().watches.get_mut(id) {
+                watch.info.last_task_id = Some(task_id);
+                watch.info.runs += 1;
+            }
+        }
+        Err(e) => log!(Error, "Watch {} failed to start a run: {}", id, e),
+    }
+}
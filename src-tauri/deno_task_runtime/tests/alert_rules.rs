@@ -0,0 +1,96 @@
+use std::thread;
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{AlertRule, RunOptions, TaskEvent, TaskRuntime};
+use serde_json::json;
+
+fn run_and_wait(runtime: &TaskRuntime, script: &str) {
+    runtime
+        .run_saved_script(script, script, None, RunOptions::default())
+        .unwrap();
+    while runtime.has_running_tasks() {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn rule(value: serde_json::Value) -> AlertRule {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn fires_rules_on_consecutive_failures() {
+    let dir = TempDataDir::new("alert_rules");
+    let runtime = TaskRuntime::new("alert_rules", dir.to_path_buf());
+    runtime
+        .save_script("flaky", "throw new Error(\"disk full\");")
+        .unwrap();
+    runtime
+        .save_script("cleanup", "RuntimeExtension.returnValue(\"cleaned\");")
+        .unwrap();
+
+    assert!(runtime
+        .set_alert_rule(rule(json!({
+            "id": "never",
+            "script": "flaky",
+            "consecutive_failures": 0,
+            "action": { "kind": "notify", "message": null },
+        })))
+        .is_err());
+    runtime
+        .set_alert_rule(rule(json!({
+            "id": "notify",
+            "script": "flaky",
+            "consecutive_failures": 2,
+            "action": { "kind": "notify", "message": "flaky keeps failing" },
+        })))
+        .unwrap();
+    runtime
+        .set_alert_rule(rule(json!({
+            "id": "repair",
+            "script": "flaky",
+            "consecutive_failures": 3,
+            "action": { "kind": "run_script", "script": "cleanup" },
+        })))
+        .unwrap();
+
+    run_and_wait(&runtime, "flaky");
+    run_and_wait(&runtime, "flaky");
+    let alerts: Vec<_> = runtime
+        .take_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            TaskEvent::Alert(alert) => Some(serde_json::to_value(alert).unwrap()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["rule_id"], "notify");
+    assert_eq!(alerts[0]["message"], "flaky keeps failing");
+    assert!(alerts[0]["error"].as_str().unwrap().contains("disk full"));
+
+    run_and_wait(&runtime, "flaky");
+    // started by the rule once the third run is done
+    let mut cleaned = false;
+    for _ in 0..500 {
+        cleaned |= runtime.take_events().into_iter().any(|event| {
+            matches!(event, TaskEvent::StateChanged(task) if task.return_value() == Some(&json!("cleaned")))
+        });
+        if cleaned {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(cleaned);
+
+    // rules are kept on disk
+    let reopened = TaskRuntime::new("alert_rules", dir.to_path_buf());
+    let ids: Vec<_> = reopened
+        .list_alert_rules()
+        .iter()
+        .map(|rule| serde_json::to_value(rule).unwrap()["id"].clone())
+        .collect();
+    assert_eq!(ids, ["notify", "repair"]);
+    reopened.remove_alert_rule("notify").unwrap();
+    assert!(reopened.remove_alert_rule("notify").is_err());
+}
@@ -0,0 +1,188 @@
+use deno_core::error::AnyError;
+use deno_core::ModuleSpecifier;
+use deno_task_runtime::test_support::{TempDataDir, TestHarness};
+use deno_task_runtime::{
+    generate_bundle_signing_key, sign_script_bundle, ModuleLoader, OpGrant, RunOptions,
+};
+use serde_json::json;
+
+const REMOTE_MODULE: &str = "https://example.invalid/answer.ts";
+
+// Serves the remote module with the given source
+struct Remote(&'static str);
+
+impl ModuleLoader for Remote {
+    fn load_source(&self, specifier: &ModuleSpecifier) -> Result<Option<String>, AnyError> {
+        if specifier.as_str() != REMOTE_MODULE {
+            return Ok(None);
+        }
+
+        Ok(Some(self.0.to_string()))
+    }
+}
+
+// Bundles are written outside the runtimes' data directories, like the user's
+fn bundle_dir(name: &str) -> TempDataDir {
+    let dir = TempDataDir::new(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn script() -> String {
+    format!(
+        "import {{ answer }} from \"{}\";\nRuntimeExtension.returnValue(answer);",
+        REMOTE_MODULE
+    )
+}
+
+#[test]
+fn imports_exported_bundles() {
+    let dir = bundle_dir("bundle_answer");
+    let path = dir.join("answer.denotask");
+
+    let exporter = TestHarness::new("bundle_export");
+    exporter
+        .runtime()
+        .set_module_loader(Remote("export const answer: number = 42;"));
+    exporter.runtime().save_script("answer", &script()).unwrap();
+    exporter
+        .runtime()
+        .set_script_grants("answer", Some(vec![OpGrant::TaskResult]))
+        .unwrap();
+    exporter
+        .runtime()
+        .export_script_bundle("answer", &path)
+        .unwrap();
+
+    let bundle: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(bundle["format"], json!("denotask"));
+    assert_eq!(bundle["metadata"]["name"], json!("answer"));
+    assert!(bundle["lockfile"][REMOTE_MODULE].is_string(), "{}", bundle);
+
+    let importer = TestHarness::new("bundle_import");
+    let runtime = importer.runtime();
+    runtime.set_module_loader(Remote("export const answer: number = 42;"));
+    assert_eq!(runtime.import_script_bundle(&path).unwrap(), "answer");
+    assert_eq!(runtime.get_script("answer").unwrap(), script());
+    assert_eq!(
+        runtime.get_script_grants("answer").unwrap(),
+        Some(vec![OpGrant::TaskResult])
+    );
+
+    let task = runtime
+        .run_saved_script("answer_run", "answer", None, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!(42)));
+
+    // a saved script isn't replaced
+    assert!(runtime.import_script_bundle(&path).is_err());
+}
+
+#[test]
+fn refuses_modules_not_matching_the_lockfile() {
+    let dir = bundle_dir("bundle_tampered");
+    let path = dir.join("tampered.denotask");
+
+    let exporter = TestHarness::new("bundle_tampered_export");
+    exporter
+        .runtime()
+        .set_module_loader(Remote("export const answer: number = 42;"));
+    exporter.runtime().save_script("answer", &script()).unwrap();
+    exporter
+        .runtime()
+        .export_script_bundle("answer", &path)
+        .unwrap();
+
+    let importer = TestHarness::new("bundle_tampered_import");
+    let runtime = importer.runtime();
+    runtime.set_module_loader(Remote("export const answer: number = 666;"));
+    runtime.import_script_bundle(&path).unwrap();
+
+    let task = runtime
+        .run_saved_script("answer_run", "answer", None, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error()
+            .contains("doesn't match the hash in the lockfile"),
+        "{}",
+        task.error()
+    );
+
+    // nor re-exported with the changed module
+    assert!(runtime.export_script_bundle("answer", &path).is_err());
+}
+
+#[test]
+fn refuses_scripts_importing_local_modules() {
+    let harness = TestHarness::new("bundle_local");
+    let runtime = harness.runtime();
+    let dir = bundle_dir("bundle_local");
+
+    runtime
+        .save_script("local", "import \"./helpers.ts\";")
+        .unwrap();
+    assert!(runtime
+        .export_script_bundle("local", &dir.join("local.denotask"))
+        .is_err());
+}
+
+#[test]
+fn checks_the_signature_of_signed_bundles() {
+    let dir = bundle_dir("bundle_signed");
+    let path = dir.join("signed.denotask");
+    let key = generate_bundle_signing_key();
+
+    let exporter = TestHarness::new("bundle_signed_export");
+    exporter
+        .runtime()
+        .save_script("signed", "RuntimeExtension.returnValue(\"signed\");")
+        .unwrap();
+    exporter
+        .runtime()
+        .export_script_bundle("signed", &path)
+        .unwrap();
+    sign_script_bundle(&path, &key.private_key).unwrap();
+
+    let importer = TestHarness::new("bundle_signed_import");
+    let runtime = importer.runtime();
+    let error = runtime.import_script_bundle(&path).unwrap_err();
+    assert!(error.contains("isn't trusted"), "{}", error);
+
+    runtime.add_trusted_key("team", &key.public_key).unwrap();
+    assert_eq!(runtime.list_trusted_keys()[0].name(), "team");
+    runtime.import_script_bundle(&path).unwrap();
+
+    let task = runtime
+        .run_saved_script("signed_run", "signed", None, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.return_value(), Some(&json!("signed")));
+
+    // revoked
+    runtime.remove_trusted_key("team").unwrap();
+    assert!(runtime
+        .run_saved_script("signed_revoked", "signed", None, RunOptions::default())
+        .is_err());
+
+    // changed after it was signed
+    let mut bundle: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    bundle["source"] = json!("RuntimeExtension.returnValue(\"tampered\");");
+    std::fs::write(&path, bundle.to_string()).unwrap();
+
+    let tampered = TestHarness::new("bundle_tampered_signature");
+    tampered
+        .runtime()
+        .add_trusted_key("team", &key.public_key)
+        .unwrap();
+    let error = tampered.runtime().import_script_bundle(&path).unwrap_err();
+    assert!(error.contains("signature doesn't match"), "{}", error);
+    assert!(tampered.runtime().list_scripts().is_empty());
+}
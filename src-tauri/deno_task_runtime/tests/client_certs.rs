@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use deno_core::error::AnyError;
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{CertificateStore, RunOptions, TaskRuntime};
+use serde_json::json;
+
+const CERT: &str = include_str!("fixtures/client_cert.pem");
+const KEY: &str = include_str!("fixtures/client_key.pem");
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Runs a task presenting `certificate`, returns its error
+fn run_with(runtime: &TaskRuntime, certificate: serde_json::Value) -> String {
+    let options: RunOptions =
+        serde_json::from_value(json!({ "client_certificate": certificate })).unwrap();
+    let task = runtime
+        .run_task("client_cert", "RuntimeExtension.returnValue(1);", options)
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    task.error().to_string()
+}
+
+struct Keychain;
+
+impl CertificateStore for Keychain {
+    fn load(&self, name: &str) -> Result<Option<(String, String)>, AnyError> {
+        Ok((name == "internal-api").then(|| (CERT.to_string(), KEY.to_string())))
+    }
+}
+
+#[test]
+fn loads_client_certificates() {
+    let dir = TempDataDir::new("client_certs");
+    let runtime = TaskRuntime::new("client_certs", dir.to_path_buf());
+
+    let files = json!({
+        "kind": "files",
+        "cert_path": fixture("client_cert.pem"),
+        "key_path": fixture("client_key.pem"),
+    });
+    assert_eq!(run_with(&runtime, files), "");
+    assert_eq!(
+        run_with(&runtime, json!({ "kind": "pem", "cert": CERT, "key": KEY })),
+        ""
+    );
+
+    let keychain = json!({ "kind": "keychain", "name": "internal-api" });
+    let error = run_with(&runtime, keychain.clone());
+    assert!(error.contains("No certificate store"), "{}", error);
+
+    runtime.set_certificate_store(Keychain);
+    assert_eq!(run_with(&runtime, keychain), "");
+    let error = run_with(&runtime, json!({ "kind": "keychain", "name": "other" }));
+    assert!(error.contains("Certificate not found: other"), "{}", error);
+}
+
+#[test]
+fn fails_tasks_with_invalid_certificates() {
+    let dir = TempDataDir::new("invalid_client_certs");
+    let runtime = TaskRuntime::new("invalid_client_certs", dir.to_path_buf());
+
+    // a key where the certificate should be
+    let error = run_with(&runtime, json!({ "kind": "pem", "cert": KEY, "key": KEY }));
+    assert!(error.contains("Invalid client certificate"), "{}", error);
+
+    let missing = json!({
+        "kind": "files",
+        "cert_path": fixture("missing.pem"),
+        "key_path": fixture("client_key.pem"),
+    });
+    let error = run_with(&runtime, missing);
+    assert!(error.contains("Failed to read"), "{}", error);
+}
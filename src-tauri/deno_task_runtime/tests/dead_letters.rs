@@ -0,0 +1,77 @@
+use std::thread;
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{RunOptions, TaskRuntime};
+use serde_json::{json, Value};
+
+fn wait_for_tasks(runtime: &TaskRuntime) {
+    while runtime.has_running_tasks() {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn dead_letters(runtime: &TaskRuntime) -> Vec<Value> {
+    runtime
+        .list_dead_letters()
+        .iter()
+        .map(|letter| serde_json::to_value(letter).unwrap())
+        .collect()
+}
+
+#[test]
+fn keeps_failed_background_runs_for_retrying() {
+    let dir = TempDataDir::new("dead_letters");
+    let runtime = TaskRuntime::new("dead_letters", dir.to_path_buf());
+    runtime
+        .save_script(
+            "sync",
+            "throw new Error(`offline, ${RuntimeExtension.args.attempt}`);",
+        )
+        .unwrap();
+
+    // interactive runs fail in front of the user
+    runtime
+        .run_task(
+            "interactive",
+            "throw new Error(\"boom\");",
+            RunOptions::default(),
+        )
+        .unwrap();
+    wait_for_tasks(&runtime);
+    assert!(runtime.list_dead_letters().is_empty());
+
+    let background: RunOptions = serde_json::from_value(json!({ "lane": "background" })).unwrap();
+    runtime
+        .run_saved_script(
+            "nightly-sync",
+            "sync",
+            Some(json!({ "attempt": 1 })),
+            background,
+        )
+        .unwrap();
+    wait_for_tasks(&runtime);
+
+    let letters = dead_letters(&runtime);
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0]["task_id"], "nightly-sync");
+    assert_eq!(letters[0]["script"], "sync");
+    assert_eq!(letters[0]["args"], json!({ "attempt": 1 }));
+    assert!(letters[0]["error"].as_str().unwrap().contains("offline, 1"));
+
+    // fails again, with the same args
+    let id = letters[0]["id"].as_str().unwrap();
+    runtime.retry_dead_letter(id).unwrap();
+    wait_for_tasks(&runtime);
+
+    let letters = dead_letters(&runtime);
+    assert_eq!(letters.len(), 1);
+    assert_ne!(letters[0]["id"], id);
+    assert!(letters[0]["error"].as_str().unwrap().contains("offline, 1"));
+    assert!(runtime.retry_dead_letter(id).is_err());
+
+    runtime
+        .dismiss_dead_letter(letters[0]["id"].as_str().unwrap())
+        .unwrap();
+    assert!(runtime.list_dead_letters().is_empty());
+}
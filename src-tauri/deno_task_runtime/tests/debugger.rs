@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{DebugPause, DebugStep, RunOptions, TaskRuntime};
+use serde_json::json;
+
+// Until the task pauses at `line` of its code
+fn paused_at(runtime: &TaskRuntime, task_id: &str, line: u32) -> DebugPause {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let pause = runtime.get_debug_pause(task_id);
+        if let Some(pause) = pause.filter(|pause| pause.call_frames()[0].line() == line) {
+            return pause;
+        }
+        assert!(Instant::now() < deadline, "never paused at line {}", line);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn steps_through_a_task() {
+    let dir = TempDataDir::new("debugger");
+    let runtime = TaskRuntime::new("debugger", dir.to_path_buf());
+
+    let handle = runtime
+        .debug_run_task(
+            "debugged",
+            "let x = 1;\n\
+             x += 1;\n\
+             const double = (n) => n * 2;\n\
+             const y = double(x);\n\
+             RuntimeExtension.returnValue(y);",
+            &[5],
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // before anything ran
+    let pause = paused_at(&runtime, "debugged", 1);
+    assert_eq!(pause.call_frames()[0].module(), None);
+    assert_eq!(runtime.debug_evaluate("debugged", "1 + 1"), Ok(json!(2)));
+
+    runtime.debug_step("debugged", DebugStep::Over).unwrap();
+    paused_at(&runtime, "debugged", 2);
+    assert_eq!(runtime.debug_evaluate("debugged", "x"), Ok(json!(1)));
+
+    runtime.debug_continue("debugged").unwrap();
+    paused_at(&runtime, "debugged", 5);
+    assert_eq!(runtime.debug_evaluate("debugged", "y"), Ok(json!(4)));
+    assert!(runtime
+        .debug_evaluate("debugged", "missing")
+        .unwrap_err()
+        .contains("ReferenceError"));
+
+    runtime.debug_continue("debugged").unwrap();
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!(4)));
+    assert!(runtime.debug_continue("debugged").is_err());
+}
+
+#[test]
+fn stops_a_paused_task() {
+    let dir = TempDataDir::new("debugger_stop");
+    let runtime = TaskRuntime::new("debugger_stop", dir.to_path_buf());
+
+    let handle = runtime
+        .debug_run_task(
+            "paused",
+            "let spins = 0;\nwhile (true) {\n  spins++;\n}",
+            &[],
+            RunOptions::default(),
+        )
+        .unwrap();
+    paused_at(&runtime, "paused", 1);
+
+    runtime.stop_task("paused").unwrap();
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "stopped");
+}
+
+#[test]
+fn evaluates_in_a_stuck_task() {
+    let dir = TempDataDir::new("evaluate_in_task");
+    let runtime = TaskRuntime::new("evaluate_in_task", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "stuck",
+            "globalThis.state = { phase: 'waiting', attempts: 3, seen: new Set([1]) };\n\
+             while (true) {}",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // until it's got to the loop
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let state = loop {
+        match runtime.evaluate_in_task("stuck", "state", false) {
+            Ok(state) if state.kind() == "object" => break state,
+            _ => assert!(Instant::now() < deadline, "never got to the loop"),
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(state.class_name(), Some("Object"));
+    let properties = state.preview().unwrap().properties();
+    let phase = properties.iter().find(|p| p.name() == "phase").unwrap();
+    assert_eq!((phase.kind(), phase.value()), ("string", "waiting"));
+    let seen = properties.iter().find(|p| p.name() == "seen").unwrap();
+    assert_eq!(seen.subtype(), Some("set"));
+
+    let attempts = runtime
+        .evaluate_in_task("stuck", "state.attempts * 2", false)
+        .unwrap();
+    assert_eq!(attempts.value(), Some(&json!(6)));
+
+    // read-only unless asked
+    assert!(runtime
+        .evaluate_in_task("stuck", "state.phase = 'changed'", false)
+        .is_err());
+    runtime
+        .evaluate_in_task("stuck", "state.attempts = 4", true)
+        .unwrap();
+    let phase = runtime
+        .evaluate_in_task("stuck", "state.phase", false)
+        .unwrap();
+    assert_eq!(phase.value(), Some(&json!("waiting")));
+    // not a debug run
+    assert_eq!(
+        runtime.debug_evaluate("stuck", "state"),
+        Err("Task stuck isn't being debugged".to_string())
+    );
+
+    runtime.stop_task("stuck").unwrap();
+    assert_eq!(handle.wait().unwrap().state().name(), "stopped");
+}
@@ -0,0 +1,160 @@
+use std::thread;
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{PermissionsResponse, RunOptions, TaskEvent, TaskHandle};
+use serde_json::json;
+
+const SUCCESS: &str = include_str!("fixtures/success.ts");
+const ERROR: &str = include_str!("fixtures/error.ts");
+const PERMISSION_PROMPT: &str = include_str!("fixtures/permission_prompt.ts");
+const TIMEOUT: &str = include_str!("fixtures/timeout.ts");
+const STOP: &str = include_str!("fixtures/stop.ts");
+
+// States the task went through, in the order the events were emitted
+fn state_changes(events: &[TaskEvent], task_id: &str) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            TaskEvent::StateChanged(task) if task.id() == task_id => {
+                Some(task.state().name().to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn wait_for_state(handle: &TaskHandle, state: &str) {
+    for _ in 0..1000 {
+        if handle
+            .state()
+            .is_some_and(|task| task.state().name() == state)
+        {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    panic!("task {} never reached {}", handle.id(), state);
+}
+
+#[test]
+fn success() {
+    let harness = TestHarness::new("e2e_success");
+
+    let handle = harness.start(SUCCESS, RunOptions::default());
+    let task = handle.wait().unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(
+        task.return_value(),
+        Some(&json!({ "total": 3, "items": ["apples", "pears", "plums"] }))
+    );
+    assert_eq!(task.output().to_string(), "Counted 3 items\n");
+    assert_eq!(
+        state_changes(&harness.take_events(), handle.id()),
+        ["completed"]
+    );
+}
+
+#[test]
+fn error() {
+    let harness = TestHarness::new("e2e_error");
+
+    let handle = harness.start(ERROR, RunOptions::default());
+    let task = handle.wait().unwrap();
+
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error().contains("Invalid amount: twelve"),
+        "{}",
+        task.error()
+    );
+    assert_eq!(
+        state_changes(&harness.take_events(), handle.id()),
+        ["error"]
+    );
+}
+
+#[test]
+fn permission_prompt() {
+    let harness = TestHarness::new("e2e_permission_prompt");
+    harness.prompter().defer("env");
+
+    let handle = harness.start(PERMISSION_PROMPT, RunOptions::default());
+    wait_for_state(&handle, "waiting_for_permission");
+
+    let prompt = handle.state().unwrap().permission_history()[0].clone();
+    assert_eq!(prompt.name(), "env");
+    assert!(prompt.message().contains("HOME"), "{}", prompt.message());
+
+    handle.respond_to_permission_prompt(PermissionsResponse::Allow);
+    let task = handle.wait().unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!("string")));
+    assert_eq!(
+        state_changes(&harness.take_events(), handle.id()),
+        ["waiting_for_permission", "running", "completed"]
+    );
+}
+
+#[test]
+fn permission_denied() {
+    let harness = TestHarness::new("e2e_permission_denied");
+    harness.prompter().defer("env");
+
+    let handle = harness.start(PERMISSION_PROMPT, RunOptions::default());
+    wait_for_state(&handle, "waiting_for_permission");
+
+    handle.respond_to_permission_prompt(PermissionsResponse::Deny);
+    let task = handle.wait().unwrap();
+
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error().contains("Requires env access"),
+        "{}",
+        task.error()
+    );
+    assert_eq!(
+        state_changes(&harness.take_events(), handle.id()),
+        ["waiting_for_permission", "running", "error"]
+    );
+}
+
+#[test]
+fn timeout() {
+    let harness = TestHarness::new("e2e_timeout");
+
+    let handle = harness.start(TIMEOUT, RunOptions::default());
+    let task = handle.wait().unwrap();
+
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error().contains("Timed out after 30000ms"),
+        "{}",
+        task.error()
+    );
+    assert_eq!(
+        state_changes(&harness.take_events(), handle.id()),
+        ["error"]
+    );
+}
+
+#[test]
+fn stop() {
+    let harness = TestHarness::new("e2e_stop");
+
+    let handle = harness.start(STOP, RunOptions::default());
+    wait_for_state(&handle, "running");
+
+    handle.stop().unwrap();
+    let task = handle.wait().unwrap();
+
+    assert_eq!(task.state().name(), "stopped");
+    assert!(!harness.runtime().has_running_tasks());
+    assert_eq!(
+        state_changes(&harness.take_events(), handle.id()),
+        ["stopping", "stopped"]
+    );
+}
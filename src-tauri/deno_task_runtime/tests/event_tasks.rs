@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{RunOptions, Task, TaskRuntime};
+use serde_json::json;
+
+#[test]
+fn runs_tasks_when_their_event_is_emitted() {
+    let harness = TestHarness::new("event_tasks");
+    let runtime = harness.runtime();
+
+    let id = runtime
+        .register_event_task(
+            "file-dropped",
+            "RuntimeExtension.returnValue(RuntimeExtension.input.path);",
+            RunOptions::default(),
+            None,
+        )
+        .unwrap();
+    assert_eq!(runtime.list_event_tasks()[0].event_name(), "file-dropped");
+
+    assert!(runtime
+        .run_event_tasks("window-focused", json!(null))
+        .is_empty());
+
+    let started = runtime.run_event_tasks("file-dropped", json!({ "path": "/tmp/report.csv" }));
+    assert_eq!(started.len(), 1);
+    assert!(started[0].starts_with(&id), "{}", started[0]);
+
+    let task = wait(runtime, &started[0]);
+    assert_eq!(task.return_value(), Some(&json!("/tmp/report.csv")));
+    assert_eq!(
+        runtime.list_event_tasks()[0].last_task_id(),
+        Some(started[0].as_str())
+    );
+
+    runtime.unregister_event_task(&id).unwrap();
+    assert!(runtime.list_event_tasks().is_empty());
+    assert!(runtime
+        .run_event_tasks("file-dropped", json!(null))
+        .is_empty());
+    assert!(runtime.unregister_event_task(&id).is_err());
+}
+
+#[test]
+fn rejects_invalid_event_names() {
+    let harness = TestHarness::new("invalid_event_tasks");
+
+    // the runtime's own events would trigger themselves
+    for event_name in ["", "file dropped", "task-state-changed"] {
+        assert!(harness
+            .runtime()
+            .register_event_task(event_name, "", RunOptions::default(), None)
+            .is_err());
+    }
+    assert!(harness
+        .runtime()
+        .register_event_task("file-dropped", "", RunOptions::default(), Some(0))
+        .is_err());
+    assert!(harness.runtime().list_event_tasks().is_empty());
+}
+
+#[test]
+fn batches_events_emitted_within_the_debounce_window() {
+    let harness = TestHarness::new("debounced_event_tasks");
+    let runtime = harness.runtime();
+
+    let id = runtime
+        .register_event_task(
+            "file-dropped",
+            "RuntimeExtension.returnValue(RuntimeExtension.input);",
+            RunOptions::default(),
+            Some(100),
+        )
+        .unwrap();
+
+    // a flurry of events, one of them twice
+    for path in ["/tmp/a.csv", "/tmp/b.csv", "/tmp/a.csv"] {
+        assert!(runtime
+            .run_event_tasks("file-dropped", json!({ "path": path }))
+            .is_empty());
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let task_id = loop {
+        if let Some(task_id) = runtime.list_event_tasks()[0].last_task_id() {
+            break task_id.to_string();
+        }
+        assert!(Instant::now() < deadline, "the batch never ran");
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    assert!(task_id.starts_with(&id), "{}", task_id);
+    assert_eq!(
+        wait(runtime, &task_id).return_value(),
+        Some(&json!([{ "path": "/tmp/a.csv" }, { "path": "/tmp/b.csv" }]))
+    );
+
+    // a single run for the batch
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(
+        runtime.list_event_tasks()[0].last_task_id(),
+        Some(task_id.as_str())
+    );
+}
+
+// Triggered runs have no handle
+fn wait(runtime: &TaskRuntime, task_id: &str) -> Task {
+    loop {
+        let task = runtime.get_task_state(task_id).unwrap();
+        if task.is_finished() {
+            return task;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
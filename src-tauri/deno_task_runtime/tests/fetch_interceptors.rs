@@ -0,0 +1,113 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{
+    register_fetch_interceptor, FetchInterceptor, FetchRequest, PermissionBroker, PermissionPrompt,
+    PermissionsResponse, RunOptions, TaskRuntime,
+};
+use serde_json::json;
+
+struct AllowAll;
+
+impl PermissionBroker for AllowAll {
+    fn prompt(&self, _task_id: &str, _prompt: &PermissionPrompt) -> Option<PermissionsResponse> {
+        Some(PermissionsResponse::Allow)
+    }
+}
+
+struct Authorize;
+
+impl FetchInterceptor for Authorize {
+    fn intercept(&self, script: &str, request: &mut FetchRequest) -> Result<(), String> {
+        request.set_header("authorization", &format!("Bearer token-of-{}", script))
+    }
+}
+
+struct BlockPath(&'static str);
+
+impl FetchInterceptor for BlockPath {
+    fn intercept(&self, _script: &str, request: &mut FetchRequest) -> Result<(), String> {
+        if request.url().ends_with(self.0) {
+            return Err(format!("{} {} isn't allowed", request.method(), self.0));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn intercepts_the_requests_of_saved_scripts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // answers the first request with the authorization header it was sent
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut authorization = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("authorization:") {
+                authorization = value.trim().to_string();
+            }
+        }
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            authorization.len(),
+            authorization
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    let dir = TempDataDir::new("fetch_interceptors");
+    let runtime = TaskRuntime::new("fetch_interceptors", dir.to_path_buf());
+    runtime.set_permission_broker(AllowAll);
+    register_fetch_interceptor("authorize", Authorize);
+    register_fetch_interceptor("block-admin", BlockPath("/admin"));
+
+    let code = format!(
+        r#"
+        const base = "http://127.0.0.1:{port}";
+        const authorization = await (await fetch(`${{base}}/hello`)).text();
+        const blocked = await fetch(`${{base}}/admin`).catch((e) => e.message);
+        RuntimeExtension.returnValue({{ authorization, blocked }});
+        "#
+    );
+    runtime.save_script("client", &code).unwrap();
+    assert!(runtime
+        .set_script_fetch_interceptors("client", vec!["unknown".to_string()])
+        .is_err());
+    runtime
+        .set_script_fetch_interceptors(
+            "client",
+            vec!["authorize".to_string(), "block-admin".to_string()],
+        )
+        .unwrap();
+    assert_eq!(
+        runtime.get_script_fetch_interceptors("client").unwrap(),
+        ["authorize", "block-admin"]
+    );
+
+    let task = runtime
+        .run_saved_script("client", "client", None, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    let result = task.return_value().unwrap();
+    assert_eq!(result["authorization"], json!("bearer token-of-client"));
+    let blocked = result["blocked"].as_str().unwrap();
+    assert!(
+        blocked.contains("Blocked by fetch interceptor block-admin: GET /admin isn't allowed"),
+        "{}",
+        blocked
+    );
+}
@@ -0,0 +1,10 @@
+// Fails with an uncaught error
+function parseAmount(input: string): number {
+  const amount = Number(input);
+  if (Number.isNaN(amount)) {
+    throw new Error(`Invalid amount: ${input}`);
+  }
+  return amount;
+}
+
+parseAmount("twelve");
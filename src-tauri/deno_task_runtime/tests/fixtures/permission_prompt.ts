@@ -0,0 +1,4 @@
+// Needs the env permission before it can complete
+const home = Deno.env.get("HOME");
+
+RuntimeExtension.returnValue(typeof home);
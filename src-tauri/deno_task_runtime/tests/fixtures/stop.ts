@@ -0,0 +1,8 @@
+// Polls forever, only ends when stopped
+let polls = 0;
+
+setInterval(() => {
+  polls += 1;
+}, 1_000);
+
+await new Promise(() => {});
@@ -0,0 +1,12 @@
+// Runs to completion and returns a value
+interface Report {
+  total: number;
+  items: string[];
+}
+
+const items = ["apples", "pears", "plums"];
+const report: Report = { total: items.length, items };
+
+console.log(`Counted ${report.total} items`);
+
+RuntimeExtension.returnValue(report);
@@ -0,0 +1,11 @@
+// Gives up on a request that never answers after 30 seconds
+function withTimeout<T>(promise: Promise<T>, ms: number): Promise<T> {
+  const timeout = new Promise<T>((_, reject) =>
+    setTimeout(() => reject(new Error(`Timed out after ${ms}ms`)), ms)
+  );
+  return Promise.race([promise, timeout]);
+}
+
+const neverAnswers = new Promise<string>(() => {});
+
+await withTimeout(neverAnswers, 30_000);
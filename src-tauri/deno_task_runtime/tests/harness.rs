@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::PermissionsResponse;
+use serde_json::json;
+
+#[test]
+fn answers_prompts_in_order() {
+    let harness = TestHarness::new("prompts_in_order");
+    harness
+        .prompter()
+        .answer("env", PermissionsResponse::Allow)
+        .answer("read", PermissionsResponse::Deny);
+    harness.fs().seed_file("/secret.txt", "hunter2");
+
+    let task = harness.run("Deno.env.get(\"PATH\");\nawait Deno.readTextFile(\"/secret.txt\");");
+
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error().contains("Requires read access"),
+        "{}",
+        task.error()
+    );
+
+    let names: Vec<String> = harness
+        .prompter()
+        .prompts()
+        .iter()
+        .map(|prompt| prompt.name().to_string())
+        .collect();
+    assert_eq!(names, ["env", "read"]);
+    assert_eq!(harness.prompter().pending_answers(), 0);
+}
+
+#[test]
+fn denies_unscripted_prompts() {
+    let harness = TestHarness::new("unscripted_prompts");
+    harness.prompter().answer("net", PermissionsResponse::Allow);
+
+    let task = harness.run("Deno.env.get(\"PATH\");");
+
+    assert_eq!(task.state().name(), "error");
+    assert_eq!(harness.prompter().pending_answers(), 1);
+}
+
+#[test]
+fn reads_and_writes_in_memory() {
+    let harness = TestHarness::new("in_memory_fs");
+    harness
+        .prompter()
+        .answer("read", PermissionsResponse::AllowAll)
+        .answer("write", PermissionsResponse::AllowAll);
+    harness.fs().seed_file("/data/name.txt", "Ada");
+
+    let task = harness.run(
+        r#"
+        const name = await Deno.readTextFile("/data/name.txt");
+        await Deno.mkdir("/out");
+        await Deno.writeTextFile("/out/greeting.txt", `Hello, ${name}`);
+        "#,
+    );
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(
+        harness.fs().read_file("/out/greeting.txt").as_deref(),
+        Some(b"Hello, Ada".as_slice())
+    );
+}
+
+#[test]
+fn missing_files_are_not_read_from_disk() {
+    let harness = TestHarness::new("in_memory_isolation");
+    harness
+        .prompter()
+        .answer("read", PermissionsResponse::AllowAll);
+
+    let task = harness.run("await Deno.readTextFile(\"/etc/hosts\");");
+
+    assert_eq!(task.state().name(), "error");
+    assert!(task.error().contains("NotFound"), "{}", task.error());
+}
+
+#[test]
+fn timers_run_on_the_virtual_clock() {
+    let harness = TestHarness::new("virtual_clock");
+
+    let started = Instant::now();
+    let task = harness.run(
+        r#"
+        await new Promise((resolve) => setTimeout(resolve, 60 * 60 * 1000));
+        RuntimeExtension.returnValue("woke up");
+        "#,
+    );
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!("woke up")));
+    assert!(started.elapsed() < Duration::from_secs(60));
+}
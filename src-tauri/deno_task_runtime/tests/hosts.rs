@@ -0,0 +1,77 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{PermissionsResponse, RunOptions};
+use serde_json::json;
+
+fn options(hosts: serde_json::Value) -> RunOptions {
+    serde_json::from_value(json!({ "hosts": hosts })).unwrap()
+}
+
+#[test]
+fn resolves_mapped_hosts_to_their_address() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // answers the first request it's sent, returns its Host header
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut host = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("host:") {
+                host = value.trim().to_string();
+            }
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstaging")
+            .unwrap();
+        host
+    });
+
+    let harness = TestHarness::new("hosts");
+    harness.prompter().answer("net", PermissionsResponse::Allow);
+
+    let code = format!(
+        "const response = await fetch(\"http://api.tasks.invalid:{}/\");\n\
+         RuntimeExtension.returnValue(await response.text());",
+        port
+    );
+    let task = harness
+        .start(&code, options(json!({ "api.tasks.invalid": "127.0.0.1" })))
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!("staging")));
+    // the request still names the mapped host
+    assert_eq!(
+        server.join().unwrap(),
+        format!("api.tasks.invalid:{}", port)
+    );
+}
+
+#[test]
+fn rejects_invalid_mappings() {
+    let harness = TestHarness::new("invalid_hosts");
+
+    for hosts in [
+        json!({ "api.tasks.invalid": "not an address" }),
+        json!({ "api tasks": "127.0.0.1" }),
+        json!({ "": "::1" }),
+    ] {
+        assert!(harness
+            .runtime()
+            .run_task("invalid-hosts", "", options(hosts))
+            .is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::RunOptions;
+use serde_json::json;
+
+fn keyed(key: &str) -> RunOptions {
+    serde_json::from_value(json!({ "idempotency_key": key })).unwrap()
+}
+
+#[test]
+fn returns_the_task_already_submitted_with_a_key() {
+    let harness = TestHarness::new("idempotency");
+    let runtime = harness.runtime();
+
+    let first = runtime
+        .run_task(
+            "order_1",
+            "RuntimeExtension.returnValue(1);",
+            keyed("order"),
+        )
+        .unwrap();
+    let duplicate = runtime
+        .run_task(
+            "order_2",
+            "RuntimeExtension.returnValue(2);",
+            keyed("order"),
+        )
+        .unwrap();
+    assert_eq!(duplicate.id(), "order_1");
+
+    let task = first.wait().unwrap();
+    assert_eq!(task.return_value(), Some(&json!(1)));
+
+    // still once completed
+    let after = runtime
+        .run_task(
+            "order_3",
+            "RuntimeExtension.returnValue(3);",
+            keyed("order"),
+        )
+        .unwrap();
+    assert_eq!(after.id(), "order_1");
+    assert!(runtime.get_task_state("order_3").is_none());
+
+    let other = runtime
+        .run_task(
+            "invoice",
+            "RuntimeExtension.returnValue(4);",
+            keyed("invoice"),
+        )
+        .unwrap();
+    assert_eq!(other.id(), "invoice");
+
+    assert!(runtime.run_task("empty", "", keyed("")).is_err());
+}
+
+#[test]
+fn runs_again_when_the_keyed_task_failed() {
+    let harness = TestHarness::new("idempotency_failed");
+    let runtime = harness.runtime();
+
+    let task = runtime
+        .run_task("sync_1", "throw new Error(\"offline\");", keyed("sync"))
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+
+    let retry = runtime
+        .run_task(
+            "sync_2",
+            "RuntimeExtension.returnValue(\"synced\");",
+            keyed("sync"),
+        )
+        .unwrap();
+    assert_eq!(retry.id(), "sync_2");
+    assert_eq!(retry.wait().unwrap().return_value(), Some(&json!("synced")));
+}
@@ -0,0 +1,121 @@
+use deno_task_runtime::lsp_request;
+use serde_json::{json, Value};
+
+const SCRIPT: &str = "import { join } from \"./paths.ts\";\n\nfunction total(values: number[]): number {\n  return values.reduce((a, b) => a + b, 0);\n}\n\nRuntimeExtension.\n";
+
+fn open(uri: &str, text: &str) {
+    lsp_request(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": uri, "languageId": "typescript", "version": 1, "text": text } }),
+    )
+    .unwrap();
+}
+
+fn at(method: &str, uri: &str, line: u32, character: u32) -> Value {
+    lsp_request(
+        method,
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        }),
+    )
+    .unwrap()
+}
+
+fn labels(completion: &Value) -> Vec<&str> {
+    completion["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn completes_runtime_extension_members() {
+    let uri = "file:///tasks/members.ts";
+    open(uri, SCRIPT);
+
+    let completion = at("textDocument/completion", uri, 6, 17);
+    assert!(
+        labels(&completion).contains(&"returnValue"),
+        "{}",
+        completion
+    );
+    assert!(
+        labels(&completion).contains(&"saveArtifact"),
+        "{}",
+        completion
+    );
+
+    lsp_request(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": "RuntimeExtension.store.s" }],
+        }),
+    )
+    .unwrap();
+
+    let completion = at("textDocument/completion", uri, 0, 24);
+    assert_eq!(labels(&completion), ["set"]);
+}
+
+#[test]
+fn completes_names_of_the_last_version_that_parsed() {
+    let uri = "file:///tasks/names.ts";
+    open(uri, &SCRIPT.replace("RuntimeExtension.\n", ""));
+
+    // doesn't parse until the call is closed
+    lsp_request(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": SCRIPT.replace("RuntimeExtension.\n", "to(") }],
+        }),
+    )
+    .unwrap();
+
+    let completion = at("textDocument/completion", uri, 6, 2);
+    assert_eq!(labels(&completion), ["total"]);
+}
+
+#[test]
+fn hovers_and_goes_to_definitions() {
+    let uri = "file:///tasks/definitions.ts";
+    open(
+        uri,
+        &SCRIPT.replace("RuntimeExtension.\n", "total([join(\"a\").length]);\n"),
+    );
+
+    let hover = at("textDocument/hover", uri, 6, 2);
+    let contents = hover["contents"]["value"].as_str().unwrap();
+    assert!(
+        contents.contains("function total(values: number[]): number"),
+        "{}",
+        contents
+    );
+
+    let definition = at("textDocument/definition", uri, 6, 2);
+    assert_eq!(definition["uri"], uri);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 2, "character": 9 })
+    );
+
+    let definition = at("textDocument/definition", uri, 6, 8);
+    assert_eq!(definition["uri"], "file:///tasks/paths.ts");
+}
+
+#[test]
+fn rejects_unknown_methods_and_documents() {
+    assert!(lsp_request("textDocument/rename", Value::Null).is_err());
+    assert!(lsp_request(
+        "textDocument/hover",
+        json!({
+            "textDocument": { "uri": "file:///tasks/closed.ts" },
+            "position": { "line": 0, "character": 0 },
+        }),
+    )
+    .is_err());
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use deno_task_runtime::test_support::{migrate, Migration, TempDataDir};
+
+// Files under `dir` with their contents, relative and sorted
+fn tree(dir: &Path) -> Vec<(String, String)> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(root, &path, files);
+            } else {
+                let relative = path.strip_prefix(root).unwrap();
+                let contents = std::fs::read_to_string(&path).unwrap();
+                files.push((relative.to_string_lossy().replace('\\', "/"), contents));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files);
+    files.sort();
+    files
+}
+
+const BASELINE: Migration = Migration {
+    version: 1,
+    name: "baseline",
+    run: |_| Ok(()),
+};
+
+#[test]
+fn restores_the_state_a_failed_migration_started_on() {
+    let dir = TempDataDir::new("failed_migration");
+    std::fs::create_dir_all(dir.join("checkpoints")).unwrap();
+    std::fs::write(dir.join("storage_version"), "1").unwrap();
+    std::fs::write(dir.join("schedules.json"), "[]").unwrap();
+    std::fs::write(dir.join("checkpoints").join("report.json"), "{}").unwrap();
+    let before = tree(&dir);
+
+    let migrations = [
+        BASELINE,
+        Migration {
+            version: 2,
+            name: "split_schedules",
+            run: |dir| {
+                std::fs::write(dir.join("schedules.json"), "{\"version\": 2}")?;
+                std::fs::create_dir_all(dir.join("schedules"))?;
+                std::fs::write(dir.join("schedules").join("daily.json"), "{}")?;
+                Ok(())
+            },
+        },
+        Migration {
+            version: 3,
+            name: "broken",
+            run: |_| Err(std::io::Error::other("disk full")),
+        },
+    ];
+
+    let error = migrate(&dir, &migrations).unwrap_err();
+    assert!(
+        error.contains("Migration 3 (broken) failed: disk full"),
+        "{}",
+        error
+    );
+    assert!(error.contains("restored the backup"), "{}", error);
+
+    // the backup aside, nothing the migrations wrote is left
+    let backups: Vec<_> = std::fs::read_dir(dir.join("backups")).unwrap().collect();
+    assert_eq!(backups.len(), 1);
+    std::fs::remove_dir_all(dir.join("backups")).unwrap();
+    assert_eq!(tree(&dir), before);
+}
+
+#[test]
+fn refuses_state_from_a_newer_version() {
+    let dir = TempDataDir::new("newer_state");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("storage_version"), "2").unwrap();
+
+    let error = migrate(&dir, &[BASELINE]).unwrap_err();
+    assert!(error.contains("only knows up to 1"), "{}", error);
+    assert_eq!(
+        tree(&dir),
+        vec![("storage_version".to_string(), "2".to_string())]
+    );
+}
@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use deno_core::error::AnyError;
+use deno_core::{
+    ModuleLoadResponse, ModuleLoader as _, ModuleSpecifier, ModuleType, RequestedModuleType,
+    ResolutionKind,
+};
+use deno_task_runtime::test_support::TypescriptModuleLoader;
+use deno_task_runtime::ModuleLoader;
+use proptest::prelude::*;
+
+// Where staged task code lives, two directories below the root
+const REFERRER: &str = "file:///base/dir/main.ts";
+
+// Serves the same source for every specifier
+struct AnySource(&'static str);
+
+impl ModuleLoader for AnySource {
+    fn load_source(&self, _specifier: &ModuleSpecifier) -> Result<Option<String>, AnyError> {
+        Ok(Some(self.0.to_string()))
+    }
+}
+
+fn loader(custom_loader: Option<Arc<dyn ModuleLoader>>) -> TypescriptModuleLoader {
+    TypescriptModuleLoader {
+        source_maps: Default::default(),
+        sources: Default::default(),
+        diagnostics: Default::default(),
+        custom_loader,
+        lock: None,
+    }
+}
+
+fn resolve(specifier: &str, referrer: &str) -> Result<ModuleSpecifier, AnyError> {
+    loader(None).resolve(specifier, referrer, ResolutionKind::Import)
+}
+
+fn segment() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9_-]{1,12}"
+}
+
+fn segments() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec(segment(), 1..5)
+}
+
+// Printable names, without what URLs give a meaning to or strip
+fn unicode_name() -> impl Strategy<Value = String> {
+    "\\PC{1,16}".prop_filter("reserved in URLs or paths", |name| {
+        !name.contains(['/', '\\', '?', '#', '%'])
+    })
+}
+
+proptest! {
+    #[test]
+    fn relative_specifiers_resolve_next_to_the_referrer(path in segments()) {
+        let specifier = format!("./{}.ts", path.join("/"));
+
+        let resolved = resolve(&specifier, REFERRER).unwrap();
+
+        prop_assert_eq!(resolved.as_str(), format!("file:///base/dir/{}.ts", path.join("/")));
+    }
+
+    #[test]
+    fn parent_segments_stop_at_the_root(ups in 0usize..8, path in segments()) {
+        let specifier = format!("{}{}.ts", "../".repeat(ups), path.join("/"));
+        let specifier = if ups == 0 { format!("./{}", specifier) } else { specifier };
+
+        let resolved = resolve(&specifier, REFERRER).unwrap();
+
+        let dir = ["base", "dir"];
+        let kept = &dir[..dir.len().saturating_sub(ups)];
+        let expected: Vec<&str> = kept
+            .iter()
+            .copied()
+            .chain(path.iter().map(String::as_str))
+            .collect();
+        prop_assert_eq!(resolved.as_str(), format!("file:///{}.ts", expected.join("/")));
+    }
+
+    #[test]
+    fn absolute_urls_ignore_the_referrer(host in segment(), path in segments()) {
+        let specifier = format!("https://{}.example/{}.js", host, path.join("/"));
+
+        let resolved = resolve(&specifier, REFERRER).unwrap();
+
+        prop_assert_eq!(resolved, ModuleSpecifier::parse(&specifier).unwrap());
+    }
+
+    #[test]
+    fn root_relative_specifiers_keep_the_referrer_origin(path in segments()) {
+        let specifier = format!("/{}.ts", path.join("/"));
+
+        let from_file = resolve(&specifier, REFERRER).unwrap();
+        let from_remote = resolve(&specifier, "https://deno.land/x/mod.ts").unwrap();
+
+        prop_assert_eq!(from_file.as_str(), format!("file://{}", specifier));
+        prop_assert_eq!(from_remote.as_str(), format!("https://deno.land{}", specifier));
+    }
+
+    #[test]
+    fn unicode_file_names_round_trip(name in unicode_name()) {
+        let resolved = resolve(&format!("./{}.ts", name), REFERRER).unwrap();
+
+        let path = resolved.to_file_path().unwrap();
+        prop_assert_eq!(path.file_name().unwrap().to_string_lossy(), format!("{}.ts", name));
+        prop_assert_eq!(path.parent().unwrap().to_string_lossy(), "/base/dir");
+    }
+
+    #[test]
+    fn backslashes_after_the_prefix_are_separators(path in segments()) {
+        let with_backslashes = resolve(&format!("./{}.ts", path.join("\\")), REFERRER).unwrap();
+        let with_slashes = resolve(&format!("./{}.ts", path.join("/")), REFERRER).unwrap();
+
+        prop_assert_eq!(with_backslashes, with_slashes);
+    }
+
+    #[test]
+    fn backslash_prefixes_are_bare_specifiers(path in segments()) {
+        let current_dir = format!(".\\{}.ts", path.join("\\"));
+        let parent_dir = format!("..\\{}.ts", path.join("\\"));
+
+        prop_assert!(resolve(&current_dir, REFERRER).is_err());
+        prop_assert!(resolve(&parent_dir, REFERRER).is_err());
+    }
+
+    // not file paths, the drive letter parses as a URL scheme the loader
+    // doesn't know
+    #[test]
+    fn windows_absolute_paths_are_urls(drive in "[a-zA-Z]", path in segments()) {
+        let specifier = format!("{}:\\{}.ts", drive, path.join("\\"));
+
+        let resolved = resolve(&specifier, REFERRER).unwrap();
+
+        prop_assert_eq!(resolved.scheme(), drive.to_lowercase());
+    }
+
+    #[test]
+    fn bare_specifiers_are_rejected(path in segments()) {
+        let specifier = format!("{}.ts", path.join("/"));
+
+        prop_assert!(resolve(&specifier, REFERRER).is_err());
+    }
+
+    #[test]
+    fn module_types_follow_the_extension(
+        name in unicode_name(),
+        extension in prop::sample::select(vec!["ts", "tsx", "mts", "js", "mjs", "jsx", "json"]),
+    ) {
+        let source = if extension == "json" { "{}" } else { "export default 1;" };
+        let loader = loader(Some(Arc::new(AnySource(source))));
+        let specifier = resolve(&format!("./{}.{}", name, extension), REFERRER).unwrap();
+
+        let ModuleLoadResponse::Sync(loaded) =
+            loader.load(&specifier, None, false, RequestedModuleType::None)
+        else {
+            panic!("the loader is synchronous");
+        };
+        let module_type = loaded.unwrap().module_type;
+
+        if extension == "json" {
+            prop_assert!(matches!(module_type, ModuleType::Json));
+        } else {
+            prop_assert!(matches!(module_type, ModuleType::JavaScript));
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::thread;
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{PipelineSpec, RunOptions, TaskEvent, TaskRuntime};
+use serde_json::{json, Value};
+
+fn runtime(name: &str, dir: &TempDataDir) -> TaskRuntime {
+    let runtime = TaskRuntime::new(name, dir.to_path_buf());
+
+    let scripts = [
+        ("fetch", "RuntimeExtension.returnValue({ items: [1, 2, 3] });"),
+        (
+            "sum",
+            "const { items, offset } = RuntimeExtension.args;\nRuntimeExtension.returnValue(items.reduce((a, b) => a + b, offset));",
+        ),
+        ("fail", "throw new Error(\"no data\");"),
+    ];
+    for (name, code) in scripts {
+        runtime.save_script(name, code).unwrap();
+    }
+
+    runtime
+}
+
+// Last state of the pipeline once it's finished
+fn wait_for_pipeline(runtime: &TaskRuntime) -> Value {
+    for _ in 0..1000 {
+        let last = runtime
+            .take_events()
+            .into_iter()
+            .rev()
+            .find_map(|event| match event {
+                TaskEvent::PipelineStateChanged(pipeline) => Some(pipeline),
+                _ => None,
+            });
+        if let Some(pipeline) = last {
+            let pipeline = serde_json::to_value(pipeline).unwrap();
+            if pipeline["state"] != "running" {
+                return pipeline;
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    panic!("pipeline never finished");
+}
+
+fn step_states(pipeline: &Value) -> Vec<&str> {
+    pipeline["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| step["state"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn passes_return_values_along() {
+    let dir = TempDataDir::new("pipeline_chain");
+    let runtime = runtime("pipeline_chain", &dir);
+
+    let spec: PipelineSpec = serde_json::from_value(json!({
+        "id": "chain",
+        "steps": [
+            { "id": "fetch", "script": "fetch" },
+            { "id": "sum", "script": "sum", "args": { "offset": 10 } },
+        ],
+    }))
+    .unwrap();
+    let id = runtime.run_pipeline(spec, RunOptions::default()).unwrap();
+    assert_eq!(id, "chain");
+
+    let pipeline = wait_for_pipeline(&runtime);
+    assert_eq!(pipeline["state"], "completed", "{}", pipeline);
+    assert_eq!(
+        runtime.get_task_state("chain-sum").unwrap().return_value(),
+        Some(&json!(16))
+    );
+}
+
+#[test]
+fn continues_past_failing_steps_when_asked() {
+    let dir = TempDataDir::new("pipeline_partial");
+    let runtime = runtime("pipeline_partial", &dir);
+
+    let spec: PipelineSpec = serde_json::from_value(json!({
+        "on_error": "continue_on_error",
+        "steps": [
+            { "id": "fail", "script": "fail" },
+            { "id": "sum", "script": "sum" },
+            { "id": "fetch", "script": "fetch", "depends_on": [] },
+        ],
+    }))
+    .unwrap();
+    runtime.run_pipeline(spec, RunOptions::default()).unwrap();
+
+    let pipeline = wait_for_pipeline(&runtime);
+    assert_eq!(pipeline["state"], "completed_with_errors", "{}", pipeline);
+    assert_eq!(step_states(&pipeline), ["failed", "skipped", "completed"]);
+    assert!(pipeline["steps"][0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("no data"));
+}
+
+#[test]
+fn describes_pipelines_as_graphs() {
+    let dir = TempDataDir::new("pipeline_graph");
+    let runtime = runtime("pipeline_graph", &dir);
+    runtime
+        .save_script(
+            "wait",
+            "await new Promise((resolve) => setTimeout(resolve, 5000));",
+        )
+        .unwrap();
+
+    let spec: PipelineSpec = serde_json::from_value(json!({
+        "id": "graph",
+        "steps": [
+            { "id": "fetch", "script": "fetch" },
+            { "id": "wait", "script": "wait", "depends_on": [] },
+            { "id": "sum", "script": "sum", "depends_on": ["fetch", "wait"] },
+        ],
+    }))
+    .unwrap();
+    runtime.run_pipeline(spec, RunOptions::default()).unwrap();
+    assert!(runtime.get_pipeline_state("missing").is_none());
+
+    let mut graph = Value::Null;
+    for _ in 0..1000 {
+        graph = serde_json::to_value(runtime.get_pipeline_state("graph").unwrap()).unwrap();
+        if graph["nodes"][0]["state"] == "completed" {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(graph["state"], "running", "{}", graph);
+    assert_eq!(
+        graph["edges"],
+        json!([
+            { "from": "fetch", "to": "sum" },
+            { "from": "wait", "to": "sum" },
+        ])
+    );
+    assert_eq!(graph["frontier"], json!(["wait"]));
+    assert!(graph["nodes"][0]["duration_ms"].is_u64(), "{}", graph);
+    assert!(graph["nodes"][2]["duration_ms"].is_null(), "{}", graph);
+
+    runtime.stop_task("graph-wait").unwrap();
+    let pipeline = wait_for_pipeline(&runtime);
+    assert_eq!(pipeline["state"], "failed", "{}", pipeline);
+    assert_eq!(
+        serde_json::to_value(runtime.get_pipeline_state("graph").unwrap()).unwrap()["frontier"],
+        json!([])
+    );
+}
+
+#[test]
+fn limits_the_steps_running_at_once() {
+    let dir = TempDataDir::new("pipeline_parallel");
+    let runtime = runtime("pipeline_parallel", &dir);
+    runtime
+        .save_script(
+            "nap",
+            "await new Promise((resolve) => setTimeout(resolve, 50));",
+        )
+        .unwrap();
+    runtime
+        .save_script(
+            "collect",
+            "RuntimeExtension.returnValue(Object.keys(RuntimeExtension.input).sort());",
+        )
+        .unwrap();
+
+    let spec: PipelineSpec = serde_json::from_value(json!({
+        "id": "parallel",
+        "max_parallel": 2,
+        "steps": [
+            { "id": "a", "script": "nap", "depends_on": [] },
+            { "id": "b", "script": "nap", "depends_on": [] },
+            { "id": "c", "script": "nap", "depends_on": [] },
+            { "id": "collect", "script": "collect", "depends_on": ["a", "b", "c"] },
+        ],
+    }))
+    .unwrap();
+    runtime.run_pipeline(spec, RunOptions::default()).unwrap();
+
+    let mut most_running = 0;
+    let mut polls = 0;
+    let pipeline = loop {
+        polls += 1;
+        assert!(polls < 1000, "pipeline never finished");
+        let states: Vec<Value> = runtime
+            .take_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                TaskEvent::PipelineStateChanged(pipeline) => {
+                    Some(serde_json::to_value(pipeline).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        for state in &states {
+            let running = step_states(state)
+                .iter()
+                .filter(|state| **state == "running")
+                .count();
+            most_running = most_running.max(running);
+        }
+        match states.last() {
+            Some(last) if last["state"] != "running" => break last.clone(),
+            _ => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+
+    assert_eq!(pipeline["state"], "completed", "{}", pipeline);
+    assert_eq!(most_running, 2);
+    assert_eq!(
+        runtime
+            .get_task_state("parallel-collect")
+            .unwrap()
+            .return_value(),
+        Some(&json!(["a", "b", "c"]))
+    );
+
+    let spec: PipelineSpec = serde_json::from_value(
+        json!({ "max_parallel": 0, "steps": [{ "id": "a", "script": "nap" }] }),
+    )
+    .unwrap();
+    assert!(runtime.run_pipeline(spec, RunOptions::default()).is_err());
+}
+
+#[test]
+fn rejects_steps_depending_on_later_ones() {
+    let dir = TempDataDir::new("pipeline_invalid");
+    let runtime = runtime("pipeline_invalid", &dir);
+
+    let spec: PipelineSpec = serde_json::from_value(json!({
+        "steps": [
+            { "id": "sum", "script": "sum", "depends_on": ["fetch"] },
+            { "id": "fetch", "script": "fetch" },
+        ],
+    }))
+    .unwrap();
+
+    assert!(runtime.run_pipeline(spec, RunOptions::default()).is_err());
+}
@@ -0,0 +1,73 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{PermissionsResponse, RunOptions};
+use serde_json::json;
+
+fn options(proxy: serde_json::Value) -> RunOptions {
+    serde_json::from_value(json!({ "proxy": proxy })).unwrap()
+}
+
+#[test]
+fn fetches_through_the_task_proxy() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // answers the first request it's sent, returns its request line
+    let proxy = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nproxied")
+            .unwrap();
+        request_line
+    });
+
+    let harness = TestHarness::new("proxy");
+    harness.prompter().answer("net", PermissionsResponse::Allow);
+
+    let task = harness
+        .start(
+            "const response = await fetch(\"http://tasks.invalid/hello\");\n\
+             RuntimeExtension.returnValue(await response.text());",
+            options(json!({ "url": format!("http://127.0.0.1:{}", port) })),
+        )
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!("proxied")));
+    assert_eq!(
+        proxy.join().unwrap().trim(),
+        "GET http://tasks.invalid/hello HTTP/1.1"
+    );
+}
+
+#[test]
+fn rejects_invalid_proxies() {
+    let harness = TestHarness::new("invalid_proxy");
+
+    for proxy in [
+        json!({ "url": "ftp://proxy.local" }),
+        json!({ "url": "not a url" }),
+        json!({ "url": "http://proxy.local", "password": "secret" }),
+    ] {
+        assert!(harness
+            .runtime()
+            .run_task("invalid-proxy", "", options(proxy))
+            .is_err());
+    }
+}
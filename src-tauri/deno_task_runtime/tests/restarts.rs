@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{RunOptions, TaskRuntime};
+use serde_json::json;
+
+// Waits for a line of stdin, so it's still running when the "app quits"
+const ASK: &str = "RuntimeExtension.returnValue(prompt(\"Go?\"));";
+
+fn write_stdin(runtime: &TaskRuntime, task_id: &str, data: &[u8]) {
+    // the pipe is set up once the task's thread starts
+    while runtime.write_task_stdin(task_id, data).is_err() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn restores_the_tasks_running_when_the_app_quit() {
+    let dir = TempDataDir::new("restarts");
+
+    let restartable: RunOptions = serde_json::from_value(json!({ "restartable": true })).unwrap();
+    let before = TaskRuntime::new("restarts", dir.to_path_buf());
+    let plain = before
+        .run_task("plain", ASK, RunOptions::default())
+        .unwrap();
+    let restarted = before.run_task("restarted", ASK, restartable).unwrap();
+    let done = before
+        .run_task(
+            "done",
+            "RuntimeExtension.returnValue(1);",
+            RunOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(done.wait().unwrap().state().name(), "completed");
+    // both are waiting on their stdin by then
+    write_stdin(&before, "plain", b"");
+    write_stdin(&before, "restarted", b"");
+
+    // the same data directory in the next session, the first one's threads
+    // still block on their stdin
+    let after = TaskRuntime::new("restarts", dir.to_path_buf());
+    let mut interrupted = after.restore_interrupted_tasks();
+    interrupted.sort();
+    assert_eq!(interrupted, ["plain", "restarted"]);
+    assert_eq!(
+        after.get_task_state("plain").unwrap().state().name(),
+        "interrupted"
+    );
+    assert!(after.get_task_state("done").is_none());
+
+    write_stdin(&after, "restarted", b"again\n");
+    let restarted_after = loop {
+        let task = after.get_task_state("restarted").unwrap();
+        if task.is_finished() {
+            break task;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(
+        restarted_after.state().name(),
+        "completed",
+        "{}",
+        restarted_after.error()
+    );
+    assert_eq!(restarted_after.return_value(), Some(&json!("again")));
+
+    // nothing left to restore
+    assert!(after.restore_interrupted_tasks().is_empty());
+
+    plain.stop().unwrap();
+    restarted.stop().unwrap();
+}
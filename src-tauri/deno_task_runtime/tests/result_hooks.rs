@@ -0,0 +1,61 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{register_result_hook, ResultHook, RunOptions, Task, TaskRuntime};
+
+struct Collect(Arc<Mutex<Vec<(String, String)>>>);
+
+impl ResultHook for Collect {
+    fn run(&self, script: &str, task: &Task) -> Result<(), String> {
+        self.0
+            .lock()
+            .unwrap()
+            .push((script.to_string(), task.return_value().unwrap().to_string()));
+        Ok(())
+    }
+}
+
+#[test]
+fn runs_the_hooks_of_saved_scripts() {
+    let dir = TempDataDir::new("result_hooks");
+    let runtime = TaskRuntime::new("result_hooks", dir.to_path_buf());
+    let results = Arc::new(Mutex::new(Vec::new()));
+    register_result_hook("collect", Collect(results.clone()));
+
+    runtime
+        .save_script("answer", "RuntimeExtension.returnValue(42);")
+        .unwrap();
+    assert!(runtime
+        .set_script_hooks("answer", vec!["unknown".to_string()])
+        .is_err());
+    runtime
+        .set_script_hooks("answer", vec!["collect".to_string()])
+        .unwrap();
+    assert_eq!(runtime.get_script_hooks("answer").unwrap(), ["collect"]);
+
+    runtime
+        .run_saved_script("answer", "answer", None, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+    // not a saved script, no hooks
+    runtime
+        .run_task(
+            "inline",
+            "RuntimeExtension.returnValue(0);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    // hooks run after the task is reported completed, on its thread
+    while runtime.has_running_tasks() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        *results.lock().unwrap(),
+        [("answer".to_string(), "42".to_string())]
+    );
+}
@@ -0,0 +1,81 @@
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{RunOptions, TaskEvent, TaskState};
+use serde_json::json;
+
+fn retry(policy: serde_json::Value) -> RunOptions {
+    serde_json::from_value(json!({ "retry": policy })).unwrap()
+}
+
+// Fails its first two attempts
+const FLAKY: &str = "
+if (RuntimeExtension.attempt < 3) {
+  throw new Error(`ECONNRESET on attempt ${RuntimeExtension.attempt}`);
+}
+RuntimeExtension.returnValue(RuntimeExtension.attempt);
+";
+
+#[test]
+fn runs_failed_tasks_again_with_backoff() {
+    let harness = TestHarness::new("retries");
+
+    // the virtual clock skips the backoff
+    let task = harness
+        .start(
+            FLAKY,
+            retry(json!({ "max_attempts": 3, "backoff_ms": 60000 })),
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.attempt(), 3);
+    assert_eq!(task.return_value(), Some(&json!(3)));
+
+    let retries: Vec<(u32, u64)> = harness
+        .take_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            TaskEvent::StateChanged(task) => match task.state() {
+                TaskState::Retrying {
+                    attempt,
+                    backoff_ms,
+                    message,
+                } => {
+                    assert!(message.contains("ECONNRESET"), "{}", message);
+                    Some((*attempt, *backoff_ms))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(retries, vec![(1, 60000), (2, 120000)]);
+}
+
+#[test]
+fn reports_the_error_once_the_attempts_are_used_up() {
+    let harness = TestHarness::new("retries_exhausted");
+
+    let task = harness
+        .start(FLAKY, retry(json!({ "max_attempts": 2 })))
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert_eq!(task.attempt(), 2);
+    assert!(task.error().contains("attempt 2"), "{}", task.error());
+
+    // only the errors it's told to
+    let task = harness
+        .start(
+            FLAKY,
+            retry(json!({ "max_attempts": 3, "retry_on": ["ETIMEDOUT"] })),
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert_eq!(task.attempt(), 1);
+
+    assert!(harness
+        .runtime()
+        .run_task("invalid", FLAKY, retry(json!({ "max_attempts": 0 })))
+        .is_err());
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{PermissionsResponse, RunOptions};
+use serde_json::json;
+
+#[test]
+fn captures_everything_a_window_shows() {
+    let harness = TestHarness::new("runtime_snapshot");
+    let runtime = harness.runtime();
+    harness.prompter().defer("read");
+    harness.fs().seed_file("/notes.txt", "hello");
+
+    let done = harness.run("RuntimeExtension.returnValue(1);");
+    let asking = harness.start(
+        "RuntimeExtension.returnValue(await Deno.readTextFile(\"/notes.txt\"));",
+        RunOptions::default(),
+    );
+    while asking
+        .state()
+        .is_none_or(|task| task.state().name() != "waiting_for_permission")
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let schedule_id = runtime
+        .schedule_task("0 * * * *", "", RunOptions::default())
+        .unwrap();
+
+    let snapshot = runtime.get_runtime_snapshot();
+    let mut task_ids = vec![done.id(), asking.id()];
+    task_ids.sort();
+    let snapshot_task_ids: Vec<&str> = snapshot.tasks().iter().map(|task| task.id()).collect();
+    assert_eq!(snapshot_task_ids, task_ids);
+
+    assert_eq!(snapshot.pending_prompts().len(), 1);
+    assert_eq!(snapshot.pending_prompts()[0].task_id(), asking.id());
+    assert_eq!(snapshot.pending_prompts()[0].prompt().name(), "read");
+
+    assert_eq!(snapshot.schedules().len(), 1);
+    assert_eq!(snapshot.schedules()[0].id(), schedule_id);
+
+    let value = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(value["queue"]["queued"], json!([]));
+    assert!(value["config"]["config"].is_object(), "{}", value);
+
+    runtime.respond_to_permission_prompt(asking.id(), PermissionsResponse::Allow);
+    let task = asking.wait().unwrap();
+    assert_eq!(task.return_value(), Some(&json!("hello")));
+    assert!(runtime.get_runtime_snapshot().pending_prompts().is_empty());
+}
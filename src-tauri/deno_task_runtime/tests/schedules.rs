@@ -0,0 +1,67 @@
+use deno_task_runtime::test_support::TestHarness;
+use deno_task_runtime::{RunOptions, Task, TaskRuntime};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+#[test]
+fn runs_schedules_when_they_are_due() {
+    let harness = TestHarness::new("schedules");
+    let runtime = harness.runtime();
+
+    let id = runtime
+        .schedule_task(
+            "0 * * * *",
+            "RuntimeExtension.returnValue(\"scheduled\");",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    let schedules = runtime.list_schedules();
+    assert_eq!(schedules.len(), 1);
+    let next = schedules[0].next_run_ms().unwrap();
+    assert_eq!(next % HOUR_MS, 0);
+
+    assert!(runtime.run_schedules_at(next - 1).is_empty());
+
+    let started = runtime.run_schedules_at(next);
+    assert_eq!(started.len(), 1);
+    assert!(started[0].starts_with(&id), "{}", started[0]);
+
+    let task = wait(runtime, &started[0]);
+    assert_eq!(task.return_value(), Some(&serde_json::json!("scheduled")));
+
+    // moved on to the next hour
+    assert!(runtime.run_schedules_at(next).is_empty());
+    let schedules = runtime.list_schedules();
+    assert_eq!(schedules[0].next_run_ms(), Some(next + HOUR_MS));
+    assert_eq!(schedules[0].last_task_id(), Some(started[0].as_str()));
+
+    runtime.cancel_schedule(&id).unwrap();
+    assert!(runtime.list_schedules().is_empty());
+    assert!(runtime.run_schedules_at(next + HOUR_MS).is_empty());
+    assert!(runtime.cancel_schedule(&id).is_err());
+}
+
+#[test]
+fn rejects_invalid_cron_expressions() {
+    let harness = TestHarness::new("invalid_schedules");
+
+    for cron in ["", "every hour", "61 * * * *"] {
+        assert!(harness
+            .runtime()
+            .schedule_task(cron, "", RunOptions::default())
+            .is_err());
+    }
+    assert!(harness.runtime().list_schedules().is_empty());
+}
+
+// Scheduled runs have no handle
+fn wait(runtime: &TaskRuntime, task_id: &str) -> Task {
+    loop {
+        let task = runtime.get_task_state(task_id).unwrap();
+        if task.is_finished() {
+            return task;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
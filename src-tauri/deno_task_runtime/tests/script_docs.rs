@@ -0,0 +1,53 @@
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::TaskRuntime;
+
+const SCRIPT: &str = r#"
+/**
+ * Resizes the images of a folder.
+ *
+ * @param dir - Folder with the images
+ * @param {number} width Width in pixels
+ * @returns How many images were resized
+ */
+export async function resize(dir: string, width = 800): Promise<number> {
+  return 0;
+}
+
+/** Lists the images. */
+export const list = ({ dir }: { dir: string }, ...extensions: string[]) => [];
+
+function internal() {}
+"#;
+
+#[test]
+fn documents_the_exported_functions() {
+    let dir = TempDataDir::new("script_docs");
+    let runtime = TaskRuntime::new("script_docs", dir.to_path_buf());
+    runtime.save_script("images", SCRIPT).unwrap();
+    assert_eq!(runtime.list_scripts(), ["images"]);
+
+    let docs = serde_json::to_value(runtime.generate_script_docs("images").unwrap()).unwrap();
+    let functions = docs["functions"].as_array().unwrap();
+    assert_eq!(functions.len(), 2, "{}", docs);
+
+    let resize = &functions[0];
+    assert_eq!(resize["name"], "resize");
+    assert_eq!(resize["description"], "Resizes the images of a folder.");
+    assert_eq!(resize["return_type"], "Promise<number>");
+    assert_eq!(resize["returns"], "How many images were resized");
+    assert_eq!(resize["is_async"], true);
+    assert_eq!(resize["params"][0]["type"], "string");
+    assert_eq!(resize["params"][0]["description"], "Folder with the images");
+    assert_eq!(resize["params"][1]["name"], "width");
+    assert_eq!(resize["params"][1]["optional"], true);
+    assert_eq!(resize["params"][1]["type"], "number");
+
+    let list = &functions[1];
+    assert_eq!(list["description"], "Lists the images.");
+    assert_eq!(list["params"][0]["name"], "{ dir }");
+    assert_eq!(list["params"][0]["type"], "{ dir: string }");
+    assert_eq!(list["params"][1]["name"], "...extensions");
+
+    runtime.delete_script("images").unwrap();
+    assert!(runtime.generate_script_docs("images").is_err());
+}
@@ -0,0 +1,64 @@
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{RunOptions, TaskRuntime};
+use serde_json::json;
+
+const SCRIPT: &str = r#"
+export const inputs = {
+  type: "object",
+  properties: {
+    city: { type: "string", minLength: 1 },
+    days: { type: "integer", minimum: 1, maximum: 14, default: 7 },
+    units: { enum: ["metric", "imperial"] },
+  },
+  required: ["city"],
+  additionalProperties: false,
+} as const;
+
+const { city, days } = RuntimeExtension.args;
+RuntimeExtension.returnValue(`${city} for ${days} days`);
+"#;
+
+#[test]
+fn runs_saved_scripts_with_validated_args() {
+    let dir = TempDataDir::new("script_inputs");
+    let runtime = TaskRuntime::new("script_inputs", dir.to_path_buf());
+    runtime.save_script("forecast", SCRIPT).unwrap();
+
+    let inputs = runtime.get_script_inputs("forecast").unwrap().unwrap();
+    assert_eq!(inputs["required"], json!(["city"]));
+    assert_eq!(inputs["properties"]["days"]["default"], 7);
+
+    let error = runtime
+        .run_saved_script(
+            "forecast-invalid",
+            "forecast",
+            Some(json!({ "days": 30, "units": "kelvin", "wind": true })),
+            RunOptions::default(),
+        )
+        .unwrap_err();
+    assert!(error.contains("args.city is required"), "{}", error);
+    assert!(error.contains("args.days must be at most 14"), "{}", error);
+    assert!(error.contains("args.units must be one of"), "{}", error);
+    assert!(error.contains("args.wind isn't an input"), "{}", error);
+
+    // `days` falls back to its default
+    let task = runtime
+        .run_saved_script(
+            "forecast",
+            "forecast",
+            Some(json!({ "city": "Lisbon" })),
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.return_value(), Some(&json!("Lisbon for 7 days")));
+
+    runtime
+        .save_script("computed", "export const inputs = { type: typeof 1 };")
+        .unwrap();
+    assert!(runtime.get_script_inputs("computed").is_err());
+
+    runtime.save_script("plain", "export const x = 1;").unwrap();
+    assert_eq!(runtime.get_script_inputs("plain").unwrap(), None);
+}
@@ -0,0 +1,79 @@
+use deno_task_runtime::{get_json_schemas, runtime_types, typescript_sdk};
+
+#[test]
+fn declares_the_task_types() {
+    let sdk = typescript_sdk();
+
+    for name in [
+        "Task",
+        "PermissionPrompt",
+        "RunOptions",
+        "TaskEvent",
+        "SequencedEvent",
+    ] {
+        assert!(
+            sdk.contains(&format!("export type {} = ", name)),
+            "{}",
+            name
+        );
+    }
+
+    // JSON numbers, not the `bigint`s ts-rs maps u64 to
+    assert!(!sdk.contains("bigint"), "{}", sdk);
+}
+
+#[test]
+fn wraps_the_task_commands_and_events() {
+    let sdk = typescript_sdk();
+
+    assert!(sdk.contains("export function runTask(args: { profile?: string; context?: string; taskId?: string; code: string; options?: Partial<RunOptions>; namespaceByWindow?: boolean; input?: unknown }): Promise<string>"), "{}", sdk);
+    assert!(sdk.contains("return invoke(\"respond_to_permission_prompt\", args);"));
+    assert!(sdk.contains("export function onTaskStateChanged("));
+    assert!(sdk.contains("return listen(\"task-output-truncated\", handler);"));
+}
+
+#[test]
+fn exports_json_schemas_of_the_payloads() {
+    let schemas = serde_json::to_value(get_json_schemas()).unwrap();
+
+    let task = &schemas["Task"];
+    assert_eq!(task["type"], "object");
+    assert_eq!(task["properties"]["output"]["type"], "string");
+    assert!(
+        task["definitions"]["PermissionPrompt"].is_object(),
+        "{}",
+        task
+    );
+
+    let response = &schemas["PermissionsResponse"];
+    assert_eq!(
+        response["enum"],
+        serde_json::json!(["Allow", "Deny", "AllowAll"])
+    );
+}
+
+#[test]
+fn declares_the_runtime_extension() {
+    let types = runtime_types();
+
+    assert!(
+        types.contains("declare var RuntimeExtension: RuntimeExtension;"),
+        "{}",
+        types
+    );
+    assert!(
+        types.contains("  returnValue(value: unknown): void;"),
+        "{}",
+        types
+    );
+    assert!(
+        types.contains("  store: RuntimeExtensionStore;"),
+        "{}",
+        types
+    );
+    assert!(
+        types.contains("interface RuntimeExtensionStore {"),
+        "{}",
+        types
+    );
+}
@@ -0,0 +1,77 @@
+use deno_task_runtime::test_support::{evict_over_quota, TempDataDir};
+use deno_task_runtime::{RunOptions, TaskRuntime};
+
+#[test]
+fn compacts_the_data_directory() {
+    let dir = TempDataDir::new("compaction");
+    let runtime = TaskRuntime::new("compaction", dir.to_path_buf());
+
+    let kept = runtime
+        .run_task(
+            "kept",
+            "RuntimeExtension.saveArtifact(\"rows.csv\", \"a,b\\n1,2\");",
+            RunOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(kept.wait().unwrap().state().name(), "completed");
+
+    // left behind by a task cleared in an earlier session
+    let orphaned = dir.join("artifacts").join("gone");
+    std::fs::create_dir_all(&orphaned).unwrap();
+    std::fs::write(orphaned.join("big.bin"), vec![0; 64 * 1024]).unwrap();
+
+    // a store whose rows were all deleted keeps its pages until vacuumed
+    let db_path = dir.join("kv").join("store.sqlite");
+    std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+    let db = rusqlite::Connection::open(&db_path).unwrap();
+    db.execute_batch(
+        "CREATE TABLE rows (data BLOB);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 256)
+         INSERT INTO rows SELECT randomblob(1024) FROM n;
+         DELETE FROM rows;",
+    )
+    .unwrap();
+    drop(db);
+    let db_bytes = std::fs::metadata(&db_path).unwrap().len();
+
+    let report = runtime.compact_storage();
+    assert_eq!(report.orphaned_artifacts(), 1);
+    assert_eq!(report.vacuumed_databases(), 1);
+    assert!(!orphaned.exists());
+    assert!(dir.join("artifacts").join("kept").join("rows.csv").exists());
+    assert!(std::fs::metadata(&db_path).unwrap().len() < db_bytes);
+    assert!(report.reclaimed_bytes() >= 64 * 1024);
+
+    // nothing left to do
+    let again = runtime.compact_storage();
+    assert_eq!(again.orphaned_artifacts(), 0);
+    assert_eq!(again.reclaimed_bytes(), 0);
+}
+
+#[test]
+fn evicts_only_cached_files_over_the_quota() {
+    let dir = TempDataDir::new("eviction");
+
+    let write = |path: &str, bytes: usize| {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![b' '; bytes]).unwrap();
+    };
+    write("config.json", 16 * 1024);
+    write("checkpoints/report.json", 16 * 1024);
+    write("cassettes/api.jsonl", 16 * 1024);
+    write("artifacts/report/rows.csv", 16 * 1024);
+    write("archives/2024-01.jsonl.gz", 16 * 1024);
+
+    // even the cached files all go before the user's
+    assert_eq!(evict_over_quota(&dir, 1024), 3);
+    assert!(dir.join("config.json").exists());
+    assert!(dir.join("checkpoints").join("report.json").exists());
+    assert!(!dir.join("cassettes").join("api.jsonl").exists());
+    assert!(!dir
+        .join("artifacts")
+        .join("report")
+        .join("rows.csv")
+        .exists());
+    assert!(!dir.join("archives").join("2024-01.jsonl.gz").exists());
+}
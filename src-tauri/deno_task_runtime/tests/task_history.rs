@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{RunOptions, TaskHistory, TaskHistoryFilter, TaskRuntime};
+use serde_json::json;
+
+fn filter(filter: serde_json::Value) -> TaskHistoryFilter {
+    serde_json::from_value(filter).unwrap()
+}
+
+// Runs are recorded once their thread is done, after they're reported
+// finished
+fn history(runtime: &TaskRuntime, filter: &TaskHistoryFilter, total: usize) -> TaskHistory {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let history = runtime.get_task_history(filter, &Default::default());
+        if history.total() >= total {
+            return history;
+        }
+        assert!(Instant::now() < deadline, "the runs were never recorded");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn keeps_finished_runs_across_restarts() {
+    let dir = TempDataDir::new("task_history");
+
+    let runtime = TaskRuntime::new("task_history", dir.to_path_buf());
+    for (task_id, code) in [
+        ("report", "RuntimeExtension.returnValue({ rows: 3 });"),
+        ("broken", "throw new Error(\"no rows\");"),
+        ("report_again", "RuntimeExtension.returnValue({ rows: 3 });"),
+    ] {
+        runtime
+            .run_task(task_id, code, RunOptions::default())
+            .unwrap()
+            .wait()
+            .unwrap();
+    }
+    history(&runtime, &TaskHistoryFilter::default(), 3);
+    drop(runtime);
+
+    let restarted = TaskRuntime::new("task_history", dir.to_path_buf());
+    let all = restarted.get_task_history(&TaskHistoryFilter::default(), &Default::default());
+    assert_eq!(all.total(), 3);
+    // newest first
+    let task_ids: Vec<&str> = all
+        .records()
+        .iter()
+        .map(|record| record.task_id())
+        .collect();
+    assert_eq!(task_ids, ["report_again", "broken", "report"]);
+    assert_eq!(all.records()[0].return_value(), Some(&json!({ "rows": 3 })));
+    assert_eq!(
+        all.records()[0].code_sha256(),
+        all.records()[2].code_sha256()
+    );
+    assert_ne!(
+        all.records()[0].code_sha256(),
+        all.records()[1].code_sha256()
+    );
+
+    let failed =
+        restarted.get_task_history(&filter(json!({ "states": ["error"] })), &Default::default());
+    assert_eq!(failed.total(), 1);
+    assert_eq!(failed.records()[0].task_id(), "broken");
+
+    let page = restarted.get_task_history(
+        &TaskHistoryFilter::default(),
+        &serde_json::from_value(json!({ "offset": 1, "limit": 1 })).unwrap(),
+    );
+    assert_eq!(page.total(), 3);
+    assert_eq!(page.records().len(), 1);
+    assert_eq!(page.records()[0].task_id(), "broken");
+}
+
+#[test]
+fn archives_old_runs_with_their_logs_and_artifacts() {
+    let dir = TempDataDir::new("task_archives");
+
+    let runtime = TaskRuntime::new("task_archives", dir.to_path_buf());
+    runtime
+        .run_task(
+            "report",
+            "console.log(\"rows\", 3);\nRuntimeExtension.saveArtifact(\"rows.csv\", \"a,b\\n1,2\");",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    let old = history(&runtime, &TaskHistoryFilter::default(), 1);
+    let before_ms = old.records()[0].finished_at_ms() + 1;
+    assert!(dir.join("artifacts").join("report").exists());
+
+    let summary = runtime.archive_tasks(before_ms).unwrap();
+    assert_eq!(summary.tasks(), 1);
+    assert_eq!(summary.artifacts(), 1);
+    // nothing older left
+    assert!(runtime
+        .archive_tasks(before_ms)
+        .unwrap()
+        .archive()
+        .is_none());
+
+    assert_eq!(
+        runtime
+            .get_task_history(&TaskHistoryFilter::default(), &Default::default())
+            .total(),
+        0
+    );
+    assert!(runtime.get_task_state("report").is_none());
+    assert!(!dir.join("artifacts").join("report").exists());
+
+    let found = runtime.search_task_archives(
+        &filter(json!({ "task_id": "report", "states": ["completed"] })),
+        &Default::default(),
+    );
+    assert_eq!(found.total(), 1);
+    let entry = &found.entries()[0];
+    assert_eq!(Some(entry.archive()), summary.archive());
+
+    let runs = runtime
+        .get_archived_task(entry.archive(), entry.task_id())
+        .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].record().task_id(), "report");
+    assert_eq!(runs[0].logs().len(), 1);
+    assert_eq!(runs[0].artifacts()[0].name(), "rows.csv");
+    assert_eq!(runs[0].artifacts()[0].data().unwrap(), b"a,b\n1,2");
+    assert!(runtime.get_archived_task("../secrets", "report").is_err());
+}
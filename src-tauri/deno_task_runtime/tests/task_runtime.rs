@@ -0,0 +1,1227 @@
+use std::sync::{Arc, Mutex};
+
+use deno_core::error::AnyError;
+use deno_core::ModuleSpecifier;
+use deno_task_runtime::test_support::TempDataDir;
+use deno_task_runtime::{
+    validate_task_id, ModuleLoader, PermissionBroker, PermissionPrompt, PermissionsResponse,
+    RunOptions, TaskOutput, TaskRuntime, TaskState, WaitingKind,
+};
+use serde_json::json;
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+const REMOTE_MODULE: &str = "https://example.invalid/answer.ts";
+
+struct Answer(PermissionsResponse);
+
+impl PermissionBroker for Answer {
+    fn prompt(&self, _task_id: &str, _prompt: &PermissionPrompt) -> Option<PermissionsResponse> {
+        Some(self.0.clone())
+    }
+}
+
+struct RemoteAnswer;
+
+impl ModuleLoader for RemoteAnswer {
+    fn load_source(&self, specifier: &ModuleSpecifier) -> Result<Option<String>, AnyError> {
+        if specifier.as_str() != REMOTE_MODULE {
+            return Ok(None);
+        }
+
+        Ok(Some("export const answer: number = 42;".to_string()))
+    }
+}
+
+#[test]
+fn runs_a_task_to_completion() {
+    let dir = TempDataDir::new("completion");
+    let runtime = TaskRuntime::new("completion", dir.to_path_buf());
+
+    let task = runtime
+        .run_task(
+            "completion",
+            "const answer: number = 40 + 2;\nRuntimeExtension.returnValue(answer);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed");
+    assert_eq!(task.return_value(), Some(&json!(42)));
+    assert!(!runtime.has_running_tasks());
+}
+
+#[test]
+fn reports_uncaught_errors() {
+    let dir = TempDataDir::new("errors");
+    let runtime = TaskRuntime::new("errors", dir.to_path_buf());
+
+    let task = runtime
+        .run_task(
+            "errors",
+            "throw new Error(\"boom\");",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "error");
+    assert!(task.error().contains("boom"), "{}", task.error());
+    // the stack is kept apart from the message
+    assert!(
+        matches!(task.state(), TaskState::Error { stack: Some(stack), .. } if stack.contains("at ")),
+        "{:?}",
+        task.state()
+    );
+}
+
+#[test]
+fn maps_error_frames_back_to_the_code_as_written() {
+    let dir = TempDataDir::new("error_frames");
+    let runtime = TaskRuntime::new("error_frames", dir.to_path_buf());
+
+    let code = "type Unit = \"c\" | \"f\";\n\nfunction convert(unit: Unit): number {\n  throw new Error(`unknown unit ${unit}`);\n}\n\nconvert(\"c\");";
+    let task = runtime
+        .run_task("error_frames", code, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    let TaskState::Error { frames, .. } = task.state() else {
+        panic!("{:?}", task.state());
+    };
+    let frame = serde_json::to_value(&frames[0]).unwrap();
+    assert_eq!(frame["file_name"], serde_json::Value::Null, "{}", frame);
+    assert_eq!(frame["function_name"], "convert");
+    assert_eq!(frame["line"], 4);
+    assert_eq!(frame["column"], 9);
+    assert_eq!(
+        frame["snippet"],
+        "  throw new Error(`unknown unit ${unit}`);"
+    );
+}
+
+#[test]
+fn captures_console_output() {
+    let dir = TempDataDir::new("output");
+    let runtime = TaskRuntime::new("output", dir.to_path_buf());
+
+    let task = runtime
+        .run_task("output", "console.log(\"hello\");", RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.output().to_string(), "hello\n");
+}
+
+#[test]
+fn keeps_the_truncated_byte_count_of_the_output() {
+    let mut output = TaskOutput::default();
+    assert_eq!(output.push("hello\n", 8), ("hello\n", false));
+    // cut before the 3 bytes of the euro sign
+    assert_eq!(output.push("€", 8), ("", true));
+    // fits in what the cut left, but comes after the dropped bytes
+    assert_eq!(output.push("!", 8), ("", false));
+    assert_eq!(output.truncated_bytes(), 4);
+
+    let json = serde_json::to_value(&output).unwrap();
+    assert_eq!(
+        json,
+        json!({ "output": "hello\n", "output_truncated_bytes": 4 })
+    );
+    let read: TaskOutput = serde_json::from_value(json).unwrap();
+    assert_eq!(read.text(), "hello\n");
+    assert_eq!(read.truncated_bytes(), 4);
+    assert_eq!(read.to_string(), "hello\n\n…4 bytes truncated…\n");
+}
+
+#[test]
+fn captures_console_calls_as_structured_logs() {
+    let dir = TempDataDir::new("logs");
+    let runtime = TaskRuntime::new("logs", dir.to_path_buf());
+
+    let code = "console.warn(\"low\", { left: 3 }, new Error(\"empty\"));";
+    runtime
+        .run_task("logs", code, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    let logs = serde_json::to_value(runtime.get_task_logs("logs").unwrap()).unwrap();
+    assert_eq!(logs.as_array().unwrap().len(), 1, "{}", logs);
+    assert_eq!(logs[0]["level"], "warn");
+    assert_eq!(logs[0]["args"][0], "low");
+    assert_eq!(logs[0]["args"][1], json!({ "left": 3 }));
+    assert_eq!(logs[0]["args"][2]["message"], "empty");
+}
+
+#[test]
+fn streams_console_output_to_late_subscribers() {
+    let dir = TempDataDir::new("output_stream");
+    let runtime = TaskRuntime::new("output_stream", dir.to_path_buf());
+
+    let code = "console.log(\"hello\");\nconsole.error(\"oops\");";
+    runtime
+        .run_task("output_stream", code, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let channel = Channel::new(move |body| {
+        if let InvokeResponseBody::Json(json) = body {
+            sink.lock().unwrap().push(json);
+        }
+        Ok(())
+    });
+    runtime
+        .subscribe_task_output("output_stream", channel, None)
+        .unwrap();
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        [
+            r#"{"stream":"stdout","text":"hello\n"}"#,
+            r#"{"stream":"stderr","text":"oops\n"}"#,
+        ]
+    );
+}
+
+#[test]
+fn broker_answers_permission_prompts() {
+    let dir = TempDataDir::new("broker_allow");
+    let runtime = TaskRuntime::new("broker_allow", dir.to_path_buf());
+    runtime.set_permission_broker(Answer(PermissionsResponse::Allow));
+
+    let task = runtime
+        .run_task(
+            "broker_allow",
+            "RuntimeExtension.returnValue(typeof Deno.env.get(\"PATH\"));",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed");
+    assert_eq!(task.return_value(), Some(&json!("string")));
+
+    let history = task.permission_history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].name(), "env");
+}
+
+#[test]
+fn broker_denials_fail_the_task() {
+    let dir = TempDataDir::new("broker_deny");
+    let runtime = TaskRuntime::new("broker_deny", dir.to_path_buf());
+    runtime.set_permission_broker(Answer(PermissionsResponse::Deny));
+
+    let task = runtime
+        .run_task(
+            "broker_deny",
+            "Deno.env.get(\"PATH\");",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error().contains("Requires env access"),
+        "{}",
+        task.error()
+    );
+}
+
+#[test]
+fn asks_before_tasks_emit_app_events() {
+    let dir = TempDataDir::new("emit");
+    let runtime = TaskRuntime::new("emit", dir.to_path_buf());
+    runtime.set_permission_broker(Answer(PermissionsResponse::Deny));
+
+    let task = runtime
+        .run_task(
+            "emit_denied",
+            "RuntimeExtension.emit(\"report-ready\", { rows: 3 });",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error().contains("Requires sending events to the app"),
+        "{}",
+        task.error()
+    );
+    let history = task.permission_history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].name(), "emit");
+
+    // refused before asking
+    let task = runtime
+        .run_task(
+            "emit_reserved",
+            "RuntimeExtension.emit(\"task-state-changed\", {});",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert!(
+        task.error().contains("is an event of the runtime"),
+        "{}",
+        task.error()
+    );
+    assert!(task.permission_history().is_empty());
+    let task = runtime
+        .run_task(
+            "emit_tauri",
+            "RuntimeExtension.emit(\"tauri://close-requested\", {});",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert!(
+        task.error().contains("is an event of Tauri"),
+        "{}",
+        task.error()
+    );
+    assert!(task.permission_history().is_empty());
+}
+
+#[test]
+fn asks_before_tasks_listen_to_app_events() {
+    let dir = TempDataDir::new("listen");
+    let runtime = TaskRuntime::new("listen", dir.to_path_buf());
+    runtime.set_permission_broker(Answer(PermissionsResponse::Deny));
+
+    let task = runtime
+        .run_task(
+            "listen_denied",
+            "RuntimeExtension.listen(\"theme-changed\", () => {});",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert!(
+        task.error()
+            .contains("Requires receiving events from the app"),
+        "{}",
+        task.error()
+    );
+    let history = task.permission_history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].name(), "listen");
+
+    // other tasks' events aren't theirs to hear
+    let task = runtime
+        .run_task(
+            "listen_reserved",
+            "RuntimeExtension.listen(\"task-log\", () => {});",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert!(
+        task.error().contains("is an event of the runtime"),
+        "{}",
+        task.error()
+    );
+    assert!(task.permission_history().is_empty());
+    let task = runtime
+        .run_task(
+            "listen_tauri",
+            "RuntimeExtension.listen(\"tauri://close-requested\", () => {});",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert!(
+        task.error().contains("is an event of Tauri"),
+        "{}",
+        task.error()
+    );
+    assert!(task.permission_history().is_empty());
+}
+
+#[test]
+fn module_loader_serves_imports() {
+    let dir = TempDataDir::new("module_loader");
+    let runtime = TaskRuntime::new("module_loader", dir.to_path_buf());
+    runtime.set_module_loader(RemoteAnswer);
+
+    let code = format!(
+        "import {{ answer }} from \"{}\";\nRuntimeExtension.returnValue(answer);",
+        REMOTE_MODULE
+    );
+
+    let task = runtime
+        .run_task("module_loader", &code, RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!(42)));
+}
+
+#[test]
+fn stops_running_tasks() {
+    let dir = TempDataDir::new("stop");
+    let runtime = TaskRuntime::new("stop", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "stop",
+            "await new Promise(() => setInterval(() => {}, 1000));",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // running before its thread even started
+    assert_eq!(handle.state().unwrap().state().name(), "running");
+    handle.stop().unwrap();
+
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "stopped");
+}
+
+#[test]
+fn stops_tasks_stuck_in_a_loop() {
+    let dir = TempDataDir::new("stop_busy");
+    let runtime = TaskRuntime::new("stop_busy", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "stop_busy",
+            "RuntimeExtension.returnValue(\"looping\");\nwhile (true) {}",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // in the loop once the value is in
+    while handle
+        .state()
+        .is_none_or(|task| task.return_value().is_none())
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    handle.stop().unwrap();
+
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "stopped");
+    while runtime.has_running_tasks() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn queues_background_tasks_by_priority() {
+    let dir = TempDataDir::new("priority");
+    let runtime = TaskRuntime::new("priority", dir.to_path_buf());
+    // holds the background tasks back
+    runtime.set_memory_pressure(true);
+
+    let mut handles = Vec::new();
+    for (task_id, priority) in [("low", -1), ("first", 0), ("high", 5), ("second", 0)] {
+        let options: RunOptions =
+            serde_json::from_value(json!({ "lane": "background", "priority": priority })).unwrap();
+        let handle = runtime.run_task(task_id, "", options).unwrap();
+        assert_eq!(handle.state().unwrap().state().name(), "queued");
+        handles.push(handle);
+    }
+
+    let queue = runtime.get_queue();
+    let queued: Vec<&str> = queue.queued().iter().map(|entry| entry.task_id()).collect();
+    assert_eq!(queued, ["high", "first", "second", "low"]);
+    assert_eq!(queue.running(), 0);
+
+    runtime.set_memory_pressure(false);
+    for handle in handles {
+        assert_eq!(handle.wait().unwrap().state().name(), "completed");
+    }
+    assert!(runtime.get_queue().queued().is_empty());
+}
+
+#[test]
+fn queues_runs_of_the_same_script_fairly() {
+    let dir = TempDataDir::new("fairness");
+    let runtime = TaskRuntime::new("fairness", dir.to_path_buf());
+    let code = "const end = Date.now() + 100;\nwhile (Date.now() < end) {}";
+    runtime.save_script("burst", code).unwrap();
+    runtime.save_script("other", code).unwrap();
+
+    let busy = runtime
+        .run_saved_script("burst_busy", "burst", None, RunOptions::default())
+        .unwrap();
+    assert_eq!(runtime.get_queue().in_flight("burst"), 1);
+    busy.wait().unwrap();
+    // the slot is freed once the thread is done
+    while runtime.get_queue().in_flight("burst") > 0 {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // holds the background tasks back
+    runtime.set_memory_pressure(true);
+
+    let mut handles = Vec::new();
+    for (task_id, script) in [
+        ("burst_1", "burst"),
+        ("burst_2", "burst"),
+        ("burst_3", "burst"),
+        ("other_1", "other"),
+    ] {
+        let options: RunOptions = serde_json::from_value(json!({ "lane": "background" })).unwrap();
+        handles.push(
+            runtime
+                .run_saved_script(task_id, script, None, options)
+                .unwrap(),
+        );
+    }
+
+    // the other script doesn't wait for the whole burst
+    let queue = runtime.get_queue();
+    let queued: Vec<&str> = queue.queued().iter().map(|entry| entry.task_id()).collect();
+    assert_eq!(queued, ["burst_1", "other_1", "burst_2", "burst_3"]);
+
+    runtime.set_memory_pressure(false);
+    for handle in handles {
+        assert_eq!(handle.wait().unwrap().state().name(), "completed");
+    }
+}
+
+#[test]
+fn pauses_and_resumes_tasks() {
+    let dir = TempDataDir::new("pause");
+    let runtime = TaskRuntime::new("pause", dir.to_path_buf());
+
+    let code = "RuntimeExtension.returnValue(\"looping\");
+const end = Date.now() + 300;
+while (Date.now() < end) {}";
+    let paused = runtime
+        .run_task("pause_resumed", code, RunOptions::default())
+        .unwrap();
+    let stopped = runtime
+        .run_task("pause_stopped", code, RunOptions::default())
+        .unwrap();
+
+    for handle in [&paused, &stopped] {
+        while handle
+            .state()
+            .is_none_or(|task| task.return_value().is_none())
+        {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        handle.pause().unwrap();
+    }
+
+    // held in the loop past its end
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert_eq!(paused.state().unwrap().state().name(), "paused");
+    assert!(paused.pause().is_err());
+
+    paused.resume().unwrap();
+    assert_eq!(paused.wait().unwrap().state().name(), "completed");
+
+    stopped.stop().unwrap();
+    assert_eq!(stopped.wait().unwrap().state().name(), "stopped");
+    while runtime.has_running_tasks() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn stopping_a_task_releases_its_prompt() {
+    let waiting_dir = TempDataDir::new("prompt_waiting");
+    let waiting = TaskRuntime::new("prompt_waiting", waiting_dir.to_path_buf());
+    let handle = waiting
+        .run_task(
+            "prompt_waiting",
+            "Deno.env.get(\"PATH\");",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    while handle
+        .state()
+        .is_none_or(|task| task.state().name() != "waiting_for_permission")
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // prompts of other tasks wait behind the pending one
+    let answered_dir = TempDataDir::new("prompt_answered");
+    let answered = TaskRuntime::new("prompt_answered", answered_dir.to_path_buf());
+    answered.set_permission_broker(Answer(PermissionsResponse::Allow));
+    let other = answered
+        .run_task(
+            "prompt_answered",
+            "RuntimeExtension.returnValue(typeof Deno.env.get(\"PATH\"));",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    handle.stop().unwrap();
+    assert_eq!(handle.wait().unwrap().state().name(), "stopped");
+
+    let task = other.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!("string")));
+}
+
+#[test]
+fn times_out_tasks_that_run_too_long() {
+    let dir = TempDataDir::new("timeout");
+    let runtime = TaskRuntime::new("timeout", dir.to_path_buf());
+    let options: RunOptions = serde_json::from_value(json!({ "timeout_ms": 200 })).unwrap();
+
+    // terminated while it's running, and while it's waiting on the event loop
+    for (task_id, code) in [
+        ("busy", "while (true) {}"),
+        (
+            "idle",
+            "await new Promise(() => setInterval(() => {}, 1000));",
+        ),
+    ] {
+        let task = runtime
+            .run_task(task_id, code, options.clone())
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(
+            matches!(task.state(), TaskState::TimedOut { timeout_ms: 200 }),
+            "{:?}",
+            task.state()
+        );
+    }
+
+    // timed out before its thread is done
+    while runtime.has_running_tasks() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let staged = std::fs::read_dir(&dir).unwrap().flatten().any(|entry| {
+        entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with("temp_code_")
+    });
+    assert!(!staged);
+
+    let task = runtime
+        .run_task("in-time", "RuntimeExtension.returnValue(1);", options)
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+
+    let options = serde_json::from_value(json!({ "timeout_ms": 0 })).unwrap();
+    assert!(runtime.run_task("no-time", "", options).is_err());
+}
+
+#[test]
+fn fails_tasks_over_their_cpu_time_limit() {
+    let dir = TempDataDir::new("cpu_time");
+    let runtime = TaskRuntime::new("cpu_time", dir.to_path_buf());
+    let options: RunOptions = serde_json::from_value(json!({ "cpu_time_limit_ms": 200 })).unwrap();
+
+    let task = runtime
+        .run_task("busy", "while (true) {}", options.clone())
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert!(
+        task.error().contains("CPU time limit of 200ms exceeded"),
+        "{}",
+        task.error()
+    );
+
+    // waiting doesn't count
+    let task = runtime
+        .run_task(
+            "idle",
+            "await new Promise((resolve) => setTimeout(resolve, 500));",
+            options,
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+
+    // recorded once the thread is done
+    while runtime.has_running_tasks() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let busy = runtime.get_task_state("busy").unwrap().cpu_time_ms();
+    assert!(busy.is_some_and(|ms| ms >= 200), "{:?}", busy);
+    let idle = runtime.get_task_state("idle").unwrap().cpu_time_ms();
+    assert!(idle.is_some_and(|ms| ms < 200), "{:?}", idle);
+}
+
+#[test]
+fn formats_in_the_task_locale_and_timezone() {
+    let dir = TempDataDir::new("intl");
+    let runtime = TaskRuntime::new("intl", dir.to_path_buf());
+    let options: RunOptions =
+        serde_json::from_value(json!({ "locale": "de-DE", "timezone": "Asia/Tokyo" })).unwrap();
+
+    let task = runtime
+        .run_task(
+            "formatted",
+            "RuntimeExtension.returnValue([
+  new Date(0).toLocaleString(),
+  (1234.5).toLocaleString(),
+  new Intl.NumberFormat(\"en-US\").format(1234.5),
+  Intl.DateTimeFormat().resolvedOptions().timeZone,
+]);",
+            options,
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(
+        task.return_value(),
+        Some(&json!([
+            "1.1.1970, 09:00:00",
+            "1.234,5",
+            "1,234.5",
+            "Asia/Tokyo"
+        ]))
+    );
+
+    let options = serde_json::from_value(json!({ "timezone": "Mars/Olympus_Mons" })).unwrap();
+    let task = runtime
+        .run_task("unknown_timezone", "", options)
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert!(
+        task.error().contains("Invalid locale or timezone"),
+        "{}",
+        task.error()
+    );
+}
+
+#[test]
+fn fails_tasks_over_their_heap_limit() {
+    let dir = TempDataDir::new("heap_limit");
+    let runtime = TaskRuntime::new("heap_limit", dir.to_path_buf());
+    let options: RunOptions = serde_json::from_value(json!({ "max_heap_size_mb": 64 })).unwrap();
+
+    let task = runtime
+        .run_task(
+            "heap_limit",
+            "const chunks = [];\nwhile (true) chunks.push(new Array(1_000_000).fill(1.5));",
+            options,
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "error");
+    assert!(task.error().contains("Out of memory"), "{}", task.error());
+
+    let options = serde_json::from_value(json!({ "max_heap_size_mb": 1 })).unwrap();
+    assert!(runtime.run_task("tiny_heap", "", options).is_err());
+}
+
+#[test]
+fn prewarmed_worker_runs_the_next_task() {
+    let dir = TempDataDir::new("prewarm");
+    let runtime = TaskRuntime::new("prewarm", dir.to_path_buf());
+    runtime.prewarm_worker();
+
+    let task = runtime
+        .run_task(
+            "prewarm",
+            "RuntimeExtension.returnValue(Deno.mainModule.includes(RuntimeExtension.taskId));",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!(true)));
+
+    // the next prewarmed worker is a fresh one
+    let task = runtime
+        .run_task(
+            "prewarm_leak",
+            "globalThis.leaked = 1;",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    let task = runtime
+        .run_task(
+            "prewarm_after_leak",
+            "RuntimeExtension.returnValue(typeof globalThis.leaked);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.return_value(), Some(&json!("undefined")));
+}
+
+#[test]
+fn reads_stdin_written_by_the_app() {
+    let dir = TempDataDir::new("stdin");
+    let runtime = TaskRuntime::new("stdin", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "stdin",
+            "const name = prompt(\"Name?\");\n\
+             const sure = confirm(\"Sure?\");\n\
+             RuntimeExtension.returnValue({ name, sure, empty: prompt(\"Empty?\", \"none\") });",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // the pipe is set up before it gets to the first prompt
+    let waiting_on = loop {
+        if let Some(waiting_on) = handle.state().and_then(|task| task.waiting_on().cloned()) {
+            break waiting_on;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert_eq!(waiting_on.kind(), WaitingKind::UserInput);
+    assert_eq!(waiting_on.detail(), "Name?");
+    runtime.write_task_stdin("stdin", b"Ada\ny\n\n").unwrap();
+
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(
+        task.return_value(),
+        Some(&json!({ "name": "Ada", "sure": true, "empty": "none" }))
+    );
+    assert!(task.waiting_on().is_none());
+    assert!(task.output().to_string().contains("Name? "));
+    assert!(runtime.write_task_stdin("stdin", b"late\n").is_err());
+}
+
+#[test]
+fn exposes_the_input_to_the_script() {
+    let dir = TempDataDir::new("input");
+    let runtime = TaskRuntime::new("input", dir.to_path_buf());
+    // read through an op, so idle workers see it too
+    runtime.prewarm_worker();
+
+    let mut options = RunOptions::default();
+    options.set_input(json!({ "city": "Lisbon", "days": [1, 2] }));
+    let task = runtime
+        .run_task(
+            "input",
+            "const { city, days } = RuntimeExtension.input;\nRuntimeExtension.returnValue(`${city}: ${days.length}`);",
+            options,
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.return_value(), Some(&json!("Lisbon: 2")));
+
+    let task = runtime
+        .run_task(
+            "no-input",
+            "RuntimeExtension.returnValue(RuntimeExtension.input === undefined);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.return_value(), Some(&json!(true)));
+}
+
+#[test]
+fn defers_tasks_until_online() {
+    let dir = TempDataDir::new("offline");
+    let runtime = TaskRuntime::new("offline", dir.to_path_buf());
+    let options: RunOptions =
+        serde_json::from_value(json!({ "queue_until_online": true })).unwrap();
+    runtime.set_online(false);
+
+    let deferred = runtime
+        .run_task(
+            "deferred",
+            "RuntimeExtension.returnValue(1);",
+            options.clone(),
+        )
+        .unwrap();
+    let stopped = runtime
+        .run_task("stopped", "RuntimeExtension.returnValue(2);", options)
+        .unwrap();
+    assert_eq!(
+        deferred.state().unwrap().state().name(),
+        "waiting_for_network"
+    );
+    assert_eq!(
+        deferred.state().unwrap().waiting_on().unwrap().kind(),
+        WaitingKind::Network
+    );
+
+    // the others run regardless
+    let task = runtime
+        .run_task(
+            "not_deferred",
+            "RuntimeExtension.returnValue(3);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+
+    stopped.stop().unwrap();
+    assert_eq!(stopped.wait().unwrap().state().name(), "stopped");
+    assert_eq!(
+        deferred.state().unwrap().state().name(),
+        "waiting_for_network"
+    );
+
+    runtime.set_online(true);
+    let task = deferred.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!(1)));
+    assert_eq!(stopped.state().unwrap().state().name(), "stopped");
+}
+
+#[test]
+fn notifies_tasks_of_memory_pressure() {
+    let dir = TempDataDir::new("memory_pressure");
+    let runtime = TaskRuntime::new("memory_pressure", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "memory_pressure",
+            "const keepAlive = setInterval(() => {}, 1000);\n\
+             const event = await new Promise((resolve) => RuntimeExtension.onHostEvent(\"memory-pressure\", resolve));\n\
+             clearInterval(keepAlive);\n\
+             RuntimeExtension.returnValue(event);",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // the task may not be listening yet
+    while !handle.state().is_some_and(|task| task.is_finished()) {
+        runtime.set_memory_pressure(true);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        runtime.set_memory_pressure(false);
+    }
+
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!("memory-pressure")));
+}
+
+#[test]
+fn rejects_path_like_task_ids() {
+    assert!(validate_task_id("nightly-report.v2").is_ok());
+    assert!(validate_task_id("../escape").is_err());
+    assert!(validate_task_id(".hidden").is_err());
+}
+
+#[test]
+fn refuses_to_start_a_task_id_that_is_running() {
+    let dir = TempDataDir::new("duplicate_id");
+    let runtime = TaskRuntime::new("duplicate_id", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "duplicate",
+            "await new Promise(() => setInterval(() => {}, 1000));",
+            RunOptions::default(),
+        )
+        .unwrap();
+    let again = runtime.run_task(
+        "duplicate",
+        "RuntimeExtension.returnValue(2);",
+        RunOptions::default(),
+    );
+    assert_eq!(
+        again.map(drop),
+        Err("Task duplicate is already running".to_string())
+    );
+
+    while handle.state().is_none() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    handle.stop().unwrap();
+    assert_eq!(handle.wait().unwrap().state().name(), "stopped");
+
+    // free again once it's stopped
+    let task = runtime
+        .run_task(
+            "duplicate",
+            "RuntimeExtension.returnValue(2);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.return_value(), Some(&json!(2)));
+}
+
+#[test]
+fn parses_permission_responses() {
+    assert!(matches!(
+        "AllowAll".parse::<PermissionsResponse>(),
+        Ok(PermissionsResponse::AllowAll)
+    ));
+    assert!("allow".parse::<PermissionsResponse>().is_err());
+}
+
+#[test]
+fn lists_tasks_newest_first() {
+    let dir = TempDataDir::new("list_tasks");
+    let runtime = TaskRuntime::new("list_tasks", dir.to_path_buf());
+    for (task_id, code) in [
+        ("first", "RuntimeExtension.returnValue(1);"),
+        ("broken", "throw new Error(\"no\");"),
+        ("chatty", "console.log(\"found the needle\");"),
+    ] {
+        runtime
+            .run_task(task_id, code, RunOptions::default())
+            .unwrap()
+            .wait()
+            .unwrap();
+        // apart in creation time
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+
+    let ids = |list: deno_task_runtime::TaskList| -> Vec<String> {
+        list.tasks()
+            .iter()
+            .map(|task| task.id().to_string())
+            .collect()
+    };
+    assert_eq!(
+        ids(runtime.list_tasks(None, None, None, 0, None)),
+        ["chatty", "broken", "first"]
+    );
+    assert_eq!(
+        ids(runtime.list_tasks(Some(&["error".to_string()]), None, None, 0, None)),
+        ["broken"]
+    );
+    assert_eq!(
+        ids(runtime.list_tasks(None, None, Some("NEEDLE"), 0, None)),
+        ["chatty"]
+    );
+
+    let page = runtime.list_tasks(None, None, None, 1, Some(1));
+    assert_eq!(page.total(), 3);
+    assert_eq!(ids(page), ["broken"]);
+}
+
+#[test]
+fn filters_tasks_by_tag() {
+    let dir = TempDataDir::new("task_tags");
+    let runtime = TaskRuntime::new("task_tags", dir.to_path_buf());
+    for (task_id, options) in [
+        (
+            "nightly_report",
+            json!({ "name": "Nightly report", "tags": ["reports", "nightly"] }),
+        ),
+        (
+            "weekly_report",
+            json!({ "name": "Weekly report", "description": "Sums up the week", "tags": ["reports"] }),
+        ),
+        ("untagged", json!({})),
+    ] {
+        let options: RunOptions = serde_json::from_value(options).unwrap();
+        runtime
+            .run_task(task_id, "RuntimeExtension.returnValue(1);", options)
+            .unwrap()
+            .wait()
+            .unwrap();
+    }
+
+    let task = runtime.get_task_state("weekly_report").unwrap();
+    assert_eq!(task.name(), Some("Weekly report"));
+    assert_eq!(task.description(), Some("Sums up the week"));
+    assert_eq!(task.tags(), ["reports"]);
+
+    let ids = |list: &deno_task_runtime::TaskList| -> Vec<String> {
+        let mut ids: Vec<String> = list
+            .tasks()
+            .iter()
+            .map(|task| task.id().to_string())
+            .collect();
+        ids.sort();
+        ids
+    };
+    let all = runtime.list_tasks(None, None, None, 0, None);
+    assert_eq!(all.tag_counts().get("reports"), Some(&2));
+    assert_eq!(all.tag_counts().get("nightly"), Some(&1));
+
+    let reports = runtime.list_tasks(None, Some(&["reports".to_string()]), None, 0, None);
+    assert_eq!(ids(&reports), ["nightly_report", "weekly_report"]);
+    let both = ["reports".to_string(), "nightly".to_string()];
+    assert_eq!(
+        ids(&runtime.list_tasks(None, Some(&both), None, 0, None)),
+        ["nightly_report"]
+    );
+    assert_eq!(
+        ids(&runtime.list_tasks(None, None, Some("the week"), 0, None)),
+        ["weekly_report"]
+    );
+
+    let empty: RunOptions = serde_json::from_value(json!({ "tags": [" "] })).unwrap();
+    assert!(runtime.run_task("empty_tag", "", empty).is_err());
+}
+
+#[test]
+fn times_queued_and_finished_tasks() {
+    let dir = TempDataDir::new("task_timings");
+    let runtime = TaskRuntime::new("task_timings", dir.to_path_buf());
+    // holds the background task back
+    runtime.set_memory_pressure(true);
+
+    let options: RunOptions = serde_json::from_value(json!({ "lane": "background" })).unwrap();
+    let handle = runtime
+        .run_task(
+            "timed",
+            "const end = Date.now() + 50;\nwhile (Date.now() < end) {}",
+            options,
+        )
+        .unwrap();
+    let queued = handle.state().unwrap();
+    assert!(queued.started_at_ms().is_none());
+    std::thread::sleep(std::time::Duration::from_millis(20));
+
+    runtime.set_memory_pressure(false);
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    // submitted when it was queued, started once a slot was free
+    assert_eq!(task.created_at_ms(), queued.created_at_ms());
+    let started_at_ms = task.started_at_ms().unwrap();
+    assert!(started_at_ms >= task.created_at_ms() + 20);
+    let finished_at_ms = task.finished_at_ms().unwrap();
+    assert_eq!(task.duration_ms(), Some(finished_at_ms - started_at_ms));
+    assert!(task.duration_ms().unwrap() >= 50);
+    assert_eq!(
+        task.timings().queue_wait_ms(),
+        started_at_ms - task.created_at_ms()
+    );
+}
+
+#[test]
+fn breaks_down_where_the_time_went() {
+    let dir = TempDataDir::new("phase_timings");
+    let runtime = TaskRuntime::new("phase_timings", dir.to_path_buf());
+
+    let task = runtime
+        .run_task(
+            "phases",
+            "const end = Date.now() + 30;
+             while (Date.now() < end) {}
+             await new Promise((resolve) => setTimeout(resolve, 0));
+             setTimeout(() => {}, 40);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    let timings = task.timings();
+    assert_eq!(timings.queue_wait_ms(), 0);
+    assert!(!timings.prewarmed());
+    assert!(timings.bootstrap_ms().is_some());
+    assert!(timings.module_load_ms().is_some());
+    assert!(timings.execution_ms().unwrap() >= 30);
+    assert!(timings.event_loop_ms().unwrap() >= 40);
+
+    // a module that fails to load never runs
+    let task = runtime
+        .run_task("unparsable", "const = ;", RunOptions::default())
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert!(task.timings().module_load_ms().is_some());
+    assert!(task.timings().execution_ms().is_none());
+    assert!(task.timings().event_loop_ms().is_none());
+}
+
+#[test]
+fn keeps_the_latest_progress() {
+    let dir = TempDataDir::new("progress");
+    let runtime = TaskRuntime::new("progress", dir.to_path_buf());
+
+    let task = runtime
+        .run_task(
+            "progress",
+            "RuntimeExtension.reportProgress(10);\nRuntimeExtension.reportProgress(50, \"Halfway\");",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    let progress = task.progress().unwrap();
+    assert_eq!(progress.percent(), 50.0);
+    assert_eq!(progress.message(), Some("Halfway"));
+
+    let task = runtime
+        .run_task(
+            "too_much_progress",
+            "RuntimeExtension.reportProgress(150);",
+            RunOptions::default(),
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+    assert_eq!(task.state().name(), "error");
+    assert!(task.error().contains("from 0 to 100"), "{}", task.error());
+    assert!(task.progress().is_none());
+}
+
+#[test]
+fn delivers_messages_to_running_tasks() {
+    let dir = TempDataDir::new("messages");
+    let runtime = TaskRuntime::new("messages", dir.to_path_buf());
+
+    let handle = runtime
+        .run_task(
+            "messages",
+            "// listening alone doesn't keep the task running\n\
+             const keepAlive = setInterval(() => {}, 1000);\n\
+             const message = await new Promise((resolve) => RuntimeExtension.onMessage(resolve));\n\
+             clearInterval(keepAlive);\n\
+             RuntimeExtension.postMessage({ received: message.n });\n\
+             RuntimeExtension.returnValue(message.n + 1);",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+    // the inbox opens once the task's thread starts
+    while runtime
+        .post_message_to_task("messages", &json!({ "n": 41 }))
+        .is_err()
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let task = handle.wait().unwrap();
+    assert_eq!(task.state().name(), "completed", "{}", task.error());
+    assert_eq!(task.return_value(), Some(&json!(42)));
+    assert!(runtime
+        .post_message_to_task("messages", &json!("late"))
+        .is_err());
+}
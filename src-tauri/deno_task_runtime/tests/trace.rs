@@ -0,0 +1,24 @@
+use deno_task_runtime::generate_bundle_signing_key;
+use deno_task_runtime::test_support::TraceEntry;
+use serde_json::json;
+
+#[test]
+fn leaves_secrets_out_of_traced_commands() {
+    let key = generate_bundle_signing_key();
+
+    let entry = TraceEntry::command(
+        "sign_script_bundle",
+        &json!({ "path": "/tmp/report.denotask", "privateKey": key.private_key }),
+    );
+    let line = serde_json::to_string(&entry).unwrap();
+    assert!(!line.contains(&key.private_key), "{}", line);
+    assert!(line.contains("/tmp/report.denotask"), "{}", line);
+
+    let entry = TraceEntry::command(
+        "write_task_stdin",
+        &json!({ "taskId": "login", "data": "hunter2\n" }),
+    );
+    let line = serde_json::to_string(&entry).unwrap();
+    assert!(!line.contains("hunter2"), "{}", line);
+    assert!(line.contains("login"), "{}", line);
+}
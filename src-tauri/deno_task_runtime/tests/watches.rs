@@ -0,0 +1,138 @@
+use std::time::{Duration, Instant};
+
+use deno_task_runtime::test_support::{TempDataDir, TestHarness};
+use deno_task_runtime::{RunOptions, Task, TaskRuntime};
+use serde_json::json;
+
+fn watched_dir(name: &str) -> TempDataDir {
+    let dir = TempDataDir::new(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn reruns_tasks_when_watched_files_change() {
+    let harness = TestHarness::new("watches");
+    let runtime = harness.runtime();
+    let dir = watched_dir("watched");
+    let file = dir.join("data.csv");
+    std::fs::write(&file, "a,b").unwrap();
+
+    let id = runtime
+        .watch_task(
+            vec![dir.to_path_buf()],
+            "RuntimeExtension.returnValue(RuntimeExtension.input.changed.length);",
+            RunOptions::default(),
+            None,
+        )
+        .unwrap();
+
+    // runs right away, nothing changed yet
+    let first = runtime.list_watches()[0]
+        .last_task_id()
+        .unwrap()
+        .to_string();
+    assert!(first.starts_with(&id), "{}", first);
+    assert_eq!(wait(runtime, &first).return_value(), Some(&json!(0)));
+
+    std::fs::write(&file, "a,b\n1,2").unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while runtime.list_watches()[0].runs() < 2 {
+        assert!(Instant::now() < deadline, "the change never ran the task");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let second = runtime.list_watches()[0]
+        .last_task_id()
+        .unwrap()
+        .to_string();
+    assert_ne!(second, first);
+    let task = wait(runtime, &second);
+    assert!(task.return_value().and_then(|changed| changed.as_u64()) >= Some(1));
+
+    runtime.unwatch_task(&id).unwrap();
+    assert!(runtime.list_watches().is_empty());
+    assert!(runtime.unwatch_task(&id).is_err());
+}
+
+#[test]
+fn batches_changes_within_the_debounce_window() {
+    let harness = TestHarness::new("debounced_watches");
+    let runtime = harness.runtime();
+    let dir = watched_dir("debounced");
+
+    runtime
+        .watch_task(
+            vec![dir.to_path_buf()],
+            "RuntimeExtension.returnValue(RuntimeExtension.input.changed);",
+            RunOptions::default(),
+            Some(300),
+        )
+        .unwrap();
+    assert_eq!(runtime.list_watches()[0].debounce_ms(), 300);
+
+    for name in ["a.csv", "b.csv", "c.csv"] {
+        std::fs::write(dir.join(name), "a,b").unwrap();
+        std::fs::write(dir.join(name), "a,b\n1,2").unwrap();
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while runtime.list_watches()[0].runs() < 2 {
+        assert!(Instant::now() < deadline, "the changes never ran the task");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let task_id = runtime.list_watches()[0]
+        .last_task_id()
+        .unwrap()
+        .to_string();
+    let changed = wait(runtime, &task_id).return_value().cloned().unwrap();
+    // the events may name the paths through symlinks, e.g. on macOS
+    for name in ["a.csv", "b.csv", "c.csv"] {
+        let listed = changed
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|changed| changed.as_str().is_some_and(|path| path.ends_with(name)))
+            .count();
+        assert_eq!(listed, 1, "{} in {}", name, changed);
+    }
+
+    // a single run for the batch
+    std::thread::sleep(Duration::from_millis(600));
+    assert_eq!(runtime.list_watches()[0].runs(), 2);
+}
+
+#[test]
+fn refuses_paths_that_cant_be_watched() {
+    let harness = TestHarness::new("invalid_watches");
+    let runtime = harness.runtime();
+    let dir = watched_dir("invalid_watched");
+
+    assert!(runtime
+        .watch_task(Vec::new(), "", RunOptions::default(), None)
+        .is_err());
+    assert!(runtime
+        .watch_task(
+            vec![dir.join("nothing_here")],
+            "",
+            RunOptions::default(),
+            None
+        )
+        .is_err());
+    assert!(runtime
+        .watch_task(vec![dir.to_path_buf()], "", RunOptions::default(), Some(0))
+        .is_err());
+    assert!(runtime.list_watches().is_empty());
+    assert!(!runtime.has_running_tasks());
+}
+
+// Watched runs have no handle
+fn wait(runtime: &TaskRuntime, task_id: &str) -> Task {
+    loop {
+        let task = runtime.get_task_state(task_id).unwrap();
+        if task.is_finished() {
+            return task;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -1,18 +1,250 @@
-mod deno;
+use deno_task_runtime as deno;
+use deno_task_runtime::Profiles;
+use tauri::ipc::{Channel, Invoke, InvokeBody};
+use tauri::{Manager, State, Window, WindowEvent};
+
+// Id of a task about to start, generated when none is given. With
+// `namespace_by_window` it's prefixed with the calling window's label
+fn new_task_id(
+    window: &Window,
+    task_id: Option<&str>,
+    namespace_by_window: Option<bool>,
+) -> Result<String, String> {
+    let namespace = namespace_by_window.unwrap_or(false).then(|| window.label());
+
+    deno::resolve_task_id(task_id, namespace)
+}
+
+/// Returns the task id, generated when none is given. With
+/// `namespace_by_window` the id is prefixed with the calling window's label.
+/// A `context` runs the task in the profile of that name, created if needed,
+/// instead of `profile`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn run_task(
+    window: Window,
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    context: Option<String>,
+    task_id: Option<String>,
+    code: &str,
+    options: Option<deno::RunOptions>,
+    namespace_by_window: Option<bool>,
+    input: Option<serde_json::Value>,
+) -> Result<String, String> {
+    let task_id = new_task_id(&window, task_id.as_deref(), namespace_by_window)?;
+
+    let mut options = options.unwrap_or_default();
+    options.set_owner_window(window.label());
+    if let Some(input) = input {
+        options.set_input(input);
+    }
+
+    let runtime = match (context, profile) {
+        (Some(_), Some(_)) => return Err("Pass either a profile or a context".to_string()),
+        (Some(context), None) => profiles.context(&context)?,
+        (None, profile) => profiles.get(profile.as_deref())?,
+    };
+
+    // the task already submitted with the same idempotency key, if any
+    let handle = runtime.run_task(&task_id, code, options)?;
+
+    Ok(handle.id().to_string())
+}
+
+#[tauri::command]
+fn post_message_to_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+    message: serde_json::Value,
+) -> Result<(), String> {
+    deno::validate_task_id(&task_id)?;
+
+    profiles
+        .get(profile.as_deref())?
+        .post_message_to_task(&task_id, &message)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn debug_run_task(
+    window: Window,
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: Option<String>,
+    code: &str,
+    breakpoints: Vec<u32>,
+    options: Option<deno::RunOptions>,
+    namespace_by_window: Option<bool>,
+) -> Result<String, String> {
+    let task_id = new_task_id(&window, task_id.as_deref(), namespace_by_window)?;
+
+    let mut options = options.unwrap_or_default();
+    options.set_owner_window(window.label());
+
+    let handle =
+        profiles
+            .get(profile.as_deref())?
+            .debug_run_task(&task_id, code, &breakpoints, options)?;
+
+    Ok(handle.id().to_string())
+}
+
+#[tauri::command]
+fn get_debug_pause(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+) -> Result<Option<deno::DebugPause>, String> {
+    deno::validate_task_id(task_id)?;
+
+    Ok(profiles.get(profile.as_deref())?.get_debug_pause(task_id))
+}
+
+/// Off the main thread, like the other debug commands, they wait for the
+/// task's thread to answer.
+#[tauri::command]
+async fn debug_step(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+    step: Option<deno::DebugStep>,
+) -> Result<(), String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        runtime.debug_step(&task_id, step.unwrap_or(deno::DebugStep::Over))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn debug_continue(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+) -> Result<(), String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.debug_continue(&task_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn debug_evaluate(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+    expression: String,
+) -> Result<serde_json::Value, String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.debug_evaluate(&task_id, &expression))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn evaluate_in_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+    expression: String,
+    allow_side_effects: Option<bool>,
+) -> Result<deno::RemoteObject, String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        runtime.evaluate_in_task(&task_id, &expression, allow_side_effects.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
 #[tauri::command]
-fn run_task(task_id: &str, code: &str) -> Result<(), String> {
-    deno::run_task(task_id, code)
+fn resume_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+) -> Result<(), String> {
+    deno::validate_task_id(task_id)?;
+
+    profiles.get(profile.as_deref())?.resume_task(task_id)?;
+
+    Ok(())
 }
 
 #[tauri::command]
-fn stop_task(task_id: &str) -> Result<(), String> {
-    deno::stop_task(task_id)
+fn pause_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+) -> Result<(), String> {
+    deno::validate_task_id(task_id)?;
+
+    profiles.get(profile.as_deref())?.pause_task(task_id)
 }
 
+/// Off the main thread, writing blocks while the task's stdin is full.
 #[tauri::command]
-fn get_task_state(task_id: String) -> Result<deno::Task, String> {
-    let Some(task_state) = deno::get_task_state(&task_id) else {
+async fn write_task_stdin(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+    data: String,
+) -> Result<(), String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        runtime.write_task_stdin(&task_id, data.as_bytes())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn stop_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+) -> Result<(), String> {
+    deno::validate_task_id(task_id)?;
+
+    profiles.get(profile.as_deref())?.stop_task(task_id)
+}
+
+#[tauri::command]
+fn get_queue(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<deno::TaskQueue, String> {
+    Ok(profiles.get(profile.as_deref())?.get_queue())
+}
+
+#[tauri::command]
+fn get_task_state(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+) -> Result<deno::Task, String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    let Some(task_state) = runtime.get_task_state(&task_id) else {
         return Err("Task not found".to_string());
     };
 
@@ -20,34 +252,992 @@ fn get_task_state(task_id: String) -> Result<deno::Task, String> {
 }
 
 #[tauri::command]
-fn clear_completed_tasks() {
-    deno::clear_completed_tasks();
+fn diff_task_runs(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    run_a: &str,
+    run_b: &str,
+) -> Result<deno::TaskRunDiff, String> {
+    deno::validate_task_id(run_a)?;
+    deno::validate_task_id(run_b)?;
+
+    profiles
+        .get(profile.as_deref())?
+        .diff_task_runs(run_a, run_b)
 }
 
+/// What a reloaded window rebuilds its state from, see
+/// `TaskRuntime::get_runtime_snapshot`.
 #[tauri::command]
-fn respond_to_permission_prompt(task_id: String, response: String) {
-    deno::respond_to_permission_prompt(&task_id, deno::PermissionsResponse::from_str(&response));
+fn get_runtime_snapshot(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<deno::RuntimeSnapshot, String> {
+    Ok(profiles.get(profile.as_deref())?.get_runtime_snapshot())
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
+#[tauri::command]
+fn sync_task_events(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    since_seq: u64,
+) -> Result<Vec<deno::SequencedEvent>, String> {
+    Ok(profiles
+        .get(profile.as_deref())?
+        .sync_task_events(since_seq))
+}
+
+#[tauri::command]
+fn get_events_since(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+    seq: u64,
+) -> Result<Vec<deno::SequencedEvent>, String> {
+    deno::validate_task_id(task_id)?;
+
+    Ok(profiles
+        .get(profile.as_deref())?
+        .get_events_since(task_id, seq))
+}
+
+/// Returns the subscription id, see `TaskRuntime::subscribe`.
+#[tauri::command]
+fn subscribe(
+    window: Window,
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    filters: deno::SubscriptionFilters,
+    on_event: Channel<deno::SequencedEvent>,
+) -> Result<u64, String> {
+    Ok(profiles
+        .get(profile.as_deref())?
+        .subscribe(filters, on_event, Some(window.label())))
+}
+
+#[tauri::command]
+fn subscribe_task_output(
+    window: Window,
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+    on_output: Channel<deno::TaskOutputChunk>,
+) -> Result<(), String> {
+    deno::validate_task_id(task_id)?;
+
+    profiles.get(profile.as_deref())?.subscribe_task_output(
+        task_id,
+        on_output,
+        Some(window.label()),
+    )
+}
+
+#[tauri::command]
+fn get_task_logs(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: &str,
+) -> Result<Vec<deno::TaskLog>, String> {
+    deno::validate_task_id(task_id)?;
+
+    profiles.get(profile.as_deref())?.get_task_logs(task_id)
+}
+
+#[tauri::command]
+fn unsubscribe(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    subscription_id: u64,
+) -> Result<(), String> {
+    profiles
+        .get(profile.as_deref())?
+        .unsubscribe(subscription_id)
+}
+
+#[tauri::command]
+fn save_script(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+    code: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.save_script(name, code)
+}
+
+#[tauri::command]
+fn get_script(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+) -> Result<String, String> {
+    profiles.get(profile.as_deref())?.get_script(name)
+}
+
+#[tauri::command]
+fn list_scripts(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<String>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_scripts())
+}
+
+#[tauri::command]
+fn delete_script(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.delete_script(name)
+}
+
+#[tauri::command]
+async fn generate_script_docs(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: String,
+) -> Result<deno::ScriptDocs, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.generate_script_docs(&name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_script_inputs(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: String,
+) -> Result<Option<serde_json::Value>, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.get_script_inputs(&name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Like `run_task` for a saved script, fails when `args` don't match its
+/// `inputs`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn run_saved_script(
+    window: Window,
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: Option<String>,
+    name: &str,
+    args: Option<serde_json::Value>,
+    options: Option<deno::RunOptions>,
+    namespace_by_window: Option<bool>,
+) -> Result<String, String> {
+    let task_id = new_task_id(&window, task_id.as_deref(), namespace_by_window)?;
+
+    let mut options = options.unwrap_or_default();
+    options.set_owner_window(window.label());
+
+    profiles
+        .get(profile.as_deref())?
+        .run_saved_script(&task_id, name, args, options)?;
+
+    Ok(task_id)
+}
+
+/// Returns the pipeline id, see `TaskRuntime::run_pipeline`.
+#[tauri::command]
+fn run_pipeline(
+    window: Window,
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    spec: deno::PipelineSpec,
+    options: Option<deno::RunOptions>,
+) -> Result<String, String> {
+    let mut options = options.unwrap_or_default();
+    options.set_owner_window(window.label());
+
+    profiles
+        .get(profile.as_deref())?
+        .run_pipeline(spec, options)
+}
+
+#[tauri::command]
+fn get_pipeline_state(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    pipeline_id: String,
+) -> Result<deno::PipelineGraph, String> {
+    profiles
+        .get(profile.as_deref())?
+        .get_pipeline_state(&pipeline_id)
+        .ok_or_else(|| format!("Pipeline not found: {}", pipeline_id))
+}
+
+#[tauri::command]
+fn list_dead_letters(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<deno::DeadLetter>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_dead_letters())
+}
+
+#[tauri::command]
+fn retry_dead_letter(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    id: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.retry_dead_letter(id)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+fn dismiss_dead_letter(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    id: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.dismiss_dead_letter(id)
+}
+
+#[tauri::command]
+fn set_alert_rule(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    rule: deno::AlertRule,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.set_alert_rule(rule)
+}
+
+#[tauri::command]
+fn list_alert_rules(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<deno::AlertRule>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_alert_rules())
+}
+
+#[tauri::command]
+fn remove_alert_rule(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    id: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.remove_alert_rule(id)
+}
+
+/// Returns the schedule id. Scheduled runs aren't tied to the window, they go
+/// on once it's closed.
+#[tauri::command]
+fn schedule_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    cron: &str,
+    code: &str,
+    options: Option<deno::RunOptions>,
+) -> Result<String, String> {
+    profiles
+        .get(profile.as_deref())?
+        .schedule_task(cron, code, options.unwrap_or_default())
+}
+
+#[tauri::command]
+fn list_schedules(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<deno::Schedule>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_schedules())
+}
+
+#[tauri::command]
+fn cancel_schedule(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    id: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.cancel_schedule(id)
+}
+
+/// Returns the registration id. Like scheduled runs, triggered ones aren't
+/// tied to a window.
+#[tauri::command]
+fn register_event_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    event_name: &str,
+    code: &str,
+    options: Option<deno::RunOptions>,
+    debounce_ms: Option<u64>,
+) -> Result<String, String> {
+    profiles.get(profile.as_deref())?.register_event_task(
+        event_name,
+        code,
+        options.unwrap_or_default(),
+        debounce_ms,
+    )
+}
+
+#[tauri::command]
+fn list_event_tasks(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<deno::EventTask>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_event_tasks())
+}
+
+#[tauri::command]
+fn unregister_event_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    id: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.unregister_event_task(id)
+}
+
+/// Returns the watch id. Watches last until unwatched or the app closes.
+#[tauri::command]
+fn watch_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    paths: Vec<std::path::PathBuf>,
+    code: &str,
+    options: Option<deno::RunOptions>,
+    debounce_ms: Option<u64>,
+) -> Result<String, String> {
+    profiles.get(profile.as_deref())?.watch_task(
+        paths,
+        code,
+        options.unwrap_or_default(),
+        debounce_ms,
+    )
+}
+
+#[tauri::command]
+fn list_watches(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<deno::TaskWatch>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_watches())
+}
+
+#[tauri::command]
+fn unwatch_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    id: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.unwatch_task(id)
+}
 
+#[tauri::command]
+fn list_result_hooks() -> Vec<String> {
+    deno::list_result_hooks()
+}
+
+#[tauri::command]
+fn set_script_hooks(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+    hooks: Vec<String>,
+) -> Result<(), String> {
+    profiles
+        .get(profile.as_deref())?
+        .set_script_hooks(name, hooks)
+}
+
+#[tauri::command]
+fn get_script_hooks(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+) -> Result<Vec<String>, String> {
+    profiles.get(profile.as_deref())?.get_script_hooks(name)
+}
+
+#[tauri::command]
+fn set_online(profiles: State<'_, Profiles>, online: bool) {
+    profiles.set_online(online)
+}
+
+#[tauri::command]
+fn list_fetch_interceptors() -> Vec<String> {
+    deno::list_fetch_interceptors()
+}
+
+#[tauri::command]
+fn set_script_fetch_interceptors(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+    interceptors: Vec<String>,
+) -> Result<(), String> {
+    profiles
+        .get(profile.as_deref())?
+        .set_script_fetch_interceptors(name, interceptors)
+}
+
+#[tauri::command]
+fn get_script_fetch_interceptors(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+) -> Result<Vec<String>, String> {
+    profiles
+        .get(profile.as_deref())?
+        .get_script_fetch_interceptors(name)
+}
+
+#[tauri::command]
+fn set_script_grants(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+    grants: Option<Vec<deno::OpGrant>>,
+) -> Result<(), String> {
+    profiles
+        .get(profile.as_deref())?
+        .set_script_grants(name, grants)
+}
+
+#[tauri::command]
+fn get_script_grants(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+) -> Result<Option<Vec<deno::OpGrant>>, String> {
+    profiles.get(profile.as_deref())?.get_script_grants(name)
+}
+
+/// Off the main thread, the remote modules of the script are fetched.
+#[tauri::command]
+async fn export_script_bundle(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: String,
+    path: std::path::PathBuf,
+) -> Result<(), String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.export_script_bundle(&name, &path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn import_script_bundle(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    path: std::path::PathBuf,
+) -> Result<String, String> {
+    profiles
+        .get(profile.as_deref())?
+        .import_script_bundle(&path)
+}
+
+#[tauri::command]
+fn add_trusted_key(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+    public_key: &str,
+) -> Result<(), String> {
+    profiles
+        .get(profile.as_deref())?
+        .add_trusted_key(name, public_key)
+}
+
+#[tauri::command]
+fn list_trusted_keys(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<Vec<deno::TrustedKey>, String> {
+    Ok(profiles.get(profile.as_deref())?.list_trusted_keys())
+}
+
+#[tauri::command]
+fn remove_trusted_key(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    name: &str,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.remove_trusted_key(name)
+}
+
+#[tauri::command]
+fn generate_bundle_signing_key() -> deno::BundleSigningKey {
+    deno::generate_bundle_signing_key()
+}
+
+#[tauri::command]
+fn sign_script_bundle(path: std::path::PathBuf, private_key: &str) -> Result<(), String> {
+    deno::sign_script_bundle(&path, private_key)
+}
+
+/// Off the main thread, the catalog is fetched.
+#[tauri::command]
+async fn list_catalog(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    url: String,
+) -> Result<Vec<deno::CatalogEntry>, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.list_catalog(&url))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn review_catalog_script(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    url: String,
+    name: String,
+) -> Result<deno::CatalogScriptReview, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.review_catalog_script(&url, &name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn install_catalog_script(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    url: String,
+    name: String,
+    reviewed_sha256: String,
+) -> Result<(), String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        runtime.install_catalog_script(&url, &name, &reviewed_sha256)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn clear_completed_tasks(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<(), String> {
+    profiles.get(profile.as_deref())?.clear_completed_tasks();
+
+    Ok(())
+}
+
+/// Summaries of the tasks, newest first, see `TaskRuntime::list_tasks`.
+#[tauri::command]
+fn list_tasks(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    state_filter: Option<Vec<String>>,
+    tags: Option<Vec<String>>,
+    search: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<deno::TaskList, String> {
+    Ok(profiles.get(profile.as_deref())?.list_tasks(
+        state_filter.as_deref(),
+        tags.as_deref(),
+        search.as_deref(),
+        offset.unwrap_or_default(),
+        limit,
+    ))
+}
+
+/// Finished runs of earlier sessions too, newest first.
+#[tauri::command]
+fn get_task_history(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    filter: Option<deno::TaskHistoryFilter>,
+    page: Option<deno::TaskHistoryPage>,
+) -> Result<deno::TaskHistory, String> {
+    Ok(profiles
+        .get(profile.as_deref())?
+        .get_task_history(&filter.unwrap_or_default(), &page.unwrap_or_default()))
+}
+
+/// Off the main thread, compressing the archive reads every artifact.
+#[tauri::command]
+async fn archive_tasks(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    before_ms: u64,
+) -> Result<deno::ArchiveSummary, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.archive_tasks(before_ms))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Off the main thread, vacuuming rewrites every database.
+#[tauri::command]
+async fn compact_storage(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<deno::CompactionReport, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.compact_storage())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn search_task_archives(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    filter: Option<deno::TaskHistoryFilter>,
+    page: Option<deno::TaskHistoryPage>,
+) -> Result<deno::ArchivedTasks, String> {
+    Ok(profiles
+        .get(profile.as_deref())?
+        .search_task_archives(&filter.unwrap_or_default(), &page.unwrap_or_default()))
+}
+
+#[tauri::command]
+async fn get_archived_task(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    archive: String,
+    task_id: String,
+) -> Result<Vec<deno::ArchivedTask>, String> {
+    deno::validate_task_id(&task_id)?;
+
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || runtime.get_archived_task(&archive, &task_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn respond_to_permission_prompt(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+    task_id: String,
+    response: String,
+) -> Result<(), String> {
+    deno::validate_task_id(&task_id)?;
+
+    profiles
+        .get(profile.as_deref())?
+        .respond_to_permission_prompt(&task_id, response.parse()?);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn runtime_health_check(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<deno::HealthReport, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || deno::runtime_health_check(&runtime))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn describe_extensions(
+    profiles: State<'_, Profiles>,
+    profile: Option<String>,
+) -> Result<deno::ExtensionsReport, String> {
+    let runtime = profiles.get(profile.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || deno::describe_extensions(&runtime))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_intl_info() -> Result<deno::IntlInfo, String> {
+    tauri::async_runtime::spawn_blocking(deno::get_intl_info)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn set_intl_config(config: deno::IntlConfig) -> Result<(), String> {
+    deno::set_intl_config(config)
+}
+
+#[tauri::command]
+async fn get_storage_usage() -> Result<deno::StorageUsage, String> {
+    tauri::async_runtime::spawn_blocking(deno::get_storage_usage)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_storage_quota(quota_bytes: Option<u64>) -> Result<deno::StorageUsage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        deno::set_storage_quota(quota_bytes)?;
+        Ok(deno::get_storage_usage())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_runtime_config() -> deno::AppliedConfig {
+    deno::get_runtime_config()
+}
+
+#[tauri::command]
+async fn set_runtime_config(config: deno::RuntimeConfig) -> Result<deno::AppliedConfig, String> {
+    tauri::async_runtime::spawn_blocking(move || deno::set_runtime_config(config))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn reload_runtime_config() -> Result<deno::AppliedConfig, String> {
+    tauri::async_runtime::spawn_blocking(deno::reload_runtime_config)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Answers the script editor's language server requests, see
+/// `deno::lsp_request`.
+#[tauri::command]
+async fn lsp_request(
+    method: String,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    tauri::async_runtime::spawn_blocking(move || deno::lsp_request(&method, params))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Declarations of `RuntimeExtension`, for the script editor.
+#[tauri::command]
+fn get_runtime_types() -> String {
+    deno::runtime_types()
+}
+
+#[tauri::command]
+fn get_runtime_versions() -> deno::RuntimeVersions {
+    deno::get_runtime_versions()
+}
+
+#[tauri::command]
+fn get_runtime_capabilities() -> deno::RuntimeCapabilities {
+    deno::get_runtime_capabilities()
+}
+
+#[tauri::command]
+fn get_json_schemas() -> deno::JsonSchemas {
+    deno::get_json_schemas()
+}
+
+/// Starts the runtimes ahead of the first task, which would otherwise do it.
+#[tauri::command]
+fn init_runtime(profiles: State<'_, Profiles>) -> Result<(), String> {
+    profiles.init()
+}
+
+#[tauri::command]
+fn list_profiles(profiles: State<'_, Profiles>) -> Result<Vec<String>, String> {
+    profiles.list()
+}
+
+#[tauri::command]
+fn create_profile(profiles: State<'_, Profiles>, name: &str) -> Result<(), String> {
+    profiles.create(name)
+}
+
+#[tauri::command]
+fn delete_profile(profiles: State<'_, Profiles>, name: &str) -> Result<(), String> {
+    profiles.delete(name)
+}
+
+/// Emits the events of a trace recorded with the `trace_file` setting again,
+/// to reproduce what the UI went through. Returns the number of events.
+#[cfg(debug_assertions)]
+#[tauri::command]
+async fn replay_trace(
+    app_handle: tauri::AppHandle,
+    path: std::path::PathBuf,
+) -> Result<usize, String> {
+    deno::replay_trace(&app_handle, &path).await
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let invoke_handler: fn(Invoke) -> bool = tauri::generate_handler![
+        run_task,
+        post_message_to_task,
+        debug_run_task,
+        get_debug_pause,
+        debug_step,
+        debug_continue,
+        debug_evaluate,
+        evaluate_in_task,
+        pause_task,
+        resume_task,
+        stop_task,
+        write_task_stdin,
+        get_task_state,
+        get_queue,
+        diff_task_runs,
+        get_runtime_snapshot,
+        sync_task_events,
+        get_events_since,
+        subscribe,
+        unsubscribe,
+        subscribe_task_output,
+        get_task_logs,
+        save_script,
+        get_script,
+        list_scripts,
+        delete_script,
+        generate_script_docs,
+        get_script_inputs,
+        run_saved_script,
+        run_pipeline,
+        get_pipeline_state,
+        list_dead_letters,
+        retry_dead_letter,
+        dismiss_dead_letter,
+        set_alert_rule,
+        list_alert_rules,
+        remove_alert_rule,
+        schedule_task,
+        list_schedules,
+        cancel_schedule,
+        register_event_task,
+        list_event_tasks,
+        unregister_event_task,
+        watch_task,
+        list_watches,
+        unwatch_task,
+        list_result_hooks,
+        set_script_hooks,
+        get_script_hooks,
+        set_online,
+        list_fetch_interceptors,
+        set_script_fetch_interceptors,
+        get_script_fetch_interceptors,
+        list_catalog,
+        review_catalog_script,
+        install_catalog_script,
+        set_script_grants,
+        get_script_grants,
+        export_script_bundle,
+        import_script_bundle,
+        add_trusted_key,
+        list_trusted_keys,
+        remove_trusted_key,
+        generate_bundle_signing_key,
+        sign_script_bundle,
+        clear_completed_tasks,
+        list_tasks,
+        get_task_history,
+        archive_tasks,
+        compact_storage,
+        search_task_archives,
+        get_archived_task,
+        respond_to_permission_prompt,
+        runtime_health_check,
+        describe_extensions,
+        get_intl_info,
+        set_intl_config,
+        get_storage_usage,
+        set_storage_quota,
+        get_runtime_config,
+        set_runtime_config,
+        reload_runtime_config,
+        lsp_request,
+        get_runtime_types,
+        get_runtime_versions,
+        get_runtime_capabilities,
+        get_json_schemas,
+        init_runtime,
+        list_profiles,
+        create_profile,
+        delete_profile,
+        #[cfg(debug_assertions)]
+        replay_trace
+    ];
+
+    // the frontend's types come from there, regenerated on every `tauri dev`
+    #[cfg(debug_assertions)]
+    if let Err(e) = deno::export_typescript_sdk(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../src/lib/bindings.ts"
+    ))) {
+        if deno::log_enabled(deno::LogLevel::Error) {
+            println!("Failed to export the TypeScript SDK: {}", e);
+        }
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_shell::init())
         .setup(move |app| {
-            deno::init_listener(app.handle().clone());
+            // the setup hook runs on the UI thread
+            deno::pin_ui_thread();
+
+            // the runtimes start with the first command that needs them
+            app.manage(Profiles::new(app.handle().clone()));
+
+            // unless the last session left schedules or running tasks behind
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                app_handle.state::<Profiles>().restore();
+            });
+
+            let report = app.path().app_data_dir()?.join("report.jsonl");
+            deno::register_result_hook("append-report", deno::AppendToReport::new(report));
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            run_task,
-            stop_task,
-            get_task_state,
-            clear_completed_tasks,
-            respond_to_permission_prompt
-        ])
+        .register_asynchronous_uri_scheme_protocol(
+            deno::TASK_RESULT_SCHEME,
+            |ctx, request, responder| {
+                let app_handle = ctx.app_handle().clone();
+
+                // reading the task waits on its runtime, not on the UI thread
+                tauri::async_runtime::spawn_blocking(move || {
+                    let profiles = app_handle.state::<Profiles>();
+                    responder.respond(deno::task_result_response(&profiles, &request));
+                });
+            },
+        )
+        .register_asynchronous_uri_scheme_protocol(
+            deno::TASK_ARTIFACT_SCHEME,
+            |ctx, request, responder| {
+                let app_handle = ctx.app_handle().clone();
+                let window = ctx.webview_label().to_string();
+
+                tauri::async_runtime::spawn_blocking(move || {
+                    let profiles = app_handle.state::<Profiles>();
+                    responder.respond(deno::task_artifact_response(&profiles, &window, &request));
+                });
+            },
+        )
+        .on_window_event(|window, event| {
+            if let WindowEvent::Destroyed = event {
+                if let Some(profiles) = window.try_state::<Profiles>() {
+                    profiles.release_window_tasks(window.label());
+                }
+            }
+        })
+        .invoke_handler(move |invoke| {
+            if let InvokeBody::Json(args) = invoke.message.payload() {
+                deno::record_command(invoke.message.command(), args);
+            }
+
+            invoke_handler(invoke)
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
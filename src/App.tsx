@@ -3,7 +3,14 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import CodeMirror from "@uiw/react-codemirror";
 import { javascript } from "@codemirror/lang-javascript";
-import { FaSpinner, FaStop, FaPlay } from "react-icons/fa";
+import {
+  FaSpinner,
+  FaStop,
+  FaPlay,
+  FaPause,
+  FaPaperPlane,
+  FaSearch,
+} from "react-icons/fa";
 import { LuAlertTriangle, LuBan } from "react-icons/lu";
 
 import { nanoid } from "./lib/nanoid";
@@ -12,16 +19,80 @@ type Task = {
   id: string;
   code: string;
   state:
+    | "queued"
+    | "waiting_for_network"
     | "running"
+    | "retrying"
     | "completed"
     | "error"
     | "stopped"
+    | "timed_out"
+    | "interrupted"
+    | "paused"
     | "stopping"
     | "waiting_for_permission";
   result?: Record<string, any>;
   error?: string;
+  output?: string;
   permissionPrompt?: PermissionPrompt;
   permissionHistory?: PermissionPrompt[];
+  diagnostics?: TaskDiagnostic[];
+  waitingOn?: WaitingOn;
+  name?: string;
+  tags?: string[];
+  startedAtMs?: number;
+  durationMs?: number;
+  progress?: TaskProgress;
+  timings?: TaskTimings;
+  lastMessage?: unknown;
+  debugPause?: DebugPause;
+  debugResult?: string;
+  inspection?: string;
+};
+
+type DebugPause = {
+  reason: string;
+  call_frames: {
+    function_name: string;
+    module: string | null;
+    line: number;
+    column: number;
+  }[];
+};
+
+type TaskTimings = {
+  queue_wait_ms: number;
+  bootstrap_ms: number | null;
+  prewarmed: boolean;
+  module_load_ms: number | null;
+  execution_ms: number | null;
+  event_loop_ms: number | null;
+};
+
+type RemoteObject = {
+  kind: string;
+  value: unknown;
+  description: string | null;
+  preview: {
+    overflow: boolean;
+    properties: { name: string; value: string }[];
+    entries: { key: string | null; value: string }[];
+  } | null;
+};
+
+type TaskProgress = {
+  percent: number;
+  message: string | null;
+};
+
+type WaitingOn = {
+  kind: "permission" | "network" | "user_input" | "queue";
+  detail: string;
+};
+
+type TaskDiagnostic = {
+  specifier: string;
+  message: string;
 };
 
 type PermissionsResponse = "Allow" | "Deny" | "AllowAll";
@@ -33,29 +104,132 @@ type PermissionPrompt = {
   is_unary: boolean;
 };
 
+type TaskState =
+  | { kind: "queued" }
+  | { kind: "waiting_for_network" }
+  | { kind: "running" }
+  | { kind: "retrying"; attempt: number; message: string; backoff_ms: number }
+  | { kind: "waiting_for_permission"; prompt: PermissionPrompt }
+  | { kind: "paused" }
+  | { kind: "stopping" }
+  | { kind: "stopped" }
+  | { kind: "timed_out"; timeout_ms: number }
+  | { kind: "completed" }
+  | { kind: "interrupted" }
+  | {
+      kind: "error";
+      message: string;
+      stack: string | null;
+      frames: StackFrame[];
+    };
+
+type StackFrame = {
+  file_name: string | null;
+  function_name: string | null;
+  line: number | null;
+  column: number | null;
+  snippet: string | null;
+};
+
 type InternalTask = {
   id: string;
-  state:
-    | "running"
-    | "completed"
-    | "error"
-    | "stopping"
-    | "stopped"
-    | "waiting_for_permission";
-  return_value?: string;
-  error?: string;
-  permission_prompt?: PermissionPrompt;
+  state: TaskState;
+  return_value: unknown;
+  output?: string;
+  output_truncated_bytes?: number;
   permission_history?: PermissionPrompt[];
+  diagnostics?: TaskDiagnostic[];
+  waiting_on?: WaitingOn | null;
+  name?: string | null;
+  tags?: string[];
+  started_at_ms?: number | null;
+  duration_ms?: number | null;
+  progress?: TaskProgress | null;
+  timings?: TaskTimings;
+};
+
+type RuntimeSnapshot = {
+  seq: number;
+  tasks: InternalTask[];
+};
+
+type HealthCheck = {
+  name: string;
+  ok: boolean;
+  detail: string;
+};
+
+type HealthReport = {
+  ok: boolean;
+  checks: HealthCheck[];
+};
+
+type CompactionReport = {
+  reclaimed_bytes: number;
+  total_bytes: number;
+  vacuumed_databases: number;
+  expired_cache_entries: number;
+  orphaned_artifacts: number;
+};
+
+type SequencedEvent<T> = T & {
+  seq: number;
+  task_seq: number;
+  event: string;
 };
 
 const eventTarget = new EventTarget();
 
-await listen<InternalTask>("task-state-changed", (event) => {
+// Last task_seq seen per task, events can arrive twice when catching up
+const lastSeqByTask = new Map<string, number>();
+
+async function dispatchTaskStateChanged(task: SequencedEvent<InternalTask>) {
+  const lastSeq = lastSeqByTask.get(task.id) ?? 0;
+  if (task.task_seq <= lastSeq) {
+    return;
+  }
+
+  // missed events, take the task state from the backend's buffered events
+  if (task.task_seq > lastSeq + 1) {
+    lastSeqByTask.set(task.id, task.task_seq);
+
+    const missed = await invoke<SequencedEvent<InternalTask>[]>(
+      "get_events_since",
+      { taskId: task.id, seq: lastSeq }
+    );
+    const latest = missed
+      .filter((event) => event.event === "task-state-changed")
+      .pop();
+    if (latest && latest.task_seq > task.task_seq) {
+      task = latest;
+      lastSeqByTask.set(task.id, task.task_seq);
+    }
+  } else {
+    lastSeqByTask.set(task.id, task.task_seq);
+  }
+
   eventTarget.dispatchEvent(
-    new CustomEvent("task-state-changed", { detail: event.payload })
+    new CustomEvent("task-state-changed", { detail: task })
   );
+}
+
+await listen<SequencedEvent<InternalTask>>("task-state-changed", (event) => {
+  dispatchTaskStateChanged(event.payload);
 });
 
+// the tasks as they were when this window (re)loaded, then the events
+// emitted since
+const snapshot = await invoke<RuntimeSnapshot>("get_runtime_snapshot");
+const missedEvents = await invoke<SequencedEvent<InternalTask>[]>(
+  "sync_task_events",
+  { sinceSeq: snapshot.seq }
+);
+for (const event of missedEvents) {
+  if (event.event === "task-state-changed") {
+    dispatchTaskStateChanged(event);
+  }
+}
+
 const initialCode = `import * as cowsay from "https://esm.sh/cowsay@1.6.0"
 
 console.log("-- taskId", RuntimeExtension.taskId)
@@ -95,39 +269,139 @@ await new Promise((resolve) => setTimeout(resolve, 5000))
 RuntimeExtension.returnValue({ text, post })
 `;
 
+function taskFields(task: InternalTask) {
+  return {
+    state: task.state.kind,
+    result: (task.return_value ?? undefined) as
+      | Record<string, any>
+      | undefined,
+    error: task.state.kind === "error" ? task.state.message : undefined,
+    output: task.output_truncated_bytes
+      ? `${task.output}\n…${task.output_truncated_bytes} bytes truncated…\n`
+      : task.output,
+    permissionPrompt:
+      task.state.kind === "waiting_for_permission"
+        ? task.state.prompt
+        : undefined,
+    permissionHistory: task.permission_history,
+    diagnostics: task.diagnostics,
+    waitingOn: task.waiting_on ?? undefined,
+    name: task.name ?? undefined,
+    tags: task.tags,
+    startedAtMs: task.started_at_ms ?? undefined,
+    durationMs: task.duration_ms ?? undefined,
+    progress: task.progress ?? undefined,
+    timings: task.timings,
+  };
+}
+
+function describeTimings(timings: TaskTimings) {
+  const phases: [string, number | null][] = [
+    ["Queued", timings.queue_wait_ms],
+    [
+      timings.prewarmed ? "Bootstrap (prewarmed)" : "Bootstrap",
+      timings.bootstrap_ms,
+    ],
+    ["Module load", timings.module_load_ms],
+    ["Execution", timings.execution_ms],
+    ["Event loop", timings.event_loop_ms],
+  ];
+
+  return phases
+    .filter(([, ms]) => ms !== null)
+    .map(([phase, ms]) => `${phase}: ${formatDuration(ms!)}`)
+    .join("\n");
+}
+
+function describeRemoteObject(object: RemoteObject) {
+  if (object.value !== null) {
+    return JSON.stringify(object.value);
+  }
+  if (!object.preview) {
+    return object.description ?? object.kind;
+  }
+
+  const { overflow, properties, entries } = object.preview;
+  const items = [
+    ...entries.map(({ key, value }) => (key ? `${key} => ${value}` : value)),
+    ...properties.map(({ name, value }) => `${name}: ${value}`),
+    ...(overflow ? ["…"] : []),
+  ];
+  return `${object.description} { ${items.join(", ")} }`;
+}
+
+function formatBytes(bytes: number) {
+  if (bytes < 1024) {
+    return `${bytes} B`;
+  }
+  if (bytes < 1024 * 1024) {
+    return `${(bytes / 1024).toFixed(1)} KB`;
+  }
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+function formatDuration(ms: number) {
+  if (ms < 1000) {
+    return `${ms} ms`;
+  }
+  if (ms < 60 * 1000) {
+    return `${(ms / 1000).toFixed(1)} s`;
+  }
+  return `${Math.floor(ms / 60000)} min ${Math.floor((ms % 60000) / 1000)} s`;
+}
+
 function App() {
   const [code, setCode] = useState(initialCode);
   const [result, setResult] = useState<Record<string, any> | undefined>();
-  const [tasks, setTasks] = useState<Task[]>([]);
+  // their code isn't kept by the backend
+  const [tasks, setTasks] = useState<Task[]>(() =>
+    snapshot.tasks.map((task) => ({
+      id: task.id,
+      code: "",
+      ...taskFields(task),
+    }))
+  );
+  const [health, setHealth] = useState<HealthReport | undefined>();
+  const [compaction, setCompaction] = useState<CompactionReport | undefined>();
+  const [isCompacting, setIsCompacting] = useState(false);
+  const [breakpoints, setBreakpoints] = useState("");
+
+  useEffect(() => {
+    invoke<HealthReport>("runtime_health_check")
+      .then(setHealth)
+      .catch((error) => console.error("Failed to check runtime:", error));
+  }, []);
+
+  // tasks run with `queue_until_online` wait while the app is offline
+  useEffect(() => {
+    const reportOnline = () =>
+      invoke("set_online", { online: navigator.onLine }).catch((error) =>
+        console.error("Failed to report the network status:", error)
+      );
+
+    reportOnline();
+    window.addEventListener("online", reportOnline);
+    window.addEventListener("offline", reportOnline);
+
+    return () => {
+      window.removeEventListener("online", reportOnline);
+      window.removeEventListener("offline", reportOnline);
+    };
+  }, []);
 
   const handleTaskStateChanged = useCallback((event: Event) => {
     const task = (event as CustomEvent<InternalTask>).detail;
 
     console.log("-- task state changed", task);
 
-    let result: Record<string, any> | undefined;
-
-    if (task.return_value) {
-      result = JSON.parse(task.return_value);
-    }
+    const fields = taskFields(task);
 
     setTasks((prev) =>
-      prev.map((t) =>
-        t.id === task.id
-          ? {
-              ...t,
-              state: task.state,
-              result,
-              error: task.error,
-              permissionPrompt: task.permission_prompt,
-              permissionHistory: task.permission_history,
-            }
-          : t
-      )
+      prev.map((t) => (t.id === task.id ? { ...t, ...fields } : t))
     );
 
-    if (task.state === "completed") {
-      setResult(result);
+    if (task.state.kind === "completed") {
+      setResult(fields.result);
     }
   }, []);
 
@@ -142,6 +416,143 @@ function App() {
     };
   }, [handleTaskStateChanged]);
 
+  useEffect(() => {
+    const unlisten = listen<{ task_id: string; progress: TaskProgress }>(
+      "task-progress",
+      (event) => {
+        const { task_id, progress } = event.payload;
+        setTasks((prev) =>
+          prev.map((t) => (t.id === task_id ? { ...t, progress } : t))
+        );
+      }
+    );
+
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<{ task_id: string; message: unknown }>(
+      "task-message",
+      (event) => {
+        const { task_id, message } = event.payload;
+        setTasks((prev) =>
+          prev.map((t) =>
+            t.id === task_id ? { ...t, lastMessage: message } : t
+          )
+        );
+      }
+    );
+
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlistenPaused = listen<{ task_id: string; pause: DebugPause }>(
+      "task-debug-paused",
+      (event) => {
+        const { task_id, pause } = event.payload;
+        setTasks((prev) =>
+          prev.map((t) => (t.id === task_id ? { ...t, debugPause: pause } : t))
+        );
+      }
+    );
+    const unlistenResumed = listen<{ task_id: string }>(
+      "task-debug-resumed",
+      (event) => {
+        const { task_id } = event.payload;
+        setTasks((prev) =>
+          prev.map((t) =>
+            t.id === task_id ? { ...t, debugPause: undefined } : t
+          )
+        );
+      }
+    );
+
+    return () => {
+      unlistenPaused.then((unlisten) => unlisten());
+      unlistenResumed.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  const handleDebugCode = async () => {
+    const newTaskId = nanoid();
+    const lines = breakpoints
+      .split(",")
+      .map((line) => parseInt(line.trim(), 10))
+      .filter((line) => line > 0);
+
+    setTasks((prev) => [...prev, { id: newTaskId, code, state: "running" }]);
+
+    try {
+      await invoke("debug_run_task", {
+        taskId: newTaskId,
+        code,
+        breakpoints: lines,
+      });
+    } catch (error) {
+      console.error("Failed to debug code:", error);
+      setTasks((prev) =>
+        prev.map((t) =>
+          t.id === newTaskId ? { ...t, state: "error", result: { error } } : t
+        )
+      );
+    }
+  };
+
+  const handleDebugCommand = async (
+    taskId: string,
+    command: "debug_step" | "debug_continue"
+  ) => {
+    try {
+      await invoke(command, { taskId });
+    } catch (error) {
+      console.error(`Failed to ${command}:`, error);
+    }
+  };
+
+  const handleDebugEvaluate = async (taskId: string) => {
+    const expression = window.prompt("Expression to evaluate");
+    if (!expression) {
+      return;
+    }
+
+    let debugResult: string;
+    try {
+      const value = await invoke("debug_evaluate", { taskId, expression });
+      debugResult = `${expression} = ${JSON.stringify(value)}`;
+    } catch (error) {
+      debugResult = `${expression}: ${error}`;
+    }
+    setTasks((prev) =>
+      prev.map((t) => (t.id === taskId ? { ...t, debugResult } : t))
+    );
+  };
+
+  const handleInspect = async (taskId: string) => {
+    const expression = window.prompt("Expression to inspect, read-only");
+    if (!expression) {
+      return;
+    }
+
+    let inspection: string;
+    try {
+      const object = await invoke<RemoteObject>("evaluate_in_task", {
+        taskId,
+        expression,
+      });
+      inspection = `${expression} = ${describeRemoteObject(object)}`;
+    } catch (error) {
+      inspection = `${expression}: ${error}`;
+    }
+    setTasks((prev) =>
+      prev.map((t) => (t.id === taskId ? { ...t, inspection } : t))
+    );
+  };
+
   const handleRunCode = async (codeToRun?: string) => {
     const newTaskId = nanoid();
     const newTask: Task = {
@@ -188,6 +599,22 @@ function App() {
     }
   };
 
+  const handlePauseTask = async (taskId: string) => {
+    try {
+      await invoke("pause_task", { taskId });
+    } catch (error) {
+      console.error("Failed to pause task:", error);
+    }
+  };
+
+  const handleResumeTask = async (taskId: string) => {
+    try {
+      await invoke("resume_task", { taskId });
+    } catch (error) {
+      console.error("Failed to resume task:", error);
+    }
+  };
+
   const handleStopTask = async (taskId: string) => {
     try {
       await invoke("stop_task", { taskId });
@@ -201,6 +628,22 @@ function App() {
     }
   };
 
+  const handlePostMessage = async (taskId: string) => {
+    const text = window.prompt("Message to send, as JSON");
+    if (text === null) {
+      return;
+    }
+
+    try {
+      await invoke("post_message_to_task", {
+        taskId,
+        message: JSON.parse(text),
+      });
+    } catch (error) {
+      console.error("Failed to post message:", error);
+    }
+  };
+
   const handlePermissionResponse = async (
     taskId: string,
     response: PermissionsResponse
@@ -217,6 +660,7 @@ function App() {
       prev.filter(
         (t) =>
           t.state === "running" ||
+          t.state === "paused" ||
           t.state === "stopping" ||
           t.state === "waiting_for_permission"
       )
@@ -224,10 +668,32 @@ function App() {
     invoke("clear_completed_tasks");
   };
 
+  const handleCompactStorage = async () => {
+    setIsCompacting(true);
+    try {
+      setCompaction(await invoke<CompactionReport>("compact_storage"));
+    } catch (error) {
+      console.error("Failed to compact storage:", error);
+    } finally {
+      setIsCompacting(false);
+    }
+  };
+
   const isAnyTaskWaitingForPermissions = tasks.some(
     (t) => t.state === "waiting_for_permission"
   );
 
+  // ticks the elapsed time of the running tasks
+  const [now, setNow] = useState(Date.now());
+  const isAnyTaskRunning = tasks.some((t) => t.durationMs === undefined);
+  useEffect(() => {
+    if (!isAnyTaskRunning) {
+      return;
+    }
+    const interval = setInterval(() => setNow(Date.now()), 1000);
+    return () => clearInterval(interval);
+  }, [isAnyTaskRunning]);
+
   return (
     <div className="min-h-screen bg-gray-50 flex">
       <div className="flex-1 p-4">
@@ -237,18 +703,49 @@ function App() {
           </div>
 
           <div className="flex flex-col gap-4 p-4">
+            {health && !health.ok && (
+              <div className="flex items-start gap-2 text-sm text-red-600 bg-red-50 p-3 rounded-md border border-red-200">
+                <LuAlertTriangle className="flex-shrink-0 w-5 h-5" />
+                <div>
+                  <div>The Deno runtime is not healthy, tasks may fail:</div>
+                  <ul className="list-disc pl-4">
+                    {health.checks
+                      .filter((check) => !check.ok)
+                      .map((check) => (
+                        <li key={check.name}>
+                          {check.name}: {check.detail}
+                        </li>
+                      ))}
+                  </ul>
+                </div>
+              </div>
+            )}
             <CodeMirror
               value={code}
               height="200px"
               extensions={[javascript({ jsx: true })]}
               onChange={(value) => setCode(value)}
             />
-            <button
-              onClick={() => handleRunCode()}
-              className="w-fit bg-blue-500 hover:bg-blue-600 text-white font-medium py-2 px-4 rounded-md transition-colors"
-            >
-              Run Code
-            </button>
+            <div className="flex items-center gap-2">
+              <button
+                onClick={() => handleRunCode()}
+                className="w-fit bg-blue-500 hover:bg-blue-600 text-white font-medium py-2 px-4 rounded-md transition-colors"
+              >
+                Run Code
+              </button>
+              <button
+                onClick={() => handleDebugCode()}
+                className="w-fit bg-gray-200 hover:bg-gray-300 text-gray-700 font-medium py-2 px-4 rounded-md transition-colors"
+              >
+                Debug
+              </button>
+              <input
+                value={breakpoints}
+                onChange={(e) => setBreakpoints(e.target.value)}
+                placeholder="Breakpoint lines, e.g. 3, 7"
+                className="border border-gray-300 rounded-md px-2 py-2 text-sm"
+              />
+            </div>
             {isAnyTaskWaitingForPermissions && (
               <div className="flex items-center gap-2 text-sm text-amber-600 bg-amber-50 p-3 rounded-md border border-amber-200">
                 <LuAlertTriangle className="flex-shrink-0 w-5 h-5" />
@@ -276,10 +773,76 @@ function App() {
                     <div key={task.id} className="bg-gray-50 p-3 rounded-md">
                       <div className="flex items-center justify-between mb-2">
                         <div className="flex items-center gap-2">
-                          <span className="font-mono text-sm">{task.id}</span>
+                          {task.name ? (
+                            <span className="text-sm" title={task.id}>
+                              {task.name}
+                            </span>
+                          ) : (
+                            <span className="font-mono text-sm">{task.id}</span>
+                          )}
+                          {task.tags?.map((tag) => (
+                            <span
+                              key={tag}
+                              className="text-xs text-gray-600 bg-gray-200 px-1.5 rounded"
+                            >
+                              {tag}
+                            </span>
+                          ))}
+                          {(task.durationMs ?? task.startedAtMs) !==
+                            undefined && (
+                            <span
+                              className="text-xs text-gray-500"
+                              title={
+                                task.timings && describeTimings(task.timings)
+                              }
+                            >
+                              {formatDuration(
+                                task.durationMs ??
+                                  Math.max(0, now - task.startedAtMs!)
+                              )}
+                            </span>
+                          )}
                           {task.state === "running" && (
                             <>
                               <FaSpinner className="animate-spin text-blue-500" />
+                              <button
+                                onClick={() => handlePauseTask(task.id)}
+                                className="text-yellow-500 hover:text-yellow-600"
+                                title="Pause this task"
+                              >
+                                <FaPause />
+                              </button>
+                              <button
+                                onClick={() => handlePostMessage(task.id)}
+                                className="text-blue-500 hover:text-blue-600"
+                                title="Send a message to this task"
+                              >
+                                <FaPaperPlane />
+                              </button>
+                              <button
+                                onClick={() => handleInspect(task.id)}
+                                className="text-blue-500 hover:text-blue-600"
+                                title="Inspect a value in this task"
+                              >
+                                <FaSearch />
+                              </button>
+                              <button
+                                onClick={() => handleStopTask(task.id)}
+                                className="text-red-500 hover:text-red-600"
+                              >
+                                <FaStop />
+                              </button>
+                            </>
+                          )}
+                          {task.state === "paused" && (
+                            <>
+                              <button
+                                onClick={() => handleResumeTask(task.id)}
+                                className="text-blue-500 hover:text-blue-600"
+                                title="Resume this task"
+                              >
+                                <FaPlay />
+                              </button>
                               <button
                                 onClick={() => handleStopTask(task.id)}
                                 className="text-red-500 hover:text-red-600"
@@ -298,6 +861,7 @@ function App() {
                           )}
                           {![
                             "running",
+                            "paused",
                             "stopping",
                             "waiting_for_permission",
                           ].includes(task.state) && (
@@ -318,16 +882,94 @@ function App() {
                               ? "text-red-500"
                               : task.state === "stopped"
                               ? "text-yellow-500"
+                              : task.state === "timed_out"
+                              ? "text-red-500"
+                              : task.state === "interrupted"
+                              ? "text-yellow-500"
+                              : task.state === "retrying"
+                              ? "text-orange-500"
+                              : task.state === "paused"
+                              ? "text-yellow-500"
                               : task.state === "stopping"
                               ? "text-yellow-500"
                               : task.state === "waiting_for_permission"
                               ? "text-orange-500"
                               : "text-blue-500"
                           }`}
+                          title={task.waitingOn?.detail}
                         >
                           {task.state}
                         </span>
                       </div>
+                      {task.progress && task.durationMs === undefined && (
+                        <div className="mb-2">
+                          <div className="h-1.5 bg-gray-200 rounded">
+                            <div
+                              className="h-1.5 bg-blue-500 rounded"
+                              style={{ width: `${task.progress.percent}%` }}
+                            />
+                          </div>
+                          {task.progress.message && (
+                            <p className="text-xs text-gray-500 mt-1">
+                              {task.progress.message}
+                            </p>
+                          )}
+                        </div>
+                      )}
+                      {task.debugPause && (
+                        <div className="mb-2 bg-blue-50 border border-blue-200 p-3 rounded-md text-sm text-blue-800">
+                          <div className="mb-2">
+                            Paused ({task.debugPause.reason}) at{" "}
+                            {task.debugPause.call_frames
+                              .map(
+                                (frame) =>
+                                  `${frame.function_name || "(top level)"} ${
+                                    frame.module ?? "line"
+                                  }:${frame.line}`
+                              )
+                              .join(" < ")}
+                          </div>
+                          <div className="flex gap-2">
+                            <button
+                              onClick={() =>
+                                handleDebugCommand(task.id, "debug_step")
+                              }
+                              className="bg-blue-500 hover:bg-blue-600 text-white text-sm py-1 px-3 rounded"
+                            >
+                              Step
+                            </button>
+                            <button
+                              onClick={() =>
+                                handleDebugCommand(task.id, "debug_continue")
+                              }
+                              className="bg-blue-500 hover:bg-blue-600 text-white text-sm py-1 px-3 rounded"
+                            >
+                              Continue
+                            </button>
+                            <button
+                              onClick={() => handleDebugEvaluate(task.id)}
+                              className="bg-gray-200 hover:bg-gray-300 text-gray-700 text-sm py-1 px-3 rounded"
+                            >
+                              Evaluate
+                            </button>
+                          </div>
+                          {task.debugResult && (
+                            <p className="mt-2 font-mono text-xs">
+                              {task.debugResult}
+                            </p>
+                          )}
+                        </div>
+                      )}
+                      {task.inspection && (
+                        <p className="mb-2 text-xs text-gray-500 font-mono truncate">
+                          {task.inspection}
+                        </p>
+                      )}
+                      {task.lastMessage !== undefined && (
+                        <p className="mb-2 text-xs text-gray-500 font-mono truncate">
+                          Last message: {JSON.stringify(task.lastMessage)}
+                        </p>
+                      )}
                       {task.state === "waiting_for_permission" &&
                         task.permissionPrompt && (
                           <div className="mb-3 bg-orange-50 border border-orange-200 p-3 rounded-md">
@@ -378,6 +1020,24 @@ function App() {
                             </div>
                           </div>
                         )}
+                      {task.diagnostics && task.diagnostics.length > 0 && (
+                        <div className="mb-2 bg-yellow-50 border border-yellow-200 p-3 rounded-md text-xs text-yellow-800">
+                          {task.diagnostics.map((diagnostic, i) => (
+                            <div key={i} className="flex items-center gap-1">
+                              <LuAlertTriangle className="shrink-0" />
+                              <span className="font-mono">
+                                {diagnostic.specifier}
+                              </span>
+                              : {diagnostic.message}
+                            </div>
+                          ))}
+                        </div>
+                      )}
+                      {task.output && (
+                        <div className="mb-2 bg-white border border-gray-200 p-3 rounded-md font-mono text-xs overflow-auto max-h-48 whitespace-pre-wrap text-gray-700">
+                          {task.output}
+                        </div>
+                      )}
                       {task.error ? (
                         <div className="bg-red-50 border border-red-200 p-3 rounded-md font-mono text-sm overflow-auto max-h-64 whitespace-pre-wrap text-red-600">
                           {task.error}
@@ -408,6 +1068,30 @@ function App() {
                 </div>
               </div>
             )}
+
+            <div className="mt-4 border-t border-gray-200 pt-4">
+              <h2 className="text-lg font-medium text-gray-900 mb-2">
+                Maintenance
+              </h2>
+              <div className="flex items-center gap-3">
+                <button
+                  onClick={handleCompactStorage}
+                  disabled={isCompacting}
+                  className="bg-gray-200 hover:bg-gray-300 disabled:opacity-50 text-gray-700 text-sm py-1 px-3 rounded inline-flex items-center gap-1"
+                >
+                  {isCompacting && <FaSpinner className="animate-spin" />}
+                  Compact storage
+                </button>
+                {compaction && (
+                  <span className="text-sm text-gray-600">
+                    Reclaimed {formatBytes(compaction.reclaimed_bytes)} (
+                    {compaction.vacuumed_databases} databases vacuumed,{" "}
+                    {compaction.orphaned_artifacts} orphaned artifacts,{" "}
+                    {compaction.expired_cache_entries} expired cache entries)
+                  </span>
+                )}
+              </div>
+            </div>
           </div>
         </div>
       </div>